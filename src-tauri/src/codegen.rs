@@ -0,0 +1,959 @@
+use crate::database::types::classify_postgres_type;
+use crate::database::ConnectionManager;
+use crate::models::{ColumnTypeFamily, DatabaseType, GeneratedCode, TableColumn, TableConstraint};
+use anyhow::{anyhow, Result};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+pub const CODE_TARGETS: &[&str] = &["rust-sqlx", "typescript", "prisma", "sqlalchemy"];
+
+/// Everything a generator needs to know about one table.
+pub struct TableSchema<'a> {
+    pub table_name: &'a str,
+    pub db_type: &'a DatabaseType,
+    pub columns: &'a [TableColumn],
+    pub foreign_keys: Vec<&'a TableConstraint>,
+    /// Target type overrides keyed by column name or by database type name (case-insensitive);
+    /// a column name wins over a type name.
+    pub type_overrides: &'a HashMap<String, String>,
+}
+
+impl<'a> TableSchema<'a> {
+    pub fn new(
+        table_name: &'a str,
+        db_type: &'a DatabaseType,
+        columns: &'a [TableColumn],
+        constraints: &'a [TableConstraint],
+        type_overrides: &'a HashMap<String, String>,
+    ) -> Self {
+        Self {
+            table_name,
+            db_type,
+            columns,
+            foreign_keys: constraints
+                .iter()
+                .filter(|constraint| constraint.constraint_type == "FOREIGN KEY")
+                .collect(),
+            type_overrides,
+        }
+    }
+
+    fn bare_table_name(&self) -> &'a str {
+        let name = self
+            .table_name
+            .rsplit('.')
+            .next()
+            .unwrap_or(self.table_name);
+        name.trim_matches('"')
+    }
+
+    /// Explicit schema, if the table lives outside the default one.
+    fn schema_name(&self) -> Option<&'a str> {
+        self.table_name
+            .rsplit_once('.')
+            .map(|(schema, _)| schema.trim_matches('"'))
+            .filter(|schema| *schema != "public" && *schema != "main")
+    }
+
+    fn override_for(&self, column: &TableColumn) -> Option<&'a String> {
+        let overrides = self.type_overrides;
+        overrides.get(&column.name).or_else(|| {
+            overrides
+                .iter()
+                .find(|(key, _)| {
+                    key.eq_ignore_ascii_case(&column.data_type)
+                        || column
+                            .raw_type
+                            .as_deref()
+                            .is_some_and(|raw| key.eq_ignore_ascii_case(raw))
+                })
+                .map(|(_, value)| value)
+        })
+    }
+
+    /// The single-column foreign key `column` belongs to, if any.
+    fn reference_of(&self, column: &TableColumn) -> Option<(&'a str, &'a str)> {
+        self.foreign_keys.iter().find_map(|fk| {
+            let position = fk
+                .column_names
+                .iter()
+                .position(|name| *name == column.name)?;
+            let table = fk.foreign_table_name.as_deref()?;
+            let referenced = fk.foreign_column_names.as_ref()?.get(position)?;
+            Some((table, referenced.as_str()))
+        })
+    }
+
+    fn is_auto_increment(&self, column: &TableColumn) -> bool {
+        ConnectionManager::is_auto_increment(column, self.columns)
+    }
+}
+
+pub fn generate(target: &str, schema: &TableSchema) -> Result<GeneratedCode> {
+    let base_name = to_snake_case(schema.bare_table_name());
+    let (code, extension) = match target {
+        "rust-sqlx" => (rust_sqlx(schema), "rs"),
+        "typescript" => (typescript(schema), "ts"),
+        "prisma" => (prisma(schema), "prisma"),
+        "sqlalchemy" => (sqlalchemy(schema), "py"),
+        other => {
+            return Err(anyhow!(
+                "Unknown code target '{}', expected one of: {}",
+                other,
+                CODE_TARGETS.join(", ")
+            ))
+        }
+    };
+    Ok(GeneratedCode {
+        target: target.to_string(),
+        file_name: format!("{}.{}", base_name, extension),
+        code,
+    })
+}
+
+fn words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut previous_lower = false;
+    for ch in name.chars() {
+        if !ch.is_alphanumeric() {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            previous_lower = false;
+            continue;
+        }
+        if ch.is_uppercase() && previous_lower && !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+        }
+        previous_lower = ch.is_lowercase() || ch.is_ascii_digit();
+        current.extend(ch.to_lowercase());
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+fn to_snake_case(name: &str) -> String {
+    let snake = words(name).join("_");
+    match snake.chars().next() {
+        None => "column".to_string(),
+        Some(first) if first.is_ascii_digit() => format!("_{}", snake),
+        Some(_) => snake,
+    }
+}
+
+fn to_pascal_case(name: &str) -> String {
+    let pascal = words(name)
+        .iter()
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+                None => String::new(),
+            }
+        })
+        .collect::<String>();
+    match pascal.chars().next() {
+        None => "Table".to_string(),
+        Some(first) if first.is_ascii_digit() => format!("T{}", pascal),
+        Some(_) => pascal,
+    }
+}
+
+fn lowercase_type(column: &TableColumn) -> String {
+    column.data_type.to_lowercase()
+}
+
+/// Element family of an array column, falling back to text.
+fn array_element_family(column: &TableColumn) -> ColumnTypeFamily {
+    let raw = column
+        .element_raw_type
+        .clone()
+        .or_else(|| {
+            column
+                .raw_type
+                .as_deref()
+                .map(|raw| raw.trim_start_matches('_').to_string())
+        })
+        .unwrap_or_default();
+    match classify_postgres_type(&raw, &raw, "b", false) {
+        ColumnTypeFamily::Unknown => ColumnTypeFamily::Text,
+        family => family,
+    }
+}
+
+fn integer_width(column: &TableColumn) -> u8 {
+    let t = lowercase_type(column);
+    if t.contains("tinyint") || t.contains("smallint") || t == "int2" || t.contains("smallserial") {
+        16
+    } else if t.contains("bigint") || t == "int8" || t.contains("bigserial") {
+        64
+    } else if column.db_type == DatabaseType::SQLite {
+        // SQLite integers are always 64-bit.
+        64
+    } else {
+        32
+    }
+}
+
+fn rust_type(family: &ColumnTypeFamily, column: &TableColumn) -> String {
+    match family {
+        ColumnTypeFamily::Boolean => "bool".to_string(),
+        ColumnTypeFamily::Integer => format!("i{}", integer_width(column)),
+        ColumnTypeFamily::Float => {
+            let t = lowercase_type(column);
+            let single_precision = match column.db_type {
+                DatabaseType::PostgreSQL => t == "real" || t == "float4",
+                DatabaseType::MySQL => t.starts_with("float"),
+                DatabaseType::MsSql => t == "real",
+                DatabaseType::SQLite => false,
+            };
+            if single_precision {
+                "f32".to_string()
+            } else {
+                "f64".to_string()
+            }
+        }
+        ColumnTypeFamily::Decimal => "sqlx::types::BigDecimal".to_string(),
+        ColumnTypeFamily::DateTime => {
+            let t = lowercase_type(column);
+            if t.contains("with time zone") || t == "timestamptz" {
+                "chrono::DateTime<chrono::Utc>".to_string()
+            } else {
+                "chrono::NaiveDateTime".to_string()
+            }
+        }
+        ColumnTypeFamily::Date => "chrono::NaiveDate".to_string(),
+        ColumnTypeFamily::Time => "chrono::NaiveTime".to_string(),
+        ColumnTypeFamily::Json => "serde_json::Value".to_string(),
+        ColumnTypeFamily::Uuid => "uuid::Uuid".to_string(),
+        ColumnTypeFamily::Binary => "Vec<u8>".to_string(),
+        ColumnTypeFamily::Array => {
+            format!("Vec<{}>", rust_type(&array_element_family(column), column))
+        }
+        _ => "String".to_string(),
+    }
+}
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "static", "struct", "trait", "true", "type", "unsafe", "use", "where",
+    "while",
+];
+
+fn rust_sqlx(schema: &TableSchema) -> String {
+    let mut out = String::new();
+    out.push_str("use serde::{Deserialize, Serialize};\n\n");
+    out.push_str(&format!("/// Row of `{}`.\n", schema.table_name));
+    out.push_str("#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]\n");
+    out.push_str(&format!(
+        "pub struct {} {{\n",
+        to_pascal_case(schema.bare_table_name())
+    ));
+    for column in schema.columns {
+        let mut field = to_snake_case(&column.name);
+        if field == "self" || field == "super" {
+            field.push('_');
+        }
+        let mut notes = Vec::new();
+        if column.is_primary_key {
+            notes.push("Primary key.".to_string());
+        }
+        if let Some((table, referenced)) = schema.reference_of(column) {
+            notes.push(format!("References `{}.{}`.", table, referenced));
+        }
+        if !notes.is_empty() {
+            out.push_str(&format!("    /// {}\n", notes.join(" ")));
+        }
+        if field != column.name {
+            out.push_str(&format!("    #[sqlx(rename = \"{}\")]\n", column.name));
+            out.push_str(&format!("    #[serde(rename = \"{}\")]\n", column.name));
+        }
+        if RUST_KEYWORDS.contains(&field.as_str()) {
+            field = format!("r#{}", field);
+        }
+        let base = schema
+            .override_for(column)
+            .cloned()
+            .unwrap_or_else(|| rust_type(&column.type_family, column));
+        let ty = if column.is_nullable && !column.is_primary_key {
+            format!("Option<{}>", base)
+        } else {
+            base
+        };
+        out.push_str(&format!("    pub {}: {},\n", field, ty));
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn typescript_type(family: &ColumnTypeFamily, column: &TableColumn) -> String {
+    match family {
+        ColumnTypeFamily::Boolean => "boolean".to_string(),
+        ColumnTypeFamily::Integer | ColumnTypeFamily::Float => "number".to_string(),
+        ColumnTypeFamily::Json => "unknown".to_string(),
+        ColumnTypeFamily::Enum => match &column.enum_values {
+            Some(values) if !values.is_empty() => values
+                .iter()
+                .map(|value| format!("\"{}\"", value.replace('"', "\\\"")))
+                .collect::<Vec<_>>()
+                .join(" | "),
+            _ => "string".to_string(),
+        },
+        ColumnTypeFamily::Array => {
+            let element = typescript_type(&array_element_family(column), column);
+            if element.contains(' ') {
+                format!("Array<{}>", element)
+            } else {
+                format!("{}[]", element)
+            }
+        }
+        // Decimals, dates and binary data arrive as strings (binary base64-encoded).
+        _ => "string".to_string(),
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+fn typescript(schema: &TableSchema) -> String {
+    let mut out = String::new();
+    out.push_str(&format!("/** Row of `{}`. */\n", schema.table_name));
+    out.push_str(&format!(
+        "export interface {} {{\n",
+        to_pascal_case(schema.bare_table_name())
+    ));
+    for column in schema.columns {
+        let key = if is_identifier(&column.name) {
+            column.name.clone()
+        } else {
+            format!("\"{}\"", column.name.replace('"', "\\\""))
+        };
+        let base = schema
+            .override_for(column)
+            .cloned()
+            .unwrap_or_else(|| typescript_type(&column.type_family, column));
+        let ty = if column.is_nullable && !column.is_primary_key {
+            format!("{} | null", base)
+        } else {
+            base
+        };
+        let mut notes = Vec::new();
+        if column.is_primary_key {
+            notes.push("primary key".to_string());
+        }
+        if let Some((table, referenced)) = schema.reference_of(column) {
+            notes.push(format!("references {}.{}", table, referenced));
+        }
+        let comment = if notes.is_empty() {
+            String::new()
+        } else {
+            format!(" // {}", notes.join(", "))
+        };
+        out.push_str(&format!("  {}: {};{}\n", key, ty, comment));
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn prisma_type(family: &ColumnTypeFamily, column: &TableColumn) -> String {
+    match family {
+        ColumnTypeFamily::Boolean => "Boolean".to_string(),
+        ColumnTypeFamily::Integer
+            if integer_width(column) == 64 && column.db_type != DatabaseType::SQLite =>
+        {
+            "BigInt".to_string()
+        }
+        ColumnTypeFamily::Integer => "Int".to_string(),
+        ColumnTypeFamily::Float => "Float".to_string(),
+        ColumnTypeFamily::Decimal => "Decimal".to_string(),
+        ColumnTypeFamily::DateTime | ColumnTypeFamily::Date | ColumnTypeFamily::Time => {
+            "DateTime".to_string()
+        }
+        ColumnTypeFamily::Json => "Json".to_string(),
+        ColumnTypeFamily::Binary => "Bytes".to_string(),
+        ColumnTypeFamily::Array => {
+            format!("{}[]", prisma_type(&array_element_family(column), column))
+        }
+        _ => "String".to_string(),
+    }
+}
+
+fn prisma_default(schema: &TableSchema, column: &TableColumn) -> Option<String> {
+    if schema.is_auto_increment(column) {
+        return Some("@default(autoincrement())".to_string());
+    }
+    let default = column.default_value.as_deref()?.trim();
+    let lower = default.to_lowercase();
+    if lower.starts_with("now()") || lower.starts_with("current_timestamp") {
+        return Some("@default(now())".to_string());
+    }
+    if lower.starts_with("gen_random_uuid()") || lower.starts_with("uuid_generate_v4()") {
+        return Some("@default(dbgenerated(\"gen_random_uuid()\"))".to_string());
+    }
+    match column.type_family {
+        ColumnTypeFamily::Boolean if matches!(lower.as_str(), "true" | "false" | "1" | "0") => {
+            Some(format!(
+                "@default({})",
+                matches!(lower.as_str(), "true" | "1")
+            ))
+        }
+        ColumnTypeFamily::Integer | ColumnTypeFamily::Float | ColumnTypeFamily::Decimal
+            if default.parse::<f64>().is_ok() =>
+        {
+            Some(format!("@default({})", default))
+        }
+        ColumnTypeFamily::Text => {
+            // Postgres reports 'value'::text, the others 'value' or value.
+            let literal = default.split("::").next().unwrap_or(default);
+            let literal = literal.trim_matches('\'');
+            Some(format!("@default(\"{}\")", literal.replace('"', "\\\"")))
+        }
+        _ => None,
+    }
+}
+
+fn prisma(schema: &TableSchema) -> String {
+    let model_name = to_pascal_case(schema.bare_table_name());
+    let primary_keys = schema
+        .columns
+        .iter()
+        .filter(|column| column.is_primary_key)
+        .collect::<Vec<_>>();
+
+    let mut rows: Vec<(String, String, Vec<String>)> = Vec::new();
+    for column in schema.columns {
+        let field = to_snake_case(&column.name);
+        let base = schema
+            .override_for(column)
+            .cloned()
+            .unwrap_or_else(|| prisma_type(&column.type_family, column));
+        let ty = if column.is_nullable && !column.is_primary_key && !base.ends_with("[]") {
+            format!("{}?", base)
+        } else {
+            base
+        };
+        let mut attributes = Vec::new();
+        if column.is_primary_key && primary_keys.len() == 1 {
+            attributes.push("@id".to_string());
+        }
+        if let Some(default) = prisma_default(schema, column) {
+            attributes.push(default);
+        }
+        if field != column.name {
+            attributes.push(format!("@map(\"{}\")", column.name));
+        }
+        if column.type_family == ColumnTypeFamily::Uuid
+            && *schema.db_type == DatabaseType::PostgreSQL
+        {
+            attributes.push("@db.Uuid".to_string());
+        }
+        rows.push((field, ty, attributes));
+    }
+
+    for fk in &schema.foreign_keys {
+        let Some(table) = fk.foreign_table_name.as_deref() else {
+            continue;
+        };
+        let referenced = fk.foreign_column_names.clone().unwrap_or_default();
+        let optional = fk.column_names.iter().any(|name| {
+            schema
+                .columns
+                .iter()
+                .any(|column| column.name == *name && column.is_nullable)
+        });
+        let target_model = to_pascal_case(table.rsplit('.').next().unwrap_or(table));
+        let mut relation_field = to_snake_case(table.rsplit('.').next().unwrap_or(table));
+        if rows.iter().any(|(name, _, _)| *name == relation_field) {
+            relation_field = format!("{}_rel", relation_field);
+        }
+        let fields = fk
+            .column_names
+            .iter()
+            .map(|name| to_snake_case(name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        rows.push((
+            relation_field,
+            format!("{}{}", target_model, if optional { "?" } else { "" }),
+            vec![format!(
+                "@relation(fields: [{}], references: [{}])",
+                fields,
+                referenced
+                    .iter()
+                    .map(|name| to_snake_case(name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )],
+        ));
+    }
+
+    let name_width = rows
+        .iter()
+        .map(|(name, _, _)| name.len())
+        .max()
+        .unwrap_or(0);
+    let type_width = rows.iter().map(|(_, ty, _)| ty.len()).max().unwrap_or(0);
+    let mut out = format!("model {} {{\n", model_name);
+    for (name, ty, attributes) in &rows {
+        let line = if attributes.is_empty() {
+            format!("  {:name_width$} {}", name, ty)
+        } else {
+            format!(
+                "  {:name_width$} {:type_width$} {}",
+                name,
+                ty,
+                attributes.join(" ")
+            )
+        };
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+
+    let mut block = Vec::new();
+    if primary_keys.len() > 1 {
+        block.push(format!(
+            "  @@id([{}])",
+            primary_keys
+                .iter()
+                .map(|column| to_snake_case(&column.name))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    if model_name != schema.bare_table_name() {
+        block.push(format!("  @@map(\"{}\")", schema.bare_table_name()));
+    }
+    if let Some(schema_name) = schema.schema_name() {
+        block.push(format!("  @@schema(\"{}\")", schema_name));
+    }
+    if !block.is_empty() {
+        out.push('\n');
+        for line in block {
+            out.push_str(&line);
+            out.push('\n');
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// Python imports grouped by module; an empty name set means `import module`.
+type PythonImports = BTreeMap<&'static str, BTreeSet<&'static str>>;
+
+fn python_type(
+    family: &ColumnTypeFamily,
+    column: &TableColumn,
+    imports: &mut PythonImports,
+) -> String {
+    match family {
+        ColumnTypeFamily::Boolean => "bool".to_string(),
+        ColumnTypeFamily::Integer => "int".to_string(),
+        ColumnTypeFamily::Float => "float".to_string(),
+        ColumnTypeFamily::Decimal => {
+            imports.entry("decimal").or_default().insert("Decimal");
+            "Decimal".to_string()
+        }
+        ColumnTypeFamily::DateTime => {
+            imports.entry("datetime").or_default().insert("datetime");
+            "datetime".to_string()
+        }
+        ColumnTypeFamily::Date => {
+            imports.entry("datetime").or_default().insert("date");
+            "date".to_string()
+        }
+        ColumnTypeFamily::Time => {
+            imports.entry("datetime").or_default().insert("time");
+            "time".to_string()
+        }
+        ColumnTypeFamily::Json => {
+            imports.entry("typing").or_default().insert("Any");
+            "Any".to_string()
+        }
+        ColumnTypeFamily::Uuid => {
+            imports.entry("uuid").or_default();
+            "uuid.UUID".to_string()
+        }
+        ColumnTypeFamily::Binary => "bytes".to_string(),
+        ColumnTypeFamily::Array => {
+            format!(
+                "list[{}]",
+                python_type(&array_element_family(column), column, imports)
+            )
+        }
+        _ => "str".to_string(),
+    }
+}
+
+const PYTHON_KEYWORDS: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+    "with", "yield",
+];
+
+/// SQLAlchemy column type for the families `Mapped[...]` can't infer on its own.
+fn sqlalchemy_column_type(family: &ColumnTypeFamily, column: &TableColumn) -> Option<String> {
+    match family {
+        ColumnTypeFamily::Json => Some("JSON".to_string()),
+        ColumnTypeFamily::Array => {
+            let element = match array_element_family(column) {
+                ColumnTypeFamily::Boolean => "Boolean",
+                ColumnTypeFamily::Integer => "Integer",
+                ColumnTypeFamily::Float => "Float",
+                ColumnTypeFamily::Decimal => "Numeric",
+                ColumnTypeFamily::DateTime => "DateTime",
+                ColumnTypeFamily::Date => "Date",
+                ColumnTypeFamily::Time => "Time",
+                ColumnTypeFamily::Json => "JSON",
+                ColumnTypeFamily::Uuid => "Uuid",
+                ColumnTypeFamily::Binary => "LargeBinary",
+                _ => "String",
+            };
+            Some(format!("ARRAY({})", element))
+        }
+        _ => None,
+    }
+}
+
+fn sqlalchemy(schema: &TableSchema) -> String {
+    let mut imports = PythonImports::new();
+    let mut sqlalchemy_imports = BTreeSet::new();
+    let mut fields = Vec::new();
+
+    for column in schema.columns {
+        let mut attribute = to_snake_case(&column.name);
+        // `metadata` and `registry` are reserved on declarative classes.
+        if PYTHON_KEYWORDS.contains(&attribute.as_str())
+            || attribute == "metadata"
+            || attribute == "registry"
+        {
+            attribute.push('_');
+        }
+        let custom = schema.override_for(column);
+        let base = match custom {
+            Some(custom) => custom.clone(),
+            None => python_type(&column.type_family, column, &mut imports),
+        };
+        let ty = if column.is_nullable && !column.is_primary_key {
+            imports.entry("typing").or_default().insert("Optional");
+            format!("Optional[{}]", base)
+        } else {
+            base
+        };
+
+        let mut arguments = Vec::new();
+        if attribute != column.name {
+            arguments.push(format!("\"{}\"", column.name));
+        }
+        if let Some(column_type) = custom
+            .is_none()
+            .then(|| sqlalchemy_column_type(&column.type_family, column))
+            .flatten()
+        {
+            for name in column_type
+                .split(|c: char| !c.is_alphanumeric())
+                .filter(|n| !n.is_empty())
+            {
+                sqlalchemy_imports.insert(name.to_string());
+            }
+            arguments.push(column_type);
+        }
+        if let Some((table, referenced)) = schema.reference_of(column) {
+            sqlalchemy_imports.insert("ForeignKey".to_string());
+            arguments.push(format!("ForeignKey(\"{}.{}\")", table, referenced));
+        }
+        if column.is_primary_key {
+            arguments.push("primary_key=True".to_string());
+        }
+        let value = if arguments.is_empty() {
+            String::new()
+        } else {
+            format!(" = mapped_column({})", arguments.join(", "))
+        };
+        fields.push(format!("    {}: Mapped[{}]{}", attribute, ty, value));
+    }
+
+    let mut out = String::new();
+    for (module, _) in imports.iter().filter(|(_, names)| names.is_empty()) {
+        out.push_str(&format!("import {}\n", module));
+    }
+    for (module, names) in imports.iter().filter(|(_, names)| !names.is_empty()) {
+        let names = names.iter().copied().collect::<Vec<_>>().join(", ");
+        out.push_str(&format!("from {} import {}\n", module, names));
+    }
+    if !imports.is_empty() {
+        out.push('\n');
+    }
+    if !sqlalchemy_imports.is_empty() {
+        out.push_str(&format!(
+            "from sqlalchemy import {}\n",
+            sqlalchemy_imports
+                .into_iter()
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    out.push_str("from sqlalchemy.orm import DeclarativeBase, Mapped, mapped_column\n\n\n");
+    out.push_str("class Base(DeclarativeBase):\n    pass\n\n\n");
+    out.push_str(&format!(
+        "class {}(Base):\n",
+        to_pascal_case(schema.bare_table_name())
+    ));
+    out.push_str(&format!(
+        "    __tablename__ = \"{}\"\n",
+        schema.bare_table_name()
+    ));
+    if let Some(schema_name) = schema.schema_name() {
+        out.push_str(&format!(
+            "    __table_args__ = {{\"schema\": \"{}\"}}\n",
+            schema_name
+        ));
+    }
+    out.push('\n');
+    for field in fields {
+        out.push_str(&field);
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(
+        name: &str,
+        data_type: &str,
+        family: ColumnTypeFamily,
+        nullable: bool,
+    ) -> TableColumn {
+        TableColumn {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            raw_type: None,
+            normalized_type: data_type.to_uppercase(),
+            type_family: family,
+            db_type: DatabaseType::PostgreSQL,
+            is_nullable: nullable,
+            default_value: None,
+            is_primary_key: false,
+            is_boolean_like: false,
+            is_array: false,
+            enum_values: None,
+            identity_kind: None,
+            generated_kind: None,
+            generation_expression: None,
+            column_comment: None,
+            collation_name: None,
+            domain_name: None,
+            domain_schema: None,
+            domain_base_type: None,
+            array_dimensions: None,
+            element_raw_type: None,
+            character_maximum_length: None,
+            numeric_precision: None,
+            numeric_scale: None,
+        }
+    }
+
+    /// `public.blog_posts` with a key, a foreign key, defaults, an enum, an array and awkward names.
+    fn fixture() -> (Vec<TableColumn>, Vec<TableConstraint>) {
+        let mut id = column("id", "integer", ColumnTypeFamily::Integer, false);
+        id.is_primary_key = true;
+        id.identity_kind = Some("a".to_string());
+        let mut title = column("title", "character varying", ColumnTypeFamily::Text, false);
+        title.default_value = Some("'Untitled'::character varying".to_string());
+        let mut kind = column("type", "post_kind", ColumnTypeFamily::Enum, false);
+        kind.enum_values = Some(vec!["draft".to_string(), "published".to_string()]);
+        let mut tags = column("tags", "text[]", ColumnTypeFamily::Array, true);
+        tags.element_raw_type = Some("text".to_string());
+        let mut published_at = column(
+            "publishedAt",
+            "timestamp with time zone",
+            ColumnTypeFamily::DateTime,
+            true,
+        );
+        published_at.default_value = Some("now()".to_string());
+
+        let columns = vec![
+            id,
+            column("author_id", "bigint", ColumnTypeFamily::Integer, false),
+            title,
+            column("Body Text", "text", ColumnTypeFamily::Text, true),
+            kind,
+            tags,
+            column("metadata", "jsonb", ColumnTypeFamily::Json, true),
+            column("rating", "numeric", ColumnTypeFamily::Decimal, true),
+            published_at,
+        ];
+        let constraints = vec![TableConstraint {
+            constraint_name: "blog_posts_author_id_fkey".to_string(),
+            constraint_type: "FOREIGN KEY".to_string(),
+            table_schema: Some("public".to_string()),
+            table_name: "blog_posts".to_string(),
+            column_names: vec!["author_id".to_string()],
+            foreign_table_schema: Some("public".to_string()),
+            foreign_table_name: Some("users".to_string()),
+            foreign_column_names: Some(vec!["id".to_string()]),
+            check_expression: None,
+            is_deferrable: None,
+            initially_deferred: None,
+        }];
+        (columns, constraints)
+    }
+
+    fn render(target: &str, overrides: &HashMap<String, String>) -> GeneratedCode {
+        let (columns, constraints) = fixture();
+        let schema = TableSchema::new(
+            "public.blog_posts",
+            &DatabaseType::PostgreSQL,
+            &columns,
+            &constraints,
+            overrides,
+        );
+        generate(target, &schema).unwrap()
+    }
+
+    #[test]
+    fn rust_sqlx_snapshot() {
+        let generated = render("rust-sqlx", &HashMap::new());
+        assert_eq!(generated.file_name, "blog_posts.rs");
+        assert_eq!(
+            generated.code,
+            r#"use serde::{Deserialize, Serialize};
+
+/// Row of `public.blog_posts`.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct BlogPosts {
+    /// Primary key.
+    pub id: i32,
+    /// References `users.id`.
+    pub author_id: i64,
+    pub title: String,
+    #[sqlx(rename = "Body Text")]
+    #[serde(rename = "Body Text")]
+    pub body_text: Option<String>,
+    pub r#type: String,
+    pub tags: Option<Vec<String>>,
+    pub metadata: Option<serde_json::Value>,
+    pub rating: Option<sqlx::types::BigDecimal>,
+    #[sqlx(rename = "publishedAt")]
+    #[serde(rename = "publishedAt")]
+    pub published_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+"#
+        );
+    }
+
+    #[test]
+    fn typescript_snapshot() {
+        let generated = render("typescript", &HashMap::new());
+        assert_eq!(generated.file_name, "blog_posts.ts");
+        assert_eq!(
+            generated.code,
+            r#"/** Row of `public.blog_posts`. */
+export interface BlogPosts {
+  id: number; // primary key
+  author_id: number; // references users.id
+  title: string;
+  "Body Text": string | null;
+  type: "draft" | "published";
+  tags: string[] | null;
+  metadata: unknown | null;
+  rating: string | null;
+  publishedAt: string | null;
+}
+"#
+        );
+    }
+
+    #[test]
+    fn prisma_snapshot() {
+        let generated = render("prisma", &HashMap::new());
+        assert_eq!(generated.file_name, "blog_posts.prisma");
+        assert_eq!(
+            generated.code,
+            r#"model BlogPosts {
+  id           Int       @id @default(autoincrement())
+  author_id    BigInt
+  title        String    @default("Untitled")
+  body_text    String?   @map("Body Text")
+  type         String
+  tags         String[]
+  metadata     Json?
+  rating       Decimal?
+  published_at DateTime? @default(now()) @map("publishedAt")
+  users        Users     @relation(fields: [author_id], references: [id])
+
+  @@map("blog_posts")
+}
+"#
+        );
+    }
+
+    #[test]
+    fn sqlalchemy_snapshot() {
+        let generated = render("sqlalchemy", &HashMap::new());
+        assert_eq!(generated.file_name, "blog_posts.py");
+        assert_eq!(
+            generated.code,
+            r#"from datetime import datetime
+from decimal import Decimal
+from typing import Any, Optional
+
+from sqlalchemy import ARRAY, ForeignKey, JSON, String
+from sqlalchemy.orm import DeclarativeBase, Mapped, mapped_column
+
+
+class Base(DeclarativeBase):
+    pass
+
+
+class BlogPosts(Base):
+    __tablename__ = "blog_posts"
+
+    id: Mapped[int] = mapped_column(primary_key=True)
+    author_id: Mapped[int] = mapped_column(ForeignKey("users.id"))
+    title: Mapped[str]
+    body_text: Mapped[Optional[str]] = mapped_column("Body Text")
+    type: Mapped[str]
+    tags: Mapped[Optional[list[str]]] = mapped_column(ARRAY(String))
+    metadata_: Mapped[Optional[Any]] = mapped_column("metadata", JSON)
+    rating: Mapped[Optional[Decimal]]
+    published_at: Mapped[Optional[datetime]] = mapped_column("publishedAt")
+"#
+        );
+    }
+
+    #[test]
+    fn overrides_apply_by_column_name_before_type_name() {
+        let overrides = HashMap::from([
+            ("rating".to_string(), "rust_decimal::Decimal".to_string()),
+            ("JSONB".to_string(), "Metadata".to_string()),
+            ("numeric".to_string(), "f64".to_string()),
+        ]);
+        let code = render("rust-sqlx", &overrides).code;
+        assert!(code.contains("pub rating: Option<rust_decimal::Decimal>,"));
+        assert!(code.contains("pub metadata: Option<Metadata>,"));
+    }
+
+    #[test]
+    fn unknown_target_is_rejected() {
+        let (columns, constraints) = fixture();
+        let overrides = HashMap::new();
+        let schema = TableSchema::new(
+            "posts",
+            &DatabaseType::SQLite,
+            &columns,
+            &constraints,
+            &overrides,
+        );
+        assert!(generate("java", &schema).is_err());
+    }
+}
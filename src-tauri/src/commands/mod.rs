@@ -1,80 +1,971 @@
+use crate::database::escaping;
+use crate::database::pinned::DEFAULT_PIN_TTL_MINUTES;
+use crate::database::subscriptions::{TableSubscription, MIN_REFRESH_SECONDS};
 use crate::database::ConnectionManager;
 use crate::models::{
-    AppliedMigration, ConnectionConfig, ConnectionTestResult, DatabaseTable, DatabaseType,
-    ExecutionPlan, ExportArchiveEntry, ForeignKeyDefinition, PostgresConnectionInfo, PostgresExtension,
-    PostgresTablePrivileges, QueryResult, TableColumn, TableConstraint, TableIndex, RelationMatch,
+    ActiveConnectionInfo, AppliedMigration, CascadePreview, CascadePreviewOptions, CellText,
+    CellTextRange, CellFileTransfer, ChecksumComparison, ChecksumOptions, ChecksumTarget,
+    CloneRowResult, ColumnStatistics, ConnectTarget, ConnectionCapabilities, ConnectionConfig,
+    ConnectionInfo, CreateDatabaseOptions, CreateSqliteDatabaseOptions, ConnectionProfile, ConnectionResolution, ConnectionTestResult,
+    CreateExtensionOptions, DatabasePermissions, DatabaseTable, DatabaseType,
+    DropExtensionOptions, ExecutedStatement, ExecutionPlan, ExternalConnectionImport, ExternalConnectionSource, ExportArchiveEntry,
+    ExtensionChangeResult, ExtensionInfo, ForeignKeyDefinition, GeneratedCode, OperationInfo,
+    PinnedExportFormat, PinnedResultDiff, PinnedResultPage, PostgresConnectionInfo,
+    PostgresExtension, PostgresTablePrivileges, QueryLogEntry, QueryPatternFinding, QueryResult,
+    QueryWithPlan, RecentSqliteFile, RowCountOptions, RowFilter, ServerMetrics, SlowQuery,
+    TopQueriesReport, TopQueryOrder, TableRowCount, SqliteQuickConnectOptions, SshTunnelStatus,
+    StatementRunOptions, TableColumn, TableChecksum, TableConstraint, TableDataPage, TableIndex,
+    TablePermissions, TableSort, TableViewPreferences, TableViewResponse, RelationMatch,
+    ParsedConnectionUrl, TelemetrySettings, TelemetryStatus, TableRef, TableStructures,
+    DecodeStrategy, UnknownColumnType, ConnectionPing, PoolStats, PasteTableOptions, PastedTable,
+    AppStoreStatus, StoreRepair, ReplaceOptions, ReplaceResult, ExpandRowRequest, ExpandedRow,
+    ConnectionDebugReport, TableListing, IndexSuggestion, CrossQueryRequest, CrossQueryResult,
+    ColumnHistogram, HistogramOptions, ObjectTarget, ScratchObject, ScratchCleanupOptions,
+    ScratchCleanupResult, ColumnViolation, BatchedDeleteOptions, BatchedDeleteResult, Workspace,
+    WorkspaceRestore, WorkspaceSummary, ResultLineage, TableViewParams, MultiQueryResult,
+    SqlCatalog, GuardedExecution, GuardResolved, AggregateFunction, AggregateSource,
+    ColumnAggregates, KeyMatchOptions, KeyMatchResult, KeySource, KeyTarget, UpdateRowsOptions,
+    UpdateRowsResult, UpdateValue, TranslatedSql,
 };
+use crate::operations::OperationRegistry;
+use crate::external_connections;
+use crate::profiles;
+use crate::store::AppStore;
+use crate::workspaces::{self, WorkspaceTracker};
 use chrono::Utc;
+use std::collections::BTreeMap;
 use tauri::State;
 
 #[tauri::command]
-pub async fn test_connection(config: ConnectionConfig) -> Result<ConnectionTestResult, String> {
+pub async fn test_connection(mut config: ConnectionConfig) -> Result<ConnectionTestResult, String> {
+    profiles::fill_stored_credential(&mut config).map_err(|e| format!("Connection test failed: {}", e))?;
     ConnectionManager::test_connection(config)
         .await
         .map_err(|e| format!("Connection test failed: {}", e))
 }
 
+#[tauri::command]
+pub async fn parse_connection_url(
+    url: String,
+    db_type: Option<DatabaseType>,
+) -> Result<ParsedConnectionUrl, String> {
+    ConnectionManager::parse_connection_url(&url, db_type.as_ref())
+        .map_err(|e| format!("Failed to parse connection URL: {}", e))
+}
+
+#[tauri::command]
+pub async fn ping_connection(
+    connection_id: String,
+    manager: State<'_, ConnectionManager>,
+) -> Result<ConnectionPing, String> {
+    manager
+        .ping_connection(&connection_id)
+        .await
+        .map_err(|e| format!("Failed to ping connection: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_pool_stats(
+    connection_id: String,
+    manager: State<'_, ConnectionManager>,
+) -> Result<PoolStats, String> {
+    manager
+        .get_pool_stats(&connection_id)
+        .await
+        .map_err(|e| format!("Failed to get pool stats: {}", e))
+}
+
+#[tauri::command]
+pub async fn debug_connection(mut config: ConnectionConfig) -> Result<ConnectionDebugReport, String> {
+    profiles::fill_stored_credential(&mut config).map_err(|e| format!("Connection debug failed: {}", e))?;
+    ConnectionManager::debug_connection(config)
+        .await
+        .map_err(|e| format!("Connection debug failed: {}", e))
+}
+
 #[tauri::command]
 pub async fn connect_database(
-    config: ConnectionConfig,
+    config: ConnectTarget,
+    operation_id: Option<String>,
+    manager: State<'_, ConnectionManager>,
+    store: State<'_, AppStore>,
+    operations: State<'_, OperationRegistry>,
+) -> Result<String, String> {
+    let config = match config {
+        ConnectTarget::Config(mut config) => {
+            profiles::fill_stored_credential(&mut config).map_err(|e| format!("Failed to connect: {}", e))?;
+            *config
+        }
+        ConnectTarget::Profile { profile } => {
+            let connection = async {
+                let resolution = profiles::resolve_connection(&store, &manager, &profile).await?;
+                profiles::single_match(&resolution, &profile)
+            }
+            .await
+            .map_err(|e| format!("Failed to connect: {}", e))?;
+            if connection.active {
+                return Ok(format!("Successfully connected to {}", connection.name));
+            }
+            profiles::profile_config(&store, &connection.id).map_err(|e| format!("Failed to connect: {}", e))?
+        }
+    };
+
+    let operation = operations.start(
+        operation_id,
+        "connect",
+        Some(&config.id),
+        &format!("Connecting to {}", config.name),
+    )
+    .map_err(|e| format!("Failed to connect: {}", e))?;
+    let result = manager.connect_with_progress(config, &operation).await;
+    operation.finish(&result);
+    result.map_err(|e| format!("Failed to connect: {}", e))
+}
+
+#[tauri::command]
+pub async fn reconnect_database(
+    mut config: ConnectionConfig,
+    manager: State<'_, ConnectionManager>,
+) -> Result<String, String> {
+    profiles::fill_stored_credential(&mut config).map_err(|e| format!("Failed to reconnect: {}", e))?;
+    let name = config.name.clone();
+    manager
+        .reconnect(config)
+        .await
+        .map_err(|e| format!("Failed to reconnect: {}", e))?;
+    Ok(format!("Reconnected to {}", name))
+}
+
+#[tauri::command]
+pub async fn list_databases(
+    connection_id: String,
+    manager: State<'_, ConnectionManager>,
+) -> Result<Vec<String>, String> {
+    manager
+        .list_databases(&connection_id)
+        .await
+        .map_err(|e| format!("Failed to list databases: {}", e))
+}
+
+#[tauri::command]
+pub async fn switch_database(
+    connection_id: String,
+    database: String,
+    manager: State<'_, ConnectionManager>,
+) -> Result<String, String> {
+    manager
+        .switch_database(&connection_id, &database)
+        .await
+        .map_err(|e| format!("Failed to switch database: {}", e))?;
+    Ok(format!("Switched to {}", database))
+}
+
+#[tauri::command]
+pub async fn create_database(
+    connection_id: String,
+    name: String,
+    options: Option<CreateDatabaseOptions>,
+    manager: State<'_, ConnectionManager>,
+) -> Result<String, String> {
+    manager
+        .create_database(&connection_id, &name, &options.unwrap_or_default())
+        .await
+        .map_err(|e| format!("Failed to create database: {}", e))?;
+    Ok(format!("Created database {}", name))
+}
+
+#[tauri::command]
+pub async fn drop_database(
+    connection_id: String,
+    name: String,
+    force: Option<bool>,
+    manager: State<'_, ConnectionManager>,
+) -> Result<String, String> {
+    manager
+        .drop_database(&connection_id, &name, force.unwrap_or(false))
+        .await
+        .map_err(|e| format!("Failed to drop database: {}", e))?;
+    Ok(format!("Dropped database {}", name))
+}
+
+#[tauri::command]
+pub async fn resolve_connection(
+    name_or_id: String,
+    manager: State<'_, ConnectionManager>,
+    store: State<'_, AppStore>,
+) -> Result<ConnectionResolution, String> {
+    profiles::resolve_connection(&store, &manager, &name_or_id)
+        .await
+        .map_err(|e| format!("Failed to resolve connection: {}", e))
+}
+
+/// Saves a connection profile, moving its secrets to the OS keychain.
+#[tauri::command]
+pub async fn save_connection_profile(
+    mut profile: ConnectionProfile,
+    store: State<'_, AppStore>,
+) -> Result<ConnectionProfile, String> {
+    if profile.config.password.is_some() {
+        profile.credentials_required = false;
+    }
+    let secrets = profiles::ProfileSecrets::take(&mut profile.config);
+    profiles::store_secrets(&profile.config.id, &secrets)
+        .map_err(|e| format!("Failed to save connection profile: {}", e))?;
+    store
+        .save_connection_profile(profile.clone())
+        .map_err(|e| format!("Failed to save connection profile: {}", e))?;
+    Ok(profile)
+}
+
+#[tauri::command]
+pub async fn list_connection_profiles(
+    store: State<'_, AppStore>,
+) -> Result<Vec<ConnectionProfile>, String> {
+    store
+        .list_connection_profiles()
+        .map_err(|e| format!("Failed to list connection profiles: {}", e))
+}
+
+/// Writes the saved connections to a JSON bundle, returning how many.
+#[tauri::command]
+pub async fn export_connections(
+    path: String,
+    include_passwords: bool,
+    store: State<'_, AppStore>,
+) -> Result<usize, String> {
+    let bundle = profiles::export_bundle(&store, include_passwords)
+        .map_err(|e| format!("Failed to export connections: {}", e))?;
+    let contents =
+        serde_json::to_string_pretty(&bundle).map_err(|e| format!("Failed to export connections: {}", e))?;
+    std::fs::write(&path, contents).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    Ok(bundle.connections.len())
+}
+
+/// Adds the connections of a JSON bundle as new profiles.
+#[tauri::command]
+pub async fn import_connections(
+    path: String,
+    store: State<'_, AppStore>,
+) -> Result<Vec<ConnectionProfile>, String> {
+    let contents = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    profiles::import_bundle(&store, &contents).map_err(|e| format!("Failed to import connections: {}", e))
+}
+
+/// Reads the connections saved by DBeaver, TablePlus or DataGrip for review;
+/// nothing is saved.
+#[tauri::command]
+pub async fn import_external_connections(
+    source: ExternalConnectionSource,
+    path: String,
+) -> Result<ExternalConnectionImport, String> {
+    let contents = std::fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    external_connections::parse(&source, &contents).map_err(|e| format!("Failed to import connections: {}", e))
+}
+
+/// Saves a connection's password to the OS keychain, for configs that set
+/// `use_stored_credential`.
+#[tauri::command]
+pub async fn save_credential(connection_id: String, secret: String) -> Result<(), String> {
+    profiles::save_credential(&connection_id, secret).map_err(|e| format!("Failed to save credential: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_credential(connection_id: String) -> Result<Option<String>, String> {
+    profiles::get_credential(&connection_id).map_err(|e| format!("Failed to read credential: {}", e))
+}
+
+#[tauri::command]
+pub async fn delete_credential(connection_id: String) -> Result<(), String> {
+    profiles::delete_credential(&connection_id).map_err(|e| format!("Failed to delete credential: {}", e))
+}
+
+#[tauri::command]
+pub async fn delete_connection_profile(
+    profile_id: String,
+    store: State<'_, AppStore>,
+) -> Result<bool, String> {
+    profiles::delete_secrets(&profile_id)
+        .map_err(|e| format!("Failed to delete connection profile: {}", e))?;
+    store
+        .delete_connection_profile(&profile_id)
+        .map_err(|e| format!("Failed to delete connection profile: {}", e))
+}
+
+#[tauri::command]
+pub async fn set_workspace_state(
+    connection_id: String,
+    state: serde_json::Value,
+    tracker: State<'_, WorkspaceTracker>,
+) -> Result<(), String> {
+    tracker.set_state(&connection_id, state);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn save_workspace(
+    name: String,
+    manager: State<'_, ConnectionManager>,
+    store: State<'_, AppStore>,
+    tracker: State<'_, WorkspaceTracker>,
+) -> Result<Workspace, String> {
+    workspaces::save(&store, &manager, &tracker, &name)
+        .await
+        .map_err(|e| format!("Failed to save workspace: {}", e))
+}
+
+#[tauri::command]
+pub async fn restore_workspace(
+    name: String,
+    manager: State<'_, ConnectionManager>,
+    store: State<'_, AppStore>,
+    tracker: State<'_, WorkspaceTracker>,
+) -> Result<WorkspaceRestore, String> {
+    workspaces::restore(&store, &manager, &tracker, &name)
+        .await
+        .map_err(|e| format!("Failed to restore workspace: {}", e))
+}
+
+#[tauri::command]
+pub async fn list_workspaces(store: State<'_, AppStore>) -> Result<Vec<WorkspaceSummary>, String> {
+    let workspaces = store
+        .list_workspaces()
+        .map_err(|e| format!("Failed to list workspaces: {}", e))?;
+    Ok(workspaces
+        .into_iter()
+        .map(|workspace| WorkspaceSummary {
+            connection_count: workspace.connections.len(),
+            name: workspace.name,
+            saved_at: workspace.saved_at,
+        })
+        .collect())
+}
+
+#[tauri::command]
+pub async fn disconnect_database(
+    connection_id: String,
+    manager: State<'_, ConnectionManager>,
+) -> Result<String, String> {
+    manager
+        .disconnect(&connection_id)
+        .await
+        .map_err(|e| format!("Failed to disconnect: {}", e))?;
+
+    Ok("Successfully disconnected".to_string())
+}
+
+#[tauri::command]
+pub async fn get_ssh_tunnel_status(
+    manager: State<'_, ConnectionManager>,
+) -> Result<Vec<SshTunnelStatus>, String> {
+    Ok(manager.ssh_tunnel_status().await)
+}
+
+#[tauri::command]
+pub async fn set_connection_read_only(
+    connection_id: String,
+    enabled: bool,
+    on_server: Option<bool>,
+    manager: State<'_, ConnectionManager>,
+) -> Result<ConnectionInfo, String> {
+    manager
+        .set_connection_read_only(&connection_id, enabled, on_server.unwrap_or(false))
+        .await
+        .map_err(|e| format!("Failed to set read-only mode: {}", e))
+}
+
+#[tauri::command]
+pub async fn set_schema_watch(
+    connection_id: String,
+    interval_seconds: Option<u64>,
+    manager: State<'_, ConnectionManager>,
+) -> Result<Option<u64>, String> {
+    manager
+        .set_schema_watch(&connection_id, interval_seconds)
+        .await
+        .map_err(|e| format!("Failed to set schema watch: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_connection_info(
+    connection_id: String,
+    manager: State<'_, ConnectionManager>,
+) -> Result<ConnectionInfo, String> {
+    manager
+        .get_connection_info(&connection_id)
+        .await
+        .map_err(|e| format!("Failed to get connection info: {}", e))
+}
+
+#[tauri::command]
+pub async fn list_tables(
+    connection_id: String,
+    db_type: DatabaseType,
+    manager: State<'_, ConnectionManager>,
+) -> Result<Vec<DatabaseTable>, String> {
+    manager
+        .list_tables(&connection_id, &db_type)
+        .await
+        .map_err(|e| format!("Failed to list tables: {}", e))
+}
+
+/// The stored table list at once, with a fresh one following as an event;
+/// see `ConnectionManager::list_tables_cached`.
+#[tauri::command]
+pub async fn list_tables_cached(
+    connection_id: String,
+    db_type: DatabaseType,
+    force_fresh: Option<bool>,
+    manager: State<'_, ConnectionManager>,
+    store: State<'_, AppStore>,
+) -> Result<TableListing, String> {
+    let cached = store
+        .cached_table_list(&connection_id)
+        .map_err(|e| format!("Failed to read cached tables: {}", e))?;
+    let store = store.inner().clone();
+    manager
+        .list_tables_cached(&connection_id, &db_type, cached, force_fresh.unwrap_or(false), move |list| {
+            let _ = store.save_table_list(list);
+        })
+        .await
+        .map_err(|e| format!("Failed to list tables: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_table_row_count(
+    connection_id: String,
+    table_name: String,
+    options: Option<RowCountOptions>,
+    db_type: DatabaseType,
+    manager: State<'_, ConnectionManager>,
+) -> Result<TableRowCount, String> {
+    manager
+        .get_table_row_count(&connection_id, &table_name, &options.unwrap_or_default(), &db_type)
+        .await
+        .map_err(|e| format!("Failed to count table rows: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_column_statistics(
+    connection_id: String,
+    table_name: String,
+    column_name: String,
+    db_type: DatabaseType,
+    manager: State<'_, ConnectionManager>,
+) -> Result<ColumnStatistics, String> {
+    manager
+        .get_column_statistics(&connection_id, &table_name, &column_name, &db_type)
+        .await
+        .map_err(|e| format!("Failed to get column statistics: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_column_histogram(
+    connection_id: String,
+    table_name: String,
+    column_name: String,
+    options: Option<HistogramOptions>,
+    db_type: DatabaseType,
+    manager: State<'_, ConnectionManager>,
+) -> Result<ColumnHistogram, String> {
+    manager
+        .get_column_histogram(&connection_id, &table_name, &column_name, &options.unwrap_or_default(), &db_type)
+        .await
+        .map_err(|e| format!("Failed to get column histogram: {}", e))
+}
+
+/// Records filters the user applied to a table's grid, for index suggestions.
+#[tauri::command]
+pub async fn record_table_filters(
+    connection_id: String,
+    table_name: String,
+    filters: Vec<RowFilter>,
+    store: State<'_, AppStore>,
+) -> Result<(), String> {
+    store
+        .record_filter_usage(&connection_id, &table_name, &filters)
+        .map_err(|e| format!("Failed to record table filters: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_index_suggestions(
+    connection_id: String,
+    table_name: String,
+    db_type: DatabaseType,
+    manager: State<'_, ConnectionManager>,
+    store: State<'_, AppStore>,
+) -> Result<Vec<IndexSuggestion>, String> {
+    let usage = store
+        .filter_usage(&connection_id, &table_name)
+        .map_err(|e| format!("Failed to get index suggestions: {}", e))?;
+    let dismissed = store
+        .dismissed_index_suggestions(&connection_id, &table_name)
+        .map_err(|e| format!("Failed to get index suggestions: {}", e))?;
+    manager
+        .get_index_suggestions(&connection_id, &table_name, &usage, &dismissed, &db_type)
+        .await
+        .map_err(|e| format!("Failed to get index suggestions: {}", e))
+}
+
+#[tauri::command]
+pub async fn dismiss_index_suggestion(
+    connection_id: String,
+    table_name: String,
+    suggestion_id: String,
+    store: State<'_, AppStore>,
+) -> Result<(), String> {
+    store
+        .dismiss_index_suggestion(&connection_id, &table_name, &suggestion_id)
+        .map_err(|e| format!("Failed to dismiss index suggestion: {}", e))
+}
+
+#[tauri::command]
+pub async fn analyze_table(
+    connection_id: String,
+    table_name: String,
+    db_type: DatabaseType,
+    manager: State<'_, ConnectionManager>,
+) -> Result<(), String> {
+    manager
+        .analyze_table(&connection_id, &table_name, &db_type)
+        .await
+        .map_err(|e| format!("Failed to analyze table: {}", e))
+}
+
+#[tauri::command]
+pub async fn analyze_column(
+    connection_id: String,
+    table_name: String,
+    column_name: String,
+    db_type: DatabaseType,
+    manager: State<'_, ConnectionManager>,
+) -> Result<ColumnStatistics, String> {
+    manager
+        .analyze_column(&connection_id, &table_name, &column_name, &db_type)
+        .await
+        .map_err(|e| format!("Failed to analyze column: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_table_structure(
+    connection_id: String,
+    table_name: String,
+    db_type: DatabaseType,
+    manager: State<'_, ConnectionManager>,
+) -> Result<Vec<TableColumn>, String> {
+    manager
+        .get_table_structure(&connection_id, &table_name, &db_type)
+        .await
+        .map_err(|e| format!("Failed to get table structure: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_table_structures(
+    connection_id: String,
+    tables: Vec<TableRef>,
+    manager: State<'_, ConnectionManager>,
+) -> Result<TableStructures, String> {
+    manager
+        .get_table_structures(&connection_id, &tables)
+        .await
+        .map_err(|e| format!("Failed to get table structures: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_table_data(
+    connection_id: String,
+    table_name: String,
+    schema: Option<String>,
+    db_type: DatabaseType,
+    page: u32,
+    page_size: u32,
+    sort: Option<TableSort>,
+    apply_saved_view: Option<bool>,
+    max_text_length: Option<usize>,
+    manager: State<'_, ConnectionManager>,
+    store: State<'_, AppStore>,
+) -> Result<TableDataPage, String> {
+    let view = if apply_saved_view.unwrap_or(false) {
+        store
+            .get_table_view(&connection_id, schema.as_deref(), &table_name)
+            .map_err(|e| format!("Failed to load saved table view: {}", e))?
+    } else {
+        None
+    };
+
+    let data = manager
+        .get_table_data(
+            &connection_id,
+            &table_name,
+            &db_type,
+            page,
+            page_size,
+            sort.as_ref(),
+            view,
+            max_text_length,
+        )
+        .await
+        .map_err(|e| format!("Failed to get table data: {}", e))?;
+
+    if let (Some(view), false) = (&data.applied_view, data.dropped_columns.is_empty()) {
+        store
+            .save_table_view(view.clone())
+            .map_err(|e| format!("Failed to update saved table view: {}", e))?;
+    }
+
+    Ok(data)
+}
+
+#[tauri::command]
+pub async fn save_table_view(
+    view: TableViewPreferences,
+    store: State<'_, AppStore>,
+) -> Result<TableViewPreferences, String> {
+    store
+        .save_table_view(view)
+        .map_err(|e| format!("Failed to save table view: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_table_view(
+    connection_id: String,
+    schema: Option<String>,
+    table_name: String,
+    db_type: DatabaseType,
+    manager: State<'_, ConnectionManager>,
+    store: State<'_, AppStore>,
+) -> Result<TableViewResponse, String> {
+    let Some(view) = store
+        .get_table_view(&connection_id, schema.as_deref(), &table_name)
+        .map_err(|e| format!("Failed to load table view: {}", e))?
+    else {
+        return Ok(TableViewResponse {
+            view: None,
+            dropped_columns: vec![],
+            notes: vec![],
+        });
+    };
+
+    // Without a live connection the saved view is returned as-is.
+    let Ok((view, dropped_columns)) = manager
+        .reconcile_table_view(&connection_id, &db_type, view.clone())
+        .await
+    else {
+        return Ok(TableViewResponse {
+            view: Some(view),
+            dropped_columns: vec![],
+            notes: vec![],
+        });
+    };
+
+    let view = if dropped_columns.is_empty() {
+        view
+    } else {
+        store
+            .save_table_view(view)
+            .map_err(|e| format!("Failed to update table view: {}", e))?
+    };
+
+    Ok(TableViewResponse {
+        view: Some(view),
+        notes: ConnectionManager::table_view_notes(&dropped_columns),
+        dropped_columns,
+    })
+}
+
+#[tauri::command]
+pub async fn subscribe_table(
+    connection_id: String,
+    db_type: DatabaseType,
+    view_params: TableViewParams,
+    interval_secs: u64,
+    manager: State<'_, ConnectionManager>,
+    store: State<'_, AppStore>,
+    operations: State<'_, OperationRegistry>,
+) -> Result<String, String> {
+    let view = if view_params.apply_saved_view {
+        store
+            .get_table_view(&connection_id, view_params.schema.as_deref(), &view_params.table_name)
+            .map_err(|e| format!("Failed to load saved table view: {}", e))?
+    } else {
+        None
+    };
+    let operation = operations.start(
+        None,
+        "table_subscription",
+        Some(&connection_id),
+        &format!("Auto-refresh {}", view_params.table_name),
+    )
+    .map_err(|e| format!("Failed to subscribe to table: {}", e))?;
+    let subscription = TableSubscription {
+        connection_id,
+        db_type,
+        params: view_params,
+        view,
+        interval: std::time::Duration::from_secs(interval_secs.max(MIN_REFRESH_SECONDS)),
+    };
+    manager
+        .subscribe_table(subscription, operation)
+        .await
+        .map_err(|e| format!("Failed to subscribe to table: {}", e))
+}
+
+/// Stops an auto-refresh; false when it had already ended.
+#[tauri::command]
+pub async fn unsubscribe_table(
+    subscription_id: String,
+    operations: State<'_, OperationRegistry>,
+) -> Result<bool, String> {
+    operations
+        .cancel(&subscription_id)
+        .map_err(|e| format!("Failed to unsubscribe from table: {}", e))
+}
+
+#[tauri::command]
+pub async fn create_sqlite_database(
+    file_path: String,
+    options: Option<CreateSqliteDatabaseOptions>,
+) -> Result<ConnectionConfig, String> {
+    ConnectionManager::create_sqlite_database(&file_path, &options.unwrap_or_default())
+        .await
+        .map_err(|e| format!("Failed to create SQLite database: {}", e))
+}
+
+#[tauri::command]
+pub async fn quick_connect_sqlite(
+    file_path: String,
+    options: Option<SqliteQuickConnectOptions>,
+    manager: State<'_, ConnectionManager>,
+    store: State<'_, AppStore>,
+) -> Result<ConnectionConfig, String> {
+    let config = manager
+        .quick_connect_sqlite(&file_path, &options.unwrap_or_default())
+        .await
+        .map_err(|e| format!("Failed to open SQLite file: {}", e))?;
+
+    store
+        .record_recent_sqlite_file(RecentSqliteFile {
+            file_path: config.file_path.clone().unwrap_or(file_path),
+            connection_id: config.id.clone(),
+            name: config.name.clone(),
+            last_opened_at: String::new(),
+            missing: false,
+        })
+        .map_err(|e| format!("Failed to record recent file: {}", e))?;
+
+    Ok(config)
+}
+
+#[tauri::command]
+pub async fn list_recent_sqlite_files(
+    store: State<'_, AppStore>,
+) -> Result<Vec<RecentSqliteFile>, String> {
+    store
+        .list_recent_sqlite_files()
+        .map_err(|e| format!("Failed to list recent files: {}", e))
+}
+
+#[tauri::command]
+pub async fn remove_recent_file(file_path: String, store: State<'_, AppStore>) -> Result<bool, String> {
+    store
+        .remove_recent_sqlite_file(&file_path)
+        .map_err(|e| format!("Failed to remove recent file: {}", e))
+}
+
+#[tauri::command]
+pub async fn export_app_data(file_path: String, store: State<'_, AppStore>) -> Result<String, String> {
+    store
+        .export_backup(&file_path)
+        .map_err(|e| format!("Failed to export app data: {}", e))?;
+
+    Ok(format!("Successfully exported app data to {}", file_path))
+}
+
+#[tauri::command]
+pub async fn get_app_store_status(store: State<'_, AppStore>) -> Result<AppStoreStatus, String> {
+    store
+        .status()
+        .map_err(|e| format!("Failed to check the local store: {}", e))
+}
+
+#[tauri::command]
+pub async fn repair_app_store(store: State<'_, AppStore>) -> Result<StoreRepair, String> {
+    store
+        .repair()
+        .map_err(|e| format!("Failed to repair the local store: {}", e))
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn execute_query(
+    connection_id: String,
+    query: String,
+    max_text_length: Option<usize>,
+    pin_result: Option<bool>,
+    pin_ttl_minutes: Option<u64>,
+    use_primary: Option<bool>,
+    query_id: Option<String>,
     manager: State<'_, ConnectionManager>,
-) -> Result<String, String> {
+) -> Result<QueryResult, String> {
+    let pin_ttl = pin_result.unwrap_or(false).then(|| {
+        std::time::Duration::from_secs(60 * pin_ttl_minutes.unwrap_or(DEFAULT_PIN_TTL_MINUTES))
+    });
+    let run = manager.execute_query_for_display(
+        &connection_id,
+        &query,
+        max_text_length,
+        pin_ttl,
+        use_primary.unwrap_or(false),
+    );
+    match query_id {
+        Some(query_id) => manager.run_as_query(&connection_id, &query_id, run).await,
+        None => run.await,
+    }
+    .map_err(|e| format!("Failed to execute query: {}", e))
+}
+
+#[tauri::command]
+pub async fn cancel_query(
+    connection_id: String,
+    query_id: String,
+    manager: State<'_, ConnectionManager>,
+) -> Result<bool, String> {
     manager
-        .connect(config.clone())
+        .cancel_query(&connection_id, &query_id)
         .await
-        .map_err(|e| format!("Failed to connect: {}", e))?;
-
-    Ok(format!("Successfully connected to {}", config.name))
+        .map_err(|e| format!("Failed to cancel query: {}", e))
 }
 
 #[tauri::command]
-pub async fn disconnect_database(
+pub async fn execute_query_with_params(
     connection_id: String,
+    query: String,
+    params: Vec<serde_json::Value>,
     manager: State<'_, ConnectionManager>,
-) -> Result<String, String> {
+) -> Result<QueryResult, String> {
     manager
-        .disconnect(&connection_id)
+        .execute_query_with_params(&connection_id, &query, &params)
         .await
-        .map_err(|e| format!("Failed to disconnect: {}", e))?;
-
-    Ok("Successfully disconnected".to_string())
+        .map_err(|e| format!("Failed to execute query: {}", e))
 }
 
 #[tauri::command]
-pub async fn list_tables(
+pub async fn execute_statement_at(
     connection_id: String,
-    db_type: DatabaseType,
+    sql: String,
+    cursor_offset: usize,
+    options: Option<StatementRunOptions>,
     manager: State<'_, ConnectionManager>,
-) -> Result<Vec<DatabaseTable>, String> {
+) -> Result<Vec<ExecutedStatement>, String> {
     manager
-        .list_tables(&connection_id, &db_type)
+        .execute_statement_at(&connection_id, &sql, cursor_offset, &options.unwrap_or_default())
         .await
-        .map_err(|e| format!("Failed to list tables: {}", e))
+        .map_err(|e| format!("Failed to execute statement: {}", e))
 }
 
 #[tauri::command]
-pub async fn get_table_structure(
+pub async fn execute_multi(
     connection_id: String,
-    table_name: String,
-    db_type: DatabaseType,
+    sql: String,
     manager: State<'_, ConnectionManager>,
-) -> Result<Vec<TableColumn>, String> {
+) -> Result<MultiQueryResult, String> {
     manager
-        .get_table_structure(&connection_id, &table_name, &db_type)
+        .execute_multi(&connection_id, &sql)
         .await
-        .map_err(|e| format!("Failed to get table structure: {}", e))
+        .map_err(|e| format!("Failed to execute batch: {}", e))
 }
 
 #[tauri::command]
-pub async fn execute_query(
+pub async fn get_result_lineage(
     connection_id: String,
     query: String,
     manager: State<'_, ConnectionManager>,
-) -> Result<QueryResult, String> {
+) -> Result<ResultLineage, String> {
     manager
-        .execute_query(&connection_id, &query)
+        .result_lineage(&connection_id, &query)
         .await
-        .map_err(|e| format!("Failed to execute query: {}", e))
+        .map_err(|e| format!("Failed to get result lineage: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_pinned_result_page(
+    result_id: String,
+    page: u32,
+    page_size: Option<u32>,
+    sort: Option<TableSort>,
+    manager: State<'_, ConnectionManager>,
+) -> Result<PinnedResultPage, String> {
+    manager
+        .get_pinned_result_page(&result_id, page, page_size, sort.as_ref())
+        .await
+        .map_err(|e| format!("Failed to read pinned result: {}", e))
+}
+
+#[tauri::command]
+pub async fn export_pinned_result(
+    result_id: String,
+    format: PinnedExportFormat,
+    path: String,
+    manager: State<'_, ConnectionManager>,
+) -> Result<String, String> {
+    let rows = manager
+        .export_pinned_result(&result_id, format, &path)
+        .await
+        .map_err(|e| format!("Failed to export pinned result: {}", e))?;
+
+    Ok(format!("Exported {} row(s) to {}", rows, path))
+}
+
+#[tauri::command]
+pub async fn diff_pinned_results(
+    a: String,
+    b: String,
+    key_columns: Vec<String>,
+    manager: State<'_, ConnectionManager>,
+) -> Result<PinnedResultDiff, String> {
+    manager
+        .diff_pinned_results(&a, &b, &key_columns)
+        .await
+        .map_err(|e| format!("Failed to diff pinned results: {}", e))
+}
+
+#[tauri::command]
+pub async fn drop_pinned_result(
+    result_id: String,
+    manager: State<'_, ConnectionManager>,
+) -> Result<bool, String> {
+    manager
+        .drop_pinned_result(&result_id)
+        .await
+        .map_err(|e| format!("Failed to drop pinned result: {}", e))
+}
+
+#[tauri::command]
+pub async fn aggregate_result_column(
+    source: AggregateSource,
+    column: String,
+    functions: Vec<AggregateFunction>,
+    manager: State<'_, ConnectionManager>,
+) -> Result<ColumnAggregates, String> {
+    manager
+        .aggregate_result_column(&source, &column, &functions)
+        .await
+        .map_err(|e| format!("Failed to aggregate column: {}", e))
+}
+
+#[tauri::command]
+pub async fn fetch_cell_text(
+    connection_id: String,
+    table_name: String,
+    key: serde_json::Value,
+    column_name: String,
+    range: Option<CellTextRange>,
+    db_type: DatabaseType,
+    manager: State<'_, ConnectionManager>,
+) -> Result<CellText, String> {
+    manager
+        .fetch_cell_text(&connection_id, &table_name, &key, &column_name, range.as_ref(), &db_type)
+        .await
+        .map_err(|e| format!("Failed to fetch cell text: {}", e))
 }
 
 #[tauri::command]
@@ -91,16 +982,70 @@ pub async fn explain_query(
         .map_err(|e| format!("Failed to explain query: {}", e))
 }
 
+#[tauri::command]
+pub async fn analyze_query_patterns(
+    connection_id: String,
+    query: String,
+    db_type: DatabaseType,
+    manager: State<'_, ConnectionManager>,
+) -> Result<Vec<QueryPatternFinding>, String> {
+    manager
+        .analyze_query_patterns(&connection_id, &query, &db_type)
+        .await
+        .map_err(|e| format!("Failed to analyze query: {}", e))
+}
+
+#[tauri::command]
+pub async fn translate_sql(
+    sql: String,
+    from_dialect: DatabaseType,
+    to_dialect: DatabaseType,
+) -> Result<TranslatedSql, String> {
+    ConnectionManager::translate_sql(&sql, &from_dialect, &to_dialect)
+        .map_err(|e| format!("Failed to translate SQL: {}", e))
+}
+
+#[tauri::command]
+pub async fn execute_with_plan(
+    connection_id: String,
+    query: String,
+    include_rows: Option<bool>,
+    row_limit: Option<usize>,
+    manager: State<'_, ConnectionManager>,
+) -> Result<QueryWithPlan, String> {
+    manager
+        .execute_with_plan(&connection_id, &query, include_rows.unwrap_or(true), row_limit)
+        .await
+        .map_err(|e| format!("Failed to run query with plan: {}", e))
+}
+
+/// Per-column problems with `data` for `table_name`, without writing anything.
+#[tauri::command]
+pub async fn validate_row_values(
+    connection_id: String,
+    table_name: String,
+    data: serde_json::Value,
+    db_type: DatabaseType,
+    insert: Option<bool>,
+    manager: State<'_, ConnectionManager>,
+) -> Result<Vec<ColumnViolation>, String> {
+    manager
+        .validate_row_values(&connection_id, &table_name, &data, &db_type, insert.unwrap_or(false))
+        .await
+        .map_err(|e| format!("Failed to validate row: {}", e))
+}
+
 #[tauri::command]
 pub async fn insert_row(
     connection_id: String,
     table_name: String,
     data: serde_json::Value,
     db_type: DatabaseType,
+    skip_validation: Option<bool>,
     manager: State<'_, ConnectionManager>,
 ) -> Result<String, String> {
     manager
-        .insert_row(&connection_id, &table_name, data, &db_type)
+        .insert_row(&connection_id, &table_name, data, &db_type, skip_validation.unwrap_or(false))
         .await
         .map_err(|e| format!("Failed to insert row: {}", e))
 }
@@ -111,12 +1056,160 @@ pub async fn bulk_insert_rows(
     table_name: String,
     rows: Vec<serde_json::Value>,
     db_type: DatabaseType,
+    operation_id: Option<String>,
     manager: State<'_, ConnectionManager>,
+    operations: State<'_, OperationRegistry>,
 ) -> Result<String, String> {
+    let operation = operations.start(
+        operation_id,
+        "bulk_insert",
+        Some(&connection_id),
+        &format!("Inserting {} rows into {}", rows.len(), table_name),
+    )
+    .map_err(|e| format!("Failed to bulk insert rows: {}", e))?;
+    let result = manager
+        .bulk_insert_rows(&connection_id, &table_name, rows, &db_type, &operation)
+        .await;
+    operation.finish(&result);
+    result.map_err(|e| format!("Failed to bulk insert rows: {}", e))
+}
+
+#[tauri::command]
+pub async fn clone_row(
+    connection_id: String,
+    table_name: String,
+    key: serde_json::Value,
+    overrides: Option<serde_json::Map<String, serde_json::Value>>,
+    exclude_columns: Option<Vec<String>>,
+    db_type: DatabaseType,
+    manager: State<'_, ConnectionManager>,
+) -> Result<CloneRowResult, String> {
+    let mut results = manager
+        .clone_rows(
+            &connection_id,
+            &table_name,
+            vec![key],
+            &overrides.unwrap_or_default(),
+            &exclude_columns.unwrap_or_default(),
+            &db_type,
+        )
+        .await
+        .map_err(|e| format!("Failed to clone row: {}", e))?;
+    results
+        .pop()
+        .ok_or_else(|| "Failed to clone row: no result".to_string())
+}
+
+#[tauri::command]
+pub async fn preview_delete_cascade(
+    connection_id: String,
+    table_name: String,
+    keys: Vec<serde_json::Value>,
+    options: Option<CascadePreviewOptions>,
+    db_type: DatabaseType,
+    manager: State<'_, ConnectionManager>,
+) -> Result<CascadePreview, String> {
+    manager
+        .preview_delete_cascade(
+            &connection_id,
+            &table_name,
+            keys,
+            &options.unwrap_or_default(),
+            &db_type,
+        )
+        .await
+        .map_err(|e| format!("Failed to preview delete cascade: {}", e))
+}
+
+#[tauri::command]
+pub async fn clone_rows(
+    connection_id: String,
+    table_name: String,
+    keys: Vec<serde_json::Value>,
+    overrides: Option<serde_json::Map<String, serde_json::Value>>,
+    exclude_columns: Option<Vec<String>>,
+    db_type: DatabaseType,
+    manager: State<'_, ConnectionManager>,
+) -> Result<Vec<CloneRowResult>, String> {
     manager
-        .bulk_insert_rows(&connection_id, &table_name, rows, &db_type)
+        .clone_rows(
+            &connection_id,
+            &table_name,
+            keys,
+            &overrides.unwrap_or_default(),
+            &exclude_columns.unwrap_or_default(),
+            &db_type,
+        )
         .await
-        .map_err(|e| format!("Failed to bulk insert rows: {}", e))
+        .map_err(|e| format!("Failed to clone rows: {}", e))
+}
+
+#[tauri::command]
+pub async fn set_cell_from_file(
+    connection_id: String,
+    table_name: String,
+    key: serde_json::Value,
+    column_name: String,
+    file_path: String,
+    db_type: DatabaseType,
+    operation_id: Option<String>,
+    manager: State<'_, ConnectionManager>,
+    operations: State<'_, OperationRegistry>,
+) -> Result<CellFileTransfer, String> {
+    let operation = operations.start(
+        operation_id,
+        "cell_upload",
+        Some(&connection_id),
+        &format!("Loading {} into {}.{}", file_path, table_name, column_name),
+    )
+    .map_err(|e| format!("Failed to set cell from file: {}", e))?;
+    let result = manager
+        .set_cell_from_file(
+            &connection_id,
+            &table_name,
+            &key,
+            &column_name,
+            &file_path,
+            &db_type,
+            &operation,
+        )
+        .await;
+    operation.finish(&result);
+    result.map_err(|e| format!("Failed to set cell from file: {}", e))
+}
+
+#[tauri::command]
+pub async fn save_cell_to_file(
+    connection_id: String,
+    table_name: String,
+    key: serde_json::Value,
+    column_name: String,
+    file_path: String,
+    db_type: DatabaseType,
+    operation_id: Option<String>,
+    manager: State<'_, ConnectionManager>,
+    operations: State<'_, OperationRegistry>,
+) -> Result<CellFileTransfer, String> {
+    let operation = operations.start(
+        operation_id,
+        "cell_download",
+        Some(&connection_id),
+        &format!("Saving {}.{} to {}", table_name, column_name, file_path),
+    )
+    .map_err(|e| format!("Failed to save cell to file: {}", e))?;
+    let result = manager
+        .save_cell_to_file(
+            &connection_id,
+            &table_name,
+            &key,
+            &column_name,
+            &file_path,
+            &db_type,
+            &operation,
+        )
+        .await;
+    operation.finish(&result);
+    result.map_err(|e| format!("Failed to save cell to file: {}", e))
 }
 
 #[tauri::command]
@@ -126,39 +1219,166 @@ pub async fn update_row(
     data: serde_json::Value,
     where_clause: String,
     db_type: DatabaseType,
+    skip_validation: Option<bool>,
+    manager: State<'_, ConnectionManager>,
+) -> Result<String, String> {
+    manager
+        .update_row(
+            &connection_id,
+            &table_name,
+            data,
+            &where_clause,
+            &db_type,
+            skip_validation.unwrap_or(false),
+        )
+        .await
+        .map_err(|e| format!("Failed to update row: {}", e))
+}
+
+#[tauri::command]
+pub async fn delete_rows(
+    connection_id: String,
+    table_name: String,
+    where_clause: String,
+    manager: State<'_, ConnectionManager>,
+) -> Result<String, String> {
+    manager
+        .delete_rows(&connection_id, &table_name, &where_clause)
+        .await
+        .map_err(|e| format!("Failed to delete rows: {}", e))
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn update_rows_by_filter(
+    connection_id: String,
+    table_name: String,
+    set: BTreeMap<String, UpdateValue>,
+    filters: Vec<RowFilter>,
+    options: Option<UpdateRowsOptions>,
+    db_type: DatabaseType,
+    manager: State<'_, ConnectionManager>,
+    store: State<'_, AppStore>,
+) -> Result<UpdateRowsResult, String> {
+    // Usage counts are advisory; failing to save them doesn't fail the update.
+    let _ = store.record_filter_usage(&connection_id, &table_name, &filters);
+    manager
+        .update_rows_by_filter(
+            &connection_id,
+            &table_name,
+            &set,
+            &filters,
+            &options.unwrap_or_default(),
+            &db_type,
+        )
+        .await
+        .map_err(|e| format!("Failed to update rows: {}", e))
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn replace_in_table(
+    connection_id: String,
+    table_name: String,
+    columns: Option<Vec<String>>,
+    find: String,
+    replace: String,
+    options: ReplaceOptions,
+    db_type: DatabaseType,
+    manager: State<'_, ConnectionManager>,
+) -> Result<ReplaceResult, String> {
+    manager
+        .replace_in_table(&connection_id, &table_name, columns.as_deref(), &find, &replace, &options, &db_type)
+        .await
+        .map_err(|e| format!("Failed to replace text: {}", e))
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn batched_delete(
+    connection_id: String,
+    table_name: String,
+    filters: Vec<RowFilter>,
+    options: Option<BatchedDeleteOptions>,
+    db_type: DatabaseType,
+    operation_id: Option<String>,
+    manager: State<'_, ConnectionManager>,
+    operations: State<'_, OperationRegistry>,
+    store: State<'_, AppStore>,
+) -> Result<BatchedDeleteResult, String> {
+    let _ = store.record_filter_usage(&connection_id, &table_name, &filters);
+    let operation = operations.start(
+        operation_id,
+        "batched_delete",
+        Some(&connection_id),
+        &format!("Deleting rows from {} in batches", table_name),
+    )
+    .map_err(|e| format!("Failed to delete rows: {}", e))?;
+    let result = manager
+        .batched_delete(
+            &connection_id,
+            &table_name,
+            &filters,
+            &options.unwrap_or_default(),
+            &db_type,
+            &operation,
+        )
+        .await;
+    operation.finish(&result);
+    result.map_err(|e| format!("Failed to delete rows: {}", e))
+}
+
+#[tauri::command]
+pub async fn create_table(
+    connection_id: String,
+    table_name: String,
+    columns: Vec<(String, String, bool, bool)>,
+    db_type: DatabaseType,
+    target: Option<ObjectTarget>,
+    manager: State<'_, ConnectionManager>,
+) -> Result<String, String> {
+    manager
+        .create_table(&connection_id, &table_name, columns, &db_type, target.unwrap_or_default())
+        .await
+        .map_err(|e| format!("Failed to create table: {}", e))
+}
+
+#[tauri::command]
+pub async fn create_table_from_text(
+    connection_id: String,
+    text: String,
+    options: PasteTableOptions,
     manager: State<'_, ConnectionManager>,
-) -> Result<String, String> {
+) -> Result<PastedTable, String> {
     manager
-        .update_row(&connection_id, &table_name, data, &where_clause, &db_type)
+        .create_table_from_text(&connection_id, &text, &options)
         .await
-        .map_err(|e| format!("Failed to update row: {}", e))
+        .map_err(|e| format!("Failed to create table from text: {}", e))
 }
 
+/// Tables and views in the connection's scratch schema.
 #[tauri::command]
-pub async fn delete_rows(
+pub async fn list_scratch_objects(
     connection_id: String,
-    table_name: String,
-    where_clause: String,
     manager: State<'_, ConnectionManager>,
-) -> Result<String, String> {
+) -> Result<Vec<ScratchObject>, String> {
     manager
-        .delete_rows(&connection_id, &table_name, &where_clause)
+        .list_scratch_objects(&connection_id)
         .await
-        .map_err(|e| format!("Failed to delete rows: {}", e))
+        .map_err(|e| format!("Failed to list scratch objects: {}", e))
 }
 
+/// Drops stale scratch objects; a dry run lists them and returns the token the drop needs.
 #[tauri::command]
-pub async fn create_table(
+pub async fn cleanup_scratch(
     connection_id: String,
-    table_name: String,
-    columns: Vec<(String, String, bool, bool)>,
-    db_type: DatabaseType,
+    options: Option<ScratchCleanupOptions>,
     manager: State<'_, ConnectionManager>,
-) -> Result<String, String> {
+) -> Result<ScratchCleanupResult, String> {
     manager
-        .create_table(&connection_id, &table_name, columns, &db_type)
+        .cleanup_scratch(&connection_id, &options.unwrap_or_default())
         .await
-        .map_err(|e| format!("Failed to create table: {}", e))
+        .map_err(|e| format!("Failed to clean up scratch schema: {}", e))
 }
 
 #[tauri::command]
@@ -254,6 +1474,243 @@ pub async fn export_table_structure(
         .map_err(|e| format!("Failed to export table structure: {}", e))
 }
 
+#[tauri::command]
+pub async fn checksum_table(
+    connection_id: String,
+    table_name: String,
+    options: Option<ChecksumOptions>,
+    db_type: DatabaseType,
+    manager: State<'_, ConnectionManager>,
+) -> Result<TableChecksum, String> {
+    manager
+        .checksum_table(&connection_id, &table_name, &options.unwrap_or_default(), &db_type)
+        .await
+        .map_err(|e| format!("Failed to checksum table: {}", e))
+}
+
+#[tauri::command]
+pub async fn compare_table_checksums(
+    left: ChecksumTarget,
+    right: ChecksumTarget,
+    options: Option<ChecksumOptions>,
+    manager: State<'_, ConnectionManager>,
+) -> Result<ChecksumComparison, String> {
+    let options = options.unwrap_or_default();
+    let left = manager
+        .checksum_table(&left.connection_id, &left.table_name, &options, &left.db_type)
+        .await
+        .map_err(|e| format!("Failed to checksum {}: {}", left.table_name, e))?;
+    let right = manager
+        .checksum_table(&right.connection_id, &right.table_name, &options, &right.db_type)
+        .await
+        .map_err(|e| format!("Failed to checksum {}: {}", right.table_name, e))?;
+
+    Ok(crate::database::checksum::compare(left, right))
+}
+
+#[tauri::command]
+pub async fn generate_code(
+    connection_id: String,
+    table_name: String,
+    target: String,
+    type_overrides: Option<std::collections::HashMap<String, String>>,
+    db_type: DatabaseType,
+    manager: State<'_, ConnectionManager>,
+) -> Result<GeneratedCode, String> {
+    manager
+        .generate_code(
+            &connection_id,
+            &table_name,
+            &target,
+            &type_overrides.unwrap_or_default(),
+            &db_type,
+        )
+        .await
+        .map_err(|e| format!("Failed to generate code: {}", e))
+}
+
+#[tauri::command]
+pub async fn export_table_csv(
+    connection_id: String,
+    table_name: String,
+    file_path: String,
+    db_type: DatabaseType,
+    operation_id: Option<String>,
+    manager: State<'_, ConnectionManager>,
+    operations: State<'_, OperationRegistry>,
+) -> Result<String, String> {
+    let operation = operations.start(
+        operation_id,
+        "export_csv",
+        Some(&connection_id),
+        &format!("Exporting {} to {}", table_name, file_path),
+    )
+    .map_err(|e| format!("Failed to export table to CSV: {}", e))?;
+    let result = manager
+        .export_table_csv(&connection_id, &table_name, &file_path, &db_type, &operation)
+        .await;
+    operation.finish(&result);
+    let rows = result.map_err(|e| format!("Failed to export table to CSV: {}", e))?;
+
+    Ok(format!("Exported {} row(s) to {}", rows, file_path))
+}
+
+#[tauri::command]
+pub async fn get_server_metrics(
+    connection_id: String,
+    manager: State<'_, ConnectionManager>,
+) -> Result<ServerMetrics, String> {
+    manager
+        .get_server_metrics(&connection_id)
+        .await
+        .map_err(|e| format!("Failed to get server metrics: {}", e))
+}
+
+#[tauri::command]
+pub async fn open_console_session(
+    connection_id: String,
+    idle_timeout_seconds: Option<u64>,
+    manager: State<'_, ConnectionManager>,
+) -> Result<String, String> {
+    manager
+        .open_console_session(
+            &connection_id,
+            idle_timeout_seconds.map(std::time::Duration::from_secs),
+        )
+        .await
+        .map_err(|e| format!("Failed to open console session: {}", e))
+}
+
+#[tauri::command]
+pub async fn execute_console(
+    session_id: String,
+    sql: String,
+    manager: State<'_, ConnectionManager>,
+) -> Result<QueryResult, String> {
+    manager
+        .execute_console(&session_id, &sql)
+        .await
+        .map_err(|e| format!("Query failed: {}", e))
+}
+
+#[tauri::command]
+pub async fn close_console_session(
+    session_id: String,
+    manager: State<'_, ConnectionManager>,
+) -> Result<(), String> {
+    manager
+        .close_console_session(&session_id)
+        .await
+        .map_err(|e| format!("Failed to close console session: {}", e))
+}
+
+#[tauri::command]
+pub async fn cross_query(
+    request: CrossQueryRequest,
+    operation_id: Option<String>,
+    manager: State<'_, ConnectionManager>,
+    operations: State<'_, OperationRegistry>,
+) -> Result<CrossQueryResult, String> {
+    let operation = operations.start(
+        operation_id,
+        "cross_query",
+        Some(&request.connection_id),
+        &format!("Joining {} from another connection", request.source.table_name),
+    )
+    .map_err(|e| format!("Failed to run cross-connection query: {}", e))?;
+    let result = manager.cross_query(&request, &operation).await;
+    operation.finish(&result);
+    result.map_err(|e| format!("Failed to run cross-connection query: {}", e))
+}
+
+#[tauri::command]
+pub async fn guarded_execute(
+    connection_id: String,
+    sql: String,
+    review_seconds: Option<u64>,
+    manager: State<'_, ConnectionManager>,
+) -> Result<GuardedExecution, String> {
+    manager
+        .guarded_execute(&connection_id, &sql, review_seconds)
+        .await
+        .map_err(|e| format!("Guarded execution failed: {}", e))
+}
+
+#[tauri::command]
+pub async fn commit_guard(
+    guard_id: String,
+    manager: State<'_, ConnectionManager>,
+) -> Result<GuardResolved, String> {
+    manager
+        .commit_guard(&guard_id)
+        .await
+        .map_err(|e| format!("Failed to commit guarded write: {}", e))
+}
+
+#[tauri::command]
+pub async fn rollback_guard(
+    guard_id: String,
+    manager: State<'_, ConnectionManager>,
+) -> Result<GuardResolved, String> {
+    manager
+        .rollback_guard(&guard_id)
+        .await
+        .map_err(|e| format!("Failed to roll back guarded write: {}", e))
+}
+
+#[tauri::command]
+pub async fn list_active_connections(
+    manager: State<'_, ConnectionManager>,
+) -> Result<Vec<ActiveConnectionInfo>, String> {
+    Ok(manager.list_active_connections().await)
+}
+
+#[tauri::command]
+pub async fn get_query_log(
+    connection_id: Option<String>,
+    limit: Option<usize>,
+    manager: State<'_, ConnectionManager>,
+) -> Result<Vec<QueryLogEntry>, String> {
+    Ok(manager.get_query_log(connection_id.as_deref(), limit.unwrap_or(100)))
+}
+
+#[tauri::command]
+pub async fn configure_telemetry(
+    settings: TelemetrySettings,
+    manager: State<'_, ConnectionManager>,
+) -> Result<TelemetryStatus, String> {
+    manager
+        .configure_telemetry(settings)
+        .map_err(|e| format!("Failed to configure telemetry: {}", e))
+}
+
+#[tauri::command]
+pub async fn set_telemetry_enabled(
+    enabled: bool,
+    manager: State<'_, ConnectionManager>,
+) -> Result<TelemetryStatus, String> {
+    manager
+        .set_telemetry_enabled(enabled)
+        .map_err(|e| format!("Failed to update telemetry: {}", e))
+}
+
+#[tauri::command]
+pub async fn list_operations(
+    operations: State<'_, OperationRegistry>,
+) -> Result<Vec<OperationInfo>, String> {
+    Ok(operations.list())
+}
+
+#[tauri::command]
+pub async fn cancel_operation(
+    operation_id: String,
+    operations: State<'_, OperationRegistry>,
+) -> Result<bool, String> {
+    operations
+        .cancel(&operation_id)
+        .map_err(|e| format!("Failed to cancel operation: {}", e))
+}
+
 #[tauri::command]
 pub async fn get_table_constraints(
     connection_id: String,
@@ -390,6 +1847,144 @@ pub async fn get_postgres_extensions(
         .map_err(|e| format!("Failed to get PostgreSQL extensions: {}", e))
 }
 
+#[tauri::command]
+pub async fn list_extensions(
+    connection_id: String,
+    manager: State<'_, ConnectionManager>,
+) -> Result<Vec<ExtensionInfo>, String> {
+    manager
+        .list_extensions(&connection_id)
+        .await
+        .map_err(|e| format!("Failed to list extensions: {}", e))
+}
+
+#[tauri::command]
+pub async fn create_extension(
+    connection_id: String,
+    name: String,
+    options: Option<CreateExtensionOptions>,
+    manager: State<'_, ConnectionManager>,
+) -> Result<ExtensionChangeResult, String> {
+    manager
+        .create_extension(&connection_id, &name, &options.unwrap_or_default())
+        .await
+        .map_err(|e| format!("Failed to create extension: {}", e))
+}
+
+#[tauri::command]
+pub async fn drop_extension(
+    connection_id: String,
+    name: String,
+    options: Option<DropExtensionOptions>,
+    manager: State<'_, ConnectionManager>,
+) -> Result<ExtensionChangeResult, String> {
+    manager
+        .drop_extension(&connection_id, &name, &options.unwrap_or_default())
+        .await
+        .map_err(|e| format!("Failed to drop extension: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_sql_functions(
+    connection_id: String,
+    refresh: Option<bool>,
+    manager: State<'_, ConnectionManager>,
+) -> Result<SqlCatalog, String> {
+    manager
+        .get_sql_functions(&connection_id, refresh.unwrap_or(false))
+        .await
+        .map_err(|e| format!("Failed to load SQL functions: {}", e))
+}
+
+#[tauri::command]
+pub async fn escape_identifier(db_type: DatabaseType, name: String) -> Result<String, String> {
+    escaping::escape_identifier(&name, &db_type)
+        .map_err(|e| format!("Failed to escape identifier: {}", e))
+}
+
+#[tauri::command]
+pub async fn escape_literal(db_type: DatabaseType, value: String) -> Result<String, String> {
+    escaping::escape_literal(&value, &db_type)
+        .map_err(|e| format!("Failed to escape literal: {}", e))
+}
+
+#[tauri::command]
+pub async fn quote_qualified(
+    db_type: DatabaseType,
+    schema: Option<String>,
+    table: String,
+) -> Result<String, String> {
+    escaping::quote_qualified(schema.as_deref(), &table, &db_type)
+        .map_err(|e| format!("Failed to quote table name: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_connection_capabilities(
+    connection_id: String,
+    manager: State<'_, ConnectionManager>,
+) -> Result<ConnectionCapabilities, String> {
+    manager
+        .get_connection_capabilities(&connection_id)
+        .await
+        .map_err(|e| format!("Failed to get connection capabilities: {}", e))
+}
+
+#[tauri::command]
+pub async fn list_unknown_types(
+    connection_id: String,
+    manager: State<'_, ConnectionManager>,
+) -> Result<Vec<UnknownColumnType>, String> {
+    Ok(manager.list_unknown_types(&connection_id))
+}
+
+#[tauri::command]
+pub async fn set_decode_overrides(
+    connection_id: String,
+    overrides: BTreeMap<String, DecodeStrategy>,
+    manager: State<'_, ConnectionManager>,
+) -> Result<(), String> {
+    manager
+        .set_decode_overrides(&connection_id, overrides)
+        .await
+        .map_err(|e| format!("Failed to set decode overrides: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_slow_queries(
+    connection_id: String,
+    limit: Option<u32>,
+    manager: State<'_, ConnectionManager>,
+) -> Result<Vec<SlowQuery>, String> {
+    manager
+        .get_slow_queries(&connection_id, limit)
+        .await
+        .map_err(|e| format!("Failed to get slow queries: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_top_queries(
+    connection_id: String,
+    order_by: Option<TopQueryOrder>,
+    limit: Option<u32>,
+    manager: State<'_, ConnectionManager>,
+) -> Result<TopQueriesReport, String> {
+    manager
+        .get_top_queries(&connection_id, order_by.unwrap_or_default(), limit)
+        .await
+        .map_err(|e| format!("Failed to get top queries: {}", e))
+}
+
+#[tauri::command]
+pub async fn reset_statement_stats(
+    connection_id: String,
+    manager: State<'_, ConnectionManager>,
+) -> Result<(), String> {
+    manager
+        .reset_statement_stats(&connection_id)
+        .await
+        .map_err(|e| format!("Failed to reset statement statistics: {}", e))
+}
+
 #[tauri::command]
 pub async fn get_postgres_table_privileges(
     connection_id: String,
@@ -402,6 +1997,29 @@ pub async fn get_postgres_table_privileges(
         .map_err(|e| format!("Failed to get PostgreSQL table privileges: {}", e))
 }
 
+#[tauri::command]
+pub async fn get_table_permissions(
+    connection_id: String,
+    table_name: String,
+    manager: State<'_, ConnectionManager>,
+) -> Result<TablePermissions, String> {
+    manager
+        .get_table_permissions(&connection_id, &table_name)
+        .await
+        .map_err(|e| format!("Failed to get table permissions: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_database_permissions(
+    connection_id: String,
+    manager: State<'_, ConnectionManager>,
+) -> Result<DatabasePermissions, String> {
+    manager
+        .get_database_permissions(&connection_id)
+        .await
+        .map_err(|e| format!("Failed to get database permissions: {}", e))
+}
+
 #[tauri::command]
 pub async fn create_new_window(app: tauri::AppHandle) -> Result<(), String> {
     let label = format!("nodadb-window-{}", Utc::now().timestamp_millis());
@@ -504,6 +2122,19 @@ pub async fn trace_id_relations(
         .map_err(|e| format!("Relation search failed: {}", e))
 }
 
+#[tauri::command]
+pub async fn expand_result_row(
+    connection_id: String,
+    request: ExpandRowRequest,
+    db_type: DatabaseType,
+    manager: State<'_, ConnectionManager>,
+) -> Result<ExpandedRow, String> {
+    manager
+        .expand_result_row(&connection_id, &request, &db_type)
+        .await
+        .map_err(|e| format!("Failed to expand row: {}", e))
+}
+
 #[tauri::command]
 pub async fn get_relation_rows(
     connection_id: String,
@@ -521,3 +2152,39 @@ pub async fn get_relation_rows(
         .map_err(|e| format!("Failed to fetch page: {}", e))
 }
 
+#[tauri::command]
+pub async fn match_keys(
+    connection_id: String,
+    table_name: String,
+    column_name: String,
+    keys: Vec<serde_json::Value>,
+    options: Option<KeyMatchOptions>,
+    db_type: DatabaseType,
+    manager: State<'_, ConnectionManager>,
+) -> Result<KeyMatchResult, String> {
+    manager
+        .match_keys(
+            &connection_id,
+            &table_name,
+            &column_name,
+            keys,
+            &options.unwrap_or_default(),
+            &db_type,
+        )
+        .await
+        .map_err(|e| format!("Failed to match keys: {}", e))
+}
+
+#[tauri::command]
+pub async fn compare_keys(
+    source: KeySource,
+    target: KeyTarget,
+    options: Option<KeyMatchOptions>,
+    manager: State<'_, ConnectionManager>,
+) -> Result<KeyMatchResult, String> {
+    manager
+        .compare_keys(&source, &target, &options.unwrap_or_default())
+        .await
+        .map_err(|e| format!("Failed to compare keys: {}", e))
+}
+
@@ -0,0 +1,208 @@
+use crate::models::{AggregateFunction, AggregatePath, AggregateValue, ColumnAggregates};
+use std::collections::HashSet;
+
+/// Functions that only make sense over numbers.
+pub fn is_numeric_only(function: AggregateFunction) -> bool {
+    matches!(function, AggregateFunction::Sum | AggregateFunction::Avg)
+}
+
+/// Whether a driver type name (as `type_info().name()` reports it) holds numbers.
+pub fn is_numeric_type_name(type_name: &str) -> bool {
+    let type_name = type_name.to_ascii_uppercase();
+    [
+        "INT", "SERIAL", "REAL", "FLOAT", "DOUBLE", "NUMERIC", "DECIMAL",
+    ]
+    .iter()
+    .any(|numeric| type_name.contains(numeric))
+        && !type_name.contains("INTERVAL")
+}
+
+/// Alias of a function's value in a wrapped aggregate query.
+pub fn alias(function: AggregateFunction) -> &'static str {
+    match function {
+        AggregateFunction::Count => "agg_count",
+        AggregateFunction::DistinctCount => "agg_distinct_count",
+        AggregateFunction::Sum => "agg_sum",
+        AggregateFunction::Avg => "agg_avg",
+        AggregateFunction::Min => "agg_min",
+        AggregateFunction::Max => "agg_max",
+    }
+}
+
+/// `SELECT` list computing `functions` over `quoted_column` of a subquery `t`.
+pub fn select_list(functions: &[AggregateFunction], quoted_column: &str) -> String {
+    functions
+        .iter()
+        .map(|function| {
+            let expression = match function {
+                AggregateFunction::Count => format!("COUNT(t.{})", quoted_column),
+                AggregateFunction::DistinctCount => format!("COUNT(DISTINCT t.{})", quoted_column),
+                AggregateFunction::Sum => format!("SUM(t.{})", quoted_column),
+                AggregateFunction::Avg => format!("AVG(t.{})", quoted_column),
+                AggregateFunction::Min => format!("MIN(t.{})", quoted_column),
+                AggregateFunction::Max => format!("MAX(t.{})", quoted_column),
+            };
+            format!("{} AS {}", expression, alias(*function))
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn as_number(value: &serde_json::Value) -> Option<f64> {
+    match value {
+        serde_json::Value::Number(number) => number.as_f64(),
+        // Decimals arrive as text so they keep their precision.
+        serde_json::Value::String(text) => text.trim().parse::<f64>().ok().filter(|n| n.is_finite()),
+        _ => None,
+    }
+}
+
+fn as_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Aggregates of one column, computed a value at a time in Rust. Text is
+/// ordered by code point, not by any database collation.
+#[derive(Debug, Default)]
+pub struct ColumnAccumulator {
+    count: u64,
+    distinct: HashSet<String>,
+    numeric: bool,
+    int_sum: Option<i128>,
+    float_sum: f64,
+    /// Extremes by numeric value, used while every value is a number.
+    numeric_min: Option<(f64, serde_json::Value)>,
+    numeric_max: Option<(f64, serde_json::Value)>,
+    /// Extremes by text, used once any value isn't a number.
+    text_min: Option<(String, serde_json::Value)>,
+    text_max: Option<(String, serde_json::Value)>,
+}
+
+impl ColumnAccumulator {
+    pub fn new() -> Self {
+        Self {
+            numeric: true,
+            int_sum: Some(0),
+            ..Self::default()
+        }
+    }
+
+    pub fn add(&mut self, value: &serde_json::Value) {
+        if value.is_null() {
+            return;
+        }
+        self.count += 1;
+        self.distinct.insert(value.to_string());
+        match as_number(value) {
+            Some(number) if self.numeric => {
+                self.float_sum += number;
+                self.int_sum = match (self.int_sum, value.as_i64()) {
+                    (Some(sum), Some(int)) => Some(sum + int as i128),
+                    _ => None,
+                };
+                if self.numeric_min.as_ref().is_none_or(|(min, _)| number < *min) {
+                    self.numeric_min = Some((number, value.clone()));
+                }
+                if self.numeric_max.as_ref().is_none_or(|(max, _)| number > *max) {
+                    self.numeric_max = Some((number, value.clone()));
+                }
+            }
+            _ => self.numeric = false,
+        }
+        let text = as_text(value);
+        if self.text_min.as_ref().is_none_or(|(min, _)| text < *min) {
+            self.text_min = Some((text.clone(), value.clone()));
+        }
+        if self.text_max.as_ref().is_none_or(|(max, _)| text > *max) {
+            self.text_max = Some((text, value.clone()));
+        }
+    }
+
+    pub fn finish(self, column: &str, functions: &[AggregateFunction]) -> ColumnAggregates {
+        let numeric = self.numeric && self.count > 0;
+        let (min, max) = if numeric {
+            (self.numeric_min.map(|(_, value)| value), self.numeric_max.map(|(_, value)| value))
+        } else {
+            (self.text_min.map(|(_, value)| value), self.text_max.map(|(_, value)| value))
+        };
+        let mut values = Vec::new();
+        let mut unsupported = Vec::new();
+        for &function in functions {
+            if !numeric && self.count > 0 && is_numeric_only(function) {
+                unsupported.push(function);
+                continue;
+            }
+            let value = match function {
+                AggregateFunction::Count => serde_json::json!(self.count),
+                AggregateFunction::DistinctCount => serde_json::json!(self.distinct.len()),
+                AggregateFunction::Sum if self.count == 0 => serde_json::Value::Null,
+                AggregateFunction::Sum => match self.int_sum.and_then(|sum| i64::try_from(sum).ok()) {
+                    Some(sum) => serde_json::json!(sum),
+                    None => serde_json::json!(self.float_sum),
+                },
+                AggregateFunction::Avg if self.count == 0 => serde_json::Value::Null,
+                AggregateFunction::Avg => serde_json::json!(self.float_sum / self.count as f64),
+                AggregateFunction::Min => min.clone().unwrap_or(serde_json::Value::Null),
+                AggregateFunction::Max => max.clone().unwrap_or(serde_json::Value::Null),
+            };
+            values.push(AggregateValue { function, value });
+        }
+        ColumnAggregates {
+            column: column.to_string(),
+            path: AggregatePath::Local,
+            sql: None,
+            numeric,
+            values,
+            unsupported,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value_of(aggregates: &ColumnAggregates, function: AggregateFunction) -> serde_json::Value {
+        aggregates
+            .values
+            .iter()
+            .find(|value| value.function == function)
+            .map(|value| value.value.clone())
+            .unwrap()
+    }
+
+    #[test]
+    fn aggregates_numbers_and_text() {
+        let all = [
+            AggregateFunction::Count,
+            AggregateFunction::DistinctCount,
+            AggregateFunction::Sum,
+            AggregateFunction::Avg,
+            AggregateFunction::Min,
+            AggregateFunction::Max,
+        ];
+        let mut numbers = ColumnAccumulator::new();
+        for value in [serde_json::json!(10), serde_json::json!(null), serde_json::json!(2), serde_json::json!("2.5")] {
+            numbers.add(&value);
+        }
+        let numbers = numbers.finish("amount", &all);
+        assert!(numbers.numeric);
+        assert_eq!(value_of(&numbers, AggregateFunction::Count), serde_json::json!(3));
+        assert_eq!(value_of(&numbers, AggregateFunction::Sum), serde_json::json!(14.5));
+        assert_eq!(value_of(&numbers, AggregateFunction::Min), serde_json::json!(2));
+        assert_eq!(value_of(&numbers, AggregateFunction::Max), serde_json::json!(10));
+
+        let mut text = ColumnAccumulator::new();
+        for value in [serde_json::json!("pear"), serde_json::json!("apple"), serde_json::json!("pear")] {
+            text.add(&value);
+        }
+        let text = text.finish("fruit", &all);
+        assert!(!text.numeric);
+        assert_eq!(text.unsupported, vec![AggregateFunction::Sum, AggregateFunction::Avg]);
+        assert_eq!(value_of(&text, AggregateFunction::DistinctCount), serde_json::json!(2));
+        assert_eq!(value_of(&text, AggregateFunction::Min), serde_json::json!("apple"));
+    }
+}
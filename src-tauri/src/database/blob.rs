@@ -0,0 +1,127 @@
+use crate::operations::OperationHandle;
+use anyhow::{anyhow, Result};
+use std::io::{Read, Write};
+
+/// Largest file accepted into (or written out of) a single cell.
+pub const MAX_CELL_FILE_BYTES: u64 = 64 * 1024 * 1024;
+const FILE_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Returned when a column holds binary data in a form NodaDB can't safely
+/// read or write, e.g. Postgres large object references.
+#[derive(Debug)]
+pub struct Unsupported(pub String);
+
+impl std::fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unsupported: {}", self.0)
+    }
+}
+
+impl std::error::Error for Unsupported {}
+
+/// Guesses a mime type from the leading bytes so the UI can preview common formats.
+pub fn sniff_mime_type(bytes: &[u8]) -> Option<&'static str> {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"BM", "image/bmp"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"SQLite format 3\0", "application/vnd.sqlite3"),
+    ];
+
+    if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        return Some("image/webp");
+    }
+    if let Some((_, mime)) = SIGNATURES
+        .iter()
+        .find(|(signature, _)| bytes.starts_with(signature))
+    {
+        return Some(mime);
+    }
+
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(256)]);
+    let head = head.trim_start();
+    if head.starts_with("<svg") || (head.starts_with("<?xml") && head.contains("<svg")) {
+        return Some("image/svg+xml");
+    }
+    None
+}
+
+/// Reads `file_path` in chunks, reporting progress and honouring cancellation.
+pub fn read_file_with_progress(file_path: &str, operation: &OperationHandle) -> Result<Vec<u8>> {
+    let mut file = std::fs::File::open(file_path)
+        .map_err(|e| anyhow!("Failed to open {}: {}", file_path, e))?;
+    let size = file.metadata()?.len();
+    if size > MAX_CELL_FILE_BYTES {
+        return Err(anyhow!(
+            "{} is {} bytes, larger than the {} byte limit for a single cell",
+            file_path,
+            size,
+            MAX_CELL_FILE_BYTES
+        ));
+    }
+
+    let mut bytes = Vec::with_capacity(size as usize);
+    let mut chunk = vec![0_u8; FILE_CHUNK_SIZE];
+    operation.report(0, Some(size), Some("Reading file"));
+    loop {
+        operation.check_cancelled()?;
+        let read = file.read(&mut chunk)?;
+        if read == 0 {
+            break;
+        }
+        bytes.extend_from_slice(&chunk[..read]);
+        operation.report(bytes.len() as u64, Some(size), Some("Reading file"));
+    }
+    Ok(bytes)
+}
+
+/// Writes `bytes` to `file_path` in chunks; a cancelled or failed write removes the partial file.
+pub fn write_file_with_progress(
+    file_path: &str,
+    bytes: &[u8],
+    operation: &OperationHandle,
+) -> Result<()> {
+    let result = (|| -> Result<()> {
+        let mut file = std::fs::File::create(file_path)
+            .map_err(|e| anyhow!("Failed to create {}: {}", file_path, e))?;
+        let total = bytes.len() as u64;
+        let mut written = 0_u64;
+        operation.report(0, Some(total), Some("Writing file"));
+        for chunk in bytes.chunks(FILE_CHUNK_SIZE) {
+            operation.check_cancelled()?;
+            file.write_all(chunk)?;
+            written += chunk.len() as u64;
+            operation.report(written, Some(total), Some("Writing file"));
+        }
+        file.flush()?;
+        Ok(())
+    })();
+
+    if result.is_err() {
+        let _ = std::fs::remove_file(file_path);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sniffs_common_formats() {
+        assert_eq!(sniff_mime_type(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), Some("image/png"));
+        assert_eq!(sniff_mime_type(b"%PDF-1.7\n"), Some("application/pdf"));
+        assert_eq!(sniff_mime_type(b"RIFF\x10\0\0\0WEBPVP8 "), Some("image/webp"));
+        assert_eq!(
+            sniff_mime_type(b"<?xml version=\"1.0\"?>\n<svg xmlns=\"http://www.w3.org/2000/svg\"/>"),
+            Some("image/svg+xml")
+        );
+        assert_eq!(sniff_mime_type(b"plain text"), None);
+        assert_eq!(sniff_mime_type(b""), None);
+    }
+}
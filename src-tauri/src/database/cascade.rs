@@ -0,0 +1,161 @@
+use crate::models::{CascadePreviewNode, DatabaseType, ForeignKeyDefinition};
+
+/// How many foreign key hops below the deleted table are followed by default.
+pub const DEFAULT_CASCADE_MAX_DEPTH: u32 = 5;
+/// Referencing rows counted (and key values carried to the next level) per table by default.
+pub const DEFAULT_CASCADE_ROW_LIMIT: u64 = 10_000;
+
+/// Normalizes a delete rule to its SQL spelling. Accepts Postgres `confdeltype`
+/// codes as well as the words reported by SQLite and MySQL.
+pub fn delete_action(raw: &str) -> String {
+    match raw.trim() {
+        "a" | "" => "NO ACTION".to_string(),
+        "r" => "RESTRICT".to_string(),
+        "c" => "CASCADE".to_string(),
+        "n" => "SET NULL".to_string(),
+        "d" => "SET DEFAULT".to_string(),
+        other => other
+            .split_whitespace()
+            .map(str::to_uppercase)
+            .collect::<Vec<_>>()
+            .join(" "),
+    }
+}
+
+/// True when deleting a parent row also deletes the referencing rows.
+pub fn deletes_rows(action: &str) -> bool {
+    action == "CASCADE"
+}
+
+/// True when referencing rows make the parent delete fail.
+pub fn blocks_delete(action: &str) -> bool {
+    action == "RESTRICT" || action == "NO ACTION"
+}
+
+fn same_table(left: &str, right: &str, db_type: &DatabaseType) -> bool {
+    match db_type {
+        // SQLite, SQL Server's default collations and (on most platforms) MySQL
+        // compare table names case-insensitively.
+        DatabaseType::SQLite | DatabaseType::MySQL | DatabaseType::MsSql => left.eq_ignore_ascii_case(right),
+        DatabaseType::PostgreSQL => left == right,
+    }
+}
+
+/// Foreign keys in `graph` that point at `table`.
+pub fn referencing<'a>(
+    graph: &'a [ForeignKeyDefinition],
+    table: &str,
+    db_type: &DatabaseType,
+) -> Vec<&'a ForeignKeyDefinition> {
+    graph
+        .iter()
+        .filter(|fk| same_table(&fk.referenced_table_name, table, db_type))
+        .collect()
+}
+
+/// Foreign keys `table` declares, pointing at other tables.
+pub fn declared_by<'a>(
+    graph: &'a [ForeignKeyDefinition],
+    table: &str,
+    db_type: &DatabaseType,
+) -> Vec<&'a ForeignKeyDefinition> {
+    graph
+        .iter()
+        .filter(|fk| same_table(&fk.table_name, table, db_type))
+        .collect()
+}
+
+/// True when `table` already appears on the path from the deleted table.
+pub fn on_path(path: &[String], table: &str, db_type: &DatabaseType) -> bool {
+    path.iter().any(|entry| same_table(entry, table, db_type))
+}
+
+/// Rows touched by the delete (deleted or updated by SET NULL / SET DEFAULT),
+/// the distinct tables they live in, and whether a RESTRICT / NO ACTION
+/// reference would make the delete fail.
+pub fn summarize(nodes: &[CascadePreviewNode]) -> (u64, Vec<String>, bool) {
+    fn visit(
+        nodes: &[CascadePreviewNode],
+        total: &mut u64,
+        tables: &mut Vec<String>,
+        blocked: &mut bool,
+    ) {
+        for node in nodes {
+            if node.row_count == 0 {
+                continue;
+            }
+            if node.blocks_delete {
+                *blocked = true;
+                continue;
+            }
+            *total += node.row_count;
+            if !tables.contains(&node.table_name) {
+                tables.push(node.table_name.clone());
+            }
+            visit(&node.children, total, tables, blocked);
+        }
+    }
+
+    let mut total = 0;
+    let mut tables = Vec::new();
+    let mut blocked = false;
+    visit(nodes, &mut total, &mut tables, &mut blocked);
+    (total, tables, blocked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(
+        table: &str,
+        action: &str,
+        rows: u64,
+        children: Vec<CascadePreviewNode>,
+    ) -> CascadePreviewNode {
+        CascadePreviewNode {
+            table_name: table.to_string(),
+            constraint_name: format!("fk_{}", table),
+            fk_columns: vec!["parent_id".to_string()],
+            referenced_columns: vec!["id".to_string()],
+            on_delete_action: action.to_string(),
+            row_count: rows,
+            row_count_capped: false,
+            blocks_delete: blocks_delete(action),
+            cycle: false,
+            depth_limited: false,
+            children,
+        }
+    }
+
+    #[test]
+    fn normalizes_delete_rules_from_every_backend() {
+        assert_eq!(delete_action("c"), "CASCADE");
+        assert_eq!(delete_action("a"), "NO ACTION");
+        assert_eq!(delete_action("set null"), "SET NULL");
+        assert_eq!(delete_action("RESTRICT"), "RESTRICT");
+    }
+
+    #[test]
+    fn summary_counts_touched_rows_and_detects_blocking_references() {
+        let nodes = vec![
+            node(
+                "orders",
+                "CASCADE",
+                3,
+                vec![
+                    node("order_items", "CASCADE", 7, vec![]),
+                    node("invoices", "SET NULL", 2, vec![]),
+                ],
+            ),
+            node("audit_log", "CASCADE", 0, vec![]),
+        ];
+        let (total, tables, blocked) = summarize(&nodes);
+        assert_eq!(total, 12);
+        assert_eq!(tables, vec!["orders", "order_items", "invoices"]);
+        assert!(!blocked);
+
+        let (_, _, blocked) = summarize(&[node("payments", "RESTRICT", 1, vec![])]);
+        assert!(blocked);
+    }
+}
@@ -0,0 +1,125 @@
+use crate::models::{DatabaseType, QueryResult, TruncatedCell};
+
+/// Prefix of the helper columns carrying the full length of truncated text columns.
+const LENGTH_ALIAS_PREFIX: &str = "__nodadb_len_";
+
+/// Alias of the helper column holding the full length of the `index`-th text column.
+pub fn length_alias(index: usize) -> String {
+    format!("{}{}", LENGTH_ALIAS_PREFIX, index)
+}
+
+/// First `limit` characters of `quoted_column`, and its full length in characters.
+pub fn truncated_expressions(
+    quoted_column: &str,
+    limit: usize,
+    db_type: &DatabaseType,
+) -> (String, String) {
+    match db_type {
+        DatabaseType::PostgreSQL => (
+            format!("LEFT({}::text, {})", quoted_column, limit),
+            format!("char_length({}::text)::int8", quoted_column),
+        ),
+        DatabaseType::MySQL => (
+            format!("LEFT({}, {})", quoted_column, limit),
+            format!("CHAR_LENGTH({})", quoted_column),
+        ),
+        DatabaseType::SQLite => (
+            format!("substr({}, 1, {})", quoted_column, limit),
+            format!("length({})", quoted_column),
+        ),
+        DatabaseType::MsSql => (
+            format!("LEFT(CAST({} AS nvarchar(max)), {})", quoted_column, limit),
+            format!("CAST(LEN(CAST({} AS nvarchar(max))) AS bigint)", quoted_column),
+        ),
+    }
+}
+
+/// Moves the helper length columns of `text_columns` (index, name) into
+/// `truncated_cells` metadata and drops them from the result.
+pub fn collect_length_columns(
+    result: &mut QueryResult,
+    text_columns: &[(usize, String)],
+    limit: usize,
+) {
+    result
+        .columns
+        .retain(|column| !column.starts_with(LENGTH_ALIAS_PREFIX));
+    for (row_index, row) in result.rows.iter_mut().enumerate() {
+        let Some(row) = row.as_object_mut() else {
+            continue;
+        };
+        for (index, column) in text_columns {
+            let full_length = row
+                .remove(&length_alias(*index))
+                .and_then(|value| value.as_u64().or_else(|| value.as_str()?.parse().ok()));
+            if let Some(full_length) = full_length.filter(|length| *length > limit as u64) {
+                result.truncated_cells.push(TruncatedCell {
+                    row: row_index,
+                    column: column.clone(),
+                    full_length,
+                });
+            }
+        }
+    }
+}
+
+/// Cuts string cells longer than `limit` characters after decoding, for
+/// arbitrary queries where the SQL can't be rewritten. Binary columns are left
+/// whole, since cutting their base64 would corrupt them.
+pub fn truncate_text_cells(result: &mut QueryResult, limit: usize) {
+    for (row_index, row) in result.rows.iter_mut().enumerate() {
+        let Some(row) = row.as_object_mut() else {
+            continue;
+        };
+        for (column, value) in row.iter_mut() {
+            if result.binary_columns.contains(column) {
+                continue;
+            }
+            let Some(text) = value.as_str() else {
+                continue;
+            };
+            let Some((cut, _)) = text.char_indices().nth(limit) else {
+                continue;
+            };
+            result.truncated_cells.push(TruncatedCell {
+                row: row_index,
+                column: column.clone(),
+                full_length: text.chars().count() as u64,
+            });
+            *value = serde_json::Value::String(text[..cut].to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn truncates_long_strings_on_character_boundaries() {
+        let mut result = QueryResult {
+            columns: vec!["id".to_string(), "body".to_string()],
+            rows: vec![
+                json!({ "id": 1, "body": "héllo wörld" }),
+                json!({ "id": 2, "body": "ok" }),
+            ],
+            rows_affected: 0,
+            attempts_made: 1,
+            truncated_cells: vec![],
+            result_id: None,
+            lineage: None,
+            status_message: None,
+            no_result_set: false,
+            decoded_columns: vec![],
+            binary_columns: vec![],
+        };
+        truncate_text_cells(&mut result, 5);
+
+        assert_eq!(result.rows[0]["body"], "héllo");
+        assert_eq!(result.rows[1]["body"], "ok");
+        assert_eq!(result.truncated_cells.len(), 1);
+        assert_eq!(result.truncated_cells[0].row, 0);
+        assert_eq!(result.truncated_cells[0].full_length, 11);
+    }
+}
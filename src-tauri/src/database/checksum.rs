@@ -0,0 +1,144 @@
+use crate::models::{ChecksumComparison, TableChecksum};
+
+pub const METHOD_POSTGRES_MD5: &str = "postgres-md5-sorted";
+pub const METHOD_MYSQL_CRC32: &str = "mysql-crc32-xor-sum";
+pub const METHOD_STREAMED_FNV: &str = "streamed-fnv1a64-sum";
+
+/// Marker used for NULL by every method, so NULL and '' hash differently.
+pub const NULL_MARKER: &str = "\\N";
+/// Separator between the values of one row (ASCII unit separator).
+pub const FIELD_SEPARATOR: char = '\u{1f}';
+
+/// Text form of a value as returned by the drivers, used by the streamed method.
+pub fn normalize_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Null => NULL_MARKER.to_string(),
+        serde_json::Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+pub fn fnv1a64(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325_u64, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Order-independent digest over rows hashed one by one on the client.
+/// Sum and xor are kept together so duplicated rows don't cancel out.
+#[derive(Debug, Default)]
+pub struct StreamedDigest {
+    sum: u64,
+    xor: u64,
+    rows: u64,
+}
+
+impl StreamedDigest {
+    pub fn add_row<'a>(&mut self, values: impl IntoIterator<Item = &'a serde_json::Value>) {
+        let line = values
+            .into_iter()
+            .map(normalize_value)
+            .collect::<Vec<_>>()
+            .join(&FIELD_SEPARATOR.to_string());
+        let hash = fnv1a64(line.as_bytes());
+        self.sum = self.sum.wrapping_add(hash);
+        self.xor ^= hash;
+        self.rows += 1;
+    }
+
+    pub fn rows(&self) -> u64 {
+        self.rows
+    }
+
+    pub fn digest(&self) -> String {
+        format!("{:016x}{:016x}", self.sum, self.xor)
+    }
+}
+
+/// Compares two checksums; digests are only meaningful when both sides used the same method.
+pub fn compare(left: TableChecksum, right: TableChecksum) -> ChecksumComparison {
+    let mut caveats = Vec::new();
+    let comparable = left.method == right.method && left.columns == right.columns;
+
+    if left.method != right.method {
+        caveats.push(format!(
+            "Methods differ ({} vs {}); run both sides with algorithm \"streamed\" to compare digests",
+            left.method, right.method
+        ));
+    }
+    if left.columns != right.columns {
+        caveats.push("The two tables were checksummed over different column sets".to_string());
+    }
+    if comparable && left.method == METHOD_MYSQL_CRC32 {
+        caveats.push("CRC32 is a 32-bit hash per row; equal digests are very likely but not guaranteed to mean equal data".to_string());
+    }
+    if comparable && left.method == METHOD_STREAMED_FNV && left.db_type != right.db_type {
+        caveats.push("Values are compared as the drivers render them; types rendered differently by the two backends (timestamps, decimals, booleans) show up as differences".to_string());
+    }
+
+    let equal = if left.row_count != right.row_count {
+        Some(false)
+    } else if comparable {
+        Some(left.digest == right.digest)
+    } else {
+        None
+    };
+
+    ChecksumComparison {
+        left,
+        right,
+        comparable,
+        equal,
+        caveats,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::DatabaseType;
+    use serde_json::json;
+
+    #[test]
+    fn streamed_digest_ignores_row_order_but_not_duplicates_or_nulls() {
+        let rows = [json!([1, "a", null]), json!([2, "b", "x"])];
+        let digest = |rows: &[serde_json::Value]| {
+            let mut digest = StreamedDigest::default();
+            for row in rows {
+                digest.add_row(row.as_array().unwrap());
+            }
+            digest.digest()
+        };
+
+        let forward = digest(&rows);
+        let reversed = digest(&[rows[1].clone(), rows[0].clone()]);
+        assert_eq!(forward, reversed);
+
+        let doubled = digest(&[rows[0].clone(), rows[0].clone()]);
+        assert_ne!(doubled, digest(&[]));
+        assert_ne!(digest(&[json!([null])]), digest(&[json!([""])]));
+    }
+
+    #[test]
+    fn digests_are_only_compared_for_matching_methods() {
+        let checksum = |method: &str, digest: &str, rows: u64| TableChecksum {
+            connection_id: "conn".to_string(),
+            table_name: "users".to_string(),
+            db_type: DatabaseType::PostgreSQL,
+            method: method.to_string(),
+            digest: digest.to_string(),
+            row_count: rows,
+            columns: vec!["id".to_string()],
+        };
+
+        let same = compare(checksum(METHOD_POSTGRES_MD5, "ab", 2), checksum(METHOD_POSTGRES_MD5, "ab", 2));
+        assert_eq!(same.equal, Some(true));
+
+        let mixed = compare(checksum(METHOD_POSTGRES_MD5, "ab", 2), checksum(METHOD_STREAMED_FNV, "cd", 2));
+        assert!(!mixed.comparable);
+        assert_eq!(mixed.equal, None);
+
+        let counts = compare(checksum(METHOD_POSTGRES_MD5, "ab", 2), checksum(METHOD_STREAMED_FNV, "cd", 3));
+        assert_eq!(counts.equal, Some(false));
+    }
+}
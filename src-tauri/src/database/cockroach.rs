@@ -0,0 +1,250 @@
+use super::lineage::split_table_name;
+use super::row_counts;
+use super::types::{classify_postgres_type, normalize_type_name};
+use crate::models::{ColumnTypeFamily, DatabaseTable, DatabaseType, PlanStep, PostgresFlavor, TableColumn};
+use anyhow::Result;
+use sqlx::postgres::{PgPool, PgRow};
+use sqlx::Row;
+
+/// Tables and views of the current database with the row estimate
+/// from the latest statistics. Cockroach has no per-table size to report.
+const TABLES_SQL: &str = r#"
+    SELECT
+      t.table_schema,
+      t.table_name,
+      t.table_type,
+      s.estimated_row_count::INT8 AS row_count
+    FROM information_schema.tables t
+    LEFT JOIN crdb_internal.tables c
+      ON c.database_name = current_database()
+     AND c.schema_name = t.table_schema
+     AND c.name = t.table_name
+     AND c.drop_time IS NULL
+    LEFT JOIN crdb_internal.table_row_statistics s ON s.table_id = c.table_id
+    WHERE t.table_catalog = current_database()
+      AND t.table_type IN ('BASE TABLE', 'VIEW')
+      AND t.table_schema NOT IN ('pg_catalog', 'information_schema', 'crdb_internal', 'pg_extension')
+    ORDER BY t.table_schema, t.table_name
+"#;
+
+/// Primary key columns of the table `$2` in schema `$1` (the current one when
+/// NULL). The hidden `rowid` of tables declared without one is left out.
+const PRIMARY_KEY_SQL: &str = r#"
+    SELECT k.column_name
+    FROM information_schema.table_constraints tc
+    JOIN information_schema.key_column_usage k
+      ON k.constraint_schema = tc.constraint_schema
+     AND k.constraint_name = tc.constraint_name
+     AND k.table_name = tc.table_name
+    JOIN information_schema.columns col
+      ON col.table_schema = k.table_schema
+     AND col.table_name = k.table_name
+     AND col.column_name = k.column_name
+    WHERE tc.constraint_type = 'PRIMARY KEY'
+      AND tc.table_schema = COALESCE($1, current_schema())
+      AND tc.table_name = $2
+      AND col.is_hidden = 'NO'
+    ORDER BY k.ordinal_position
+"#;
+
+/// Visible columns of the table `$2` in schema `$1`, in the order `column` reads them.
+const COLUMNS_SQL: &str = r#"
+    SELECT
+      c.column_name,
+      c.data_type,
+      c.udt_name,
+      c.is_nullable = 'YES' AS nullable,
+      c.column_default,
+      EXISTS (
+        SELECT 1
+        FROM information_schema.table_constraints tc
+        JOIN information_schema.key_column_usage k
+          ON k.constraint_schema = tc.constraint_schema
+         AND k.constraint_name = tc.constraint_name
+         AND k.table_name = tc.table_name
+        WHERE tc.constraint_type = 'PRIMARY KEY'
+          AND tc.table_schema = c.table_schema
+          AND tc.table_name = c.table_name
+          AND k.column_name = c.column_name
+      ) AS is_primary_key,
+      NULLIF(c.generation_expression, '') AS generation_expression,
+      c.collation_name,
+      c.character_maximum_length::INT8,
+      c.numeric_precision::INT8,
+      c.numeric_scale::INT8
+    FROM information_schema.columns c
+    WHERE c.table_schema = COALESCE($1, current_schema())
+      AND c.table_name = $2
+      AND c.is_hidden = 'NO'
+    ORDER BY c.ordinal_position
+"#;
+
+/// The Postgres-protocol server a connection reached, from its `version()`.
+pub fn flavor(version: &str) -> PostgresFlavor {
+    if version.contains("CockroachDB") {
+        PostgresFlavor::CockroachDb
+    } else {
+        PostgresFlavor::Postgres
+    }
+}
+
+/// `CockroachDB v23.1.11` from `CockroachDB CCL v23.1.11 (x86_64-pc-linux-gnu, ...)`.
+pub fn display_version(version: &str) -> String {
+    match version.split_whitespace().find(|word| word.starts_with('v')) {
+        Some(release) => format!("CockroachDB {}", release),
+        None => "CockroachDB".to_string(),
+    }
+}
+
+pub async fn tables(pool: &PgPool, scratch_schema: &str) -> Result<Vec<DatabaseTable>> {
+    let rows = sqlx::query(TABLES_SQL).fetch_all(pool).await?;
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let schema: String = row.try_get(0).unwrap_or_else(|_| "public".to_string());
+            let name: String = row.try_get(1).unwrap_or_default();
+            let row_count: Option<i64> = row.try_get(3).ok().flatten();
+            DatabaseTable {
+                full_name: Some(format!("{}.{}", schema, name)),
+                is_scratch: schema == scratch_schema,
+                name,
+                schema: Some(schema),
+                row_count_accuracy: row_count.map(|_| row_counts::ACCURACY_ESTIMATE.to_string()),
+                row_count,
+                size_kb: None,
+                table_type: row.try_get(2).ok(),
+            }
+        })
+        .collect())
+}
+
+pub async fn primary_keys(pool: &PgPool, table_name: &str) -> Result<Vec<String>> {
+    let (schema, table) = split_table_name(table_name);
+    let rows = sqlx::query(PRIMARY_KEY_SQL).bind(schema).bind(table).fetch_all(pool).await?;
+    Ok(rows.iter().map(|row| row.try_get(0).unwrap_or_default()).collect())
+}
+
+pub async fn columns(pool: &PgPool, table_name: &str) -> Result<Vec<TableColumn>> {
+    let (schema, table) = split_table_name(table_name);
+    let rows = sqlx::query(COLUMNS_SQL).bind(schema).bind(table).fetch_all(pool).await?;
+    Ok(rows.iter().map(column).collect())
+}
+
+/// A row of `COLUMNS_SQL`.
+fn column(row: &PgRow) -> TableColumn {
+    let name: String = row.try_get(0).unwrap_or_default();
+    let data_type: String = row.try_get(1).unwrap_or_default();
+    let raw_type: String = row.try_get(2).unwrap_or_default();
+    let is_array = data_type == "ARRAY";
+    let family = classify_postgres_type(&data_type, &raw_type, "b", is_array);
+    let generation_expression: Option<String> = row.try_get(6).ok().flatten();
+
+    TableColumn {
+        name,
+        normalized_type: normalize_type_name(&data_type),
+        data_type,
+        raw_type: Some(raw_type),
+        type_family: family.clone(),
+        db_type: DatabaseType::PostgreSQL,
+        is_nullable: row.try_get(3).unwrap_or(true),
+        default_value: row.try_get(4).ok().flatten(),
+        is_primary_key: row.try_get(5).unwrap_or(false),
+        is_boolean_like: matches!(family, ColumnTypeFamily::Boolean),
+        is_array,
+        enum_values: None,
+        identity_kind: None,
+        generated_kind: generation_expression.as_ref().map(|_| "s".to_string()),
+        generation_expression,
+        column_comment: None,
+        collation_name: row.try_get(7).ok().flatten(),
+        domain_name: None,
+        domain_schema: None,
+        domain_base_type: None,
+        array_dimensions: None,
+        element_raw_type: None,
+        character_maximum_length: row.try_get(8).ok().flatten(),
+        numeric_precision: row.try_get(9).ok().flatten(),
+        numeric_scale: row.try_get(10).ok().flatten(),
+    }
+}
+
+/// Steps of a plain `EXPLAIN` (or `EXPLAIN ANALYZE`) tree, one per `•` node
+/// in plan order, with the attributes listed under each node.
+pub fn plan_steps<S: AsRef<str>>(lines: &[S]) -> Vec<PlanStep> {
+    let mut steps: Vec<PlanStep> = Vec::new();
+    for line in lines {
+        let line = line.as_ref().trim_start_matches([' ', '│', '├', '└', '─']).trim_end();
+        if let Some(node) = line.strip_prefix('•') {
+            steps.push(PlanStep {
+                step_type: node.trim().to_string(),
+                table_name: None,
+                rows: None,
+                cost: None,
+                filter_condition: None,
+                index_used: None,
+                children: vec![],
+                actual_rows: None,
+            });
+            continue;
+        }
+        // Lines before the first node describe the whole plan.
+        let (Some(step), Some((key, value))) = (steps.last_mut(), line.split_once(": ")) else {
+            continue;
+        };
+        match key {
+            "estimated row count" => step.rows = leading_count(value),
+            "actual row count" => step.actual_rows = leading_count(value),
+            "filter" => step.filter_condition = Some(value.to_string()),
+            "table" => {
+                let (table, index) = value.split_once('@').unwrap_or((value, ""));
+                step.table_name = Some(table.to_string());
+                step.index_used = Some(index.to_string()).filter(|index| !index.is_empty());
+            }
+            _ => {}
+        }
+    }
+    steps
+}
+
+/// `1000` from `1,000 (100% of the table; stats collected 3 minutes ago)`.
+fn leading_count(value: &str) -> Option<i64> {
+    value
+        .split_whitespace()
+        .next()?
+        .replace(',', "")
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cockroach_servers_and_text_plans_are_recognized() {
+        let version = "CockroachDB CCL v23.1.11 (x86_64-pc-linux-gnu, built 2023/09/27 01:53:43, go1.19.10)";
+        assert_eq!(flavor(version), PostgresFlavor::CockroachDb);
+        assert_eq!(flavor("PostgreSQL 16.2 on x86_64-pc-linux-gnu"), PostgresFlavor::Postgres);
+        assert_eq!(display_version(version), "CockroachDB v23.1.11");
+
+        let plan = [
+            "distribution: local",
+            "vectorized: true",
+            "",
+            "• filter",
+            "│ estimated row count: 1",
+            "│ filter: total > 10",
+            "│",
+            "└── • scan",
+            "      actual row count: 998",
+            "      estimated row count: 1,000 (100% of the table; stats collected 3 minutes ago)",
+            "      table: orders@orders_pkey",
+            "      spans: FULL SCAN",
+        ];
+        let steps = plan_steps(&plan);
+        assert_eq!(steps.len(), 2);
+        assert_eq!((steps[0].step_type.as_str(), steps[0].filter_condition.as_deref()), ("filter", Some("total > 10")));
+        assert_eq!((steps[1].table_name.as_deref(), steps[1].index_used.as_deref()), (Some("orders"), Some("orders_pkey")));
+        assert_eq!((steps[1].rows, steps[1].actual_rows), (Some(1000), Some(998)));
+    }
+}
@@ -0,0 +1,260 @@
+use crate::models::ValueFrequency;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+
+/// Buckets requested when building a MySQL histogram for a column.
+pub const MYSQL_HISTOGRAM_BUCKETS: u32 = 100;
+/// Estimates off by at least this factor from the actual row count get flagged.
+pub const ESTIMATE_DIVERGENCE_FACTOR: f64 = 10.0;
+/// Plan nodes below this many rows (estimated and actual) are too small to matter.
+pub const MIN_DIVERGENT_ROWS: i64 = 100;
+
+/// Normalized view of a MySQL 8 histogram.
+#[derive(Debug, Default, PartialEq)]
+pub struct MySqlHistogram {
+    pub null_fraction: Option<f64>,
+    pub distinct_values: Option<f64>,
+    pub most_common_values: Vec<ValueFrequency>,
+    pub histogram_bounds: Vec<serde_json::Value>,
+    pub last_updated: Option<String>,
+}
+
+/// Absolute distinct count from Postgres `n_distinct`, which is a negated
+/// fraction of the row count when the planner expects it to scale with the table.
+pub fn pg_distinct(n_distinct: f64, reltuples: Option<f64>) -> Option<f64> {
+    if n_distinct >= 0.0 {
+        Some(n_distinct)
+    } else {
+        reltuples
+            .filter(|rows| *rows >= 0.0)
+            .map(|rows| (-n_distinct * rows).round())
+    }
+}
+
+/// Histogram values of string columns come back as `base64:typeNNN:<data>`.
+fn mysql_value(value: &serde_json::Value) -> serde_json::Value {
+    let Some(text) = value.as_str() else {
+        return value.clone();
+    };
+    let Some(encoded) = text
+        .strip_prefix("base64:type")
+        .and_then(|rest| rest.split_once(':'))
+        .map(|(_, data)| data)
+    else {
+        return value.clone();
+    };
+    match base64::engine::general_purpose::STANDARD.decode(encoded) {
+        Ok(bytes) => serde_json::Value::String(String::from_utf8_lossy(&bytes).into_owned()),
+        Err(_) => value.clone(),
+    }
+}
+
+/// Reads the `HISTOGRAM` JSON of `information_schema.column_statistics`.
+/// Singleton buckets hold one value each and become most-common values;
+/// equi-height buckets become histogram bounds.
+pub fn mysql_histogram(histogram: &serde_json::Value) -> MySqlHistogram {
+    let buckets = histogram
+        .get("buckets")
+        .and_then(|buckets| buckets.as_array())
+        .cloned()
+        .unwrap_or_default();
+    let kind = histogram
+        .get("histogram-type")
+        .and_then(|kind| kind.as_str())
+        .unwrap_or_default();
+
+    let mut parsed = MySqlHistogram {
+        null_fraction: histogram.get("null-values").and_then(|v| v.as_f64()),
+        last_updated: histogram
+            .get("last-updated")
+            .and_then(|v| v.as_str())
+            .map(|v| v.to_string()),
+        ..Default::default()
+    };
+    let mut previous = 0.0;
+    if kind == "singleton" {
+        for bucket in &buckets {
+            let (Some(value), Some(cumulative)) =
+                (bucket.get(0), bucket.get(1).and_then(|v| v.as_f64()))
+            else {
+                continue;
+            };
+            parsed.most_common_values.push(ValueFrequency {
+                value: mysql_value(value),
+                frequency: cumulative - previous,
+            });
+            previous = cumulative;
+        }
+        parsed.distinct_values = Some(parsed.most_common_values.len() as f64);
+        parsed
+            .most_common_values
+            .sort_by(|a, b| b.frequency.total_cmp(&a.frequency));
+    } else {
+        let mut distinct = 0.0;
+        for (index, bucket) in buckets.iter().enumerate() {
+            if index == 0 {
+                if let Some(lower) = bucket.get(0) {
+                    parsed.histogram_bounds.push(mysql_value(lower));
+                }
+            }
+            if let Some(upper) = bucket.get(1) {
+                parsed.histogram_bounds.push(mysql_value(upper));
+            }
+            distinct += bucket.get(3).and_then(|v| v.as_f64()).unwrap_or(0.0);
+        }
+        parsed.distinct_values = (!buckets.is_empty()).then_some(distinct);
+    }
+    parsed
+}
+
+/// Distinct values of an index's leading column from a `sqlite_stat1` stat
+/// string ("<rows> <avg rows per first-column value> ...").
+pub fn sqlite_stat1_distinct(stat: &str) -> Option<f64> {
+    let mut numbers = stat.split_whitespace().map(|part| part.parse::<f64>().ok());
+    let rows = numbers.next()??;
+    let per_value = numbers.next()??;
+    (per_value > 0.0).then(|| (rows / per_value).round())
+}
+
+fn read_varint(bytes: &[u8], position: &mut usize) -> Option<u64> {
+    let mut value = 0_u64;
+    for i in 0..9 {
+        let byte = *bytes.get(*position)?;
+        *position += 1;
+        if i == 8 {
+            return Some((value << 8) | byte as u64);
+        }
+        value = (value << 7) | (byte & 0x7f) as u64;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    Some(value)
+}
+
+/// First field of a SQLite record, the format of `sqlite_stat4.sample`.
+pub fn sqlite_record_first_value(record: &[u8]) -> Option<serde_json::Value> {
+    let mut position = 0;
+    let header_size = read_varint(record, &mut position)? as usize;
+    let serial_type = read_varint(record, &mut position)?;
+    let body = record.get(header_size..)?;
+    let int = |len: usize| -> Option<serde_json::Value> {
+        let bytes = body.get(..len)?;
+        let mut value = if bytes[0] & 0x80 != 0 { -1_i64 } else { 0 };
+        for byte in bytes {
+            value = (value << 8) | *byte as i64;
+        }
+        Some(serde_json::json!(value))
+    };
+    match serial_type {
+        0 => Some(serde_json::Value::Null),
+        1..=4 => int(serial_type as usize),
+        5 => int(6),
+        6 => int(8),
+        7 => {
+            let bytes: [u8; 8] = body.get(..8)?.try_into().ok()?;
+            Some(serde_json::json!(f64::from_be_bytes(bytes)))
+        }
+        8 => Some(serde_json::json!(0)),
+        9 => Some(serde_json::json!(1)),
+        n if n >= 12 && n % 2 == 0 => {
+            let bytes = body.get(..((n - 12) / 2) as usize)?;
+            Some(serde_json::Value::String(
+                base64::engine::general_purpose::STANDARD.encode(bytes),
+            ))
+        }
+        n if n >= 13 => {
+            let bytes = body.get(..((n - 13) / 2) as usize)?;
+            Some(serde_json::Value::String(
+                String::from_utf8_lossy(bytes).into_owned(),
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// Bytes of a `hex()` string.
+pub fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// True when a plan node's estimate is off from its actual row count by at
+/// least `ESTIMATE_DIVERGENCE_FACTOR`.
+pub fn estimate_diverges(estimated: i64, actual: i64) -> bool {
+    if estimated.max(actual) < MIN_DIVERGENT_ROWS {
+        return false;
+    }
+    let (estimated, actual) = (estimated.max(1) as f64, actual.max(1) as f64);
+    estimated / actual >= ESTIMATE_DIVERGENCE_FACTOR || actual / estimated >= ESTIMATE_DIVERGENCE_FACTOR
+}
+
+/// "3 days", "6 months" and so on.
+pub fn describe_age(analyzed: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let days = (now - analyzed).num_days();
+    let (count, unit) = match days {
+        d if d < 1 => ((now - analyzed).num_hours(), "hour"),
+        d if d < 60 => (d, "day"),
+        d if d < 730 => (d / 30, "month"),
+        d => (d / 365, "year"),
+    };
+    format!("{} {}{}", count, unit, if count == 1 { "" } else { "s" })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_mysql_histograms() {
+        let singleton = serde_json::json!({
+            "buckets": [["base64:type254:YQ==", 0.25], ["base64:type254:Yg==", 0.9]],
+            "null-values": 0.1,
+            "last-updated": "2024-01-02 03:04:05.000000",
+            "histogram-type": "singleton"
+        });
+        let parsed = mysql_histogram(&singleton);
+        assert_eq!(parsed.null_fraction, Some(0.1));
+        assert_eq!(parsed.distinct_values, Some(2.0));
+        assert_eq!(parsed.most_common_values[0].value, serde_json::json!("b"));
+        assert!((parsed.most_common_values[0].frequency - 0.65).abs() < 1e-9);
+        assert_eq!(parsed.last_updated.as_deref(), Some("2024-01-02 03:04:05.000000"));
+
+        let equi_height = serde_json::json!({
+            "buckets": [[1, 10, 0.5, 10], [11, 40, 1.0, 25]],
+            "null-values": 0.0,
+            "histogram-type": "equi-height"
+        });
+        let parsed = mysql_histogram(&equi_height);
+        assert_eq!(parsed.histogram_bounds, vec![serde_json::json!(1), serde_json::json!(10), serde_json::json!(40)]);
+        assert_eq!(parsed.distinct_values, Some(35.0));
+    }
+
+    #[test]
+    fn decodes_sqlite_statistics() {
+        assert_eq!(sqlite_stat1_distinct("1000 4 1"), Some(250.0));
+        assert_eq!(sqlite_stat1_distinct("1000"), None);
+        // Header of 3 bytes: text of length 2, then integer 1.
+        let record = [3, 17, 1, b'h', b'i', 7];
+        assert_eq!(sqlite_record_first_value(&record), Some(serde_json::json!("hi")));
+        assert_eq!(sqlite_record_first_value(&[2, 1, 0xff]), Some(serde_json::json!(-1)));
+        assert_eq!(decode_hex("03110168690"), None);
+        assert_eq!(decode_hex("0311"), Some(vec![3, 17]));
+        assert_eq!(pg_distinct(-0.5, Some(1000.0)), Some(500.0));
+    }
+
+    #[test]
+    fn describes_statistics_age_and_divergence() {
+        let now = Utc::now();
+        assert_eq!(describe_age(now - chrono::Duration::days(185), now), "6 months");
+        assert_eq!(describe_age(now - chrono::Duration::days(1), now), "1 day");
+        assert!(estimate_diverges(100, 50_000));
+        assert!(!estimate_diverges(1, 50));
+        assert!(!estimate_diverges(1000, 2000));
+    }
+}
@@ -0,0 +1,319 @@
+use crate::models::{ConcurrencyStats, QueueLane};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// Emitted while an operation waits for a free slot, and once when it gets one.
+pub const CONNECTION_QUEUED_EVENT: &str = "connection-queued";
+/// How long an operation waits before the UI hears about it.
+pub const QUEUE_NOTICE_AFTER: Duration = Duration::from_millis(500);
+/// How often a waiting operation re-checks its queue position.
+pub const QUEUE_POSITION_POLL: Duration = Duration::from_millis(250);
+
+tokio::task_local! {
+    /// Connection whose slot the current task already holds, so nested calls
+    /// (a row count reading the structure) don't queue behind themselves.
+    pub static ADMITTED: String;
+}
+
+/// Which lane an operation queues in by name: what the user ran from the
+/// editor goes first, introspection waits behind it.
+pub fn lane_for(operation: &str) -> QueueLane {
+    match operation {
+        "execute_query" | "execute_multi" | "explain_query" => QueueLane::Interactive,
+        _ => QueueLane::Background,
+    }
+}
+
+struct Waiter {
+    ticket: u64,
+    lane: QueueLane,
+    grant: oneshot::Sender<()>,
+}
+
+#[derive(Default)]
+struct LimiterState {
+    limit: usize,
+    running: usize,
+    next_ticket: u64,
+    waiting: Vec<Waiter>,
+    max_queue_depth: usize,
+    queued_operations: u64,
+    total_wait: Duration,
+}
+
+impl LimiterState {
+    /// Index of the waiter served next: the oldest interactive one, else the oldest.
+    fn next_index(&self) -> Option<usize> {
+        self.waiting
+            .iter()
+            .position(|waiter| waiter.lane == QueueLane::Interactive)
+            .or_else(|| (!self.waiting.is_empty()).then_some(0))
+    }
+
+    fn grant_waiting(&mut self) {
+        while self.running < self.limit {
+            let Some(index) = self.next_index() else {
+                break;
+            };
+            let waiter = self.waiting.remove(index);
+            // The ticket owns the receiver until it has removed itself under
+            // this lock, so a send here can't fail.
+            if waiter.grant.send(()).is_ok() {
+                self.running += 1;
+            }
+        }
+    }
+}
+
+/// In-app cap on how many operations use a connection at once, in front of
+/// the pool so bursts queue here instead of timing out on acquire.
+pub struct ConnectionLimiter {
+    state: Mutex<LimiterState>,
+}
+
+pub enum Admission {
+    Granted(LimiterPermit),
+    Queued(QueueTicket),
+}
+
+impl ConnectionLimiter {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            state: Mutex::new(LimiterState {
+                limit: limit.max(1),
+                ..LimiterState::default()
+            }),
+        }
+    }
+
+    pub fn set_limit(&self, limit: usize) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.limit = limit.max(1);
+        state.grant_waiting();
+    }
+
+    /// Takes a free slot, or joins the queue. Nobody overtakes a waiting
+    /// operation of the same or a higher lane.
+    pub fn enter(self: &Arc<Self>, lane: QueueLane) -> Admission {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let overtakes = match lane {
+            QueueLane::Interactive => !state.waiting.iter().any(|waiter| waiter.lane == QueueLane::Interactive),
+            QueueLane::Background => state.waiting.is_empty(),
+        };
+        if state.running < state.limit && overtakes {
+            state.running += 1;
+            return Admission::Granted(LimiterPermit {
+                limiter: Some(self.clone()),
+            });
+        }
+        let (grant, granted) = oneshot::channel();
+        state.next_ticket += 1;
+        let ticket = state.next_ticket;
+        state.waiting.push(Waiter { ticket, lane, grant });
+        state.queued_operations += 1;
+        state.max_queue_depth = state.max_queue_depth.max(state.waiting.len());
+        Admission::Queued(QueueTicket {
+            limiter: self.clone(),
+            ticket,
+            granted: Some(granted),
+        })
+    }
+
+    /// Operations served before `ticket`, or `None` once it has a slot.
+    pub fn ahead_of(&self, ticket: u64) -> Option<usize> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let waiting = state.waiting.iter().find(|waiter| waiter.ticket == ticket)?;
+        let ahead = match waiting.lane {
+            QueueLane::Interactive => state
+                .waiting
+                .iter()
+                .take_while(|waiter| waiter.ticket != ticket)
+                .filter(|waiter| waiter.lane == QueueLane::Interactive)
+                .count(),
+            QueueLane::Background => {
+                state
+                    .waiting
+                    .iter()
+                    .filter(|waiter| waiter.lane == QueueLane::Interactive)
+                    .count()
+                    + state
+                        .waiting
+                        .iter()
+                        .take_while(|waiter| waiter.ticket != ticket)
+                        .filter(|waiter| waiter.lane == QueueLane::Background)
+                        .count()
+            }
+        };
+        Some(ahead)
+    }
+
+    pub fn stats(&self) -> ConcurrencyStats {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        ConcurrencyStats {
+            limit: state.limit,
+            running: state.running,
+            queued: state.waiting.len(),
+            max_queue_depth: state.max_queue_depth,
+            queued_operations: state.queued_operations,
+            total_wait_ms: state.total_wait.as_millis() as u64,
+        }
+    }
+
+    fn release(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.running = state.running.saturating_sub(1);
+        state.grant_waiting();
+    }
+}
+
+/// A slot on the connection, given back when dropped.
+pub struct LimiterPermit {
+    limiter: Option<Arc<ConnectionLimiter>>,
+}
+
+impl Drop for LimiterPermit {
+    fn drop(&mut self) {
+        if let Some(limiter) = self.limiter.take() {
+            limiter.release();
+        }
+    }
+}
+
+/// A place in the queue. Dropping it leaves the queue, or gives the slot
+/// back if it was granted but never turned into a permit.
+pub struct QueueTicket {
+    limiter: Arc<ConnectionLimiter>,
+    ticket: u64,
+    granted: Option<oneshot::Receiver<()>>,
+}
+
+impl QueueTicket {
+    pub fn id(&self) -> u64 {
+        self.ticket
+    }
+
+    /// Waits for a slot; call again after a timeout to keep waiting.
+    pub async fn granted(&mut self) {
+        if let Some(granted) = self.granted.as_mut() {
+            // The sender only goes away by granting, so an error can't happen.
+            let _ = granted.await;
+        }
+    }
+
+    /// Turns a granted ticket into a permit, recording how long it waited.
+    pub fn into_permit(mut self, waited: Duration) -> LimiterPermit {
+        self.granted = None;
+        let mut state = self.limiter.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.total_wait += waited;
+        drop(state);
+        LimiterPermit {
+            limiter: Some(self.limiter.clone()),
+        }
+    }
+}
+
+impl Drop for QueueTicket {
+    fn drop(&mut self) {
+        if self.granted.is_none() {
+            return;
+        }
+        let mut state = self.limiter.state.lock().unwrap_or_else(|e| e.into_inner());
+        match state.waiting.iter().position(|waiter| waiter.ticket == self.ticket) {
+            Some(index) => {
+                state.waiting.remove(index);
+            }
+            None => {
+                // Granted while the caller was going away.
+                state.running = state.running.saturating_sub(1);
+                state.grant_waiting();
+            }
+        }
+    }
+}
+
+/// Interactive operations in flight per connection, whether or not it has a
+/// limit, so background work can stand aside while the user waits.
+#[derive(Default)]
+pub struct InteractiveCounts {
+    counts: Mutex<HashMap<String, usize>>,
+}
+
+impl InteractiveCounts {
+    pub fn enter(self: &Arc<Self>, connection_id: &str) -> InteractiveGuard {
+        let mut counts = self.counts.lock().unwrap_or_else(|e| e.into_inner());
+        *counts.entry(connection_id.to_string()).or_default() += 1;
+        InteractiveGuard {
+            counts: self.clone(),
+            connection_id: connection_id.to_string(),
+        }
+    }
+
+    pub fn is_busy(&self, connection_id: &str) -> bool {
+        let counts = self.counts.lock().unwrap_or_else(|e| e.into_inner());
+        counts.get(connection_id).is_some_and(|count| *count > 0)
+    }
+}
+
+pub struct InteractiveGuard {
+    counts: Arc<InteractiveCounts>,
+    connection_id: String,
+}
+
+impl Drop for InteractiveGuard {
+    fn drop(&mut self) {
+        let mut counts = self.counts.counts.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(count) = counts.get_mut(&self.connection_id) {
+            *count -= 1;
+            if *count == 0 {
+                counts.remove(&self.connection_id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn permit(admission: Admission) -> LimiterPermit {
+        match admission {
+            Admission::Granted(permit) => permit,
+            Admission::Queued(_) => panic!("expected a free slot"),
+        }
+    }
+
+    fn ticket(admission: Admission) -> QueueTicket {
+        match admission {
+            Admission::Queued(ticket) => ticket,
+            Admission::Granted(_) => panic!("expected to queue"),
+        }
+    }
+
+    #[tokio::test]
+    async fn serves_interactive_operations_before_background_ones() {
+        let limiter = Arc::new(ConnectionLimiter::new(1));
+        let running = permit(limiter.enter(QueueLane::Background));
+        let background = ticket(limiter.enter(QueueLane::Background));
+        let mut interactive = ticket(limiter.enter(QueueLane::Interactive));
+        assert_eq!(limiter.ahead_of(interactive.id()), Some(0));
+        assert_eq!(limiter.ahead_of(background.id()), Some(1));
+
+        drop(running);
+        interactive.granted().await;
+        assert_eq!(limiter.ahead_of(interactive.id()), None);
+        let interactive = interactive.into_permit(Duration::from_millis(5));
+        assert_eq!(limiter.ahead_of(background.id()), Some(0));
+
+        // Leaving the queue frees nothing; dropping the permit hands the slot on.
+        drop(background);
+        drop(interactive);
+        let stats = limiter.stats();
+        assert_eq!((stats.running, stats.queued), (0, 0));
+        assert_eq!(stats.max_queue_depth, 2);
+        assert_eq!(stats.queued_operations, 2);
+        assert_eq!(stats.total_wait_ms, 5);
+        let _free = permit(limiter.enter(QueueLane::Background));
+    }
+}
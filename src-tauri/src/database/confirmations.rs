@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long a confirmation token from a dry run stays valid.
+pub const CONFIRMATION_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// One-shot tokens confirming a destructive write. A dry run issues a token
+/// for the exact statement it previewed; running that statement redeems it.
+/// Every change to the map is a single insert or remove, so it stays usable
+/// after a panic elsewhere poisoned its lock.
+#[derive(Debug, Default)]
+pub struct WriteConfirmations {
+    pending: Mutex<HashMap<String, (String, Instant)>>,
+}
+
+impl WriteConfirmations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Issues a token for `fingerprint`, the statement text and its bound values.
+    pub fn issue(&self, fingerprint: String) -> String {
+        let token = uuid::Uuid::new_v4().to_string();
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        pending.retain(|_, (_, issued)| issued.elapsed() < CONFIRMATION_TTL);
+        pending.insert(token.clone(), (fingerprint, Instant::now()));
+        token
+    }
+
+    /// Consumes `token` if it was issued for the same statement and hasn't expired.
+    pub fn redeem(&self, token: &str, fingerprint: &str) -> bool {
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        match pending.get(token) {
+            Some((expected, issued)) if expected == fingerprint => {
+                let fresh = issued.elapsed() < CONFIRMATION_TTL;
+                pending.remove(token);
+                fresh
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_are_single_use_and_bound_to_the_statement() {
+        let confirmations = WriteConfirmations::new();
+        let token = confirmations.issue("UPDATE t SET a = 1".to_string());
+        assert!(!confirmations.redeem(&token, "UPDATE t SET a = 2"));
+        assert!(confirmations.redeem(&token, "UPDATE t SET a = 1"));
+        assert!(!confirmations.redeem(&token, "UPDATE t SET a = 1"));
+        assert!(!confirmations.redeem("unknown", "UPDATE t SET a = 1"));
+    }
+
+    #[test]
+    fn a_poisoned_lock_still_issues_and_redeems_tokens() {
+        let confirmations = std::sync::Arc::new(WriteConfirmations::new());
+        let poisoner = confirmations.clone();
+        let _ = std::thread::spawn(move || {
+            let _pending = poisoner.pending.lock().unwrap();
+            panic!("command panicked while holding the lock");
+        })
+        .join();
+        assert!(confirmations.pending.is_poisoned());
+
+        let token = confirmations.issue("DELETE FROM t".to_string());
+        assert!(confirmations.redeem(&token, "DELETE FROM t"));
+    }
+}
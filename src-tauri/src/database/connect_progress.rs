@@ -0,0 +1,210 @@
+use crate::models::{ConnectPhase, ConnectionConfig};
+use crate::operations::OperationHandle;
+use anyhow::{anyhow, Result};
+use std::future::Future;
+use std::time::Duration;
+
+/// Emitted as a connect moves from one phase to the next.
+pub const CONNECT_PROGRESS_EVENT: &str = "connect-progress";
+/// How long a connect may take when the connection doesn't say.
+pub const DEFAULT_CONNECT_TIMEOUT_SECONDS: u64 = 10;
+/// How often a connect in progress checks whether it was cancelled.
+pub const CANCEL_POLL: Duration = Duration::from_millis(100);
+
+/// Error kinds a failed connect is reported with, for the connection form.
+pub const KIND_TIMED_OUT: &str = "timed_out";
+pub const KIND_HOST_NOT_FOUND: &str = "host_not_found";
+pub const KIND_CONNECTION_REFUSED: &str = "connection_refused";
+
+/// A connect that got no answer within the connection's connect timeout.
+#[derive(Debug)]
+pub struct ConnectTimedOut {
+    pub seconds: u64,
+    pub endpoint: String,
+}
+
+impl std::fmt::Display for ConnectTimedOut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Connection timed out after {}s connecting to {}", self.seconds, self.endpoint)
+    }
+}
+
+impl std::error::Error for ConnectTimedOut {}
+
+/// A host name that DNS doesn't know.
+#[derive(Debug)]
+pub struct HostNotFound {
+    pub host: String,
+    pub reason: String,
+}
+
+impl std::fmt::Display for HostNotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Could not resolve host {}: {}", self.host, self.reason)
+    }
+}
+
+impl std::error::Error for HostNotFound {}
+
+/// A server that turned the connection away: nothing listens on the port,
+/// or a firewall rejects it.
+#[derive(Debug)]
+pub struct ConnectionRefused {
+    pub endpoint: String,
+}
+
+impl std::fmt::Display for ConnectionRefused {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Connection refused by {}: is the server running and listening on that port?",
+            self.endpoint
+        )
+    }
+}
+
+impl std::error::Error for ConnectionRefused {}
+
+/// The `KIND_*` of a failed connect, when it failed for one of those reasons.
+pub fn error_kind(error: &anyhow::Error) -> Option<&'static str> {
+    if error.is::<ConnectTimedOut>() {
+        Some(KIND_TIMED_OUT)
+    } else if error.is::<HostNotFound>() {
+        Some(KIND_HOST_NOT_FOUND)
+    } else if error.is::<ConnectionRefused>() {
+        Some(KIND_CONNECTION_REFUSED)
+    } else {
+        None
+    }
+}
+
+pub fn connect_timeout(config: &ConnectionConfig) -> Duration {
+    Duration::from_secs(
+        config
+            .connect_timeout_seconds
+            .unwrap_or(DEFAULT_CONNECT_TIMEOUT_SECONDS)
+            .max(1),
+    )
+}
+
+/// Position of `phase` for operation progress, out of `PHASE_COUNT`.
+pub fn phase_step(phase: ConnectPhase) -> u64 {
+    match phase {
+        ConnectPhase::ResolvingHost => 0,
+        ConnectPhase::EstablishingTunnel => 1,
+        ConnectPhase::ConnectingPool => 2,
+        ConnectPhase::ProbingCapabilities => 3,
+        ConnectPhase::Done => 4,
+    }
+}
+
+pub const PHASE_COUNT: u64 = 4;
+
+pub fn phase_label(phase: ConnectPhase) -> &'static str {
+    match phase {
+        ConnectPhase::ResolvingHost => "Resolving host",
+        ConnectPhase::EstablishingTunnel => "Establishing SSH tunnel",
+        ConnectPhase::ConnectingPool => "Connecting",
+        ConnectPhase::ProbingCapabilities => "Probing capabilities",
+        ConnectPhase::Done => "Connected",
+    }
+}
+
+/// Looks `host` up so a typo fails fast with a clear message instead of as a
+/// pool timeout. Unix socket paths are left to the driver.
+pub async fn resolve_host(host: &str, port: u16) -> Result<()> {
+    if host.is_empty() || host.starts_with('/') {
+        return Ok(());
+    }
+    let not_found = |reason: String| HostNotFound {
+        host: host.to_string(),
+        reason,
+    };
+    let mut addresses = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| not_found(e.to_string()))?;
+    addresses
+        .next()
+        .map(|_| ())
+        .ok_or_else(|| not_found("it has no addresses".to_string()).into())
+}
+
+/// Fails with `ConnectionRefused` when nothing accepts connections on
+/// `host:port`; sqlx pools retry a refused port until they time out. Any
+/// other failure is left for the driver to report.
+pub async fn ensure_listening(host: &str, port: u16) -> Result<()> {
+    if host.is_empty() || host.starts_with('/') {
+        return Ok(());
+    }
+    match tokio::net::TcpStream::connect((host, port)).await {
+        Err(e) if e.kind() == std::io::ErrorKind::ConnectionRefused => Err(ConnectionRefused {
+            endpoint: format!("{}:{}", host, port),
+        }
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+/// Runs `work` until it finishes or `operation` is cancelled. Cancelling drops
+/// `work` where it is waiting, along with anything it opened so far.
+pub async fn until_cancelled<T>(operation: &OperationHandle, work: impl Future<Output = Result<T>>) -> Result<T> {
+    operation.check_cancelled()?;
+    let cancelled = async {
+        while !operation.is_cancelled() {
+            tokio::time::sleep(CANCEL_POLL).await;
+        }
+    };
+    tokio::select! {
+        result = work => result,
+        _ = cancelled => Err(anyhow!("Connect cancelled")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::OperationRegistry;
+
+    #[tokio::test]
+    async fn cancelling_abandons_the_work_in_progress() {
+        let registry = OperationRegistry::new();
+        let operation = registry.start(None, "connect", None, "test").unwrap();
+        let id = operation.id().to_string();
+
+        let finished = until_cancelled(&operation, async { Ok(7) }).await.unwrap();
+        assert_eq!(finished, 7);
+
+        let cancel = async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            registry.cancel(&id).unwrap();
+        };
+        let (result, _) = tokio::join!(
+            until_cancelled(&operation, async {
+                tokio::time::sleep(Duration::from_secs(30)).await;
+                Ok(())
+            }),
+            cancel
+        );
+        assert_eq!(result.unwrap_err().to_string(), "Connect cancelled");
+    }
+
+    #[tokio::test]
+    async fn unknown_hosts_and_refused_ports_are_told_apart() {
+        let error = resolve_host("nodadb-test.invalid", 5432).await.unwrap_err();
+        assert_eq!(error_kind(&error), Some(KIND_HOST_NOT_FOUND));
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+        let error = ensure_listening("127.0.0.1", port).await.unwrap_err();
+        assert_eq!(error_kind(&error), Some(KIND_CONNECTION_REFUSED));
+        assert!(error.to_string().starts_with("Connection refused by 127.0.0.1:"));
+
+        let error = anyhow::Error::from(ConnectTimedOut {
+            seconds: 3,
+            endpoint: "db:5432".to_string(),
+        });
+        assert_eq!(error.to_string(), "Connection timed out after 3s connecting to db:5432");
+        assert_eq!(error_kind(&error), Some(KIND_TIMED_OUT));
+    }
+}
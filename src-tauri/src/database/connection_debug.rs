@@ -0,0 +1,886 @@
+use super::redaction::{config_secrets as secrets, mask_secrets as redact};
+use super::mssql::{self, MsSqlPool, DEFAULT_PORT as MSSQL_DEFAULT_PORT};
+use super::{connect_progress, connection_url, encryption, mysql_tls, unix_socket};
+use crate::models::{
+    ConnectionConfig, ConnectionDebugReport, ConnectionDebugStep, DatabaseType, DebugStepStatus, SSHAuthMethod,
+};
+use crate::ssh_tunnel::{SshSession, SshTunnel};
+use sqlx::Row;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long the TCP probe waits for the server to answer.
+pub const TCP_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub const STEP_VALIDATE: &str = "Validate configuration";
+pub const STEP_SSH_CONFIG: &str = "SSH configuration";
+pub const STEP_DNS: &str = "Resolve host";
+pub const STEP_TCP: &str = "TCP reachability";
+pub const STEP_SSH_TUNNEL: &str = "SSH tunnel";
+pub const STEP_HANDSHAKE: &str = "Database handshake";
+pub const STEP_TLS: &str = "TLS";
+
+/// Everything `ssh` would take from a `Host` block of `~/.ssh/config`.
+#[derive(Debug, Default, PartialEq)]
+pub struct SshConfigEntry {
+    pub host_name: Option<String>,
+    pub port: Option<u16>,
+    pub user: Option<String>,
+    pub identity_file: Option<String>,
+}
+
+struct Steps {
+    steps: Vec<ConnectionDebugStep>,
+    secrets: Vec<String>,
+}
+
+impl Steps {
+    fn push(&mut self, name: &str, started: Instant, status: DebugStepStatus, detail: String, causes: Vec<String>) {
+        self.steps.push(ConnectionDebugStep {
+            name: name.to_string(),
+            status,
+            duration_ms: started.elapsed().as_millis() as u64,
+            detail: redact(&detail, &self.secrets),
+            likely_causes: causes.iter().map(|cause| redact(cause, &self.secrets)).collect(),
+        });
+    }
+
+    fn skip(&mut self, names: &[&str], reason: &str) {
+        for name in names {
+            self.push(name, Instant::now(), DebugStepStatus::Skipped, reason.to_string(), Vec::new());
+        }
+    }
+}
+
+/// Runs every step of connecting to `config`, stopping at the first failure,
+/// and reports how each went. Nothing is saved and the connection is closed.
+pub async fn run(config: &ConnectionConfig) -> ConnectionDebugReport {
+    let started = Instant::now();
+    let mut steps = Steps {
+        steps: Vec::new(),
+        secrets: secrets(config),
+    };
+    run_steps(config, &mut steps).await;
+
+    let success = steps.steps.iter().all(|step| step.status != DebugStepStatus::Failed);
+    let mut report = ConnectionDebugReport {
+        connection_name: config.name.clone(),
+        db_type: config.db_type.clone(),
+        success,
+        total_ms: started.elapsed().as_millis() as u64,
+        steps: steps.steps,
+        text: String::new(),
+    };
+    report.text = render(&report, config);
+    report
+}
+
+async fn run_steps(config: &ConnectionConfig, steps: &mut Steps) {
+    let network_steps = [STEP_SSH_CONFIG, STEP_DNS, STEP_TCP, STEP_SSH_TUNNEL, STEP_HANDSHAKE, STEP_TLS];
+
+    let at = Instant::now();
+    let problems = validation_problems(config);
+    if !problems.is_empty() {
+        steps.push(
+            STEP_VALIDATE,
+            at,
+            DebugStepStatus::Failed,
+            problems.join("; "),
+            vec!["The connection form is missing fields this database type needs".to_string()],
+        );
+        steps.skip(&network_steps, "Not run: the configuration is incomplete");
+        return;
+    }
+    steps.push(STEP_VALIDATE, at, DebugStepStatus::Ok, "Required fields are set".to_string(), Vec::new());
+
+    if config.db_type == DatabaseType::SQLite {
+        steps.skip(&[STEP_SSH_CONFIG, STEP_DNS, STEP_TCP, STEP_SSH_TUNNEL], "Not used by SQLite files");
+        sqlite_handshake(config, steps).await;
+        steps.skip(&[STEP_TLS], "Not used by SQLite files");
+        return;
+    }
+
+    if let Some(path) = unix_socket::path(config) {
+        steps.skip(&[STEP_SSH_CONFIG, STEP_DNS, STEP_TCP, STEP_SSH_TUNNEL], "Not used by Unix socket connections");
+        server_handshake(config, steps, "", 0, &format!(" through the Unix socket {}", path), false).await;
+        return;
+    }
+
+    let db_host = config.host.clone().unwrap_or_default();
+    let db_port = config.port.unwrap_or_default();
+    let ssh = config.ssh_config.as_ref().filter(|ssh| ssh.enabled);
+
+    // SSH configuration
+    let at = Instant::now();
+    let ssh_entry = ssh.and_then(|ssh| {
+        let contents = std::fs::read_to_string(home_ssh_config()?).ok()?;
+        ssh_config_entry(&contents, &ssh.host)
+    });
+    match ssh {
+        None => steps.skip(&[STEP_SSH_CONFIG], "No SSH tunnel configured"),
+        Some(ssh) => {
+            let mut detail = format!(
+                "{}@{}:{} with {} authentication",
+                ssh.username,
+                ssh.host,
+                ssh.port,
+                match ssh.auth_method {
+                    SSHAuthMethod::Password => "password",
+                    SSHAuthMethod::PrivateKey => "private key",
+                }
+            );
+            let mut causes = Vec::new();
+            let mut status = DebugStepStatus::Ok;
+            if let (SSHAuthMethod::PrivateKey, Some(key)) = (&ssh.auth_method, ssh.private_key_path.as_deref()) {
+                if !std::path::Path::new(key).is_file() {
+                    status = DebugStepStatus::Failed;
+                    detail.push_str(&format!("; key file {} doesn't exist", key));
+                    causes.push("The private key path is wrong or the file was moved".to_string());
+                }
+            }
+            if let Some(entry) = &ssh_entry {
+                let differences = ssh_config_differences(entry, ssh);
+                if !differences.is_empty() && status == DebugStepStatus::Ok {
+                    status = DebugStepStatus::Warning;
+                }
+                if !differences.is_empty() {
+                    detail.push_str(&format!(
+                        "; ~/.ssh/config has a Host entry for {} ({}), which NodaDB doesn't apply",
+                        ssh.host,
+                        differences.join(", ")
+                    ));
+                    causes.push(
+                        "The SSH host is an alias from ~/.ssh/config; enter its HostName, Port and User in the connection"
+                            .to_string(),
+                    );
+                }
+            }
+            steps.push(STEP_SSH_CONFIG, at, status, detail, causes);
+            if status == DebugStepStatus::Failed {
+                steps.skip(&network_steps[1..], "Not run: the SSH configuration is unusable");
+                return;
+            }
+        }
+    }
+
+    // DNS, TCP and (optionally) the tunnel go to the SSH server when there is one.
+    let (probe_host, probe_port) = match ssh {
+        Some(ssh) => (ssh.host.clone(), ssh.port),
+        None => (db_host.clone(), db_port),
+    };
+
+    let at = Instant::now();
+    let addresses = if probe_host.starts_with('/') {
+        steps.push(STEP_DNS, at, DebugStepStatus::Skipped, format!("{} is a Unix socket", probe_host), Vec::new());
+        Vec::new()
+    } else {
+        match tokio::time::timeout(TCP_PROBE_TIMEOUT, tokio::net::lookup_host((probe_host.as_str(), probe_port))).await {
+            Ok(Ok(addresses)) => {
+                let addresses = addresses.collect::<Vec<_>>();
+                let listed = addresses.iter().map(|address| address.ip().to_string()).collect::<Vec<_>>();
+                steps.push(
+                    STEP_DNS,
+                    at,
+                    DebugStepStatus::Ok,
+                    format!("{} resolves to {}", probe_host, listed.join(", ")),
+                    Vec::new(),
+                );
+                addresses
+            }
+            failure => {
+                let error = match failure {
+                    Ok(Err(e)) => e.to_string(),
+                    _ => format!("no answer within {} seconds", TCP_PROBE_TIMEOUT.as_secs()),
+                };
+                let mut causes = vec![
+                    "The host name is misspelled".to_string(),
+                    "The host only resolves on a VPN or internal DNS that isn't connected".to_string(),
+                ];
+                if ssh_entry.is_some() {
+                    causes.insert(0, format!("{} is an alias from ~/.ssh/config, which NodaDB doesn't read", probe_host));
+                }
+                steps.push(STEP_DNS, at, DebugStepStatus::Failed, format!("Could not resolve {}: {}", probe_host, error), causes);
+                steps.skip(&network_steps[2..], "Not run: the host didn't resolve");
+                return;
+            }
+        }
+    };
+
+    if addresses.is_empty() {
+        steps.skip(&[STEP_TCP], "Unix sockets are left to the driver");
+    } else {
+        let at = Instant::now();
+        match probe_tcp(&addresses).await {
+            Ok(address) => steps.push(
+                STEP_TCP,
+                at,
+                DebugStepStatus::Ok,
+                format!("{} accepted a TCP connection", address),
+                Vec::new(),
+            ),
+            Err(error) => {
+                let db_type = if ssh.is_some() { None } else { Some(&config.db_type) };
+                steps.push(
+                    STEP_TCP,
+                    at,
+                    DebugStepStatus::Failed,
+                    format!("{}:{}: {}", probe_host, probe_port, error),
+                    tcp_failure_causes(&error, probe_port, db_type),
+                );
+                steps.skip(&network_steps[3..], "Not run: the server isn't reachable");
+                return;
+            }
+        }
+    }
+
+    // The tunnel has to stay open until the handshake is done.
+    let mut _tunnel = None;
+    let (actual_host, actual_port) = match ssh {
+        None => {
+            steps.skip(&[STEP_SSH_TUNNEL], "No SSH tunnel configured");
+            (db_host.clone(), db_port)
+        }
+        Some(ssh) => {
+            let at = Instant::now();
+            let ssh = ssh.clone();
+            let (forward_host, forward_port) = (db_host.clone(), db_port);
+            let opened = tokio::task::spawn_blocking(move || {
+                let session = SshSession::open(&ssh)?;
+                SshTunnel::forward(Arc::new(session), &forward_host, forward_port)
+            })
+            .await
+            .map_err(|e| anyhow::anyhow!("SSH tunnel setup failed: {}", e))
+            .and_then(|result| result);
+            match opened {
+                Ok(tunnel) => {
+                    let local_port = tunnel.local_port();
+                    steps.push(
+                        STEP_SSH_TUNNEL,
+                        at,
+                        DebugStepStatus::Ok,
+                        format!("Forwarding 127.0.0.1:{} to {}:{}", local_port, db_host, db_port),
+                        Vec::new(),
+                    );
+                    _tunnel = Some(tunnel);
+                    ("127.0.0.1".to_string(), local_port)
+                }
+                Err(e) => {
+                    let message = e.to_string();
+                    steps.push(STEP_SSH_TUNNEL, at, DebugStepStatus::Failed, message.clone(), ssh_failure_causes(&message));
+                    steps.skip(&[STEP_HANDSHAKE, STEP_TLS], "Not run: the SSH tunnel didn't open");
+                    return;
+                }
+            }
+        }
+    };
+
+    let via = if ssh.is_some() {
+        format!(" through the SSH tunnel to {}:{}", db_host, db_port)
+    } else {
+        String::new()
+    };
+    server_handshake(config, steps, &actual_host, actual_port, &via, ssh.is_some()).await;
+}
+
+/// Logs in to `config`'s server at `actual_host:actual_port`, or at its Unix
+/// socket when it names one, and records the handshake and TLS steps.
+async fn server_handshake(
+    config: &ConnectionConfig,
+    steps: &mut Steps,
+    actual_host: &str,
+    actual_port: u16,
+    via: &str,
+    tunnelled: bool,
+) {
+    let at = Instant::now();
+    let timeout = connect_progress::connect_timeout(config);
+    let username = config.username.clone().unwrap_or_default();
+    let password = config.password.clone().unwrap_or_default();
+    let database = config.database.clone().unwrap_or_default();
+    let socket_path = unix_socket::path(config);
+    match config.db_type {
+        DatabaseType::PostgreSQL => {
+            let options = match socket_path {
+                Some(path) => unix_socket::postgres_options(config, path),
+                None => format!("postgresql://{}:{}@{}:{}/{}", username, password, actual_host, actual_port, database)
+                    .parse::<sqlx::postgres::PgConnectOptions>()
+                    .map_err(anyhow::Error::from),
+            };
+            let options = match options {
+                Ok(options) => options,
+                Err(e) => {
+                    steps.push(STEP_HANDSHAKE, at, DebugStepStatus::Failed, e.to_string(), Vec::new());
+                    steps.skip(&[STEP_TLS], "Not run: the handshake failed");
+                    return;
+                }
+            };
+            let connected = tokio::time::timeout(timeout, async {
+                let mut conn = <sqlx::PgConnection as sqlx::Connection>::connect_with(&options).await?;
+                let version: String = sqlx::query_scalar("SELECT version()").fetch_one(&mut conn).await?;
+                let tls = sqlx::query("SELECT ssl, version FROM pg_stat_ssl WHERE pid = pg_backend_pid()")
+                    .fetch_optional(&mut conn)
+                    .await
+                    .ok()
+                    .flatten()
+                    .map(|row| {
+                        let on: bool = row.try_get(0).unwrap_or(false);
+                        on.then(|| row.try_get::<Option<String>, _>(1).ok().flatten().unwrap_or_default())
+                    });
+                let _ = sqlx::Connection::close(conn).await;
+                Ok::<_, sqlx::Error>((version, tls))
+            })
+            .await;
+            finish_handshake(steps, at, connected, timeout, &config.db_type, via, tunnelled);
+        }
+        DatabaseType::MySQL => {
+            let options = match socket_path {
+                Some(path) => unix_socket::mysql_options(config, path),
+                None => {
+                    let url = format!("mysql://{}:{}@{}:{}/{}", username, password, actual_host, actual_port, database);
+                    mysql_tls::connect_options(&url, config.mysql_ssl.as_ref())
+                }
+            };
+            let options = match options {
+                Ok(options) => options,
+                Err(e) => {
+                    steps.push(STEP_HANDSHAKE, at, DebugStepStatus::Failed, e.to_string(), Vec::new());
+                    steps.skip(&[STEP_TLS], "Not run: the handshake failed");
+                    return;
+                }
+            };
+            let connected = tokio::time::timeout(timeout, async {
+                let mut conn = <sqlx::MySqlConnection as sqlx::Connection>::connect_with(&options).await?;
+                let version: String = sqlx::query_scalar("SELECT VERSION()").fetch_one(&mut conn).await?;
+                let tls = sqlx::query("SHOW SESSION STATUS LIKE 'Ssl_version'")
+                    .fetch_optional(&mut conn)
+                    .await
+                    .ok()
+                    .flatten()
+                    .map(|row| row.try_get::<String, _>(1).ok().filter(|version| !version.is_empty()));
+                let _ = sqlx::Connection::close(conn).await;
+                Ok::<_, sqlx::Error>((format!("MySQL {}", version), tls))
+            })
+            .await;
+            finish_handshake(steps, at, connected, timeout, &config.db_type, via, tunnelled);
+        }
+        DatabaseType::MsSql => {
+            let connected = tokio::time::timeout(timeout, async {
+                let pool = MsSqlPool::connect(config, actual_host, actual_port).await?;
+                let version = pool.version().await;
+                pool.close().await;
+                version
+            })
+            .await;
+            match connected {
+                Ok(Ok(version)) => {
+                    let version = mssql::display_version(&version);
+                    steps.push(
+                        STEP_HANDSHAKE,
+                        at,
+                        DebugStepStatus::Ok,
+                        format!("Logged in{}; server is {}", via, version),
+                        Vec::new(),
+                    );
+                    steps.skip(&[STEP_TLS], "Not checked for SQL Server");
+                }
+                Ok(Err(e)) => {
+                    steps.push(STEP_HANDSHAKE, at, DebugStepStatus::Failed, e.to_string(), Vec::new());
+                    steps.skip(&[STEP_TLS], "Not run: the handshake failed");
+                }
+                Err(_) => {
+                    steps.push(
+                        STEP_HANDSHAKE,
+                        at,
+                        DebugStepStatus::Failed,
+                        format!("No answer within {} seconds", timeout.as_secs()),
+                        Vec::new(),
+                    );
+                    steps.skip(&[STEP_TLS], "Not run: the handshake failed");
+                }
+            }
+        }
+        DatabaseType::SQLite => unreachable!("SQLite returns before the network steps"),
+    }
+}
+
+type Handshake = Result<Result<(String, Option<Option<String>>), sqlx::Error>, tokio::time::error::Elapsed>;
+
+fn finish_handshake(
+    steps: &mut Steps,
+    at: Instant,
+    connected: Handshake,
+    timeout: Duration,
+    db_type: &DatabaseType,
+    via: &str,
+    tunnelled: bool,
+) {
+    match connected {
+        Ok(Ok((version, tls))) => {
+            steps.push(
+                STEP_HANDSHAKE,
+                at,
+                DebugStepStatus::Ok,
+                format!("Logged in{}; server is {}", via, version),
+                Vec::new(),
+            );
+            let at = Instant::now();
+            match tls {
+                Some(Some(protocol)) => steps.push(
+                    STEP_TLS,
+                    at,
+                    DebugStepStatus::Ok,
+                    format!("Encrypted with {}", if protocol.is_empty() { "TLS" } else { protocol.as_str() }),
+                    Vec::new(),
+                ),
+                Some(None) if tunnelled => steps.push(
+                    STEP_TLS,
+                    at,
+                    DebugStepStatus::Ok,
+                    "Not encrypted by the server; the SSH tunnel encrypts the traffic".to_string(),
+                    Vec::new(),
+                ),
+                Some(None) => steps.push(
+                    STEP_TLS,
+                    at,
+                    DebugStepStatus::Warning,
+                    "Not encrypted: the server didn't offer TLS, so the connection fell back to plain text".to_string(),
+                    vec!["TLS is turned off on the server".to_string()],
+                ),
+                None => steps.push(
+                    STEP_TLS,
+                    at,
+                    DebugStepStatus::Skipped,
+                    "The server didn't say whether the session is encrypted".to_string(),
+                    Vec::new(),
+                ),
+            }
+        }
+        Ok(Err(error)) => {
+            let causes = handshake_failure_causes(&error, db_type, tunnelled);
+            steps.push(STEP_HANDSHAKE, at, DebugStepStatus::Failed, error.to_string(), causes);
+            steps.skip(&[STEP_TLS], "Not run: the handshake failed");
+        }
+        Err(_) => {
+            let mut causes = vec![
+                "The server accepted the TCP connection but never finished the handshake".to_string(),
+                "Something between NodaDB and the server (proxy, load balancer) holds the connection open".to_string(),
+            ];
+            if tunnelled {
+                causes.insert(0, "The SSH server can't reach the database host and port".to_string());
+            }
+            steps.push(
+                STEP_HANDSHAKE,
+                at,
+                DebugStepStatus::Failed,
+                format!("No answer within {} seconds{}", timeout.as_secs(), via),
+                causes,
+            );
+            steps.skip(&[STEP_TLS], "Not run: the handshake failed");
+        }
+    }
+}
+
+async fn sqlite_handshake(config: &ConnectionConfig, steps: &mut Steps) {
+    let at = Instant::now();
+    let path = config.file_path.clone().unwrap_or_default();
+    let opened = async {
+        let pool = encryption::open_pool(
+            sqlx::sqlite::SqlitePoolOptions::new().max_connections(1),
+            connection_url::sqlite_connect_options(config)?,
+            config.encryption_key.as_ref(),
+            config.cipher_settings.as_ref(),
+        )
+        .await?;
+        let version: String = sqlx::query_scalar("SELECT sqlite_version()").fetch_one(&pool).await?;
+        pool.close().await;
+        Ok::<_, anyhow::Error>(version)
+    }
+    .await;
+    match opened {
+        Ok(version) => steps.push(
+            STEP_HANDSHAKE,
+            at,
+            DebugStepStatus::Ok,
+            format!("Opened {} with SQLite {}", path, version),
+            Vec::new(),
+        ),
+        Err(e) => {
+            let mut causes = Vec::new();
+            if encryption::error_kind(&e).is_some() {
+                causes.push("The encryption key or cipher settings don't match the file".to_string());
+            }
+            if !std::path::Path::new(&path).exists() {
+                causes.push("The file doesn't exist; it may have been moved or deleted".to_string());
+            }
+            causes.push("NodaDB isn't allowed to read the file or its folder".to_string());
+            steps.push(STEP_HANDSHAKE, at, DebugStepStatus::Failed, e.to_string(), causes);
+        }
+    }
+}
+
+/// What `config` lacks to be connectable at all.
+pub fn validation_problems(config: &ConnectionConfig) -> Vec<String> {
+    let missing = |value: &Option<String>| value.as_deref().is_none_or(|value| value.trim().is_empty());
+    let mut problems = Vec::new();
+    if config.db_type == DatabaseType::SQLite {
+        if missing(&config.file_path) {
+            problems.push("SQLite file path is required".to_string());
+        }
+        return problems;
+    }
+    // A socket replaces host and port, and its login may need no password.
+    let socket = unix_socket::path(config).is_some();
+    if !socket && missing(&config.host) {
+        problems.push("Host is required".to_string());
+    }
+    if !socket && config.port.is_none_or(|port| port == 0) {
+        problems.push("Port is required".to_string());
+    }
+    if missing(&config.username) {
+        problems.push("Username is required".to_string());
+    }
+    if !socket && config.password.is_none() {
+        problems.push("Password is required".to_string());
+    }
+    if missing(&config.database) {
+        problems.push("Database is required".to_string());
+    }
+    if let Some(ssh) = config.ssh_config.as_ref().filter(|ssh| ssh.enabled) {
+        if socket {
+            problems.push("A Unix socket connection can't go through an SSH tunnel".to_string());
+        }
+        if ssh.host.trim().is_empty() || ssh.username.trim().is_empty() {
+            problems.push("SSH host and username are required".to_string());
+        }
+        match ssh.auth_method {
+            SSHAuthMethod::Password if ssh.password.is_none() => problems.push("SSH password is required".to_string()),
+            SSHAuthMethod::PrivateKey if missing(&ssh.private_key_path) => {
+                problems.push("SSH private key path is required".to_string())
+            }
+            _ => {}
+        }
+    }
+    problems
+}
+
+fn home_ssh_config() -> Option<std::path::PathBuf> {
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+    Some(std::path::Path::new(&home).join(".ssh").join("config"))
+}
+
+/// Settings `ssh` would use for `alias` from the contents of an ssh config
+/// file. As in OpenSSH, the first value found for each setting wins.
+pub fn ssh_config_entry(contents: &str, alias: &str) -> Option<SshConfigEntry> {
+    let mut entry = SshConfigEntry::default();
+    let mut matched_any = false;
+    let mut applies = false;
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = line
+            .split_once(|c: char| c.is_whitespace() || c == '=')
+            .map(|(key, value)| (key, value.trim_start_matches(|c: char| c.is_whitespace() || c == '=').trim()))
+            .unwrap_or((line, ""));
+        match key.to_ascii_lowercase().as_str() {
+            "host" => {
+                let patterns = value.split_whitespace().collect::<Vec<_>>();
+                let negated = patterns
+                    .iter()
+                    .any(|pattern| pattern.strip_prefix('!').is_some_and(|pattern| host_matches(pattern, alias)));
+                applies = !negated
+                    && patterns
+                        .iter()
+                        .any(|pattern| !pattern.starts_with('!') && host_matches(pattern, alias));
+                // `Host *` alone says nothing about this host in particular.
+                matched_any |= applies && patterns != ["*"];
+            }
+            "match" => applies = false,
+            "hostname" if applies => {
+                entry.host_name.get_or_insert_with(|| value.to_string());
+            }
+            "port" if applies && entry.port.is_none() => entry.port = value.parse().ok(),
+            "user" if applies => {
+                entry.user.get_or_insert_with(|| value.to_string());
+            }
+            "identityfile" if applies => {
+                entry.identity_file.get_or_insert_with(|| value.to_string());
+            }
+            _ => {}
+        }
+    }
+    matched_any.then_some(entry)
+}
+
+/// `*` and `?` wildcards, compared case-insensitively.
+fn host_matches(pattern: &str, host: &str) -> bool {
+    fn matches(pattern: &[char], host: &[char]) -> bool {
+        match pattern.split_first() {
+            None => host.is_empty(),
+            Some(('*', rest)) => (0..=host.len()).any(|skip| matches(rest, &host[skip..])),
+            Some(('?', rest)) => !host.is_empty() && matches(rest, &host[1..]),
+            Some((c, rest)) => host.first().is_some_and(|h| h.eq_ignore_ascii_case(c)) && matches(rest, &host[1..]),
+        }
+    }
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let host = host.chars().collect::<Vec<_>>();
+    matches(&pattern, &host)
+}
+
+fn ssh_config_differences(entry: &SshConfigEntry, ssh: &crate::models::SSHConfig) -> Vec<String> {
+    let mut differences = Vec::new();
+    if let Some(host_name) = entry.host_name.as_ref().filter(|host_name| !host_name.eq_ignore_ascii_case(&ssh.host)) {
+        differences.push(format!("HostName {}", host_name));
+    }
+    if let Some(port) = entry.port.filter(|port| *port != ssh.port) {
+        differences.push(format!("Port {}", port));
+    }
+    if let Some(user) = entry.user.as_ref().filter(|user| **user != ssh.username) {
+        differences.push(format!("User {}", user));
+    }
+    if let Some(identity) = entry
+        .identity_file
+        .as_ref()
+        .filter(|identity| ssh.private_key_path.as_deref() != Some(identity.as_str()))
+    {
+        differences.push(format!("IdentityFile {}", identity));
+    }
+    differences
+}
+
+/// Tries each address in turn and returns the first that accepts.
+async fn probe_tcp(addresses: &[SocketAddr]) -> std::io::Result<SocketAddr> {
+    let mut last_error = std::io::Error::new(std::io::ErrorKind::NotFound, "no addresses");
+    for address in addresses {
+        match tokio::time::timeout(TCP_PROBE_TIMEOUT, tokio::net::TcpStream::connect(address)).await {
+            Ok(Ok(_)) => return Ok(*address),
+            Ok(Err(e)) => last_error = e,
+            Err(_) => {
+                last_error = std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    format!("no answer within {} seconds", TCP_PROBE_TIMEOUT.as_secs()),
+                )
+            }
+        }
+    }
+    Err(last_error)
+}
+
+pub fn default_port(db_type: &DatabaseType) -> Option<u16> {
+    match db_type {
+        DatabaseType::PostgreSQL => Some(5432),
+        DatabaseType::MySQL => Some(3306),
+        DatabaseType::MsSql => Some(MSSQL_DEFAULT_PORT),
+        DatabaseType::SQLite => None,
+    }
+}
+
+/// Likely reasons a TCP connect to `port` failed; `db_type` is None when the
+/// port is an SSH server's.
+pub fn tcp_failure_causes(error: &std::io::Error, port: u16, db_type: Option<&DatabaseType>) -> Vec<String> {
+    use std::io::ErrorKind;
+    let usual = match db_type {
+        Some(db_type) => default_port(db_type),
+        None => Some(22),
+    };
+    let unusual_port = usual
+        .filter(|usual| *usual != port)
+        .map(|usual| format!("Port {} is not the usual {}; check the port number", port, usual));
+    let mut causes = match error.kind() {
+        ErrorKind::ConnectionRefused => vec![
+            format!("Nothing is listening on port {}: the port is wrong or the server is down", port),
+            "The server only listens on localhost or a socket (listen_addresses / bind-address)".to_string(),
+        ],
+        ErrorKind::TimedOut => vec![
+            "A firewall or security group drops the traffic".to_string(),
+            "The host is right but the server is on a private network that needs a VPN or SSH tunnel".to_string(),
+        ],
+        ErrorKind::HostUnreachable | ErrorKind::NetworkUnreachable => vec![
+            "There is no route to the host; a VPN may be disconnected".to_string(),
+        ],
+        ErrorKind::ConnectionReset => vec![
+            "The server or a proxy closed the connection straight away; it may not allow this address".to_string(),
+        ],
+        _ => vec!["The network between NodaDB and the server is blocking the connection".to_string()],
+    };
+    // A wrong port is the usual reason for a refusal.
+    match unusual_port {
+        Some(unusual_port) if error.kind() == ErrorKind::ConnectionRefused => causes.insert(0, unusual_port),
+        Some(unusual_port) => causes.push(unusual_port),
+        None => {}
+    }
+    causes
+}
+
+/// Likely reasons the database refused the login.
+pub fn handshake_failure_causes(error: &sqlx::Error, db_type: &DatabaseType, tunnelled: bool) -> Vec<String> {
+    let auth = || {
+        vec![
+            "The username or password is wrong".to_string(),
+            match db_type {
+                DatabaseType::MySQL => "The user isn't allowed to log in from this host ('user'@'host')".to_string(),
+                _ => "pg_hba.conf doesn't allow this user from this address or with this method".to_string(),
+            },
+        ]
+    };
+    match error {
+        sqlx::Error::Database(db) => {
+            let mysql_number = db
+                .try_downcast_ref::<sqlx::mysql::MySqlDatabaseError>()
+                .map(|error| error.number());
+            match (db_type, db.code().as_deref(), mysql_number) {
+                (DatabaseType::MySQL, _, Some(1045)) | (DatabaseType::PostgreSQL, Some("28P01" | "28000"), _) => auth(),
+                (DatabaseType::MySQL, _, Some(1049)) | (DatabaseType::PostgreSQL, Some("3D000"), _) => {
+                    vec!["The database name is misspelled or the database doesn't exist on this server".to_string()]
+                }
+                (DatabaseType::MySQL, _, Some(1044)) | (DatabaseType::PostgreSQL, Some("42501"), _) => {
+                    vec!["The user exists but has no access to this database".to_string()]
+                }
+                (DatabaseType::MySQL, _, Some(1040)) | (DatabaseType::PostgreSQL, Some("53300"), _) => {
+                    vec!["The server has reached its connection limit".to_string()]
+                }
+                _ => vec!["The server rejected the login; see its log for details".to_string()],
+            }
+        }
+        sqlx::Error::Tls(_) => vec![
+            "The server requires TLS settings this client doesn't support, or its certificate is invalid".to_string(),
+        ],
+        sqlx::Error::Io(e) if tunnelled => {
+            let mut causes = vec!["The SSH server can't reach the database host and port".to_string()];
+            causes.extend(tcp_failure_causes(e, 0, None).into_iter().skip(1));
+            causes
+        }
+        sqlx::Error::Io(e) => tcp_failure_causes(e, 0, Some(db_type))
+            .into_iter()
+            .filter(|cause| !cause.starts_with("Port 0"))
+            .collect(),
+        sqlx::Error::Protocol(_) => vec![format!(
+            "Something other than {} answers on this port",
+            match db_type {
+                DatabaseType::MySQL => "MySQL",
+                _ => "PostgreSQL",
+            }
+        )],
+        _ => Vec::new(),
+    }
+}
+
+pub fn ssh_failure_causes(message: &str) -> Vec<String> {
+    let message = message.to_lowercase();
+    if message.contains("authentication") {
+        vec![
+            "The SSH username, password or key is wrong".to_string(),
+            "The key needs a passphrase, which NodaDB can't supply".to_string(),
+        ]
+    } else if message.contains("handshake") {
+        vec!["The SSH port answers but isn't an SSH server".to_string()]
+    } else {
+        vec!["The SSH server refused or dropped the connection".to_string()]
+    }
+}
+
+fn render(report: &ConnectionDebugReport, config: &ConnectionConfig) -> String {
+    let target = match config.db_type {
+        DatabaseType::SQLite => config.file_path.clone().unwrap_or_default(),
+        _ => format!(
+            "{}@{}:{}/{}",
+            config.username.as_deref().unwrap_or_default(),
+            config.host.as_deref().unwrap_or_default(),
+            config.port.unwrap_or_default(),
+            config.database.as_deref().unwrap_or_default()
+        ),
+    };
+    let mut lines = vec![
+        format!("NodaDB connection report: {}", report.connection_name),
+        format!("Database: {:?} {}", report.db_type, target),
+    ];
+    if let Some(ssh) = config.ssh_config.as_ref().filter(|ssh| ssh.enabled) {
+        lines.push(format!("SSH: {}@{}:{}", ssh.username, ssh.host, ssh.port));
+    }
+    lines.push(format!(
+        "Result: {} in {} ms",
+        if report.success { "connected" } else { "failed" },
+        report.total_ms
+    ));
+    lines.push(String::new());
+    for (number, step) in report.steps.iter().enumerate() {
+        let status = match step.status {
+            DebugStepStatus::Ok => "OK",
+            DebugStepStatus::Warning => "WARN",
+            DebugStepStatus::Failed => "FAIL",
+            DebugStepStatus::Skipped => "SKIP",
+        };
+        lines.push(format!(
+            "{}. [{}] {} ({} ms): {}",
+            number + 1,
+            status,
+            step.name,
+            step.duration_ms,
+            step.detail
+        ));
+        for cause in &step.likely_causes {
+            lines.push(format!("   - {}", cause));
+        }
+    }
+    redact(&lines.join("\n"), &secrets(config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ssh_config_entries_apply_first_match_wins() {
+        let contents = "\
+Host bastion prod-*
+    HostName 10.0.0.5
+    User deploy
+Host *
+    User everyone
+    Port 2222
+";
+        assert_eq!(
+            ssh_config_entry(contents, "prod-db"),
+            Some(SshConfigEntry {
+                host_name: Some("10.0.0.5".to_string()),
+                port: Some(2222),
+                user: Some("deploy".to_string()),
+                identity_file: None,
+            })
+        );
+        assert_eq!(ssh_config_entry(contents, "staging"), None, "only the catch-all matched");
+        assert!(host_matches("db?.internal", "DB1.internal"));
+    }
+
+    #[test]
+    fn failures_come_with_likely_causes_and_no_secrets() {
+        let refused = std::io::Error::from(std::io::ErrorKind::ConnectionRefused);
+        let causes = tcp_failure_causes(&refused, 5433, Some(&DatabaseType::PostgreSQL));
+        assert!(causes[0].contains("not the usual 5432"));
+        assert!(causes[1].contains("Nothing is listening"));
+        let timed_out = std::io::Error::from(std::io::ErrorKind::TimedOut);
+        assert!(tcp_failure_causes(&timed_out, 5432, Some(&DatabaseType::PostgreSQL))[0].contains("firewall"));
+
+        assert!(ssh_failure_causes("SSH password authentication failed: -18")[0].contains("password"));
+        assert_eq!(
+            redact("postgresql://app:hunter2@db:5432/app", &["hunter2".to_string()]),
+            "postgresql://app:***@db:5432/app"
+        );
+    }
+
+    #[test]
+    fn socket_connections_need_no_host_or_port() {
+        let mut config: ConnectionConfig = serde_json::from_value(serde_json::json!({
+            "id": "c", "name": "c", "db_type": "mysql", "username": "app", "database": "shop"
+        }))
+        .unwrap();
+        assert_eq!(
+            validation_problems(&config),
+            vec!["Host is required", "Port is required", "Password is required"]
+        );
+        config.socket_path = Some("/var/run/mysqld/mysqld.sock".to_string());
+        assert!(validation_problems(&config).is_empty());
+    }
+}
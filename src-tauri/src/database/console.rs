@@ -0,0 +1,133 @@
+use super::{mssql, ConnectionManager, DatabasePool};
+use crate::models::QueryResult;
+use anyhow::{anyhow, Result};
+use base64::Engine;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use sqlx::pool::PoolConnection;
+use sqlx::types::BigDecimal;
+use sqlx::{Column, Row, TypeInfo};
+use std::time::{Duration, Instant};
+
+pub const CONSOLE_SESSION_CLOSED_EVENT: &str = "console-session-closed";
+/// Idle period after which a console session is closed when the caller doesn't pick one.
+pub const DEFAULT_CONSOLE_IDLE_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+/// How often idle console sessions are looked for.
+pub const CONSOLE_REAPER_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A single pooled connection checked out for the lifetime of a console session.
+pub enum ConsoleConnection {
+    Sqlite(PoolConnection<sqlx::Sqlite>),
+    Postgres(PoolConnection<sqlx::Postgres>),
+    MySql(PoolConnection<sqlx::MySql>),
+}
+
+pub struct ConsoleSession {
+    pub connection_id: String,
+    pub opened_at: String,
+    pub idle_timeout: Duration,
+    pub last_used: Instant,
+    connection: ConsoleConnection,
+    /// DROP statements for temp tables left in the session by cross-connection
+    /// queries; run on close so they don't linger on the pooled connection.
+    temp_tables: Vec<String>,
+}
+
+impl ConsoleSession {
+    pub async fn open(connection_id: &str, pool: &DatabasePool, idle_timeout: Duration) -> Result<Self> {
+        let connection = match pool {
+            DatabasePool::Sqlite(pool) => ConsoleConnection::Sqlite(pool.acquire().await?),
+            DatabasePool::Postgres(pool) => ConsoleConnection::Postgres(pool.acquire().await?),
+            DatabasePool::MySql(pool) => ConsoleConnection::MySql(pool.acquire().await?),
+            DatabasePool::MsSql(_) => return Err(mssql::unsupported("Console sessions")),
+        };
+        Ok(Self {
+            connection_id: connection_id.to_string(),
+            opened_at: Utc::now().to_rfc3339(),
+            idle_timeout,
+            last_used: Instant::now(),
+            connection,
+            temp_tables: Vec::new(),
+        })
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.last_used.elapsed() >= self.idle_timeout
+    }
+
+    /// Runs `sql` on the pinned connection, so SET, temp tables and prepared
+    /// statements from earlier console queries are still in effect.
+    pub async fn execute(&mut self, sql: &str) -> Result<QueryResult> {
+        self.last_used = Instant::now();
+        let result = match &mut self.connection {
+            ConsoleConnection::Sqlite(connection) => {
+                let rows = sqlx::query(sql)
+                    .fetch_all(&mut **connection)
+                    .await
+                    .map_err(ConnectionManager::format_sqlx_error)?;
+                let converter = |r: Vec<sqlx::sqlite::SqliteRow>| -> Result<QueryResult> {
+                    Ok(process_rows!(r, common))
+                };
+                converter(rows)
+            }
+            ConsoleConnection::Postgres(connection) => {
+                let rows = sqlx::query(sql)
+                    .fetch_all(&mut **connection)
+                    .await
+                    .map_err(ConnectionManager::format_sqlx_error)?;
+                let converter = |r: Vec<sqlx::postgres::PgRow>| -> Result<QueryResult> {
+                    Ok(process_rows!(r, postgres))
+                };
+                converter(rows)
+            }
+            ConsoleConnection::MySql(connection) => {
+                let rows = sqlx::query(sql)
+                    .fetch_all(&mut **connection)
+                    .await
+                    .map_err(ConnectionManager::format_sqlx_error)?;
+                let converter = |r: Vec<sqlx::mysql::MySqlRow>| -> Result<QueryResult> {
+                    Ok(process_rows!(r, common))
+                };
+                converter(rows)
+            }
+        };
+        self.last_used = Instant::now();
+        result
+    }
+
+    /// Runs a statement that returns no rows and reports how many rows it changed.
+    pub async fn execute_write(&mut self, sql: &str) -> Result<u64> {
+        self.last_used = Instant::now();
+        let affected = match &mut self.connection {
+            ConsoleConnection::Sqlite(connection) => sqlx::query(sql)
+                .execute(&mut **connection)
+                .await
+                .map(|done| done.rows_affected()),
+            ConsoleConnection::Postgres(connection) => sqlx::query(sql)
+                .execute(&mut **connection)
+                .await
+                .map(|done| done.rows_affected()),
+            ConsoleConnection::MySql(connection) => sqlx::query(sql)
+                .execute(&mut **connection)
+                .await
+                .map(|done| done.rows_affected()),
+        }
+        .map_err(ConnectionManager::format_sqlx_error)?;
+        self.last_used = Instant::now();
+        Ok(affected)
+    }
+
+    pub fn keep_temp_table(&mut self, drop_statement: String) {
+        self.temp_tables.push(drop_statement);
+    }
+
+    /// Drops the temp tables kept in the session, ignoring ones already gone.
+    pub async fn drop_temp_tables(&mut self) {
+        for statement in std::mem::take(&mut self.temp_tables) {
+            let _ = self.execute_write(&statement).await;
+        }
+    }
+}
+
+pub fn session_not_found(session_id: &str) -> anyhow::Error {
+    anyhow!("Console session {} not found (it may have been closed after being idle)", session_id)
+}
@@ -0,0 +1,128 @@
+use crate::models::{ColumnTypeFamily, DatabaseType};
+use anyhow::{anyhow, Result};
+
+/// Most rows a cross-connection query copies, whatever the request's cap.
+pub const MAX_ROW_CAP: u64 = 100_000;
+/// Rows per INSERT while copying.
+pub const COPY_CHUNK_SIZE: usize = 500;
+/// Stands for the quoted temp table name in the user's query.
+pub const TEMP_TABLE_PLACEHOLDER: &str = "{{temp_table}}";
+
+/// Name for the temp table: the requested one, or one derived from the
+/// source table that won't clash with another copy.
+pub fn temp_table_name(requested: Option<&str>, source_table: &str) -> Result<String> {
+    if let Some(name) = requested {
+        let valid = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            && name.len() <= 63;
+        return if valid {
+            Ok(name.to_string())
+        } else {
+            Err(anyhow!(
+                "Temp table name {} must be letters, digits and underscores, starting with a letter",
+                name
+            ))
+        };
+    }
+    let base = source_table
+        .rsplit('.')
+        .next()
+        .unwrap_or(source_table)
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .take(32)
+        .collect::<String>()
+        .to_ascii_lowercase();
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    Ok(format!("noda_cross_{}_{}", base, &suffix[..8]))
+}
+
+/// Column type on `target` for values of `family` read from another backend.
+/// Anything without a close equivalent is kept as text.
+pub fn column_type(family: &ColumnTypeFamily, target: &DatabaseType) -> &'static str {
+    match (target, family) {
+        (DatabaseType::PostgreSQL, ColumnTypeFamily::Integer) => "BIGINT",
+        (DatabaseType::PostgreSQL, ColumnTypeFamily::Float) => "DOUBLE PRECISION",
+        (DatabaseType::PostgreSQL, ColumnTypeFamily::Decimal) => "NUMERIC",
+        (DatabaseType::PostgreSQL, ColumnTypeFamily::Boolean) => "BOOLEAN",
+        (DatabaseType::PostgreSQL, ColumnTypeFamily::DateTime) => "TIMESTAMP",
+        (DatabaseType::PostgreSQL, ColumnTypeFamily::Date) => "DATE",
+        (DatabaseType::PostgreSQL, ColumnTypeFamily::Json) => "JSONB",
+        (DatabaseType::PostgreSQL, _) => "TEXT",
+        (DatabaseType::MySQL, ColumnTypeFamily::Integer) => "BIGINT",
+        (DatabaseType::MySQL, ColumnTypeFamily::Float) => "DOUBLE",
+        (DatabaseType::MySQL, ColumnTypeFamily::Decimal) => "DECIMAL(65, 30)",
+        (DatabaseType::MySQL, ColumnTypeFamily::Boolean) => "BOOLEAN",
+        (DatabaseType::MySQL, ColumnTypeFamily::DateTime) => "DATETIME(6)",
+        (DatabaseType::MySQL, ColumnTypeFamily::Date) => "DATE",
+        (DatabaseType::MySQL, ColumnTypeFamily::Json) => "JSON",
+        (DatabaseType::MySQL, _) => "LONGTEXT",
+        (DatabaseType::SQLite, ColumnTypeFamily::Integer | ColumnTypeFamily::Boolean) => "INTEGER",
+        (DatabaseType::SQLite, ColumnTypeFamily::Float) => "REAL",
+        (DatabaseType::SQLite, ColumnTypeFamily::Decimal) => "NUMERIC",
+        (DatabaseType::SQLite, _) => "TEXT",
+        (DatabaseType::MsSql, ColumnTypeFamily::Integer) => "BIGINT",
+        (DatabaseType::MsSql, ColumnTypeFamily::Float) => "FLOAT",
+        (DatabaseType::MsSql, ColumnTypeFamily::Decimal) => "DECIMAL(38, 10)",
+        (DatabaseType::MsSql, ColumnTypeFamily::Boolean) => "BIT",
+        (DatabaseType::MsSql, ColumnTypeFamily::DateTime) => "DATETIME2",
+        (DatabaseType::MsSql, ColumnTypeFamily::Date) => "DATE",
+        (DatabaseType::MsSql, _) => "NVARCHAR(MAX)",
+    }
+}
+
+pub fn create_statement(quoted_table: &str, columns: &[(String, &str)]) -> String {
+    let definitions = columns
+        .iter()
+        .map(|(quoted, column_type)| format!("{} {}", quoted, column_type))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("CREATE TEMPORARY TABLE {} ({})", quoted_table, definitions)
+}
+
+/// Drops the temp table and never a real table that happens to share its name.
+pub fn drop_statement(target: &DatabaseType, quoted_table: &str) -> String {
+    match target {
+        DatabaseType::PostgreSQL => format!("DROP TABLE IF EXISTS pg_temp.{}", quoted_table),
+        DatabaseType::MySQL => format!("DROP TEMPORARY TABLE IF EXISTS {}", quoted_table),
+        DatabaseType::SQLite => format!("DROP TABLE IF EXISTS temp.{}", quoted_table),
+        DatabaseType::MsSql => format!("DROP TABLE IF EXISTS {}", quoted_table),
+    }
+}
+
+pub fn expand_query(query: &str, quoted_table: &str) -> String {
+    query.replace(TEMP_TABLE_PLACEHOLDER, quoted_table)
+}
+
+pub fn over_cap_error(source_table: &str, row_cap: u64) -> anyhow::Error {
+    anyhow!(
+        "The selection from {} has more than {} rows. Add filters or raise the row cap (at most {}); \
+         for larger copies export the rows and import them instead",
+        source_table,
+        row_cap,
+        MAX_ROW_CAP
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn temp_tables_are_named_safely_and_dropped_only_as_temp_tables() {
+        let name = temp_table_name(None, "public.Price-List").unwrap();
+        assert!(name.starts_with("noda_cross_pricelist_"));
+        assert_eq!(name.len(), "noda_cross_pricelist_".len() + 8);
+        assert_eq!(temp_table_name(Some("prices"), "x").unwrap(), "prices");
+        assert!(temp_table_name(Some("prices; DROP TABLE users"), "x").is_err());
+        assert!(temp_table_name(Some("1prices"), "x").is_err());
+
+        assert_eq!(drop_statement(&DatabaseType::PostgreSQL, "\"p\""), "DROP TABLE IF EXISTS pg_temp.\"p\"");
+        assert_eq!(drop_statement(&DatabaseType::MySQL, "`p`"), "DROP TEMPORARY TABLE IF EXISTS `p`");
+        assert_eq!(
+            expand_query("SELECT * FROM items JOIN {{temp_table}} p ON p.id = items.id", "\"p\""),
+            "SELECT * FROM items JOIN \"p\" p ON p.id = items.id"
+        );
+        assert_eq!(column_type(&ColumnTypeFamily::Uuid, &DatabaseType::SQLite), "TEXT");
+    }
+}
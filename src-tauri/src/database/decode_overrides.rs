@@ -0,0 +1,285 @@
+use crate::models::{DecodeStrategy, DecodedColumn, UnknownColumnType};
+use base64::Engine;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Extension types read as text on every connection unless overridden.
+const BUILTIN_AS_TEXT: &[&str] = &["citext", "hstore"];
+/// Column names kept per unknown type.
+const MAX_UNKNOWN_COLUMNS: usize = 10;
+
+/// Built-in Postgres OIDs that can appear inside a composite value.
+const BOOL_OID: u32 = 16;
+const INT8_OID: u32 = 20;
+const INT2_OID: u32 = 21;
+const INT4_OID: u32 = 23;
+const FLOAT4_OID: u32 = 700;
+const FLOAT8_OID: u32 = 701;
+
+/// A column the decoder couldn't read, so its non-null values came back null.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodeFailure {
+    pub type_name: String,
+    pub oid: Option<u32>,
+    pub column_name: String,
+}
+
+/// Decode overrides for one batch of rows, and what happened while decoding.
+pub struct Decoding {
+    /// Strategy per lower-cased type name, or per OID written in digits.
+    overrides: BTreeMap<String, DecodeStrategy>,
+    pub decoded: Vec<DecodedColumn>,
+    pub failures: Vec<DecodeFailure>,
+    /// Text that isn't UTF-8 is read as MySQL latin1 instead of failing.
+    pub latin1_text: bool,
+}
+
+impl Default for Decoding {
+    fn default() -> Self {
+        Self::new(&BTreeMap::new())
+    }
+}
+
+impl Decoding {
+    /// The built-in defaults with a connection's `overrides` on top.
+    pub fn new(overrides: &BTreeMap<String, DecodeStrategy>) -> Self {
+        let mut merged = BUILTIN_AS_TEXT
+            .iter()
+            .map(|name| (name.to_string(), DecodeStrategy::AsText))
+            .collect::<BTreeMap<_, _>>();
+        for (key, strategy) in overrides {
+            merged.insert(key.trim().to_lowercase(), *strategy);
+        }
+        Self {
+            overrides: merged,
+            decoded: Vec::new(),
+            failures: Vec::new(),
+            latin1_text: false,
+        }
+    }
+
+    pub fn with_latin1_text(mut self, latin1_text: bool) -> Self {
+        self.latin1_text = latin1_text;
+        self
+    }
+
+    /// The override for a column type, by OID first when the driver has one.
+    pub fn strategy(&self, type_name: &str, oid: Option<u32>) -> Option<DecodeStrategy> {
+        oid.and_then(|oid| self.overrides.get(&oid.to_string()))
+            .or_else(|| self.overrides.get(&type_name.to_lowercase()))
+            .copied()
+    }
+
+    /// Notes that `column_name` was decoded with an override.
+    pub fn mark(&mut self, column_name: &str, type_name: &str, strategy: DecodeStrategy) {
+        if !self.decoded.iter().any(|decoded| decoded.column_name == column_name) {
+            self.decoded.push(DecodedColumn {
+                column_name: column_name.to_string(),
+                type_name: type_name.to_lowercase(),
+                strategy,
+            });
+        }
+    }
+
+    /// Notes that a non-null value of `column_name` couldn't be decoded.
+    pub fn failed(&mut self, column_name: &str, type_name: &str, oid: Option<u32>) {
+        let failure = DecodeFailure {
+            type_name: type_name.to_lowercase(),
+            oid,
+            column_name: column_name.to_string(),
+        };
+        if !self.failures.contains(&failure) {
+            self.failures.push(failure);
+        }
+    }
+}
+
+/// Adds one batch's `failures` to a connection's unknown types.
+pub fn record_failures(known: &mut BTreeMap<String, UnknownColumnType>, failures: &[DecodeFailure], seen_at: &str) {
+    for failure in failures {
+        let entry = known
+            .entry(failure.type_name.clone())
+            .or_insert_with(|| UnknownColumnType {
+                type_name: failure.type_name.clone(),
+                oid: failure.oid,
+                columns: Vec::new(),
+                failures: 0,
+                last_seen: String::new(),
+            });
+        entry.failures += 1;
+        entry.last_seen = seen_at.to_string();
+        if !entry.columns.contains(&failure.column_name) && entry.columns.len() < MAX_UNKNOWN_COLUMNS {
+            entry.columns.push(failure.column_name.clone());
+        }
+    }
+}
+
+/// Raw bytes as a JSON string, base64 encoded like BLOB cells.
+pub fn binary(bytes: &[u8]) -> Value {
+    Value::String(base64::engine::general_purpose::STANDARD.encode(bytes))
+}
+
+/// Bytes as text when they read as printable UTF-8, else as `\x` hex the
+/// way psql shows bytea.
+fn text_or_hex(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text) if !text.chars().any(|c| c.is_control() && !matches!(c, '\t' | '\n' | '\r')) => text.to_string(),
+        _ => format!("\\x{}", bytes.iter().map(|byte| format!("{:02x}", byte)).collect::<String>()),
+    }
+}
+
+/// Reads a big-endian i32 length or count from the front of `bytes`.
+fn take_i32(bytes: &mut &[u8]) -> Option<i32> {
+    let (head, rest) = bytes.split_first_chunk::<4>()?;
+    *bytes = rest;
+    Some(i32::from_be_bytes(*head))
+}
+
+fn take_bytes<'a>(bytes: &mut &'a [u8], length: i32) -> Option<Option<&'a [u8]>> {
+    if length < 0 {
+        return Some(None);
+    }
+    let length = length as usize;
+    if bytes.len() < length {
+        return None;
+    }
+    let (head, rest) = bytes.split_at(length);
+    *bytes = rest;
+    Some(Some(head))
+}
+
+fn quote_hstore(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// hstore's binary form as its text form: `"a"=>"1", "b"=>NULL`.
+fn hstore_text(mut bytes: &[u8]) -> Option<String> {
+    let count = take_i32(&mut bytes)?;
+    let mut pairs = Vec::with_capacity(count.max(0) as usize);
+    for _ in 0..count {
+        let key_length = take_i32(&mut bytes)?;
+        let key = take_bytes(&mut bytes, key_length)??;
+        let value_length = take_i32(&mut bytes)?;
+        let value = take_bytes(&mut bytes, value_length)?;
+        pairs.push(format!(
+            "{}=>{}",
+            quote_hstore(&String::from_utf8_lossy(key)),
+            value.map_or_else(|| "NULL".to_string(), |value| quote_hstore(&String::from_utf8_lossy(value)))
+        ));
+    }
+    Some(pairs.join(", "))
+}
+
+/// A field of a binary composite in Postgres' text form.
+fn field_text(oid: u32, bytes: &[u8]) -> String {
+    let number = |bytes: &[u8]| -> Option<String> {
+        Some(match (oid, bytes.len()) {
+            (BOOL_OID, 1) => if bytes[0] == 0 { "f" } else { "t" }.to_string(),
+            (INT2_OID, 2) => i16::from_be_bytes(bytes.try_into().ok()?).to_string(),
+            (INT4_OID, 4) => i32::from_be_bytes(bytes.try_into().ok()?).to_string(),
+            (INT8_OID, 8) => i64::from_be_bytes(bytes.try_into().ok()?).to_string(),
+            (FLOAT4_OID, 4) => f32::from_be_bytes(bytes.try_into().ok()?).to_string(),
+            (FLOAT8_OID, 8) => f64::from_be_bytes(bytes.try_into().ok()?).to_string(),
+            _ => return None,
+        })
+    };
+    number(bytes).unwrap_or_else(|| text_or_hex(bytes))
+}
+
+/// A composite's binary form as its text form: `(1,"two words",)`. Fields of
+/// types other than text, booleans and plain numbers are shown as hex.
+fn composite_text(mut bytes: &[u8]) -> Option<String> {
+    let count = take_i32(&mut bytes)?;
+    let mut fields = Vec::with_capacity(count.max(0) as usize);
+    for _ in 0..count {
+        let oid = take_i32(&mut bytes)? as u32;
+        let length = take_i32(&mut bytes)?;
+        let field = take_bytes(&mut bytes, length)?.map(|value| field_text(oid, value));
+        fields.push(match field {
+            None => String::new(),
+            Some(text) if text.is_empty() || text.contains(|c: char| "(),\"\\".contains(c) || c.is_whitespace()) => {
+                format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\"\""))
+            }
+            Some(text) => text,
+        });
+    }
+    Some(format!("({})", fields.join(",")))
+}
+
+/// A Postgres value read as text. Values in text format are taken as they
+/// are; binary hstore and composite values are rendered the way the server
+/// would print them, and anything else that isn't text is shown as hex.
+pub fn pg_text(type_name: &str, composite: bool, binary_format: bool, bytes: &[u8]) -> Value {
+    if !binary_format {
+        return Value::String(String::from_utf8_lossy(bytes).into_owned());
+    }
+    let rendered = if type_name.eq_ignore_ascii_case("hstore") {
+        hstore_text(bytes)
+    } else if composite {
+        composite_text(bytes)
+    } else {
+        None
+    };
+    Value::String(rendered.unwrap_or_else(|| text_or_hex(bytes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn length_prefixed(parts: &[Option<&[u8]>]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for part in parts {
+            match part {
+                Some(part) => {
+                    bytes.extend((part.len() as i32).to_be_bytes());
+                    bytes.extend(*part);
+                }
+                None => bytes.extend((-1i32).to_be_bytes()),
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn citext_and_hstore_are_read_as_text_by_default() {
+        let mut overrides = BTreeMap::new();
+        overrides.insert("HSTORE".to_string(), DecodeStrategy::Skip);
+        overrides.insert("16385".to_string(), DecodeStrategy::AsBinary);
+        let decoding = Decoding::new(&overrides);
+        assert_eq!(decoding.strategy("CITEXT", None), Some(DecodeStrategy::AsText));
+        assert_eq!(decoding.strategy("hstore", None), Some(DecodeStrategy::Skip));
+        assert_eq!(decoding.strategy("mood", Some(16385)), Some(DecodeStrategy::AsBinary));
+        assert_eq!(decoding.strategy("int4", Some(23)), None);
+
+        assert_eq!(pg_text("citext", false, true, b"Hello"), Value::String("Hello".to_string()));
+        let mut hstore = 2i32.to_be_bytes().to_vec();
+        hstore.extend(length_prefixed(&[Some(b"a"), Some(b"say \"hi\""), Some(b"b"), None]));
+        assert_eq!(pg_text("hstore", false, true, &hstore), Value::String("\"a\"=>\"say \\\"hi\\\"\", \"b\"=>NULL".to_string()));
+
+        let mut composite = 3i32.to_be_bytes().to_vec();
+        composite.extend(INT4_OID.to_be_bytes());
+        composite.extend(length_prefixed(&[Some(&7i32.to_be_bytes())]));
+        composite.extend(25u32.to_be_bytes());
+        composite.extend(length_prefixed(&[Some(b"two words")]));
+        composite.extend(25u32.to_be_bytes());
+        composite.extend(length_prefixed(&[None]));
+        assert_eq!(pg_text("pair", true, true, &composite), Value::String("(7,\"two words\",)".to_string()));
+        assert_eq!(pg_text("blob", false, true, &[0, 255]), Value::String("\\x00ff".to_string()));
+    }
+
+    #[test]
+    fn decode_failures_are_counted_per_type() {
+        let mut decoding = Decoding::default();
+        decoding.failed("shape", "BOX3D", None);
+        decoding.failed("shape", "BOX3D", None);
+        decoding.failed("bounds", "BOX3D", None);
+        let mut known = BTreeMap::new();
+        record_failures(&mut known, &decoding.failures, "2026-10-18T00:00:00Z");
+        record_failures(&mut known, &decoding.failures[..1], "2026-10-19T00:00:00Z");
+        let box3d = &known["box3d"];
+        assert_eq!(box3d.failures, 3);
+        assert_eq!(box3d.columns, vec!["shape".to_string(), "bounds".to_string()]);
+        assert_eq!(box3d.last_seen, "2026-10-19T00:00:00Z");
+    }
+}
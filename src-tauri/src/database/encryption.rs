@@ -0,0 +1,164 @@
+use super::escaping;
+use crate::models::{SecretString, SqlCipherSettings};
+use anyhow::{anyhow, Result};
+use sqlx::pool::PoolOptions;
+use sqlx::sqlite::{Sqlite, SqliteConnectOptions, SqlitePool};
+use sqlx::{ConnectOptions, Connection};
+
+/// `ConnectionTestResult::error_kind` for a wrong SQLCipher key.
+pub const ERROR_KIND_BAD_KEY: &str = "bad_encryption_key";
+
+/// `ConnectionTestResult::error_kind` for a key given for a plain file.
+pub const ERROR_KIND_NOT_ENCRYPTED: &str = "not_encrypted";
+
+/// SQLITE_NOTADB, what SQLite reports for an encrypted file read with the wrong key.
+const SQLITE_NOTADB: &str = "26";
+
+/// The key doesn't decrypt the file (or the file isn't a SQLCipher database).
+#[derive(Debug)]
+pub struct BadEncryptionKey;
+
+impl std::fmt::Display for BadEncryptionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("The encryption key is wrong, or the file is not an encrypted SQLite database")
+    }
+}
+
+impl std::error::Error for BadEncryptionKey {}
+
+/// A key was given, but the file opens without one.
+#[derive(Debug)]
+pub struct NotEncrypted;
+
+impl std::fmt::Display for NotEncrypted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("The file is not encrypted; remove the encryption key to open it")
+    }
+}
+
+impl std::error::Error for NotEncrypted {}
+
+/// Replaces any occurrence of `key` in `error` so it can be shown or logged.
+fn scrub(error: impl std::fmt::Display, key: Option<&SecretString>) -> anyhow::Error {
+    let message = error.to_string();
+    match key.filter(|key| !key.0.is_empty()) {
+        Some(key) => anyhow!(message.replace(&key.0, "***")),
+        None => anyhow!(message),
+    }
+}
+
+fn is_not_a_database(error: &sqlx::Error) -> bool {
+    match error {
+        sqlx::Error::Database(db_err) => db_err.code().as_deref() == Some(SQLITE_NOTADB),
+        _ => false,
+    }
+}
+
+/// Why a key didn't open the file: `NotEncrypted` when the file reads fine
+/// without it, `BadEncryptionKey` otherwise.
+async fn key_failure(plain: SqliteConnectOptions) -> anyhow::Error {
+    let opens_without_key = match plain.connect().await {
+        Ok(mut conn) => {
+            let read = sqlx::query("SELECT count(*) FROM sqlite_master").fetch_one(&mut conn).await.is_ok();
+            let _ = conn.close().await;
+            read
+        }
+        Err(_) => false,
+    };
+    if opens_without_key {
+        NotEncrypted.into()
+    } else {
+        BadEncryptionKey.into()
+    }
+}
+
+/// Opens a SQLite pool with `connect_options`. With a key, every pooled connection runs
+/// `PRAGMA key` (and the cipher settings) before anything else, and a schema
+/// read checks the key up front so a wrong one fails as `BadEncryptionKey`,
+/// or as `NotEncrypted` for a file that needs no key.
+pub async fn open_pool(
+    options: PoolOptions<Sqlite>,
+    mut connect_options: SqliteConnectOptions,
+    key: Option<&SecretString>,
+    settings: Option<&SqlCipherSettings>,
+) -> Result<SqlitePool> {
+    let plain = connect_options.clone();
+    if let Some(key) = key {
+        if !cfg!(feature = "sqlcipher") {
+            return Err(anyhow!(
+                "This build can't open encrypted SQLite files; rebuild with the sqlcipher feature"
+            ));
+        }
+        // sqlx runs the SQLCipher pragmas first on every new connection.
+        connect_options = connect_options.pragma("key", escaping::quote_standard_literal(&key.0));
+        if let Some(settings) = settings {
+            if let Some(kdf_iter) = settings.kdf_iter {
+                connect_options = connect_options.pragma("kdf_iter", kdf_iter.to_string());
+            }
+            if let Some(page_size) = settings.cipher_page_size {
+                connect_options = connect_options.pragma("cipher_page_size", page_size.to_string());
+            }
+            if let Some(compatibility) = settings.cipher_compatibility {
+                connect_options =
+                    connect_options.pragma("cipher_compatibility", compatibility.to_string());
+            }
+        }
+    }
+
+    let pool = match options.connect_with(connect_options).await {
+        Ok(pool) => pool,
+        Err(error) if key.is_some() && is_not_a_database(&error) => return Err(key_failure(plain).await),
+        Err(error) => return Err(scrub(error, key)),
+    };
+
+    match sqlx::query("SELECT count(*) FROM sqlite_master")
+        .fetch_one(&pool)
+        .await
+    {
+        Ok(_) => Ok(pool),
+        Err(error) => {
+            pool.close().await;
+            if !is_not_a_database(&error) {
+                Err(scrub(error, key))
+            } else if key.is_some() {
+                Err(key_failure(plain).await)
+            } else {
+                Err(anyhow!(
+                    "File is not a database; if it is encrypted, provide its encryption key"
+                ))
+            }
+        }
+    }
+}
+
+/// Error kind for the UI when `error` is one of this module's typed errors.
+pub fn error_kind(error: &anyhow::Error) -> Option<&'static str> {
+    if error.is::<BadEncryptionKey>() {
+        Some(ERROR_KIND_BAD_KEY)
+    } else if error.is::<NotEncrypted>() {
+        Some(ERROR_KIND_NOT_ENCRYPTED)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scrubs_the_key_from_error_messages() {
+        let key = SecretString("hunter2".to_string());
+        let error = scrub("near \"hunter2\": syntax error", Some(&key));
+        assert_eq!(error.to_string(), "near \"***\": syntax error");
+        assert_eq!(format!("{:?}", key), "\"***\"");
+        assert_eq!(
+            error_kind(&anyhow::Error::from(BadEncryptionKey)),
+            Some(ERROR_KIND_BAD_KEY)
+        );
+        assert_eq!(
+            error_kind(&anyhow::Error::from(NotEncrypted)),
+            Some(ERROR_KIND_NOT_ENCRYPTED)
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use crate::models::DatabaseType;
+
+/// Longest identifier Postgres keeps (NAMEDATALEN - 1), in bytes.
+pub const POSTGRES_IDENTIFIER_BYTES: usize = 63;
+/// Longest identifier MySQL accepts, in characters.
+pub const MYSQL_IDENTIFIER_CHARS: usize = 64;
+
+/// SQL Server's `sysname` limit, in characters.
+pub const MSSQL_IDENTIFIER_CHARS: usize = 128;
+
+/// Why a name or value can't be safely quoted for a backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EscapeError {
+    Empty,
+    ContainsNul,
+    TooLong {
+        limit: usize,
+        unit: &'static str,
+        length: usize,
+    },
+}
+
+impl std::fmt::Display for EscapeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EscapeError::Empty => f.write_str("Identifiers can't be empty"),
+            EscapeError::ContainsNul => f.write_str("The value contains a NUL character"),
+            EscapeError::TooLong { limit, unit, length } => write!(
+                f,
+                "The identifier is {} {} long; the limit is {}",
+                length, unit, limit
+            ),
+        }
+    }
+}
+
+impl std::error::Error for EscapeError {}
+
+/// `name` as a delimited identifier, with embedded delimiters doubled.
+pub fn quote_identifier(name: &str, db_type: &DatabaseType) -> String {
+    match db_type {
+        DatabaseType::PostgreSQL | DatabaseType::SQLite => {
+            format!("\"{}\"", name.replace('"', "\"\""))
+        }
+        DatabaseType::MySQL => format!("`{}`", name.replace('`', "``")),
+        DatabaseType::MsSql => format!("[{}]", name.replace(']', "]]")),
+    }
+}
+
+/// A standard SQL string literal: quotes doubled, nothing else special.
+pub fn quote_standard_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// `value` as a string literal. MySQL treats backslashes as escapes unless
+/// `NO_BACKSLASH_ESCAPES` is set, so they are escaped too; the result reads
+/// back the same under either mode.
+pub fn quote_literal(value: &str, db_type: &DatabaseType) -> String {
+    match db_type {
+        DatabaseType::PostgreSQL | DatabaseType::SQLite => quote_standard_literal(value),
+        // N keeps characters outside the database's code page.
+        DatabaseType::MsSql => format!("N{}", quote_standard_literal(value)),
+        DatabaseType::MySQL => {
+            let mut quoted = String::with_capacity(value.len() + 2);
+            quoted.push('\'');
+            for ch in value.chars() {
+                match ch {
+                    '\'' => quoted.push_str("''"),
+                    '\\' => quoted.push_str("\\\\"),
+                    '\0' => quoted.push_str("\\0"),
+                    other => quoted.push(other),
+                }
+            }
+            quoted.push('\'');
+            quoted
+        }
+    }
+}
+
+/// Checks `name` against what the backend can store as an identifier.
+pub fn validate_identifier(name: &str, db_type: &DatabaseType) -> Result<(), EscapeError> {
+    if name.is_empty() {
+        return Err(EscapeError::Empty);
+    }
+    if name.contains('\0') {
+        return Err(EscapeError::ContainsNul);
+    }
+    let too_long = match db_type {
+        DatabaseType::PostgreSQL => (name.len() > POSTGRES_IDENTIFIER_BYTES)
+            .then_some((POSTGRES_IDENTIFIER_BYTES, "bytes", name.len())),
+        DatabaseType::MySQL => {
+            let chars = name.chars().count();
+            (chars > MYSQL_IDENTIFIER_CHARS).then_some((MYSQL_IDENTIFIER_CHARS, "characters", chars))
+        }
+        DatabaseType::MsSql => {
+            let chars = name.chars().count();
+            (chars > MSSQL_IDENTIFIER_CHARS).then_some((MSSQL_IDENTIFIER_CHARS, "characters", chars))
+        }
+        DatabaseType::SQLite => None,
+    };
+    match too_long {
+        Some((limit, unit, length)) => Err(EscapeError::TooLong { limit, unit, length }),
+        None => Ok(()),
+    }
+}
+
+/// `name` quoted as an identifier, or why it can't be one. Postgres would
+/// silently truncate an overlong name, so it's rejected instead.
+pub fn escape_identifier(name: &str, db_type: &DatabaseType) -> Result<String, EscapeError> {
+    validate_identifier(name, db_type)?;
+    Ok(quote_identifier(name, db_type))
+}
+
+/// `value` quoted as a string literal. Only MySQL can spell a NUL inside a
+/// literal; Postgres rejects it and SQLite would cut the string short.
+pub fn escape_literal(value: &str, db_type: &DatabaseType) -> Result<String, EscapeError> {
+    if value.contains('\0') && !matches!(db_type, DatabaseType::MySQL) {
+        return Err(EscapeError::ContainsNul);
+    }
+    Ok(quote_literal(value, db_type))
+}
+
+/// `schema.table` with both parts quoted, or just the table without a schema.
+pub fn quote_qualified(
+    schema: Option<&str>,
+    table: &str,
+    db_type: &DatabaseType,
+) -> Result<String, EscapeError> {
+    let table = escape_identifier(table, db_type)?;
+    match schema.filter(|schema| !schema.is_empty()) {
+        Some(schema) => Ok(format!("{}.{}", escape_identifier(schema, db_type)?, table)),
+        None => Ok(table),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_identifiers_and_literals_per_backend() {
+        assert_eq!(quote_identifier("a\"b", &DatabaseType::PostgreSQL), "\"a\"\"b\"");
+        assert_eq!(quote_identifier("a`b\"", &DatabaseType::MySQL), "`a``b\"`");
+        assert_eq!(quote_literal("it's", &DatabaseType::SQLite), "'it''s'");
+        assert_eq!(quote_literal("C:\\tmp\\'x", &DatabaseType::PostgreSQL), "'C:\\tmp\\''x'");
+        assert_eq!(quote_literal("C:\\tmp\\'x\0", &DatabaseType::MySQL), "'C:\\\\tmp\\\\''x\\0'");
+        assert_eq!(
+            quote_qualified(Some("sales"), "order\"s", &DatabaseType::PostgreSQL).unwrap(),
+            "\"sales\".\"order\"\"s\""
+        );
+        assert_eq!(quote_qualified(None, "t", &DatabaseType::MySQL).unwrap(), "`t`");
+    }
+
+    #[test]
+    fn rejects_nul_and_overlong_names() {
+        assert_eq!(escape_identifier("", &DatabaseType::SQLite), Err(EscapeError::Empty));
+        assert_eq!(escape_identifier("a\0b", &DatabaseType::SQLite), Err(EscapeError::ContainsNul));
+        assert_eq!(escape_literal("a\0b", &DatabaseType::PostgreSQL), Err(EscapeError::ContainsNul));
+        assert!(escape_literal("a\0b", &DatabaseType::MySQL).is_ok());
+
+        // 32 two-byte characters: within MySQL's 64 characters, over Postgres' 63 bytes.
+        let accented = "é".repeat(32);
+        assert_eq!(
+            escape_identifier(&accented, &DatabaseType::PostgreSQL),
+            Err(EscapeError::TooLong { limit: 63, unit: "bytes", length: 64 })
+        );
+        assert!(escape_identifier(&accented, &DatabaseType::MySQL).is_ok());
+        assert!(escape_identifier(&"x".repeat(63), &DatabaseType::PostgreSQL).is_ok());
+        assert!(matches!(
+            escape_identifier(&"x".repeat(65), &DatabaseType::MySQL),
+            Err(EscapeError::TooLong { limit: 64, .. })
+        ));
+        assert!(escape_identifier(&"x".repeat(500), &DatabaseType::SQLite).is_ok());
+    }
+}
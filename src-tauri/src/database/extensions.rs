@@ -0,0 +1,84 @@
+use crate::models::{ConnectionCapabilities, ExtensionInfo};
+
+pub const PG_STAT_STATEMENTS: &str = "pg_stat_statements";
+pub const HYPOPG: &str = "hypopg";
+pub const POSTGIS: &str = "postgis";
+
+/// Error kind reported to the UI for a failed CREATE / DROP EXTENSION.
+pub fn error_kind(sqlstate: &str, message: &str) -> &'static str {
+    match sqlstate {
+        "42501" => "permission_denied",
+        "42710" => "already_exists",
+        "42704" => "not_installed",
+        "2BP01" => "dependent_objects",
+        // Missing control file (58P01) or unsupported version (22023).
+        "58P01" | "0A000" => "not_available",
+        "22023" if message.contains("version") => "not_available",
+        _ => "database",
+    }
+}
+
+/// Feature flags derived from the installed extensions. `slow_query_stats`
+/// is passed separately because pg_stat_statements can be installed without
+/// being loaded through shared_preload_libraries.
+pub fn capabilities(
+    extensions: &[ExtensionInfo],
+    slow_query_stats: bool,
+) -> ConnectionCapabilities {
+    let installed: Vec<String> = extensions
+        .iter()
+        .filter(|extension| extension.installed_version.is_some())
+        .map(|extension| extension.name.clone())
+        .collect();
+    let has = |name: &str| installed.iter().any(|installed| installed == name);
+
+    ConnectionCapabilities {
+        slow_query_stats: slow_query_stats && has(PG_STAT_STATEMENTS),
+        hypothetical_indexes: has(HYPOPG),
+        spatial: has(POSTGIS),
+        installed_extensions: installed,
+        table_valued_pragmas: false,
+        mysql_flavor: None,
+        postgres_flavor: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extension(name: &str, installed: bool) -> ExtensionInfo {
+        ExtensionInfo {
+            name: name.to_string(),
+            installed_version: installed.then(|| "1.0".to_string()),
+            default_version: Some("1.0".to_string()),
+            available_versions: vec!["1.0".to_string()],
+            schema: installed.then(|| "public".to_string()),
+            comment: None,
+        }
+    }
+
+    #[test]
+    fn capabilities_follow_installed_extensions() {
+        let extensions = vec![
+            extension(PG_STAT_STATEMENTS, true),
+            extension(HYPOPG, false),
+            extension(POSTGIS, true),
+        ];
+        let caps = capabilities(&extensions, true);
+        assert!(caps.slow_query_stats);
+        assert!(!caps.hypothetical_indexes);
+        assert!(caps.spatial);
+        assert_eq!(caps.installed_extensions, vec![PG_STAT_STATEMENTS, POSTGIS]);
+
+        assert!(!capabilities(&extensions, false).slow_query_stats);
+        assert_eq!(
+            error_kind("42501", "permission denied"),
+            "permission_denied"
+        );
+        assert_eq!(
+            error_kind("58P01", "could not open extension control file"),
+            "not_available"
+        );
+    }
+}
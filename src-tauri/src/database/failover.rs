@@ -0,0 +1,83 @@
+use super::replicas;
+use crate::models::{ConnectionConfig, DatabaseType};
+use anyhow::{anyhow, Result};
+use sqlx::postgres::PgPool;
+
+/// The configs to try, in order, for a connection with failover hosts: its
+/// own host first, then each failover host. Each is a copy of `config` with
+/// only the host and port changed. Without failover hosts, just `config`.
+pub fn candidates(config: &ConnectionConfig) -> Vec<ConnectionConfig> {
+    let mut first = config.clone();
+    first.failover_hosts = Vec::new();
+    if config.failover_hosts.is_empty() || config.db_type == DatabaseType::SQLite {
+        return vec![first];
+    }
+
+    let mut candidates = vec![first];
+    for host in &config.failover_hosts {
+        let mut candidate = config.clone();
+        candidate.failover_hosts = Vec::new();
+        // The URL was resolved into the fields when the connection opened,
+        // and would name the first host again.
+        candidate.connection_url = None;
+        candidate.socket_path = None;
+        candidate.host = Some(host.host.trim().to_string());
+        candidate.port = host.port.or(config.port);
+        if candidates.iter().all(|other| replicas::endpoint(other) != replicas::endpoint(&candidate)) {
+            candidates.push(candidate);
+        }
+    }
+    candidates
+}
+
+/// Fails when the server is a standby in recovery, which can't take writes.
+pub async fn ensure_read_write(pool: &PgPool) -> Result<()> {
+    let in_recovery: bool = sqlx::query_scalar("SELECT pg_is_in_recovery()").fetch_one(pool).await?;
+    if in_recovery {
+        return Err(anyhow!("the server is a read-only standby"));
+    }
+    Ok(())
+}
+
+/// One error for a connect that failed on every host, naming each.
+pub fn all_failed(errors: Vec<(String, anyhow::Error)>) -> anyhow::Error {
+    match errors.len() {
+        1 => errors.into_iter().next().map(|(_, error)| error).unwrap_or_else(|| anyhow!("No host to connect to")),
+        _ => anyhow!(
+            "No host could be used: {}",
+            errors
+                .iter()
+                .map(|(endpoint, error)| format!("{}: {}", endpoint, error))
+                .collect::<Vec<_>>()
+                .join("; ")
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::FailoverHost;
+
+    #[test]
+    fn the_configured_host_is_tried_before_each_failover_host() {
+        let mut config: ConnectionConfig = serde_json::from_value(serde_json::json!({
+            "id": "c", "name": "c", "db_type": "postgresql", "host": "db-1", "port": 5432,
+            "connection_url": "postgres://app@db-1:5432/shop"
+        }))
+        .unwrap();
+        assert_eq!(candidates(&config).len(), 1);
+
+        config.failover_hosts = vec![
+            FailoverHost { host: "db-2".to_string(), port: None },
+            FailoverHost { host: "db-3".to_string(), port: Some(6432) },
+            FailoverHost { host: "db-1".to_string(), port: None },
+        ];
+        let candidates = candidates(&config);
+        let endpoints: Vec<_> = candidates.iter().map(replicas::endpoint).collect();
+        assert_eq!(endpoints, vec!["db-1:5432", "db-2:5432", "db-3:6432"]);
+        assert!(candidates[0].connection_url.is_some());
+        assert!(candidates[1].connection_url.is_none());
+        assert!(candidates.iter().all(|candidate| candidate.failover_hosts.is_empty()));
+    }
+}
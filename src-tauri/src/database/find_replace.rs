@@ -0,0 +1,244 @@
+use super::params::{placeholder, BindValue};
+use crate::models::{DatabaseType, ReplaceOptions};
+use anyhow::{anyhow, Result};
+
+/// Before/after pairs a dry run shows per column.
+pub const SAMPLE_ROWS: u64 = 5;
+/// Most old values a real run keeps so the change can be undone.
+pub const PRE_IMAGE_CAP: usize = 1_000;
+
+/// How the backend spells regular-expression replacement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegexFlavor {
+    Postgres,
+    /// MySQL 8.0.4 and later (ICU).
+    MySql,
+    /// MariaDB 10.0.5 and later (PCRE).
+    MariaDb,
+}
+
+/// The search needs regular expressions and the backend has none.
+#[derive(Debug)]
+pub struct RegexUnsupported {
+    pub backend: String,
+    pub reason: String,
+}
+
+impl std::fmt::Display for RegexUnsupported {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} can't run this replacement: {}", self.backend, self.reason)
+    }
+}
+
+impl std::error::Error for RegexUnsupported {}
+
+/// Whether the options go beyond a plain case-sensitive substring replace.
+pub fn needs_regex(options: &ReplaceOptions) -> bool {
+    options.regex || options.whole_word || !options.case_sensitive
+}
+
+/// Regex support for a MySQL `version`, which may be MariaDB's.
+pub fn mysql_flavor(version: &str) -> Result<RegexFlavor> {
+    let parts = version
+        .split(|c: char| !c.is_ascii_digit())
+        .take(3)
+        .map(|part| part.parse::<u32>().unwrap_or(0))
+        .collect::<Vec<_>>();
+    let at_least = |wanted: (u32, u32, u32)| match parts.as_slice() {
+        [major, minor, patch] => (*major, *minor, *patch) >= wanted,
+        _ => false,
+    };
+    if version.to_ascii_lowercase().contains("mariadb") {
+        if at_least((10, 0, 5)) {
+            return Ok(RegexFlavor::MariaDb);
+        }
+    } else if at_least((8, 0, 4)) {
+        return Ok(RegexFlavor::MySql);
+    }
+    Err(RegexUnsupported {
+        backend: format!("MySQL {}", version),
+        reason: "REGEXP_REPLACE needs MySQL 8.0.4 or MariaDB 10.0.5; only case-sensitive plain text can be replaced"
+            .to_string(),
+    }
+    .into())
+}
+
+/// Regex support on backends without a server version to check.
+pub fn flavor(db_type: &DatabaseType) -> Result<RegexFlavor> {
+    match db_type {
+        DatabaseType::PostgreSQL => Ok(RegexFlavor::Postgres),
+        DatabaseType::SQLite => Err(RegexUnsupported {
+            backend: "SQLite".to_string(),
+            reason: "it has no regexp_replace(); only case-sensitive plain text can be replaced".to_string(),
+        }
+        .into()),
+        DatabaseType::MySQL => Err(anyhow!("MySQL regex support depends on the server version")),
+        DatabaseType::MsSql => Err(RegexUnsupported {
+            backend: "SQL Server".to_string(),
+            reason: "it has no regex functions; only plain text can be replaced".to_string(),
+        }
+        .into()),
+    }
+}
+
+fn escape_regex(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\^$.|?*+()[]{}".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// `replace` with its backreference syntax escaped, for a literal replacement.
+fn escape_replacement(replace: &str, flavor: RegexFlavor) -> String {
+    let special = match flavor {
+        RegexFlavor::MySql => "\\$",
+        RegexFlavor::Postgres | RegexFlavor::MariaDb => "\\",
+    };
+    let mut escaped = String::with_capacity(replace.len());
+    for c in replace.chars() {
+        if special.contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn bind(value: String, db_type: &DatabaseType, binds: &mut Vec<BindValue>) -> String {
+    binds.push(BindValue::Text(value));
+    placeholder(db_type, binds.len(), None)
+}
+
+/// The search pattern as the backend's regex, with case folding spelled the
+/// way MariaDB wants it.
+fn pattern(find: &str, options: &ReplaceOptions, flavor: RegexFlavor) -> String {
+    let mut pattern = if options.regex { find.to_string() } else { escape_regex(find) };
+    if options.whole_word {
+        let boundary = if flavor == RegexFlavor::Postgres { "\\y" } else { "\\b" };
+        pattern = format!("{b}(?:{}){b}", pattern, b = boundary);
+    }
+    if flavor == RegexFlavor::MariaDb {
+        let flag = if options.case_sensitive { "(?-i)" } else { "(?i)" };
+        pattern = format!("{}{}", flag, pattern);
+    }
+    pattern
+}
+
+fn regex_flavor(flavor: Option<RegexFlavor>) -> Result<RegexFlavor> {
+    flavor.ok_or_else(|| anyhow!("No regex support known for this connection"))
+}
+
+/// Expression giving `quoted_column` with the replacement made, binding its
+/// search terms. `flavor` is only consulted when the options need regexes.
+pub fn replace_expression(
+    quoted_column: &str,
+    find: &str,
+    replace: &str,
+    options: &ReplaceOptions,
+    db_type: &DatabaseType,
+    flavor: Option<RegexFlavor>,
+    binds: &mut Vec<BindValue>,
+) -> Result<String> {
+    if !needs_regex(options) {
+        return Ok(format!(
+            "REPLACE({}, {}, {})",
+            quoted_column,
+            bind(find.to_string(), db_type, binds),
+            bind(replace.to_string(), db_type, binds)
+        ));
+    }
+    let flavor = regex_flavor(flavor)?;
+    let pattern = bind(pattern(find, options, flavor), db_type, binds);
+    let replacement = if options.regex { replace.to_string() } else { escape_replacement(replace, flavor) };
+    let replacement = bind(replacement, db_type, binds);
+    Ok(match flavor {
+        RegexFlavor::Postgres => {
+            let flags = if options.case_sensitive { "g" } else { "gi" };
+            format!("regexp_replace({}, {}, {}, '{}')", quoted_column, pattern, replacement, flags)
+        }
+        RegexFlavor::MySql => {
+            let match_type = if options.case_sensitive { "c" } else { "i" };
+            format!("REGEXP_REPLACE({}, {}, {}, 1, 0, '{}')", quoted_column, pattern, replacement, match_type)
+        }
+        RegexFlavor::MariaDb => format!("REGEXP_REPLACE({}, {}, {})", quoted_column, pattern, replacement),
+    })
+}
+
+/// Condition matching the rows whose `quoted_column` contains the search.
+pub fn match_condition(
+    quoted_column: &str,
+    find: &str,
+    options: &ReplaceOptions,
+    db_type: &DatabaseType,
+    flavor: Option<RegexFlavor>,
+    binds: &mut Vec<BindValue>,
+) -> Result<String> {
+    if !needs_regex(options) {
+        let find = bind(find.to_string(), db_type, binds);
+        return Ok(match db_type {
+            DatabaseType::PostgreSQL => format!("strpos({}, {}) > 0", quoted_column, find),
+            DatabaseType::SQLite => format!("instr({}, {}) > 0", quoted_column, find),
+            // LOCATE follows the column's collation, which usually ignores case.
+            DatabaseType::MySQL => format!("LOCATE(CAST({} AS BINARY), CAST({} AS BINARY)) > 0", find, quoted_column),
+            // CHARINDEX follows the column's collation too.
+            DatabaseType::MsSql => format!(
+                "CHARINDEX({} COLLATE Latin1_General_100_BIN2, {} COLLATE Latin1_General_100_BIN2) > 0",
+                find, quoted_column
+            ),
+        });
+    }
+    let flavor = regex_flavor(flavor)?;
+    let pattern = bind(pattern(find, options, flavor), db_type, binds);
+    Ok(match flavor {
+        RegexFlavor::Postgres => {
+            let operator = if options.case_sensitive { "~" } else { "~*" };
+            format!("{} {} {}", quoted_column, operator, pattern)
+        }
+        RegexFlavor::MySql => {
+            let match_type = if options.case_sensitive { "c" } else { "i" };
+            format!("REGEXP_LIKE({}, {}, '{}')", quoted_column, pattern, match_type)
+        }
+        RegexFlavor::MariaDb => format!("{} REGEXP {}", quoted_column, pattern),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn literal_searches_are_escaped_for_each_flavor() {
+        let options = ReplaceOptions {
+            whole_word: true,
+            ..Default::default()
+        };
+        let pg = DatabaseType::PostgreSQL;
+        let postgres = Some(RegexFlavor::Postgres);
+        let mut binds = Vec::new();
+        let expression = replace_expression("\"name\"", "A.C.M.E", "$1 Corp\\", &options, &pg, postgres, &mut binds).unwrap();
+        let condition = match_condition("\"name\"", "A.C.M.E", &options, &pg, postgres, &mut binds).unwrap();
+        assert_eq!(expression, "regexp_replace(\"name\", $1, $2, 'gi')");
+        assert_eq!(condition, "\"name\" ~* $3");
+        assert!(matches!(&binds[0], BindValue::Text(pattern) if pattern == "\\y(?:A\\.C\\.M\\.E)\\y"));
+        assert!(matches!(&binds[1], BindValue::Text(replacement) if replacement == "$1 Corp\\\\"));
+
+        let plain = ReplaceOptions {
+            case_sensitive: true,
+            ..Default::default()
+        };
+        let mut binds = Vec::new();
+        let condition = match_condition("`name`", "Acme", &plain, &DatabaseType::MySQL, None, &mut binds).unwrap();
+        assert_eq!(condition, "LOCATE(CAST(? AS BINARY), CAST(`name` AS BINARY)) > 0");
+
+        let mariadb = mysql_flavor("10.11.6-MariaDB").unwrap();
+        assert_eq!(mariadb, RegexFlavor::MariaDb);
+        assert_eq!(pattern("a+b", &ReplaceOptions::default(), mariadb), "(?i)a\\+b");
+        assert_eq!(mysql_flavor("8.0.36").unwrap(), RegexFlavor::MySql);
+        assert!(mysql_flavor("5.7.44").unwrap_err().downcast_ref::<RegexUnsupported>().is_some());
+        assert!(flavor(&DatabaseType::SQLite).is_err());
+    }
+}
@@ -0,0 +1,67 @@
+use super::console::ConsoleSession;
+use crate::models::DatabaseType;
+use sqlparser::ast::Statement;
+use std::time::{Duration, Instant};
+
+/// Longest review window a guarded write may hold its rows locked for.
+pub const MAX_REVIEW_SECONDS: u64 = 60;
+pub const DEFAULT_REVIEW_SECONDS: u64 = 10;
+/// Emitted every second of a review window with the seconds left.
+pub const GUARD_COUNTDOWN_EVENT: &str = "guarded-write-countdown";
+/// Emitted once a guarded write was committed or rolled back.
+pub const GUARD_RESOLVED_EVENT: &str = "guarded-write-resolved";
+
+/// An UPDATE or DELETE that ran inside a transaction still open for review.
+pub struct GuardedWrite {
+    pub connection_id: String,
+    pub session: ConsoleSession,
+    pub deadline: Instant,
+}
+
+/// Only single UPDATE and DELETE statements are guarded: they are what a
+/// review window helps with, and they can run inside a transaction everywhere.
+pub fn is_guardable(statements: &[Statement]) -> bool {
+    matches!(
+        statements,
+        [Statement::Update { .. } | Statement::Delete { .. }]
+    )
+}
+
+/// The requested review window, capped so rows aren't held locked for long.
+pub fn review_window(requested: Option<u64>) -> Duration {
+    Duration::from_secs(
+        requested
+            .unwrap_or(DEFAULT_REVIEW_SECONDS)
+            .clamp(1, MAX_REVIEW_SECONDS),
+    )
+}
+
+pub fn begin_statement(db_type: &DatabaseType) -> &'static str {
+    match db_type {
+        DatabaseType::MySQL => "START TRANSACTION",
+        DatabaseType::MsSql => "BEGIN TRANSACTION",
+        DatabaseType::PostgreSQL | DatabaseType::SQLite => "BEGIN",
+    }
+}
+
+pub fn guard_not_found(guard_id: &str) -> anyhow::Error {
+    anyhow::anyhow!("Guarded write {} not found (it may already have been committed or rolled back)", guard_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::query_patterns;
+
+    #[test]
+    fn guards_single_updates_and_deletes_only() {
+        let guardable = |sql: &str| is_guardable(&query_patterns::parse(sql, &DatabaseType::PostgreSQL).unwrap());
+        assert!(guardable("UPDATE t SET a = 1 WHERE id = 2"));
+        assert!(guardable("DELETE FROM t WHERE id = 2"));
+        assert!(!guardable("INSERT INTO t VALUES (1)"));
+        assert!(!guardable("DROP TABLE t"));
+        assert!(!guardable("DELETE FROM t; DELETE FROM u"));
+        assert_eq!(review_window(Some(600)), Duration::from_secs(MAX_REVIEW_SECONDS));
+        assert_eq!(review_window(Some(0)), Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,471 @@
+use crate::models::{DatabaseType, HistogramBucket, HistogramUnit};
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, NaiveDateTime, Timelike};
+use std::collections::HashMap;
+
+pub const DEFAULT_BUCKETS: u32 = 20;
+pub const MAX_BUCKETS: u32 = 1000;
+/// Tables with more rows than this are sampled unless the caller says otherwise.
+pub const DEFAULT_SAMPLE_THRESHOLD: u64 = 1_000_000;
+/// Rows a sampled histogram aims to read.
+pub const SAMPLE_ROWS: u64 = 100_000;
+/// Bucket of numeric values outside the requested bounds.
+pub const OUT_OF_RANGE_BUCKET: i64 = -1;
+/// Bucket of temporal values outside the requested bounds.
+pub const OUT_OF_RANGE_KEY: &str = "";
+/// Format temporal bucket keys and bounds are compared and returned in.
+pub const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Reads a number the way the drivers hand them over; decimals arrive as text.
+pub fn number(value: &serde_json::Value) -> Option<f64> {
+    match value {
+        serde_json::Value::Number(number) => number.as_f64(),
+        serde_json::Value::String(text) => text.trim().parse::<f64>().ok(),
+        _ => None,
+    }
+    .filter(|number| number.is_finite())
+}
+
+/// Fraction of the table to read, once it has more rows than `threshold`.
+pub fn sample_fraction(row_count: Option<i64>, threshold: u64) -> Option<f64> {
+    let rows = row_count.filter(|rows| *rows > 0 && *rows as u64 > threshold)?;
+    Some((SAMPLE_ROWS as f64 / rows as f64).min(1.0))
+}
+
+/// FROM item and extra WHERE condition reading about `fraction` of the rows.
+/// Both are repeatable where the backend allows, so the bounds query and the
+/// bucket query see the same rows.
+pub fn sampled_source(db_type: &DatabaseType, quoted_table: &str, fraction: Option<f64>) -> (String, Option<String>) {
+    let Some(fraction) = fraction else {
+        return (quoted_table.to_string(), None);
+    };
+    match db_type {
+        DatabaseType::PostgreSQL => (
+            format!("{} TABLESAMPLE SYSTEM ({:.6}) REPEATABLE (0)", quoted_table, fraction * 100.0),
+            None,
+        ),
+        DatabaseType::MySQL => (quoted_table.to_string(), Some(format!("RAND(0) < {:.6}", fraction))),
+        // abs(random()) overflows on the smallest integer, so mask the low bits.
+        DatabaseType::SQLite => (
+            quoted_table.to_string(),
+            Some(format!("(random() & 1048575) < {}", (fraction * 1_048_576.0).ceil() as i64)),
+        ),
+        DatabaseType::MsSql => (
+            quoted_table.to_string(),
+            Some(format!("(CHECKSUM(NEWID()) & 1048575) < {}", (fraction * 1_048_576.0).ceil() as i64)),
+        ),
+    }
+}
+
+fn literal(value: f64) -> String {
+    format!("({:?})", value)
+}
+
+/// Equal-width buckets over a numeric range. Integer columns get whole-number
+/// widths, so no bucket straddles part of a value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumericLayout {
+    pub lower: f64,
+    /// Upper edge of the last bucket, which is inclusive.
+    pub upper: f64,
+    pub width: f64,
+    pub count: u32,
+    pub integer: bool,
+}
+
+impl NumericLayout {
+    pub fn new(min: f64, max: f64, buckets: u32, integer: bool) -> Self {
+        let buckets = buckets.clamp(1, MAX_BUCKETS) as f64;
+        if integer {
+            let (min, max) = (min.floor(), max.floor());
+            let range = max - min + 1.0;
+            let width = (range / buckets).ceil().max(1.0);
+            let count = (range / width).ceil() as u32;
+            return Self {
+                lower: min,
+                upper: min + count as f64 * width,
+                width,
+                count,
+                integer,
+            };
+        }
+        if max <= min {
+            return Self {
+                lower: min,
+                upper: min,
+                width: 0.0,
+                count: 1,
+                integer,
+            };
+        }
+        Self {
+            lower: min,
+            upper: max,
+            width: (max - min) / buckets,
+            count: buckets as u32,
+            integer,
+        }
+    }
+
+    /// Bucket index of `value`, which lies between the edges.
+    fn index(&self, db_type: &DatabaseType, value: &str) -> String {
+        if self.count == 1 {
+            return "0".to_string();
+        }
+        let last = self.count - 1;
+        match db_type {
+            DatabaseType::PostgreSQL => format!(
+                "LEAST(width_bucket(({})::float8, {}, {}, {}), {}) - 1",
+                value,
+                literal(self.lower),
+                literal(self.upper),
+                self.count,
+                self.count
+            ),
+            DatabaseType::MySQL => format!(
+                "CAST(LEAST(FLOOR(({} - {}) / {}), {}) AS SIGNED)",
+                value,
+                literal(self.lower),
+                literal(self.width),
+                last
+            ),
+            // CAST truncates, which is FLOOR for values at or above the lower edge.
+            DatabaseType::SQLite => format!(
+                "MIN(CAST(({} - {}) / {} AS INTEGER), {})",
+                value,
+                literal(self.lower),
+                literal(self.width),
+                last
+            ),
+            DatabaseType::MsSql => {
+                let index = format!("FLOOR(({} - {}) / {})", value, literal(self.lower), literal(self.width));
+                format!("CAST(CASE WHEN {i} > {l} THEN {l} ELSE {i} END AS BIGINT)", i = index, l = last)
+            }
+        }
+    }
+
+    /// Bucket of `value`: NULL for nulls, `OUT_OF_RANGE_BUCKET` outside
+    /// `range`, and without a range values past the edges go to the outer buckets.
+    pub fn bucket_expression(&self, db_type: &DatabaseType, value: &str, range: Option<(f64, f64)>) -> String {
+        let outside = match range {
+            Some((min, max)) => format!(
+                "WHEN {v} < {} OR {v} > {} THEN {}",
+                literal(min),
+                literal(max),
+                OUT_OF_RANGE_BUCKET,
+                v = value
+            ),
+            None => format!(
+                "WHEN {v} <= {} THEN 0 WHEN {v} >= {} THEN {}",
+                literal(self.lower),
+                literal(self.upper),
+                self.count - 1,
+                v = value
+            ),
+        };
+        format!(
+            "CASE WHEN {} IS NULL THEN NULL {} ELSE {} END",
+            value,
+            outside,
+            self.index(db_type, value)
+        )
+    }
+
+    pub fn buckets(&self, counts: &HashMap<i64, u64>) -> Vec<HistogramBucket> {
+        // Enough decimals to tell neighbouring edges apart.
+        let decimals = if self.width > 0.0 {
+            (1.0 - self.width.log10().floor()).clamp(0.0, 10.0) as usize
+        } else {
+            2
+        };
+        let edge = |value: f64| -> serde_json::Value {
+            if self.integer {
+                serde_json::json!(value as i64)
+            } else {
+                serde_json::json!(value)
+            }
+        };
+        (0..self.count)
+            .map(|index| {
+                let lower = self.lower + index as f64 * self.width;
+                let upper = if index + 1 == self.count { self.upper } else { lower + self.width };
+                let label = match (self.integer, self.width) {
+                    (true, width) if width <= 1.0 => format!("{}", lower as i64),
+                    (true, _) => format!("{}–{}", lower as i64, upper as i64 - 1),
+                    (false, _) => format!("{:.*}–{:.*}", decimals, lower, decimals, upper),
+                };
+                HistogramBucket {
+                    lower: edge(lower),
+                    upper: edge(upper),
+                    label,
+                    count: counts.get(&(index as i64)).copied().unwrap_or_default(),
+                }
+            })
+            .collect()
+    }
+}
+
+const UNITS: [(HistogramUnit, i64); 7] = [
+    (HistogramUnit::Second, 1),
+    (HistogramUnit::Minute, 60),
+    (HistogramUnit::Hour, 3_600),
+    (HistogramUnit::Day, 86_400),
+    (HistogramUnit::Week, 604_800),
+    (HistogramUnit::Month, 2_629_746),
+    (HistogramUnit::Year, 31_556_952),
+];
+
+/// Finest unit that spans `min` to `max` in at most `buckets` buckets; date
+/// columns go no finer than a day.
+pub fn temporal_unit(min: NaiveDateTime, max: NaiveDateTime, buckets: u32, date_only: bool) -> HistogramUnit {
+    let span = (max - min).num_seconds().max(0);
+    let finest = if date_only { 3 } else { 0 };
+    UNITS[finest..]
+        .iter()
+        .find(|(_, seconds)| span / seconds < buckets.max(1) as i64)
+        .map_or(HistogramUnit::Year, |(unit, _)| *unit)
+}
+
+fn unit_name(unit: HistogramUnit) -> &'static str {
+    match unit {
+        HistogramUnit::Second => "second",
+        HistogramUnit::Minute => "minute",
+        HistogramUnit::Hour => "hour",
+        HistogramUnit::Day => "day",
+        HistogramUnit::Week => "week",
+        HistogramUnit::Month => "month",
+        HistogramUnit::Year => "year",
+    }
+}
+
+/// Start of the bucket holding `at`; weeks start on Monday.
+pub fn truncate(at: NaiveDateTime, unit: HistogramUnit) -> NaiveDateTime {
+    let date = at.date();
+    let midnight = |date: NaiveDate| date.and_hms_opt(0, 0, 0).unwrap_or(at);
+    match unit {
+        HistogramUnit::Second => at.with_nanosecond(0).unwrap_or(at),
+        HistogramUnit::Minute => date.and_hms_opt(at.hour(), at.minute(), 0).unwrap_or(at),
+        HistogramUnit::Hour => date.and_hms_opt(at.hour(), 0, 0).unwrap_or(at),
+        HistogramUnit::Day => midnight(date),
+        HistogramUnit::Week => midnight(date - Duration::days(date.weekday().num_days_from_monday() as i64)),
+        HistogramUnit::Month => midnight(date.with_day(1).unwrap_or(date)),
+        HistogramUnit::Year => midnight(NaiveDate::from_ymd_opt(date.year(), 1, 1).unwrap_or(date)),
+    }
+}
+
+fn next(start: NaiveDateTime, unit: HistogramUnit) -> Option<NaiveDateTime> {
+    match unit {
+        HistogramUnit::Second => start.checked_add_signed(Duration::seconds(1)),
+        HistogramUnit::Minute => start.checked_add_signed(Duration::minutes(1)),
+        HistogramUnit::Hour => start.checked_add_signed(Duration::hours(1)),
+        HistogramUnit::Day => start.checked_add_signed(Duration::days(1)),
+        HistogramUnit::Week => start.checked_add_signed(Duration::weeks(1)),
+        HistogramUnit::Month => start.checked_add_months(Months::new(1)),
+        HistogramUnit::Year => start.checked_add_months(Months::new(12)),
+    }
+}
+
+fn label(start: NaiveDateTime, unit: HistogramUnit) -> String {
+    let format = match unit {
+        HistogramUnit::Year => "%Y",
+        HistogramUnit::Month => "%Y-%m",
+        HistogramUnit::Week | HistogramUnit::Day => "%Y-%m-%d",
+        HistogramUnit::Hour | HistogramUnit::Minute => "%Y-%m-%d %H:%M",
+        HistogramUnit::Second => TIMESTAMP_FORMAT,
+    };
+    start.format(format).to_string()
+}
+
+/// Reads a bound or bucket key: a timestamp, an RFC 3339 instant or a date.
+pub fn parse_timestamp(text: &str) -> Option<NaiveDateTime> {
+    let text = text.trim();
+    ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+        .or_else(|| DateTime::parse_from_rfc3339(text).ok().map(|at| at.naive_local()))
+        .or_else(|| {
+            NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+}
+
+/// `value` as `TIMESTAMP_FORMAT` text, so it compares and groups the same on
+/// every backend.
+pub fn timestamp_text(db_type: &DatabaseType, value: &str) -> String {
+    match db_type {
+        DatabaseType::PostgreSQL => format!("to_char(({})::timestamp, 'YYYY-MM-DD HH24:MI:SS')", value),
+        DatabaseType::MySQL => format!("DATE_FORMAT({}, '%Y-%m-%d %H:%i:%s')", value),
+        DatabaseType::SQLite => format!("strftime('%Y-%m-%d %H:%M:%S', {})", value),
+        DatabaseType::MsSql => format!("CONVERT(varchar(19), CAST({} AS datetime2), 120)", value),
+    }
+}
+
+fn truncated_text(db_type: &DatabaseType, value: &str, unit: HistogramUnit) -> String {
+    match db_type {
+        DatabaseType::PostgreSQL => format!(
+            "to_char(date_trunc('{}', ({})::timestamp), 'YYYY-MM-DD HH24:MI:SS')",
+            unit_name(unit),
+            value
+        ),
+        DatabaseType::MySQL => match unit {
+            HistogramUnit::Week => format!(
+                "DATE_FORMAT(DATE_SUB({v}, INTERVAL WEEKDAY({v}) DAY), '%Y-%m-%d 00:00:00')",
+                v = value
+            ),
+            _ => {
+                let format = match unit {
+                    HistogramUnit::Second => "%Y-%m-%d %H:%i:%s",
+                    HistogramUnit::Minute => "%Y-%m-%d %H:%i:00",
+                    HistogramUnit::Hour => "%Y-%m-%d %H:00:00",
+                    HistogramUnit::Month => "%Y-%m-01 00:00:00",
+                    HistogramUnit::Year => "%Y-01-01 00:00:00",
+                    HistogramUnit::Day | HistogramUnit::Week => "%Y-%m-%d 00:00:00",
+                };
+                format!("DATE_FORMAT({}, '{}')", value, format)
+            }
+        },
+        DatabaseType::SQLite => match unit {
+            // 'weekday 0' moves on to Sunday, six days after that week's Monday.
+            HistogramUnit::Week => format!("strftime('%Y-%m-%d 00:00:00', {}, 'weekday 0', '-6 days')", value),
+            _ => {
+                let format = match unit {
+                    HistogramUnit::Second => "%Y-%m-%d %H:%M:%S",
+                    HistogramUnit::Minute => "%Y-%m-%d %H:%M:00",
+                    HistogramUnit::Hour => "%Y-%m-%d %H:00:00",
+                    HistogramUnit::Month => "%Y-%m-01 00:00:00",
+                    HistogramUnit::Year => "%Y-01-01 00:00:00",
+                    HistogramUnit::Day | HistogramUnit::Week => "%Y-%m-%d 00:00:00",
+                };
+                format!("strftime('{}', {})", format, value)
+            }
+        },
+        // Style 120 is `yyyy-mm-dd hh:mi:ss`; the kept prefix ends at the unit.
+        DatabaseType::MsSql => match unit {
+            HistogramUnit::Week => format!(
+                "CONVERT(varchar(10), DATEADD(day, -((DATEPART(weekday, {v}) + @@DATEFIRST - 2) % 7), CAST({v} AS date)), 120) \
+                 + ' 00:00:00'",
+                v = value
+            ),
+            _ => {
+                let (length, rest) = match unit {
+                    HistogramUnit::Second => (19, ""),
+                    HistogramUnit::Minute => (16, ":00"),
+                    HistogramUnit::Hour => (13, ":00:00"),
+                    HistogramUnit::Month => (7, "-01 00:00:00"),
+                    HistogramUnit::Year => (4, "-01-01 00:00:00"),
+                    HistogramUnit::Day | HistogramUnit::Week => (10, " 00:00:00"),
+                };
+                format!("CONVERT(varchar({}), CAST({} AS datetime2), 120) + '{}'", length, value, rest)
+            }
+        },
+    }
+}
+
+/// Start of the bucket holding `value` as text, or `OUT_OF_RANGE_KEY` outside
+/// `range`; NULL for nulls.
+pub fn temporal_bucket_expression(
+    db_type: &DatabaseType,
+    value: &str,
+    unit: HistogramUnit,
+    range: Option<(NaiveDateTime, NaiveDateTime)>,
+) -> String {
+    let key = truncated_text(db_type, value, unit);
+    let Some((min, max)) = range else {
+        return key;
+    };
+    let text = timestamp_text(db_type, value);
+    format!(
+        "CASE WHEN {t} < '{}' OR {t} > '{}' THEN '{}' ELSE {} END",
+        min.format(TIMESTAMP_FORMAT),
+        max.format(TIMESTAMP_FORMAT),
+        OUT_OF_RANGE_KEY,
+        key,
+        t = text
+    )
+}
+
+/// One bucket per `unit` from `min` to `max`, empty ones included, widened
+/// to cover any counted key outside them.
+pub fn temporal_buckets(
+    min: NaiveDateTime,
+    max: NaiveDateTime,
+    unit: HistogramUnit,
+    counts: &HashMap<String, u64>,
+) -> Vec<HistogramBucket> {
+    let keys = counts.keys().filter_map(|key| parse_timestamp(key)).collect::<Vec<_>>();
+    let mut start = truncate(keys.iter().copied().fold(min, NaiveDateTime::min), unit);
+    let end = truncate(keys.iter().copied().fold(max, NaiveDateTime::max), unit);
+    let mut buckets = Vec::new();
+    while start <= end && buckets.len() < (MAX_BUCKETS * 2) as usize {
+        let Some(upper) = next(start, unit) else {
+            break;
+        };
+        let lower = start.format(TIMESTAMP_FORMAT).to_string();
+        buckets.push(HistogramBucket {
+            count: counts.get(&lower).copied().unwrap_or_default(),
+            label: label(start, unit),
+            lower: serde_json::Value::String(lower),
+            upper: serde_json::Value::String(upper.format(TIMESTAMP_FORMAT).to_string()),
+        });
+        start = upper;
+    }
+    buckets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> NaiveDateTime {
+        parse_timestamp(text).unwrap()
+    }
+
+    #[test]
+    fn integer_columns_get_whole_number_buckets() {
+        let layout = NumericLayout::new(1.0, 5.0, 20, true);
+        assert_eq!((layout.width, layout.count), (1.0, 5));
+        let counts = HashMap::from([(0, 3), (4, 1)]);
+        let buckets = layout.buckets(&counts);
+        assert_eq!(buckets.iter().map(|b| b.label.as_str()).collect::<Vec<_>>(), ["1", "2", "3", "4", "5"]);
+        assert_eq!(buckets.iter().map(|b| b.count).collect::<Vec<_>>(), [3, 0, 0, 0, 1]);
+
+        let layout = NumericLayout::new(0.0, 99.0, 10, true);
+        assert_eq!((layout.width, layout.count), (10.0, 10));
+        assert_eq!(layout.buckets(&HashMap::new())[9].label, "90–99");
+
+        let layout = NumericLayout::new(0.0, 1.0, 4, false);
+        let buckets = layout.buckets(&HashMap::new());
+        assert_eq!(buckets[3].upper, serde_json::json!(1.0));
+        assert_eq!(buckets[1].label, "0.25–0.50");
+        assert_eq!(NumericLayout::new(2.5, 2.5, 10, false).count, 1);
+    }
+
+    #[test]
+    fn temporal_buckets_follow_the_calendar() {
+        assert_eq!(
+            temporal_unit(at("2024-01-01"), at("2024-01-10"), 20, false),
+            HistogramUnit::Day
+        );
+        assert_eq!(
+            temporal_unit(at("2024-01-01 10:00:00"), at("2024-01-01 10:30:00"), 20, true),
+            HistogramUnit::Day
+        );
+        assert_eq!(temporal_unit(at("2020-01-15"), at("2024-06-01"), 20, false), HistogramUnit::Year);
+        assert_eq!(truncate(at("2024-05-02 13:45:10"), HistogramUnit::Week), at("2024-04-29"));
+
+        let counts = HashMap::from([("2024-01-01 00:00:00".to_string(), 2), ("2024-03-01 00:00:00".to_string(), 1)]);
+        let buckets = temporal_buckets(at("2024-01-20"), at("2024-03-05"), HistogramUnit::Month, &counts);
+        assert_eq!(buckets.iter().map(|b| b.label.as_str()).collect::<Vec<_>>(), ["2024-01", "2024-02", "2024-03"]);
+        assert_eq!(buckets.iter().map(|b| b.count).collect::<Vec<_>>(), [2, 0, 1]);
+        assert_eq!(buckets[1].upper, serde_json::json!("2024-03-01 00:00:00"));
+    }
+
+    #[test]
+    fn large_tables_are_sampled() {
+        assert_eq!(sample_fraction(Some(500), DEFAULT_SAMPLE_THRESHOLD), None);
+        assert_eq!(sample_fraction(Some(2_000_000), DEFAULT_SAMPLE_THRESHOLD), Some(0.05));
+        let (source, condition) = sampled_source(&DatabaseType::PostgreSQL, "\"t\"", Some(0.05));
+        assert_eq!(source, "\"t\" TABLESAMPLE SYSTEM (5.000000) REPEATABLE (0)");
+        assert_eq!(condition, None);
+    }
+}
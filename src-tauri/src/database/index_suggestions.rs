@@ -0,0 +1,360 @@
+use super::escaping;
+use crate::models::{
+    DatabaseType, FilterColumnKind, FilterColumnUsage, FilterOperator, FilterUsage, IndexSuggestion, RowFilter,
+    TableIndex,
+};
+use chrono::{DateTime, Utc};
+
+/// Uses lose half their weight after this many days.
+pub const HALF_LIFE_DAYS: f64 = 14.0;
+/// Weight a combination of filters needs before it is worth an index.
+pub const MIN_SCORE: f64 = 3.0;
+/// Combinations kept in the local store; the least used go first.
+pub const MAX_USAGE_ENTRIES: usize = 500;
+/// Below this many rows a scan is cheap and an index rarely pays off.
+pub const SMALL_TABLE_ROWS: i64 = 1_000;
+
+/// The columns `filters` constrain in a way an index can help with, sorted.
+pub fn filter_shape(filters: &[RowFilter]) -> Vec<FilterColumnUsage> {
+    let mut columns = filters
+        .iter()
+        .filter_map(|filter| {
+            let kind = match filter.operator {
+                FilterOperator::Eq if filter.value.is_null() => FilterColumnKind::IsNull,
+                FilterOperator::NotEq if filter.value.is_null() => FilterColumnKind::IsNotNull,
+                FilterOperator::Eq | FilterOperator::In => FilterColumnKind::Equality,
+                FilterOperator::Lt | FilterOperator::Lte | FilterOperator::Gt | FilterOperator::Gte => {
+                    FilterColumnKind::Range
+                }
+                // Only a pattern with a fixed prefix can use a b-tree.
+                FilterOperator::Like
+                    if filter
+                        .value
+                        .as_str()
+                        .is_some_and(|pattern| !pattern.is_empty() && !pattern.starts_with(['%', '_'])) =>
+                {
+                    FilterColumnKind::Range
+                }
+                FilterOperator::IsNull => FilterColumnKind::IsNull,
+                FilterOperator::IsNotNull => FilterColumnKind::IsNotNull,
+                _ => return None,
+            };
+            Some(FilterColumnUsage {
+                column: filter.column.clone(),
+                kind,
+            })
+        })
+        .collect::<Vec<_>>();
+    columns.sort();
+    columns.dedup();
+    columns
+}
+
+/// `score` as it stands at `now`, having halved every `HALF_LIFE_DAYS` since `since`.
+pub fn decayed(score: f64, since: &str, now: DateTime<Utc>) -> f64 {
+    let Ok(at) = DateTime::parse_from_rfc3339(since) else {
+        return score;
+    };
+    let days = (now - at.with_timezone(&Utc)).num_seconds().max(0) as f64 / 86_400.0;
+    score * 0.5_f64.powf(days / HALF_LIFE_DAYS)
+}
+
+/// Counts one use of `columns` on a table, forgetting the weakest
+/// combinations once there are more than `MAX_USAGE_ENTRIES`.
+pub fn record(
+    usage: &mut Vec<FilterUsage>,
+    connection_id: &str,
+    table_name: &str,
+    columns: Vec<FilterColumnUsage>,
+    now: DateTime<Utc>,
+) {
+    if columns.is_empty() {
+        return;
+    }
+    let stamp = now.to_rfc3339();
+    match usage.iter_mut().find(|entry| {
+        entry.connection_id == connection_id && entry.table_name == table_name && entry.columns == columns
+    }) {
+        Some(entry) => {
+            entry.score = decayed(entry.score, &entry.last_used_at, now) + 1.0;
+            entry.hits += 1;
+            entry.last_used_at = stamp;
+        }
+        None => usage.push(FilterUsage {
+            connection_id: connection_id.to_string(),
+            table_name: table_name.to_string(),
+            columns,
+            score: 1.0,
+            hits: 1,
+            last_used_at: stamp,
+        }),
+    }
+    if usage.len() > MAX_USAGE_ENTRIES {
+        usage.sort_by(|a, b| {
+            decayed(b.score, &b.last_used_at, now).total_cmp(&decayed(a.score, &a.last_used_at, now))
+        });
+        usage.truncate(MAX_USAGE_ENTRIES);
+    }
+}
+
+struct Candidate {
+    columns: Vec<String>,
+    predicate: Option<String>,
+    score: f64,
+    hits: u64,
+    last_used_at: String,
+}
+
+/// Whether an existing index already leads with `columns`, in any order.
+fn covered(columns: &[String], indexes: &[TableIndex]) -> bool {
+    let mut wanted = columns.to_vec();
+    wanted.sort();
+    indexes.iter().any(|index| {
+        if index.is_valid == Some(false) || index.expression.is_some() || index.columns.len() < wanted.len() {
+            return false;
+        }
+        let mut leading = index.columns[..wanted.len()].to_vec();
+        leading.sort();
+        leading == wanted
+    })
+}
+
+fn index_name(table_name: &str, columns: &[String]) -> String {
+    let table = table_name.rsplit('.').next().unwrap_or(table_name);
+    let name = format!("idx_{}_{}", table, columns.join("_"))
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+        .collect::<String>();
+    name.chars().take(63).collect()
+}
+
+/// Indexes that would serve the filters recorded for a table, strongest first.
+/// Equality columns lead and at most one range column follows; null checks
+/// become the condition of a partial index where the backend has them.
+/// Combinations an existing index or a longer suggestion covers are left out,
+/// as are dismissed suggestions.
+#[allow(clippy::too_many_arguments)]
+pub fn suggestions(
+    table_name: &str,
+    quoted_table: &str,
+    db_type: &DatabaseType,
+    usage: &[FilterUsage],
+    indexes: &[TableIndex],
+    dismissed: &[String],
+    estimated_rows: Option<i64>,
+    now: DateTime<Utc>,
+) -> Vec<IndexSuggestion> {
+    let mut candidates: Vec<Candidate> = Vec::new();
+    for entry in usage {
+        let of_kind = |kind: FilterColumnKind| {
+            entry
+                .columns
+                .iter()
+                .filter(move |usage| usage.kind == kind)
+                .map(|usage| usage.column.clone())
+        };
+        let mut columns = of_kind(FilterColumnKind::Equality).collect::<Vec<_>>();
+        if let Some(range) = of_kind(FilterColumnKind::Range).find(|column| !columns.contains(column)) {
+            columns.push(range);
+        }
+        if columns.is_empty() {
+            continue;
+        }
+        let null_checks = entry
+            .columns
+            .iter()
+            .filter(|usage| !columns.contains(&usage.column))
+            .filter_map(|usage| {
+                let quoted = escaping::quote_identifier(&usage.column, db_type);
+                match usage.kind {
+                    FilterColumnKind::IsNull => Some(format!("{} IS NULL", quoted)),
+                    FilterColumnKind::IsNotNull => Some(format!("{} IS NOT NULL", quoted)),
+                    _ => None,
+                }
+            })
+            .collect::<Vec<_>>();
+        // MySQL has no partial indexes; the plain index still serves the filter.
+        let predicate = (*db_type != DatabaseType::MySQL && !null_checks.is_empty()).then(|| null_checks.join(" AND "));
+        let score = decayed(entry.score, &entry.last_used_at, now);
+        match candidates
+            .iter_mut()
+            .find(|candidate| candidate.columns == columns && candidate.predicate == predicate)
+        {
+            Some(candidate) => {
+                candidate.score += score;
+                candidate.hits += entry.hits;
+                candidate.last_used_at = candidate.last_used_at.clone().max(entry.last_used_at.clone());
+            }
+            None => candidates.push(Candidate {
+                columns,
+                predicate,
+                score,
+                hits: entry.hits,
+                last_used_at: entry.last_used_at.clone(),
+            }),
+        }
+    }
+
+    // A composite index also serves filters on its leading columns.
+    candidates.sort_by_key(|candidate| std::cmp::Reverse(candidate.columns.len()));
+    let mut merged: Vec<Candidate> = Vec::new();
+    for candidate in candidates {
+        match merged.iter_mut().find(|longer| {
+            longer.predicate == candidate.predicate && longer.columns.starts_with(&candidate.columns)
+        }) {
+            Some(longer) => {
+                longer.score += candidate.score;
+                longer.hits += candidate.hits;
+            }
+            None => merged.push(candidate),
+        }
+    }
+
+    let mut suggestions = merged
+        .into_iter()
+        .filter(|candidate| candidate.score >= MIN_SCORE && !covered(&candidate.columns, indexes))
+        .filter_map(|candidate| {
+            let suggestion_id = format!(
+                "{}({}){}",
+                table_name,
+                candidate.columns.join(","),
+                candidate
+                    .predicate
+                    .as_ref()
+                    .map(|predicate| format!(" WHERE {}", predicate))
+                    .unwrap_or_default()
+            );
+            if dismissed.contains(&suggestion_id) {
+                return None;
+            }
+            let quoted_columns = candidate
+                .columns
+                .iter()
+                .map(|column| escaping::quote_identifier(column, db_type))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let ddl = format!(
+                "CREATE INDEX {} ON {} ({}){}",
+                escaping::quote_identifier(&index_name(table_name, &candidate.columns), db_type),
+                quoted_table,
+                quoted_columns,
+                candidate
+                    .predicate
+                    .as_ref()
+                    .map(|predicate| format!(" WHERE {}", predicate))
+                    .unwrap_or_default()
+            );
+            let last_used = DateTime::parse_from_rfc3339(&candidate.last_used_at)
+                .map(|at| at.format("%Y-%m-%d").to_string())
+                .unwrap_or_else(|_| candidate.last_used_at.clone());
+            let mut benefit = format!(
+                "Filtered on {} {} time(s) in total, last on {}.",
+                candidate.columns.join(", "),
+                candidate.hits,
+                last_used
+            );
+            match estimated_rows {
+                Some(rows) if rows < SMALL_TABLE_ROWS => benefit.push_str(&format!(
+                    " The table has about {} rows, so scanning it is already cheap and the gain is small.",
+                    rows
+                )),
+                Some(rows) => benefit.push_str(&format!(
+                    " Those lookups would read the matching rows instead of scanning about {} rows.",
+                    rows
+                )),
+                None => {}
+            }
+            if let Some(predicate) = &candidate.predicate {
+                benefit.push_str(&format!(" Only rows where {} are indexed, which keeps the index small.", predicate));
+            }
+            Some(IndexSuggestion {
+                suggestion_id,
+                table_name: table_name.to_string(),
+                columns: candidate.columns,
+                predicate: candidate.predicate,
+                ddl,
+                score: candidate.score,
+                hits: candidate.hits,
+                last_used_at: candidate.last_used_at,
+                benefit,
+            })
+        })
+        .collect::<Vec<_>>();
+    suggestions.sort_by(|a, b| b.score.total_cmp(&a.score));
+    suggestions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(column: &str, operator: FilterOperator, value: serde_json::Value) -> RowFilter {
+        RowFilter {
+            column: column.to_string(),
+            operator,
+            value,
+        }
+    }
+
+    fn index(columns: &[&str]) -> TableIndex {
+        TableIndex {
+            index_name: "existing".to_string(),
+            method: None,
+            is_unique: false,
+            is_primary: false,
+            is_valid: Some(true),
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+            expression: None,
+            predicate: None,
+            definition: None,
+        }
+    }
+
+    #[test]
+    fn repeated_filters_become_composite_partial_index_suggestions() {
+        let now = DateTime::parse_from_rfc3339("2024-05-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        let filters = vec![
+            filter("created_at", FilterOperator::Gte, serde_json::json!("2024-01-01")),
+            filter("status", FilterOperator::Eq, serde_json::json!("open")),
+            filter("deleted_at", FilterOperator::IsNull, serde_json::Value::Null),
+            filter("title", FilterOperator::Like, serde_json::json!("%bug%")),
+        ];
+        let shape = filter_shape(&filters);
+        assert_eq!(shape.len(), 3);
+
+        let mut usage = Vec::new();
+        for _ in 0..4 {
+            record(&mut usage, "c", "orders", shape.clone(), now);
+        }
+        record(
+            &mut usage,
+            "c",
+            "orders",
+            filter_shape(&[filter("status", FilterOperator::In, serde_json::json!(["a"]))]),
+            now,
+        );
+        assert_eq!(usage[0].hits, 4);
+
+        let found = suggestions("orders", "\"orders\"", &DatabaseType::PostgreSQL, &usage, &[], &[], Some(50_000), now);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].columns, vec!["status", "created_at"]);
+        assert_eq!(
+            found[0].ddl,
+            "CREATE INDEX \"idx_orders_status_created_at\" ON \"orders\" (\"status\", \"created_at\") WHERE \"deleted_at\" IS NULL"
+        );
+        assert_eq!(found[0].hits, 4);
+
+        let mysql = suggestions("orders", "`orders`", &DatabaseType::MySQL, &usage, &[], &[], None, now);
+        assert_eq!(mysql[0].predicate, None);
+        assert_eq!(mysql[0].hits, 5);
+
+        let existing = [index(&["created_at", "status", "id"])];
+        assert!(suggestions("orders", "\"orders\"", &DatabaseType::PostgreSQL, &usage, &existing, &[], None, now).is_empty());
+        let dismissed = [found[0].suggestion_id.clone()];
+        assert!(suggestions("orders", "\"orders\"", &DatabaseType::PostgreSQL, &usage, &[], &dismissed, None, now).is_empty());
+
+        let month_later = now + chrono::Duration::days(28);
+        assert!((decayed(4.0, &usage[0].last_used_at, month_later) - 1.0).abs() < 1e-9);
+        assert!(suggestions("orders", "\"orders\"", &DatabaseType::PostgreSQL, &usage, &[], &[], None, month_later).is_empty());
+    }
+}
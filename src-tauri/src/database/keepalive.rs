@@ -0,0 +1,51 @@
+use super::{DatabasePool, PING_TIMEOUT_SECONDS};
+use crate::models::ConnectionConfig;
+use anyhow::{anyhow, Result};
+use sqlx::database::HasArguments;
+use sqlx::IntoArguments;
+use std::time::Duration;
+
+/// How often `config` asks to be pinged; unset or 0 means never.
+pub fn interval(config: &ConnectionConfig) -> Option<Duration> {
+    config
+        .keepalive_seconds
+        .filter(|seconds| *seconds > 0)
+        .map(Duration::from_secs)
+}
+
+/// Runs `SELECT 1` on a connection taken from `pool`. Fails only when the
+/// server looks gone; a pool with every connection busy is plainly in use.
+pub async fn check(pool: &DatabasePool) -> Result<()> {
+    let checked = match pool {
+        DatabasePool::Sqlite(pool) => select_one(pool).await,
+        DatabasePool::Postgres(pool) => select_one(pool).await,
+        DatabasePool::MySql(pool) => select_one(pool).await,
+        DatabasePool::MsSql(pool) => pool.execute("SELECT 1").await.map(|_| ()),
+    };
+    match checked {
+        Err(error) if matches!(error.downcast_ref::<sqlx::Error>(), Some(sqlx::Error::PoolTimedOut)) => Ok(()),
+        checked => checked,
+    }
+}
+
+async fn select_one<DB>(pool: &sqlx::Pool<DB>) -> Result<()>
+where
+    DB: sqlx::Database,
+    for<'c> &'c mut DB::Connection: sqlx::Executor<'c, Database = DB>,
+    for<'q> <DB as HasArguments<'q>>::Arguments: IntoArguments<'q, DB>,
+{
+    let mut conn = pool.acquire().await?;
+    let answered = tokio::time::timeout(
+        Duration::from_secs(PING_TIMEOUT_SECONDS),
+        sqlx::query("SELECT 1").execute(&mut *conn),
+    )
+    .await;
+    match answered {
+        Ok(result) => result.map(|_| ()).map_err(Into::into),
+        Err(_) => {
+            // Whatever the server answers later would land on the next user.
+            let _ = conn.close().await;
+            Err(anyhow!("No answer within {} seconds", PING_TIMEOUT_SECONDS))
+        }
+    }
+}
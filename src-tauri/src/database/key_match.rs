@@ -0,0 +1,99 @@
+use crate::models::DatabaseType;
+use std::collections::HashSet;
+
+/// Rows returned with a key match when the caller sets no cap.
+pub const DEFAULT_KEY_MATCH_ROWS: usize = 1000;
+
+/// Keys matched per statement. Each key is one bound parameter and one arm of
+/// a `UNION ALL`, so SQLite stays under its default 999 parameters and 500
+/// compound-select terms; the servers just keep statements a reasonable size.
+pub fn chunk_size(db_type: &DatabaseType) -> usize {
+    match db_type {
+        DatabaseType::SQLite => 400,
+        DatabaseType::PostgreSQL | DatabaseType::MySQL => 1000,
+        // SQL Server takes at most 2100 parameters per request.
+        DatabaseType::MsSql => 1000,
+    }
+}
+
+/// Keys in first-seen order without repeats, and how many repeats were dropped.
+pub fn dedupe_keys(keys: Vec<serde_json::Value>) -> (Vec<serde_json::Value>, usize) {
+    let mut seen = HashSet::new();
+    let total = keys.len();
+    let unique: Vec<_> = keys
+        .into_iter()
+        .filter(|key| seen.insert(key.to_string()))
+        .collect();
+    let duplicates = total - unique.len();
+    (unique, duplicates)
+}
+
+/// Query returning the chunk positions (`idx`) of keys that have a match in
+/// `quoted_table`. The database does the comparison, so its type coercion and
+/// collation decide what matches. `placeholders` holds one placeholder per key.
+pub fn matched_positions_sql(
+    quoted_table: &str,
+    quoted_column: &str,
+    placeholders: &[String],
+    collation: Option<&str>,
+) -> String {
+    let keys = placeholders
+        .iter()
+        .enumerate()
+        .map(|(index, placeholder)| {
+            if index == 0 {
+                format!("SELECT 0 AS idx, {} AS key_value", placeholder)
+            } else {
+                format!("SELECT {}, {}", index, placeholder)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" UNION ALL ");
+    format!(
+        "SELECT k.idx FROM ({}) k WHERE EXISTS (SELECT 1 FROM {} target WHERE target.{} = k.key_value{})",
+        keys,
+        quoted_table,
+        quoted_column,
+        collate_clause(collation)
+    )
+}
+
+/// ` COLLATE <name>` for an already quoted collation name, or nothing.
+pub fn collate_clause(collation: Option<&str>) -> String {
+    collation
+        .map(|collation| format!(" COLLATE {}", collation))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dedupes_keys_by_value_and_type() {
+        let keys = vec![
+            serde_json::json!(1),
+            serde_json::json!("1"),
+            serde_json::json!(1),
+            serde_json::json!("a"),
+        ];
+        let (unique, duplicates) = dedupe_keys(keys);
+        assert_eq!(unique, vec![serde_json::json!(1), serde_json::json!("1"), serde_json::json!("a")]);
+        assert_eq!(duplicates, 1);
+    }
+
+    #[test]
+    fn builds_a_positional_key_list() {
+        let sql = matched_positions_sql(
+            "\"orders\"",
+            "\"id\"",
+            &["$1::int4".to_string(), "$2::int4".to_string()],
+            Some("\"C\""),
+        );
+        assert_eq!(
+            sql,
+            "SELECT k.idx FROM (SELECT 0 AS idx, $1::int4 AS key_value UNION ALL SELECT 1, $2::int4) k \
+             WHERE EXISTS (SELECT 1 FROM \"orders\" target WHERE target.\"id\" = k.key_value COLLATE \"C\")"
+        );
+    }
+}
@@ -0,0 +1,58 @@
+/// Source table OID and column number of a Postgres result column, from the
+/// RowDescription. sqlx 0.7 keeps both private on `PgColumn`, and its `Debug`
+/// output is the only place they show; they're read from the end so a column
+/// name can't pass for them.
+pub fn pg_column_source(column: &sqlx::postgres::PgColumn) -> Option<(i64, i64)> {
+    parse_pg_column_debug(&format!("{:?}", column))
+}
+
+fn parse_pg_column_debug(debug: &str) -> Option<(i64, i64)> {
+    let field = |name: &str| -> Option<i64> {
+        let start = debug.rfind(&format!("{}: ", name))? + name.len() + 2;
+        let value = debug[start..].strip_prefix("Some(")?;
+        value[..value.find(')')?].parse().ok()
+    };
+    // Oids above i32::MAX come through sqlx's i32 as negative numbers.
+    let table_oid = field("relation_id")? as i32 as u32 as i64;
+    let attnum = field("relation_attribute_no")?;
+    (attnum > 0).then_some((table_oid, attnum))
+}
+
+/// Schema, table and column names of the given table OIDs' columns.
+pub fn pg_attribute_names_sql(table_oids: &[i64]) -> String {
+    format!(
+        "SELECT c.oid::int8 AS table_oid, a.attnum::int8 AS attnum, n.nspname AS schema_name, \
+         c.relname AS table_name, a.attname AS column_name \
+         FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace \
+         JOIN pg_attribute a ON a.attrelid = c.oid \
+         WHERE c.oid IN ({}) AND a.attnum > 0 AND NOT a.attisdropped",
+        table_oids.iter().map(|oid| oid.to_string()).collect::<Vec<_>>().join(", ")
+    )
+}
+
+/// Splits a table name as written in a query into its schema and table.
+pub fn split_table_name(name: &str) -> (Option<String>, String) {
+    match name.rsplit_once('.') {
+        Some((schema, table)) => (Some(schema.to_string()), table.to_string()),
+        None => (None, name.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_source_from_pg_column_debug_output() {
+        let debug = |name: &str, relation: &str, attnum: &str| {
+            format!(
+                "PgColumn {{ ordinal: 0, name: {:?}, type_info: PgTypeInfo(Int4), relation_id: {}, relation_attribute_no: {} }}",
+                name, relation, attnum
+            )
+        };
+        assert_eq!(parse_pg_column_debug(&debug("id", "Some(16384)", "Some(1)")), Some((16384, 1)));
+        assert_eq!(parse_pg_column_debug(&debug("relation_id: Some(1)", "None", "None")), None);
+        assert_eq!(parse_pg_column_debug(&debug("x", "Some(-2)", "Some(3)")), Some((4294967294, 3)));
+        assert_eq!(split_table_name("shop.orders"), (Some("shop".to_string()), "orders".to_string()));
+    }
+}
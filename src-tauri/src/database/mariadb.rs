@@ -0,0 +1,130 @@
+use crate::models::{MySqlFlavor, PlanStep};
+
+/// What older MariaDB servers put before their version, so clients that only
+/// read the first number take them for MySQL 5.5.
+const COMPAT_PREFIX: &str = "5.5.5-";
+
+/// The server a MySQL connection reached, from its `VERSION()`.
+pub fn flavor(version: &str) -> MySqlFlavor {
+    if version.to_ascii_lowercase().contains("mariadb") {
+        MySqlFlavor::MariaDb
+    } else {
+        MySqlFlavor::MySql
+    }
+}
+
+/// Major, minor and patch of a MySQL or MariaDB `version`.
+pub fn release(version: &str) -> Option<(u32, u32, u32)> {
+    let version = match flavor(version) {
+        MySqlFlavor::MariaDb => version.strip_prefix(COMPAT_PREFIX).unwrap_or(version),
+        MySqlFlavor::MySql => version,
+    };
+    let mut parts = version
+        .split(|c: char| !c.is_ascii_digit())
+        .take(3)
+        .map(|part| part.parse::<u32>().ok());
+    Some((parts.next()??, parts.next()??, parts.next().flatten().unwrap_or(0)))
+}
+
+/// How a connection test names the server: `MariaDB 10.11.6` or `MySQL 8.0.36`.
+pub fn display_version(version: &str) -> String {
+    match (flavor(version), release(version)) {
+        (MySqlFlavor::MariaDb, Some((major, minor, patch))) => format!("MariaDB {}.{}.{}", major, minor, patch),
+        (MySqlFlavor::MariaDb, None) => format!("MariaDB {}", version),
+        (MySqlFlavor::MySql, _) => format!("MySQL {}", version),
+    }
+}
+
+/// Row estimate and size in KiB from `information_schema.tables`. MariaDB
+/// lists sequences there too, with figures that say nothing about them.
+pub fn table_stats_columns(flavor: MySqlFlavor) -> &'static str {
+    match flavor {
+        MySqlFlavor::MySql => "table_rows, CAST(ROUND((data_length + index_length) / 1024) AS SIGNED)",
+        MySqlFlavor::MariaDb => {
+            "IF(table_type = 'SEQUENCE', NULL, table_rows), \
+             IF(table_type = 'SEQUENCE', NULL, CAST(ROUND((data_length + index_length) / 1024) AS SIGNED))"
+        }
+    }
+}
+
+/// A column default as CREATE TABLE spells it. MariaDB's information_schema
+/// already holds SQL, quotes included, and `NULL` for no default.
+pub fn export_default(default: &str, flavor: MySqlFlavor) -> Option<&str> {
+    match flavor {
+        _ if default.is_empty() => None,
+        MySqlFlavor::MariaDb if default.eq_ignore_ascii_case("NULL") => None,
+        _ => Some(default),
+    }
+}
+
+/// Every table a MariaDB `EXPLAIN FORMAT=JSON` plan reads, in plan order,
+/// wherever it sits under joins, sorts or subqueries. MariaDB reports `rows`
+/// and `cost` where MySQL has `rows_examined_per_scan` and `cost_info`.
+pub fn plan_steps(plan: &serde_json::Value) -> Vec<PlanStep> {
+    let mut steps = Vec::new();
+    collect_tables(plan, &mut steps);
+    steps
+}
+
+fn collect_tables(value: &serde_json::Value, steps: &mut Vec<PlanStep>) {
+    match value {
+        serde_json::Value::Object(fields) => {
+            for (key, child) in fields {
+                if key == "table" && child.get("table_name").is_some() {
+                    steps.push(table_step(child));
+                }
+                collect_tables(child, steps);
+            }
+        }
+        serde_json::Value::Array(items) => items.iter().for_each(|item| collect_tables(item, steps)),
+        _ => {}
+    }
+}
+
+fn table_step(table: &serde_json::Value) -> PlanStep {
+    let text = |key: &str| table.get(key).and_then(|value| value.as_str()).map(str::to_string);
+    PlanStep {
+        step_type: text("access_type").unwrap_or_else(|| "Unknown".to_string()),
+        table_name: text("table_name"),
+        rows: table.get("rows").and_then(|rows| rows.as_i64()),
+        cost: table.get("cost").and_then(|cost| cost.as_f64()),
+        filter_condition: text("attached_condition"),
+        index_used: text("key"),
+        children: vec![],
+        // Only ANALYZE FORMAT=JSON has it.
+        actual_rows: table.get("r_rows").and_then(|rows| rows.as_f64()).map(|rows| rows.round() as i64),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mariadb_versions_and_plans_are_recognized() {
+        assert_eq!(flavor("8.0.36"), MySqlFlavor::MySql);
+        assert_eq!(display_version("8.0.36-log"), "MySQL 8.0.36-log");
+        assert_eq!(display_version("10.11.6-MariaDB-1:10.11.6+maria~ubu2204"), "MariaDB 10.11.6");
+        assert_eq!(release("5.5.5-10.4.12-MariaDB"), Some((10, 4, 12)));
+        assert_eq!(release("5.5.5-log"), Some((5, 5, 5)));
+        assert_eq!(export_default("NULL", MySqlFlavor::MariaDb), None);
+        assert_eq!(export_default("'draft'", MySqlFlavor::MariaDb), Some("'draft'"));
+
+        let plan = serde_json::json!({
+            "query_block": {
+                "select_id": 1,
+                "nested_loop": [
+                    { "table": { "table_name": "orders", "access_type": "ALL", "rows": 20000, "filtered": 100,
+                                 "attached_condition": "orders.total > 10" } },
+                    { "block-nl-join": { "table": { "table_name": "customers", "access_type": "eq_ref",
+                                                    "key": "PRIMARY", "rows": 1, "r_rows": 1.0 } } }
+                ]
+            }
+        });
+        let steps = plan_steps(&plan);
+        let tables: Vec<_> = steps.iter().map(|step| step.table_name.as_deref().unwrap()).collect();
+        assert_eq!(tables, vec!["orders", "customers"]);
+        assert_eq!((steps[0].rows, steps[0].filter_condition.as_deref()), (Some(20000), Some("orders.total > 10")));
+        assert_eq!((steps[1].index_used.as_deref(), steps[1].actual_rows), (Some("PRIMARY"), Some(1)));
+    }
+}
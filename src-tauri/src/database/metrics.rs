@@ -0,0 +1,221 @@
+use crate::models::{MetricDiagnostic, ServerMetrics};
+use sqlx::Row;
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Transaction counter sample kept between polls to derive a rate.
+#[derive(Debug, Clone, Copy)]
+pub struct CounterSample {
+    pub taken_at: Instant,
+    pub value: u64,
+}
+
+/// Per-second rate between two counter samples. `None` on the first poll or
+/// after a counter reset (server restart).
+pub fn rate_per_second(previous: Option<CounterSample>, current: CounterSample) -> Option<f64> {
+    let previous = previous?;
+    let elapsed = current.taken_at.duration_since(previous.taken_at).as_secs_f64();
+    if elapsed <= 0.0 || current.value < previous.value {
+        return None;
+    }
+    Some((current.value - previous.value) as f64 / elapsed)
+}
+
+fn record(metrics: &mut ServerMetrics, metric: &str, error: impl std::fmt::Display) {
+    metrics.diagnostics.push(MetricDiagnostic {
+        metric: metric.to_string(),
+        error: error.to_string(),
+    });
+}
+
+/// Fills Postgres metrics and returns the committed + rolled back transaction counter.
+/// Every query only reads statistics views, never user tables.
+pub async fn collect_postgres(pool: &sqlx::PgPool, metrics: &mut ServerMetrics) -> Option<u64> {
+    match sqlx::query_scalar::<_, f64>(
+        "SELECT EXTRACT(EPOCH FROM (now() - pg_postmaster_start_time()))::float8",
+    )
+    .fetch_one(pool)
+    .await
+    {
+        Ok(seconds) => metrics.uptime_seconds = Some(seconds as u64),
+        Err(e) => record(metrics, "uptime", e),
+    }
+
+    match sqlx::query(
+        "SELECT (SELECT count(*) FROM pg_stat_activity)::int8, \
+         (SELECT setting FROM pg_settings WHERE name = 'max_connections')::int8",
+    )
+    .fetch_one(pool)
+    .await
+    {
+        Ok(row) => {
+            metrics.active_connections = row.try_get(0).ok();
+            metrics.max_connections = row.try_get(1).ok();
+        }
+        Err(e) => record(metrics, "connections", e),
+    }
+
+    let mut transactions = None;
+    match sqlx::query(
+        "SELECT blks_hit::float8, blks_read::float8, (xact_commit + xact_rollback)::int8 \
+         FROM pg_stat_database WHERE datname = current_database()",
+    )
+    .fetch_one(pool)
+    .await
+    {
+        Ok(row) => {
+            let hit: f64 = row.try_get(0).unwrap_or_default();
+            let read: f64 = row.try_get(1).unwrap_or_default();
+            if hit + read > 0.0 {
+                metrics.cache_hit_ratio = Some(hit / (hit + read));
+            }
+            transactions = row.try_get::<i64, _>(2).ok().map(|v| v as u64);
+        }
+        Err(e) => record(metrics, "database_stats", e),
+    }
+
+    // On a standby this is the replay delay; on a primary the worst replica lag.
+    match sqlx::query_scalar::<_, Option<f64>>(
+        "SELECT CASE WHEN pg_is_in_recovery() \
+         THEN EXTRACT(EPOCH FROM (now() - pg_last_xact_replay_timestamp()))::float8 \
+         ELSE (SELECT max(EXTRACT(EPOCH FROM replay_lag))::float8 FROM pg_stat_replication) END",
+    )
+    .fetch_one(pool)
+    .await
+    {
+        Ok(lag) => metrics.replication_lag_seconds = lag,
+        Err(e) => record(metrics, "replication_lag", e),
+    }
+
+    transactions
+}
+
+/// Fills MySQL metrics from `SHOW GLOBAL STATUS` / `SHOW VARIABLES` and returns the
+/// handler commit + rollback counter.
+pub async fn collect_mysql(pool: &sqlx::MySqlPool, metrics: &mut ServerMetrics) -> Option<u64> {
+    let status = match sqlx::query(
+        "SHOW GLOBAL STATUS WHERE Variable_name IN ('Uptime', 'Threads_connected', \
+         'Innodb_buffer_pool_read_requests', 'Innodb_buffer_pool_reads', 'Handler_commit', \
+         'Handler_rollback', 'Innodb_buffer_pool_pages_total', 'Innodb_buffer_pool_pages_free', \
+         'Innodb_buffer_pool_pages_dirty')",
+    )
+    .fetch_all(pool)
+    .await
+    {
+        Ok(rows) => rows
+            .iter()
+            .filter_map(|row| {
+                let name: String = row.try_get(0).ok()?;
+                let value: String = row.try_get(1).ok()?;
+                Some((name, value.parse::<u64>().ok()?))
+            })
+            .collect::<HashMap<_, _>>(),
+        Err(e) => {
+            record(metrics, "global_status", e);
+            HashMap::new()
+        }
+    };
+
+    metrics.uptime_seconds = status.get("Uptime").copied();
+    metrics.active_connections = status.get("Threads_connected").map(|v| *v as i64);
+    if let (Some(requests), Some(reads)) = (
+        status.get("Innodb_buffer_pool_read_requests"),
+        status.get("Innodb_buffer_pool_reads"),
+    ) {
+        if *requests > 0 {
+            metrics.cache_hit_ratio = Some(1.0 - (*reads as f64 / *requests as f64));
+        }
+    }
+    metrics.buffer_pool_total_pages = status.get("Innodb_buffer_pool_pages_total").copied();
+    metrics.buffer_pool_free_pages = status.get("Innodb_buffer_pool_pages_free").copied();
+    metrics.buffer_pool_dirty_pages = status.get("Innodb_buffer_pool_pages_dirty").copied();
+
+    match sqlx::query(
+        "SHOW GLOBAL VARIABLES WHERE Variable_name IN ('max_connections', 'innodb_buffer_pool_size')",
+    )
+    .fetch_all(pool)
+    .await
+    {
+        Ok(rows) => {
+            for row in rows {
+                let name: String = row.try_get(0).unwrap_or_default();
+                let value = row
+                    .try_get::<String, _>(1)
+                    .ok()
+                    .and_then(|v| v.parse::<u64>().ok());
+                match name.as_str() {
+                    "max_connections" => metrics.max_connections = value.map(|v| v as i64),
+                    "innodb_buffer_pool_size" => metrics.buffer_pool_size_bytes = value,
+                    _ => {}
+                }
+            }
+        }
+        Err(e) => record(metrics, "global_variables", e),
+    }
+
+    match (status.get("Handler_commit"), status.get("Handler_rollback")) {
+        (Some(commits), Some(rollbacks)) => Some(commits + rollbacks),
+        _ => None,
+    }
+}
+
+/// Fills SQLite file metrics: database size from the page counters and the size
+/// of the `-wal` file next to the main database, if any.
+pub async fn collect_sqlite(pool: &sqlx::SqlitePool, metrics: &mut ServerMetrics) {
+    match sqlx::query("SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()")
+        .fetch_one(pool)
+        .await
+    {
+        Ok(row) => {
+            metrics.database_size_bytes = row.try_get::<i64, _>(0).ok().map(|v| v as u64);
+        }
+        Err(e) => record(metrics, "database_size", e),
+    }
+
+    match sqlx::query("SELECT file FROM pragma_database_list WHERE name = 'main'")
+        .fetch_optional(pool)
+        .await
+    {
+        Ok(Some(row)) => {
+            let file: String = row.try_get(0).unwrap_or_default();
+            // In-memory databases have no file and therefore no WAL.
+            if !file.is_empty() {
+                metrics.wal_size_bytes = Some(
+                    std::fs::metadata(format!("{}-wal", file))
+                        .map(|meta| meta.len())
+                        .unwrap_or(0),
+                );
+            }
+        }
+        Ok(None) => {}
+        Err(e) => record(metrics, "wal_size", e),
+    }
+
+    metrics.active_connections = Some(pool.size() as i64);
+    metrics.max_connections = Some(pool.options().get_max_connections() as i64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn rate_needs_a_previous_sample_and_a_monotonic_counter() {
+        let start = Instant::now();
+        let first = CounterSample { taken_at: start, value: 100 };
+        let second = CounterSample {
+            taken_at: start + Duration::from_secs(2),
+            value: 150,
+        };
+
+        assert_eq!(rate_per_second(None, first), None);
+        assert_eq!(rate_per_second(Some(first), second), Some(25.0));
+
+        let after_restart = CounterSample {
+            taken_at: start + Duration::from_secs(4),
+            value: 3,
+        };
+        assert_eq!(rate_per_second(Some(second), after_restart), None);
+    }
+}
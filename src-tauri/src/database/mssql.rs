@@ -0,0 +1,570 @@
+use super::cascade;
+use super::lineage::split_table_name;
+use super::params::BindValue;
+use super::row_counts;
+use super::session_params::DEFAULT_APPLICATION_NAME;
+use super::statement_status;
+use super::types::{classify_mssql_type, normalize_type_name};
+use crate::models::{
+    ColumnTypeFamily, ConnectionConfig, DatabaseTable, DatabaseType, ForeignKeyDefinition, QueryResult, TableColumn,
+    TableConstraint,
+};
+use anyhow::{anyhow, Result};
+use base64::Engine;
+use std::borrow::Cow;
+use std::sync::Arc;
+use tiberius::{AuthMethod, Client, ColumnData, ColumnType, Config, EncryptionLevel, FromSql, Row, ToSql};
+use tokio::net::TcpStream;
+use tokio::sync::Mutex;
+use tokio_util::compat::{Compat, TokioAsyncWriteCompatExt};
+
+pub const DEFAULT_PORT: u16 = 1433;
+
+/// User tables and views with row counts and sizes from the partition stats.
+const TABLES_SQL: &str = r#"
+    SELECT
+      s.name AS schema_name,
+      o.name AS table_name,
+      CASE o.type WHEN 'U' THEN 'BASE TABLE' ELSE 'VIEW' END AS table_type,
+      (SELECT SUM(p.row_count) FROM sys.dm_db_partition_stats p
+        WHERE p.object_id = o.object_id AND p.index_id IN (0, 1)) AS row_count,
+      (SELECT SUM(p.reserved_page_count) * 8 FROM sys.dm_db_partition_stats p
+        WHERE p.object_id = o.object_id) AS size_kb
+    FROM sys.objects o
+    JOIN sys.schemas s ON s.schema_id = o.schema_id
+    WHERE o.type IN ('U', 'V') AND o.is_ms_shipped = 0
+    ORDER BY s.name, o.name
+"#;
+
+/// `TABLES_SQL` for logins without VIEW DATABASE STATE, which the partition
+/// stats need: row counts from sys.partitions and no sizes.
+const TABLES_WITHOUT_STATS_SQL: &str = r#"
+    SELECT
+      s.name AS schema_name,
+      o.name AS table_name,
+      CASE o.type WHEN 'U' THEN 'BASE TABLE' ELSE 'VIEW' END AS table_type,
+      (SELECT SUM(p.rows) FROM sys.partitions p
+        WHERE p.object_id = o.object_id AND p.index_id IN (0, 1)) AS row_count,
+      CAST(NULL AS bigint) AS size_kb
+    FROM sys.objects o
+    JOIN sys.schemas s ON s.schema_id = o.schema_id
+    WHERE o.type IN ('U', 'V') AND o.is_ms_shipped = 0
+    ORDER BY s.name, o.name
+"#;
+
+/// Primary key columns of the table `@P2` in schema `@P1` (the login's
+/// default schema when NULL).
+const PRIMARY_KEY_SQL: &str = r#"
+    SELECT k.COLUMN_NAME
+    FROM INFORMATION_SCHEMA.TABLE_CONSTRAINTS tc
+    JOIN INFORMATION_SCHEMA.KEY_COLUMN_USAGE k
+      ON k.CONSTRAINT_SCHEMA = tc.CONSTRAINT_SCHEMA
+     AND k.CONSTRAINT_NAME = tc.CONSTRAINT_NAME
+    WHERE tc.CONSTRAINT_TYPE = 'PRIMARY KEY'
+      AND tc.TABLE_SCHEMA = COALESCE(@P1, SCHEMA_NAME())
+      AND tc.TABLE_NAME = @P2
+    ORDER BY k.ORDINAL_POSITION
+"#;
+
+/// Columns of the table `@P2` in schema `@P1`, in the order `column` reads them.
+const COLUMNS_SQL: &str = r#"
+    SELECT
+      c.COLUMN_NAME,
+      c.DATA_TYPE,
+      c.IS_NULLABLE,
+      c.COLUMN_DEFAULT,
+      CAST(CASE WHEN k.COLUMN_NAME IS NULL THEN 0 ELSE 1 END AS bit) AS is_primary_key,
+      CAST(COLUMNPROPERTY(OBJECT_ID(QUOTENAME(c.TABLE_SCHEMA) + '.' + QUOTENAME(c.TABLE_NAME)),
+        c.COLUMN_NAME, 'IsIdentity') AS bit) AS is_identity,
+      CAST(c.CHARACTER_MAXIMUM_LENGTH AS bigint) AS character_maximum_length,
+      CAST(c.NUMERIC_PRECISION AS bigint) AS numeric_precision,
+      CAST(c.NUMERIC_SCALE AS bigint) AS numeric_scale,
+      c.COLLATION_NAME
+    FROM INFORMATION_SCHEMA.COLUMNS c
+    LEFT JOIN (
+      SELECT ku.TABLE_SCHEMA, ku.TABLE_NAME, ku.COLUMN_NAME
+      FROM INFORMATION_SCHEMA.TABLE_CONSTRAINTS tc
+      JOIN INFORMATION_SCHEMA.KEY_COLUMN_USAGE ku
+        ON ku.CONSTRAINT_SCHEMA = tc.CONSTRAINT_SCHEMA
+       AND ku.CONSTRAINT_NAME = tc.CONSTRAINT_NAME
+      WHERE tc.CONSTRAINT_TYPE = 'PRIMARY KEY'
+    ) k
+      ON k.TABLE_SCHEMA = c.TABLE_SCHEMA
+     AND k.TABLE_NAME = c.TABLE_NAME
+     AND k.COLUMN_NAME = c.COLUMN_NAME
+    WHERE c.TABLE_SCHEMA = COALESCE(@P1, SCHEMA_NAME())
+      AND c.TABLE_NAME = @P2
+    ORDER BY c.ORDINAL_POSITION
+"#;
+
+/// Foreign key columns of the table `@P2` in schema `@P1`, or of every
+/// table when `@P2` is NULL, one row per column pair.
+const FOREIGN_KEYS_SQL: &str = r#"
+    SELECT
+      fk.name,
+      SCHEMA_NAME(t.schema_id) AS table_schema,
+      t.name AS table_name,
+      pc.name AS column_name,
+      SCHEMA_NAME(rt.schema_id) AS referenced_schema,
+      rt.name AS referenced_table,
+      rc.name AS referenced_column,
+      fk.update_referential_action_desc,
+      fk.delete_referential_action_desc
+    FROM sys.foreign_keys fk
+    JOIN sys.foreign_key_columns fkc ON fkc.constraint_object_id = fk.object_id
+    JOIN sys.tables t ON t.object_id = fk.parent_object_id
+    JOIN sys.columns pc ON pc.object_id = fkc.parent_object_id AND pc.column_id = fkc.parent_column_id
+    JOIN sys.tables rt ON rt.object_id = fk.referenced_object_id
+    JOIN sys.columns rc ON rc.object_id = fkc.referenced_object_id AND rc.column_id = fkc.referenced_column_id
+    WHERE @P2 IS NULL OR (SCHEMA_NAME(t.schema_id) = COALESCE(@P1, SCHEMA_NAME()) AND t.name = @P2)
+    ORDER BY SCHEMA_NAME(t.schema_id), t.name, fk.name, fkc.constraint_column_id
+"#;
+
+// tiberius futures are large, so calls on the client are boxed; otherwise
+// every manager future that matches over the pools grows with them.
+type Connection = Client<Compat<TcpStream>>;
+
+/// The error for a feature NodaDB doesn't have for SQL Server yet.
+pub fn unsupported(feature: &str) -> anyhow::Error {
+    anyhow!("{} isn't available for SQL Server connections yet", feature)
+}
+
+/// The first line of `@@VERSION`: `Microsoft SQL Server 2022 (RTM) - 16.0.1000.6 (X64)`.
+pub fn display_version(version: &str) -> String {
+    version.lines().next().unwrap_or_default().trim().to_string()
+}
+
+/// A SQL Server connection. tiberius has no pool, so a connection is one
+/// session whose statements take turns.
+#[derive(Clone)]
+pub struct MsSqlPool {
+    client: Arc<Mutex<Option<Connection>>>,
+}
+
+impl MsSqlPool {
+    /// Logs in with SQL Server authentication at `host`:`port`, which may be
+    /// an SSH tunnel's end rather than the configured server.
+    pub async fn connect(config: &ConnectionConfig, host: &str, port: u16) -> Result<Self> {
+        let mut options = Config::new();
+        options.host(host);
+        options.port(if port == 0 { DEFAULT_PORT } else { port });
+        if let Some(database) = config.database.as_deref().filter(|database| !database.is_empty()) {
+            options.database(database);
+        }
+        options.authentication(AuthMethod::sql_server(
+            config.username.as_deref().unwrap_or_default(),
+            config.password.as_deref().unwrap_or_default(),
+        ));
+        options.application_name(
+            config
+                .application_name
+                .as_deref()
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .unwrap_or(DEFAULT_APPLICATION_NAME),
+        );
+        // Always over TLS, so a server without it refuses the login. Its
+        // certificate is verified unless the config says to trust it.
+        options.encryption(EncryptionLevel::On);
+        if config.trust_server_certificate {
+            options.trust_cert();
+        }
+
+        let client = match Box::pin(open(options.clone())).await {
+            // Azure SQL sends the login on to the node that holds the database.
+            Err(tiberius::error::Error::Routing { host, port }) => {
+                options.host(host);
+                options.port(port);
+                Box::pin(open(options)).await?
+            }
+            opened => opened?,
+        };
+        Ok(Self { client: Arc::new(Mutex::new(Some(client))) })
+    }
+
+    pub async fn close(&self) {
+        if let Some(client) = self.client.lock().await.take() {
+            let _ = client.close().await;
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.client.try_lock().is_ok_and(|client| client.is_none())
+    }
+
+    /// Whether a statement is running on the connection right now.
+    pub fn is_busy(&self) -> bool {
+        self.client.try_lock().is_err()
+    }
+
+    /// `SELECT @@VERSION`, e.g. `Microsoft SQL Server 2022 (RTM) - 16.0.1000.6 (X64) ...`.
+    pub async fn version(&self) -> Result<String> {
+        let rows = self.fetch_rows("SELECT @@VERSION", &[]).await?;
+        Ok(rows
+            .first()
+            .and_then(|row| value::<&str>(row, 0))
+            .unwrap_or("Unknown")
+            .to_string())
+    }
+
+    /// Runs `sql` as sent, returning the rows of every result set and the
+    /// rows a statement that returns none changed.
+    pub async fn execute(&self, sql: &str) -> Result<(QueryResult, u64)> {
+        let mut guard = self.client.lock().await;
+        let client = guard.as_mut().ok_or_else(closed)?;
+        if statement_status::command_tag(sql).is_some() {
+            let done = Box::pin(client.execute(sql, &[])).await?;
+            return Ok((query_result(Vec::new()), done.total()));
+        }
+        let rows = Box::pin(client.simple_query(sql)).await?.into_results().await?;
+        Ok((query_result(rows.into_iter().flatten().collect()), 0))
+    }
+
+    /// `execute` with `binds` as `@P1`, `@P2`, ...; without binds the batch is
+    /// sent as it is.
+    pub async fn execute_with(&self, sql: &str, binds: &[BindValue]) -> Result<(QueryResult, u64)> {
+        if binds.is_empty() {
+            return self.execute(sql).await;
+        }
+        if statement_status::command_tag(sql).is_some() {
+            return Ok((query_result(Vec::new()), self.execute_bound(sql, binds).await?));
+        }
+        Ok((self.fetch_bound(sql, binds).await?, 0))
+    }
+
+    /// Rows of `sql` with `binds` as `@P1`, `@P2`, ...
+    pub async fn fetch_bound(&self, sql: &str, binds: &[BindValue]) -> Result<QueryResult> {
+        let params: Vec<&dyn ToSql> = binds.iter().map(|bind| bind as &dyn ToSql).collect();
+        Ok(query_result(self.fetch_rows(sql, &params).await?))
+    }
+
+    pub async fn execute_bound(&self, sql: &str, binds: &[BindValue]) -> Result<u64> {
+        let mut guard = self.client.lock().await;
+        let client = guard.as_mut().ok_or_else(closed)?;
+        let params: Vec<&dyn ToSql> = binds.iter().map(|bind| bind as &dyn ToSql).collect();
+        Ok(Box::pin(client.execute(sql, &params)).await?.total())
+    }
+
+    /// Runs `statements` in one transaction, rolled back if any fails.
+    pub async fn execute_in_transaction(&self, statements: &[(&str, &[BindValue])]) -> Result<Vec<u64>> {
+        self.transaction(statements, |_| true).await
+    }
+
+    /// Runs one statement in a transaction that is only committed when
+    /// `keep` accepts the number of rows it changed.
+    pub async fn execute_committed_if(&self, sql: &str, binds: &[BindValue], keep: impl FnOnce(u64) -> bool) -> Result<u64> {
+        let affected = self.transaction(&[(sql, binds)], |affected| keep(affected[0])).await?;
+        Ok(affected[0])
+    }
+
+    async fn transaction(
+        &self,
+        statements: &[(&str, &[BindValue])],
+        keep: impl FnOnce(&[u64]) -> bool,
+    ) -> Result<Vec<u64>> {
+        let mut guard = self.client.lock().await;
+        let client = guard.as_mut().ok_or_else(closed)?;
+        Box::pin(client.simple_query("BEGIN TRANSACTION")).await?.into_results().await?;
+        let mut affected = Vec::with_capacity(statements.len());
+        for (sql, binds) in statements {
+            let params: Vec<&dyn ToSql> = binds.iter().map(|bind| bind as &dyn ToSql).collect();
+            match Box::pin(client.execute(*sql, &params)).await {
+                Ok(done) => affected.push(done.total()),
+                Err(error) => {
+                    Box::pin(client.simple_query("IF @@TRANCOUNT > 0 ROLLBACK")).await?.into_results().await?;
+                    return Err(error.into());
+                }
+            }
+        }
+        let end = if keep(&affected) { "COMMIT" } else { "ROLLBACK" };
+        Box::pin(client.simple_query(end)).await?.into_results().await?;
+        Ok(affected)
+    }
+
+    pub async fn tables(&self, scratch_schema: &str) -> Result<Vec<DatabaseTable>> {
+        let rows = match self.fetch_rows(TABLES_SQL, &[]).await {
+            Ok(rows) => rows,
+            Err(_) => self.fetch_rows(TABLES_WITHOUT_STATS_SQL, &[]).await?,
+        };
+        Ok(rows
+            .iter()
+            .map(|row| {
+                let schema = value::<&str>(row, 0).unwrap_or("dbo").to_string();
+                let name = value::<&str>(row, 1).unwrap_or_default().to_string();
+                let row_count: Option<i64> = value(row, 3);
+                DatabaseTable {
+                    full_name: Some(format!("{}.{}", schema, name)),
+                    is_scratch: schema == scratch_schema,
+                    name,
+                    schema: Some(schema),
+                    row_count_accuracy: row_count.map(|_| row_counts::ACCURACY_ESTIMATE.to_string()),
+                    row_count,
+                    size_kb: value(row, 4),
+                    table_type: value::<&str>(row, 2).map(str::to_string),
+                }
+            })
+            .collect())
+    }
+
+    pub async fn columns(&self, table_name: &str) -> Result<Vec<TableColumn>> {
+        let (schema, table) = split_table_name(table_name);
+        let rows = self.fetch_rows(COLUMNS_SQL, &[&schema, &table]).await?;
+        Ok(rows.iter().map(column).collect())
+    }
+
+    pub async fn primary_keys(&self, table_name: &str) -> Result<Vec<String>> {
+        let (schema, table) = split_table_name(table_name);
+        let rows = self.fetch_rows(PRIMARY_KEY_SQL, &[&schema, &table]).await?;
+        Ok(rows
+            .iter()
+            .filter_map(|row| value::<&str>(row, 0).map(str::to_string))
+            .collect())
+    }
+
+    /// Foreign keys declared on `table_name`, or on every table when `None`.
+    pub async fn foreign_keys(&self, table_name: Option<&str>) -> Result<Vec<TableConstraint>> {
+        let (schema, table) = match table_name.map(split_table_name) {
+            Some((schema, table)) => (schema, Some(table)),
+            None => (None, None),
+        };
+        let rows = self.fetch_rows(FOREIGN_KEYS_SQL, &[&schema, &table]).await?;
+        let text = |row: &Row, index: usize| value::<&str>(row, index).unwrap_or_default().to_string();
+        // SQL Server spells the actions NO_ACTION, SET_NULL, ...
+        let action = |row: &Row, index: usize| text(row, index).replace('_', " ");
+        let mut constraints: Vec<TableConstraint> = Vec::new();
+        for row in &rows {
+            let (name, table_schema, table_name) = (text(row, 0), text(row, 1), text(row, 2));
+            match constraints.last_mut() {
+                Some(last)
+                    if last.constraint_name == name
+                        && last.table_schema.as_deref() == Some(table_schema.as_str())
+                        && last.table_name == table_name =>
+                {
+                    last.column_names.push(text(row, 3));
+                    last.foreign_column_names.get_or_insert_with(Vec::new).push(text(row, 6));
+                }
+                _ => constraints.push(TableConstraint {
+                    constraint_name: name,
+                    constraint_type: "FOREIGN KEY".to_string(),
+                    table_schema: Some(table_schema),
+                    table_name,
+                    column_names: vec![text(row, 3)],
+                    foreign_table_schema: Some(text(row, 4)),
+                    foreign_table_name: Some(text(row, 5)),
+                    foreign_column_names: Some(vec![text(row, 6)]),
+                    check_expression: Some(format!("ON UPDATE {} ON DELETE {}", action(row, 7), action(row, 8))),
+                    is_deferrable: None,
+                    initially_deferred: None,
+                }),
+            }
+        }
+        Ok(constraints)
+    }
+
+    /// Every foreign key in the database, tables named `schema.table`.
+    pub async fn foreign_key_graph(&self) -> Result<Vec<ForeignKeyDefinition>> {
+        let constraints = self.foreign_keys(None).await?;
+        Ok(constraints
+            .into_iter()
+            .map(|fk| {
+                let rules = fk.check_expression.as_deref().and_then(|rules| rules.strip_prefix("ON UPDATE "));
+                let (on_update, on_delete) = rules.and_then(|rules| rules.split_once(" ON DELETE ")).unzip();
+                ForeignKeyDefinition {
+                    constraint_name: fk.constraint_name,
+                    table_name: format!("{}.{}", fk.table_schema.unwrap_or_default(), fk.table_name),
+                    column_names: fk.column_names,
+                    referenced_table_name: format!(
+                        "{}.{}",
+                        fk.foreign_table_schema.unwrap_or_default(),
+                        fk.foreign_table_name.unwrap_or_default()
+                    ),
+                    referenced_column_names: fk.foreign_column_names.unwrap_or_default(),
+                    on_delete: on_delete.map(cascade::delete_action),
+                    on_update: on_update.map(cascade::delete_action),
+                }
+            })
+            .collect())
+    }
+
+    async fn fetch_rows(&self, sql: &str, params: &[&dyn ToSql]) -> Result<Vec<Row>> {
+        let mut guard = self.client.lock().await;
+        let client = guard.as_mut().ok_or_else(closed)?;
+        let rows = Box::pin(client.query(sql, params)).await?.into_first_result().await?;
+        Ok(rows)
+    }
+}
+
+async fn open(options: Config) -> tiberius::Result<Connection> {
+    let tcp = TcpStream::connect(options.get_addr()).await?;
+    tcp.set_nodelay(true)?;
+    Client::connect(options, tcp.compat_write()).await
+}
+
+/// Cell `index` of `row`; NULL and values of another type read as `None`.
+fn value<'a, T: FromSql<'a>>(row: &'a Row, index: usize) -> Option<T> {
+    row.try_get(index).ok().flatten()
+}
+
+fn closed() -> anyhow::Error {
+    anyhow!("The SQL Server connection is closed")
+}
+
+impl ToSql for BindValue {
+    fn to_sql(&self) -> ColumnData<'_> {
+        match self {
+            BindValue::Null => ColumnData::String(None),
+            BindValue::Bool(value) => ColumnData::Bit(Some(*value)),
+            BindValue::Int(value) => ColumnData::I64(Some(*value)),
+            BindValue::Float(value) => ColumnData::F64(Some(*value)),
+            BindValue::Text(value) => ColumnData::String(Some(Cow::Borrowed(value))),
+            BindValue::Bytes(value) => ColumnData::Binary(Some(Cow::Borrowed(value))),
+        }
+    }
+}
+
+/// `rows` in the shape `process_rows!` gives the sqlx drivers.
+fn query_result(rows: Vec<Row>) -> QueryResult {
+    let columns: Vec<String> = rows
+        .first()
+        .map(|row| row.columns().iter().map(|column| column.name().to_string()).collect())
+        .unwrap_or_default();
+    let binary_columns = rows
+        .first()
+        .map(|row| {
+            row.columns()
+                .iter()
+                .filter(|column| {
+                    matches!(column.column_type(), ColumnType::BigVarBin | ColumnType::BigBinary | ColumnType::Image)
+                })
+                .map(|column| column.name().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    let rows = rows
+        .into_iter()
+        .map(|row| {
+            let names: Vec<String> = row.columns().iter().map(|column| column.name().to_string()).collect();
+            let cells = row.into_iter().map(|data| cell(&data));
+            serde_json::Value::Object(names.into_iter().zip(cells).collect())
+        })
+        .collect();
+    QueryResult {
+        columns,
+        rows,
+        rows_affected: 0,
+        attempts_made: 1,
+        truncated_cells: vec![],
+        result_id: None,
+        lineage: None,
+        status_message: None,
+        no_result_set: false,
+        decoded_columns: vec![],
+        binary_columns,
+    }
+}
+
+/// A cell as JSON: numbers and bools as themselves, decimals and dates as
+/// text, binary as base64, like the other drivers.
+pub fn cell(data: &ColumnData<'static>) -> serde_json::Value {
+    use serde_json::Value;
+    let text = |value: Option<String>| value.map(Value::String).unwrap_or(Value::Null);
+    match data {
+        ColumnData::U8(value) => value.map(Value::from).unwrap_or(Value::Null),
+        ColumnData::I16(value) => value.map(Value::from).unwrap_or(Value::Null),
+        ColumnData::I32(value) => value.map(Value::from).unwrap_or(Value::Null),
+        ColumnData::I64(value) => value.map(Value::from).unwrap_or(Value::Null),
+        ColumnData::F32(value) => value.map(|n| serde_json::json!(n)).unwrap_or(Value::Null),
+        ColumnData::F64(value) => value.map(|n| serde_json::json!(n)).unwrap_or(Value::Null),
+        ColumnData::Bit(value) => value.map(Value::Bool).unwrap_or(Value::Null),
+        ColumnData::String(value) => text(value.as_ref().map(|value| value.to_string())),
+        ColumnData::Guid(value) => text(value.map(|uuid| uuid.to_string())),
+        ColumnData::Numeric(value) => text(value.map(|decimal| decimal.to_string())),
+        ColumnData::Xml(value) => text(value.as_ref().map(|xml| xml.to_string())),
+        ColumnData::Binary(value) => text(
+            value
+                .as_ref()
+                .map(|bytes| base64::engine::general_purpose::STANDARD.encode(bytes)),
+        ),
+        ColumnData::Date(_) => text(
+            chrono::NaiveDate::from_sql(data)
+                .ok()
+                .flatten()
+                .map(|date| date.format("%Y-%m-%d").to_string()),
+        ),
+        ColumnData::Time(_) => text(
+            chrono::NaiveTime::from_sql(data)
+                .ok()
+                .flatten()
+                .map(|time| time.format("%H:%M:%S").to_string()),
+        ),
+        ColumnData::DateTimeOffset(_) => text(
+            chrono::DateTime::<chrono::FixedOffset>::from_sql(data)
+                .ok()
+                .flatten()
+                .map(|datetime| datetime.to_rfc3339()),
+        ),
+        ColumnData::DateTime(_) | ColumnData::SmallDateTime(_) | ColumnData::DateTime2(_) => text(
+            chrono::NaiveDateTime::from_sql(data)
+                .ok()
+                .flatten()
+                .map(|datetime| datetime.format("%Y-%m-%d %H:%M:%S").to_string()),
+        ),
+    }
+}
+
+/// A row of `COLUMNS_SQL`.
+fn column(row: &Row) -> TableColumn {
+    let data_type = value::<&str>(row, 1).unwrap_or_default().to_string();
+    let family = classify_mssql_type(&data_type);
+    let is_identity = value::<bool>(row, 5).unwrap_or(false);
+    let numeric: Option<i64> = value(row, 7);
+    let scale: Option<i64> = value(row, 8);
+
+    TableColumn {
+        name: value::<&str>(row, 0).unwrap_or_default().to_string(),
+        raw_type: Some(data_type.clone()),
+        normalized_type: normalize_type_name(&data_type),
+        type_family: family.clone(),
+        db_type: DatabaseType::MsSql,
+        is_nullable: value::<&str>(row, 2) == Some("YES"),
+        default_value: value::<&str>(row, 3).map(str::to_string),
+        is_primary_key: value::<bool>(row, 4).unwrap_or(false),
+        is_boolean_like: family == ColumnTypeFamily::Boolean,
+        is_array: false,
+        enum_values: None,
+        identity_kind: is_identity.then(|| "identity".to_string()),
+        generated_kind: None,
+        generation_expression: None,
+        column_comment: None,
+        collation_name: value::<&str>(row, 9).map(str::to_string),
+        domain_name: None,
+        domain_schema: None,
+        domain_base_type: None,
+        array_dimensions: None,
+        element_raw_type: None,
+        // -1 stands for (max).
+        character_maximum_length: value::<i64>(row, 6).filter(|length| *length > 0),
+        numeric_precision: (family == ColumnTypeFamily::Decimal).then_some(numeric).flatten(),
+        numeric_scale: (family == ColumnTypeFamily::Decimal).then_some(scale).flatten(),
+        data_type,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cells_come_out_like_the_other_drivers() {
+        assert_eq!(cell(&ColumnData::I32(Some(7))), serde_json::json!(7));
+        assert_eq!(cell(&ColumnData::Bit(Some(true))), serde_json::json!(true));
+        assert_eq!(cell(&ColumnData::String(None)), serde_json::Value::Null);
+        assert_eq!(cell(&ColumnData::Binary(Some(Cow::Owned(vec![1, 2, 3])))), serde_json::json!("AQID"));
+        let numeric = tiberius::numeric::Numeric::new_with_scale(12345, 2);
+        assert_eq!(cell(&ColumnData::Numeric(Some(numeric))), serde_json::json!("123.45"));
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+        assert_eq!(cell(&tiberius::IntoSql::into_sql(date)), serde_json::json!("2024-02-29"));
+        assert_eq!(classify_mssql_type("NVARCHAR"), ColumnTypeFamily::Text);
+        assert_eq!(classify_mssql_type("uniqueidentifier"), ColumnTypeFamily::Uuid);
+    }
+}
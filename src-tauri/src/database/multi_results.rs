@@ -0,0 +1,85 @@
+use super::ConnectionManager;
+use anyhow::Result;
+use futures_util::TryStreamExt;
+use sqlx::{Column, Either, Row, TypeInfo};
+
+/// Rows of every result set a batch produced, and the status of every
+/// statement, both in the order the server sent them.
+pub struct DrainedBatch<DB: sqlx::Database> {
+    pub result_sets: Vec<Vec<DB::Row>>,
+    pub statuses: Vec<DB::QueryResult>,
+}
+
+/// Runs `query` and collects everything it sends back. A plain `&str` has no
+/// bind parameters, so it goes over the simple (text) protocol: Postgres
+/// accepts several statements in one Query message, and MySQL's COM_QUERY
+/// streams every result set of a batch or a CALL, which sqlx negotiates with
+/// CLIENT_MULTI_STATEMENTS and CLIENT_MULTI_RESULTS. A `sqlx::query` is
+/// prepared and holds a single statement.
+pub async fn drain<'e, DB, E, Q>(executor: E, query: Q) -> Result<DrainedBatch<DB>>
+where
+    DB: sqlx::Database,
+    E: sqlx::Executor<'e, Database = DB> + 'e,
+    Q: sqlx::Execute<'e, DB> + 'e,
+{
+    let mut stream = executor.fetch_many(query);
+    let mut batch = DrainedBatch {
+        result_sets: Vec::new(),
+        statuses: Vec::new(),
+    };
+    let mut rows = Vec::new();
+    while let Some(item) = stream
+        .try_next()
+        .await
+        .map_err(ConnectionManager::format_sqlx_error)?
+    {
+        match item {
+            Either::Left(status) => {
+                // A status closes the result set whose rows came before it.
+                if !rows.is_empty() {
+                    batch.result_sets.push(std::mem::take(&mut rows));
+                }
+                batch.statuses.push(status);
+            }
+            Either::Right(row) => rows.push(row),
+        }
+    }
+    if !rows.is_empty() {
+        batch.result_sets.push(rows);
+    }
+    Ok(batch)
+}
+
+/// Driver type name of each column of a result set.
+pub fn column_types<R: Row>(rows: &[R]) -> Vec<String> {
+    rows.first()
+        .map(|row| {
+            row.columns()
+                .iter()
+                .map(|column| column.type_info().name().to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Names of the Postgres refcursors a result set holds.
+pub fn refcursor_names(rows: &[sqlx::postgres::PgRow]) -> Vec<String> {
+    let Some(first) = rows.first() else {
+        return Vec::new();
+    };
+    let cursor_columns: Vec<usize> = first
+        .columns()
+        .iter()
+        .filter(|column| column.type_info().name().eq_ignore_ascii_case("REFCURSOR"))
+        .map(|column| column.ordinal())
+        .collect();
+    rows.iter()
+        .flat_map(|row| {
+            cursor_columns
+                .iter()
+                // Simple-protocol values are text, which is what a cursor name is.
+                .filter_map(|&index| row.try_get_unchecked::<Option<String>, _>(index).ok().flatten())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
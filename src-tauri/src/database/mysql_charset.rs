@@ -0,0 +1,64 @@
+use crate::models::{ConnectionConfig, DatabaseType};
+use sqlx::mysql::MySqlConnectOptions;
+
+/// Characters MySQL's latin1 (really Windows-1252) has at 0x80..=0x9F; the
+/// rest of its bytes are the Unicode code points of the same value. MySQL
+/// leaves 0x81, 0x8D, 0x8F, 0x90 and 0x9D undefined and passes them through.
+const CP1252_HIGH: [char; 32] = [
+    '\u{20AC}', '\u{81}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{8D}', '\u{017D}', '\u{8F}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{9D}', '\u{017E}', '\u{0178}',
+];
+
+fn trimmed(value: Option<&str>) -> Option<&str> {
+    value.map(str::trim).filter(|value| !value.is_empty())
+}
+
+/// `options` with the session charset and collation `config` asks for;
+/// utf8mb4 and its default collation otherwise.
+pub fn apply(mut options: MySqlConnectOptions, config: &ConnectionConfig) -> MySqlConnectOptions {
+    if let Some(charset) = trimmed(config.charset.as_deref()) {
+        options = options.charset(charset);
+    }
+    if let Some(collation) = trimmed(config.collation.as_deref()) {
+        options = options.collation(collation);
+    }
+    options
+}
+
+/// Whether `config` is a MySQL connection whose sessions talk latin1, so text
+/// that isn't UTF-8 comes back as latin1 bytes.
+pub fn is_latin1(config: &ConnectionConfig) -> bool {
+    config.db_type == DatabaseType::MySQL
+        && trimmed(config.charset.as_deref()).is_some_and(|charset| charset.eq_ignore_ascii_case("latin1"))
+}
+
+/// `bytes` read as MySQL latin1.
+pub fn latin1_text(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&byte| match byte {
+            0x80..=0x9F => CP1252_HIGH[(byte - 0x80) as usize],
+            _ => byte as char,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latin1_bytes_are_read_as_windows_1252() {
+        assert_eq!(latin1_text(b"caf\xE9 \x80 5 \x93ok\x94"), "café € 5 \u{201C}ok\u{201D}");
+
+        let mut config: ConnectionConfig = serde_json::from_value(serde_json::json!({
+            "id": "c", "name": "c", "db_type": "mysql", "charset": " LATIN1 "
+        }))
+        .unwrap();
+        assert!(is_latin1(&config));
+        config.db_type = DatabaseType::PostgreSQL;
+        assert!(!is_latin1(&config));
+    }
+}
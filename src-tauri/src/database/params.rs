@@ -0,0 +1,329 @@
+use super::query_patterns::dialect;
+use crate::models::{ColumnTypeFamily, DatabaseType, TableColumn};
+use anyhow::{anyhow, Result};
+use base64::Engine;
+use sqlparser::tokenizer::{Token, Tokenizer};
+use sqlx::database::HasArguments;
+use sqlx::query::Query;
+use sqlx::{Database, Encode, Type};
+
+/// A JSON value converted to something sqlx can bind as a statement parameter.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BindValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+impl BindValue {
+    /// Converts a value coming from the frontend. Binary columns receive the same
+    /// base64 text that `process_rows!` produces, so it is decoded back to bytes.
+    pub fn from_json(value: &serde_json::Value, column: Option<&TableColumn>) -> Result<Self> {
+        if let Some(bind) = column.and_then(|column| Self::boolean(value, column)) {
+            return Ok(bind);
+        }
+        let is_binary = column.is_some_and(|c| c.type_family == ColumnTypeFamily::Binary);
+        Ok(match value {
+            serde_json::Value::Null => BindValue::Null,
+            serde_json::Value::Bool(b) => BindValue::Bool(*b),
+            serde_json::Value::Number(n) => match n.as_i64() {
+                Some(i) => BindValue::Int(i),
+                None => BindValue::Float(n.as_f64().unwrap_or_default()),
+            },
+            serde_json::Value::String(s) if is_binary => BindValue::Bytes(
+                base64::engine::general_purpose::STANDARD
+                    .decode(s)
+                    .map_err(|e| {
+                        anyhow!(
+                            "Value for binary column {} is not valid base64: {}",
+                            column.map(|c| c.name.as_str()).unwrap_or_default(),
+                            e
+                        )
+                    })?,
+            ),
+            serde_json::Value::String(s) => BindValue::Text(s.clone()),
+            other => BindValue::Text(other.to_string()),
+        })
+    }
+
+    /// Converts the `index`-th (1-based) value given for a query's placeholders.
+    /// Only scalars can be bound.
+    pub fn from_parameter(index: usize, value: &serde_json::Value) -> Result<Self> {
+        match value {
+            serde_json::Value::Array(_) | serde_json::Value::Object(_) => Err(anyhow!(
+                "Parameter {} is {}; only numbers, strings, booleans and null can be bound",
+                index,
+                if value.is_array() { "an array" } else { "an object" }
+            )),
+            value => Self::from_json(value, None),
+        }
+    }
+
+    /// A true/false value for a boolean column, in the form its backend stores:
+    /// a native bool on Postgres, 0/1 for MySQL's TINYINT(1) and SQLite's
+    /// integers. SQLite columns declared as text keep what they were sent.
+    fn boolean(value: &serde_json::Value, column: &TableColumn) -> Option<Self> {
+        if !is_boolean_column(column) {
+            return None;
+        }
+        let flag = json_boolean(value)?;
+        Some(match column.db_type {
+            DatabaseType::PostgreSQL => BindValue::Bool(flag),
+            DatabaseType::MySQL => BindValue::Int(flag as i64),
+            DatabaseType::MsSql => BindValue::Bool(flag),
+            DatabaseType::SQLite if is_textual(&column.data_type) => return None,
+            DatabaseType::SQLite => BindValue::Int(flag as i64),
+        })
+    }
+}
+
+pub fn is_boolean_column(column: &TableColumn) -> bool {
+    column.type_family == ColumnTypeFamily::Boolean || column.is_boolean_like
+}
+
+/// Reads a JSON bool, 0/1, or one of the spellings CSV files use for booleans.
+pub fn json_boolean(value: &serde_json::Value) -> Option<bool> {
+    match value {
+        serde_json::Value::Bool(flag) => Some(*flag),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(0) => Some(false),
+            Some(1) => Some(true),
+            _ => None,
+        },
+        serde_json::Value::String(text) => match text.trim().to_ascii_lowercase().as_str() {
+            "t" | "true" | "yes" | "1" => Some(true),
+            "f" | "false" | "no" | "0" => Some(false),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn is_textual(declared_type: &str) -> bool {
+    let declared = declared_type.to_ascii_lowercase();
+    ["char", "text", "clob"].iter().any(|word| declared.contains(word))
+}
+
+/// Placeholder for the `index`-th (1-based) parameter. Postgres parameters are
+/// cast to the column type so text-encoded values (dates, numerics, enums, ...)
+/// are accepted by typed columns.
+pub fn placeholder(db_type: &DatabaseType, index: usize, column: Option<&TableColumn>) -> String {
+    match db_type {
+        DatabaseType::PostgreSQL => match column {
+            Some(column) if !column.data_type.is_empty() => {
+                format!("${}::{}", index, column.data_type)
+            }
+            _ => format!("${}", index),
+        },
+        DatabaseType::SQLite | DatabaseType::MySQL => "?".to_string(),
+        DatabaseType::MsSql => format!("@P{}", index),
+    }
+}
+
+fn placeholder_style(db_type: &DatabaseType) -> &'static str {
+    match db_type {
+        DatabaseType::PostgreSQL => "PostgreSQL expects $1, $2, ...",
+        DatabaseType::MySQL => "MySQL expects ?",
+        DatabaseType::SQLite => "SQLite expects ? or ?1, ?2, ...",
+        DatabaseType::MsSql => "SQL Server expects @P1, @P2, ...",
+    }
+}
+
+/// Checks that `sql` has a placeholder, in `db_type`'s style, for each of the
+/// `given` values. Placeholders in another database's style never get a
+/// value, so the error says which style is expected.
+pub fn check_placeholder_count(sql: &str, db_type: &DatabaseType, given: usize) -> Result<()> {
+    let tokens = Tokenizer::new(dialect(db_type).as_ref(), sql)
+        .tokenize()
+        .map_err(|e| anyhow!("Could not read query: {}", e))?;
+    let (mut questions, mut numbered, mut dollars, mut at_params) = (0, 0, 0, 0);
+    for token in tokens {
+        let placeholder = match token {
+            Token::Question => "?".to_string(),
+            Token::Placeholder(placeholder) => placeholder,
+            Token::Word(word) if word.value.starts_with('@') => word.value.to_ascii_uppercase(),
+            _ => continue,
+        };
+        let number = |prefix: &str| placeholder.strip_prefix(prefix).and_then(|n| n.parse::<usize>().ok());
+        if placeholder == "?" {
+            questions += 1;
+        } else if let Some(n) = number("?") {
+            numbered = n.max(numbered);
+        } else if let Some(n) = number("$") {
+            dollars = n.max(dollars);
+        } else if let Some(n) = number("@P") {
+            at_params = n.max(at_params);
+        }
+    }
+
+    let (expected, other_style) = match db_type {
+        DatabaseType::PostgreSQL => (dollars, questions + numbered + at_params > 0),
+        DatabaseType::MySQL => (questions, numbered + dollars + at_params > 0),
+        DatabaseType::SQLite => (questions + numbered.max(dollars), at_params > 0),
+        DatabaseType::MsSql => (at_params, questions + numbered + dollars > 0),
+    };
+    if expected == given {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "The query has {} placeholder(s) for {} value(s); {} placeholders{}",
+        expected,
+        given,
+        placeholder_style(db_type),
+        if other_style { ", but it uses another database's style" } else { "" }
+    ))
+}
+
+pub fn bind_values<'q, DB>(
+    mut query: Query<'q, DB, <DB as HasArguments<'q>>::Arguments>,
+    values: &[BindValue],
+) -> Query<'q, DB, <DB as HasArguments<'q>>::Arguments>
+where
+    DB: Database,
+    Option<bool>: Encode<'q, DB> + Type<DB>,
+    Option<i64>: Encode<'q, DB> + Type<DB>,
+    Option<f64>: Encode<'q, DB> + Type<DB>,
+    Option<String>: Encode<'q, DB> + Type<DB>,
+    Option<Vec<u8>>: Encode<'q, DB> + Type<DB>,
+{
+    for value in values {
+        query = match value.clone() {
+            BindValue::Null => query.bind(None::<String>),
+            BindValue::Bool(v) => query.bind(Some(v)),
+            BindValue::Int(v) => query.bind(Some(v)),
+            BindValue::Float(v) => query.bind(Some(v)),
+            BindValue::Text(v) => query.bind(Some(v)),
+            BindValue::Bytes(v) => query.bind(Some(v)),
+        };
+    }
+    query
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str, data_type: &str, family: ColumnTypeFamily) -> TableColumn {
+        TableColumn {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            raw_type: None,
+            normalized_type: data_type.to_uppercase(),
+            type_family: family,
+            db_type: DatabaseType::PostgreSQL,
+            is_nullable: true,
+            default_value: None,
+            is_primary_key: false,
+            is_boolean_like: false,
+            is_array: false,
+            enum_values: None,
+            identity_kind: None,
+            generated_kind: None,
+            generation_expression: None,
+            column_comment: None,
+            collation_name: None,
+            domain_name: None,
+            domain_schema: None,
+            domain_base_type: None,
+            array_dimensions: None,
+            element_raw_type: None,
+            character_maximum_length: None,
+            numeric_precision: None,
+            numeric_scale: None,
+        }
+    }
+
+    #[test]
+    fn converts_json_values_by_kind() {
+        assert_eq!(BindValue::from_json(&serde_json::json!(null), None).unwrap(), BindValue::Null);
+        assert_eq!(BindValue::from_json(&serde_json::json!(7), None).unwrap(), BindValue::Int(7));
+        assert_eq!(BindValue::from_json(&serde_json::json!(1.5), None).unwrap(), BindValue::Float(1.5));
+        assert_eq!(
+            BindValue::from_json(&serde_json::json!({"a": 1}), None).unwrap(),
+            BindValue::Text("{\"a\":1}".to_string())
+        );
+    }
+
+    #[test]
+    fn decodes_base64_for_binary_columns() {
+        let blob = column("data", "bytea", ColumnTypeFamily::Binary);
+        assert_eq!(
+            BindValue::from_json(&serde_json::json!("AQI="), Some(&blob)).unwrap(),
+            BindValue::Bytes(vec![1, 2])
+        );
+        assert!(BindValue::from_json(&serde_json::json!("not base64!"), Some(&blob)).is_err());
+    }
+
+    #[test]
+    fn postgres_placeholders_are_cast_to_the_column_type() {
+        let created = column("created_at", "timestamp without time zone", ColumnTypeFamily::DateTime);
+        assert_eq!(
+            placeholder(&DatabaseType::PostgreSQL, 2, Some(&created)),
+            "$2::timestamp without time zone"
+        );
+        assert_eq!(placeholder(&DatabaseType::PostgreSQL, 1, None), "$1");
+        assert_eq!(placeholder(&DatabaseType::MySQL, 3, Some(&created)), "?");
+    }
+
+    #[test]
+    fn booleans_are_bound_the_way_each_backend_stores_them() {
+        let pg = column("active", "boolean", ColumnTypeFamily::Boolean);
+        let mysql = TableColumn {
+            data_type: "tinyint".to_string(),
+            type_family: ColumnTypeFamily::Integer,
+            db_type: DatabaseType::MySQL,
+            is_boolean_like: true,
+            ..pg.clone()
+        };
+        let sqlite = TableColumn {
+            data_type: "BOOLEAN".to_string(),
+            db_type: DatabaseType::SQLite,
+            ..pg.clone()
+        };
+        let sqlite_text = TableColumn {
+            data_type: "BOOLEAN_TEXT".to_string(),
+            ..sqlite.clone()
+        };
+
+        let yes = serde_json::json!("Yes");
+        assert_eq!(BindValue::from_json(&serde_json::json!(true), Some(&pg)).unwrap(), BindValue::Bool(true));
+        assert_eq!(BindValue::from_json(&serde_json::json!("f"), Some(&pg)).unwrap(), BindValue::Bool(false));
+        assert_eq!(BindValue::from_json(&serde_json::json!(0), Some(&pg)).unwrap(), BindValue::Bool(false));
+        assert_eq!(BindValue::from_json(&yes, Some(&mysql)).unwrap(), BindValue::Int(1));
+        assert_eq!(BindValue::from_json(&serde_json::json!(false), Some(&sqlite)).unwrap(), BindValue::Int(0));
+        assert_eq!(BindValue::from_json(&yes, Some(&sqlite_text)).unwrap(), BindValue::Text("Yes".to_string()));
+        // Anything else is left for the database to judge.
+        assert_eq!(
+            BindValue::from_json(&serde_json::json!("maybe"), Some(&pg)).unwrap(),
+            BindValue::Text("maybe".to_string())
+        );
+    }
+
+    #[test]
+    fn counts_placeholders_in_each_backends_style() {
+        let check = check_placeholder_count;
+        assert!(check("SELECT * FROM t WHERE a = $1 AND b = $2 OR c = $1", &DatabaseType::PostgreSQL, 2).is_ok());
+        assert!(check("SELECT * FROM t WHERE a = ? AND b = '?'", &DatabaseType::MySQL, 1).is_ok());
+        assert!(check("SELECT ?, ?", &DatabaseType::SQLite, 2).is_ok());
+        assert!(check("SELECT ?1, ?2, ?1", &DatabaseType::SQLite, 2).is_ok());
+        assert!(check("SELECT @P1, @p2", &DatabaseType::MsSql, 2).is_ok());
+
+        let missing = check("SELECT $1", &DatabaseType::PostgreSQL, 2).unwrap_err().to_string();
+        assert!(missing.starts_with("The query has 1 placeholder(s) for 2 value(s)"), "{}", missing);
+        assert!(!missing.contains("another database"), "{}", missing);
+        let foreign = check("SELECT * FROM t WHERE a = ?", &DatabaseType::PostgreSQL, 1).unwrap_err().to_string();
+        assert!(foreign.contains("PostgreSQL expects $1") && foreign.contains("another database's style"), "{}", foreign);
+    }
+
+    #[test]
+    fn only_scalars_are_accepted_as_query_parameters() {
+        assert_eq!(BindValue::from_parameter(1, &serde_json::json!(true)).unwrap(), BindValue::Bool(true));
+        assert_eq!(BindValue::from_parameter(1, &serde_json::json!("x")).unwrap(), BindValue::Text("x".to_string()));
+        let error = BindValue::from_parameter(2, &serde_json::json!([1, 2])).unwrap_err().to_string();
+        assert!(error.starts_with("Parameter 2 is an array"), "{}", error);
+        assert!(BindValue::from_parameter(1, &serde_json::json!({"a": 1})).is_err());
+    }
+}
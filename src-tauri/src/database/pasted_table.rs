@@ -0,0 +1,314 @@
+use super::params::BindValue;
+use crate::models::{ColumnTypeFamily, PasteIssue};
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+
+/// Rows read to infer column types when the request doesn't say.
+pub const DEFAULT_SAMPLE_ROWS: usize = 1_000;
+/// Bound values per INSERT, under SQLite's oldest variable limit.
+pub const MAX_BINDS_PER_STATEMENT: usize = 900;
+/// Delimiters tried when sniffing, in order of preference on a tie.
+const DELIMITERS: &[char] = &['\t', ',', ';', '|'];
+const MAX_IDENTIFIER_LENGTH: usize = 63;
+
+pub struct PastedColumn {
+    pub name: String,
+    pub family: ColumnTypeFamily,
+}
+
+/// Pasted text split into named, typed columns; cells are None when empty.
+pub struct PastedData {
+    pub delimiter: char,
+    pub columns: Vec<PastedColumn>,
+    pub rows: Vec<Vec<Option<String>>>,
+    pub issues: Vec<PasteIssue>,
+}
+
+/// The delimiter that splits the first lines into the same number of fields
+/// most often, preferring tabs as spreadsheets copy them.
+pub fn sniff_delimiter(text: &str) -> char {
+    let lines = text.lines().filter(|line| !line.trim().is_empty()).take(20).collect::<Vec<_>>();
+    let mut best = ('\t', 0);
+    for delimiter in DELIMITERS {
+        let counts = lines
+            .iter()
+            .map(|line| line.matches(*delimiter).count())
+            .collect::<Vec<_>>();
+        let Some(&first) = counts.first().filter(|count| **count > 0) else {
+            continue;
+        };
+        let consistent = counts.iter().filter(|count| **count == first).count();
+        if consistent > best.1 {
+            best = (*delimiter, consistent);
+        }
+    }
+    best.0
+}
+
+/// Records with the line each starts on. Fields may be double-quoted, with
+/// `""` for a quote and line breaks kept inside quotes; blank lines are skipped.
+pub fn split_records(text: &str, delimiter: char) -> Vec<(usize, Vec<String>)> {
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if in_quotes => in_quotes = false,
+            '"' if field.is_empty() => in_quotes = true,
+            '\r' if !in_quotes && chars.peek() == Some(&'\n') => {}
+            '\n' if !in_quotes => {
+                fields.push(std::mem::take(&mut field));
+                if fields.iter().any(|field| !field.trim().is_empty()) {
+                    records.push((record_line, std::mem::take(&mut fields)));
+                }
+                fields.clear();
+                line += 1;
+                record_line = line;
+            }
+            c if c == delimiter && !in_quotes => fields.push(std::mem::take(&mut field)),
+            c => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+        }
+    }
+    fields.push(field);
+    if fields.iter().any(|field| !field.trim().is_empty()) {
+        records.push((record_line, fields));
+    }
+    records
+}
+
+/// `header` as a lowercase identifier that needs no quoting.
+pub fn identifier(header: &str, fallback: &str) -> String {
+    let mut name = String::new();
+    for c in header.trim().chars().flat_map(char::to_lowercase) {
+        if c.is_ascii_alphanumeric() {
+            name.push(c);
+        } else if !name.ends_with('_') {
+            name.push('_');
+        }
+    }
+    let name = name.trim_matches('_');
+    let mut name = if name.is_empty() { fallback.to_string() } else { name.to_string() };
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    name.truncate(MAX_IDENTIFIER_LENGTH);
+    name
+}
+
+/// `headers` as identifiers, numbering repeats: `name`, `name_2`, ...
+pub fn column_names(headers: &[String]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::with_capacity(headers.len());
+    for (index, header) in headers.iter().enumerate() {
+        let base = identifier(header, &format!("column_{}", index + 1));
+        let mut name = base.clone();
+        let mut suffix = 2;
+        while names.contains(&name) {
+            name = format!("{}_{}", base, suffix);
+            suffix += 1;
+        }
+        names.push(name);
+    }
+    names
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "yes" | "t" | "y" => Some(true),
+        "false" | "no" | "f" | "n" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()
+}
+
+fn fits(value: &str, family: &ColumnTypeFamily) -> bool {
+    match family {
+        ColumnTypeFamily::Integer => value.parse::<i64>().is_ok(),
+        ColumnTypeFamily::Float => value.parse::<f64>().is_ok_and(f64::is_finite),
+        ColumnTypeFamily::Boolean => parse_bool(value).is_some(),
+        ColumnTypeFamily::Date => parse_date(value).is_some(),
+        _ => true,
+    }
+}
+
+/// The narrowest type every value fits, widening integers to floats. When a
+/// value forces a typed column back to text, also its position and the type
+/// the values before it had.
+fn infer<'a>(values: impl Iterator<Item = &'a str>) -> (ColumnTypeFamily, Option<(usize, ColumnTypeFamily)>) {
+    let mut family = None;
+    for (position, value) in values.enumerate() {
+        let next = match &family {
+            None => [
+                ColumnTypeFamily::Integer,
+                ColumnTypeFamily::Float,
+                ColumnTypeFamily::Boolean,
+                ColumnTypeFamily::Date,
+            ]
+            .into_iter()
+            .find(|family| fits(value, family))
+            .unwrap_or(ColumnTypeFamily::Text),
+            Some(ColumnTypeFamily::Integer) if fits(value, &ColumnTypeFamily::Integer) => ColumnTypeFamily::Integer,
+            Some(ColumnTypeFamily::Integer | ColumnTypeFamily::Float) if fits(value, &ColumnTypeFamily::Float) => {
+                ColumnTypeFamily::Float
+            }
+            Some(current) if fits(value, current) => current.clone(),
+            Some(current) => return (ColumnTypeFamily::Text, Some((position, current.clone()))),
+        };
+        if next == ColumnTypeFamily::Text {
+            return (next, None);
+        }
+        family = Some(next);
+    }
+    (family.unwrap_or(ColumnTypeFamily::Text), None)
+}
+
+/// Splits `text` into columns typed from the first `sample_rows` rows. Ragged
+/// rows are padded or cut, and a column whose later values don't fit its
+/// type falls back to text; each case is reported with its line.
+pub fn read(text: &str, delimiter: Option<char>, has_header: bool, sample_rows: usize) -> Result<PastedData> {
+    let delimiter = delimiter.unwrap_or_else(|| sniff_delimiter(text));
+    let mut records = split_records(text, delimiter);
+    if records.is_empty() {
+        return Err(anyhow!("The pasted text has no rows"));
+    }
+    let headers = if has_header {
+        records.remove(0).1
+    } else {
+        vec![String::new(); records[0].1.len()]
+    };
+    let width = headers.len();
+    let names = column_names(&headers);
+
+    let mut issues = Vec::new();
+    let mut lines = Vec::with_capacity(records.len());
+    let mut rows = Vec::with_capacity(records.len());
+    for (line, mut fields) in records {
+        if fields.len() != width {
+            issues.push(PasteIssue {
+                line,
+                column: None,
+                message: if fields.len() < width {
+                    format!("{} fields instead of {}; the rest were left empty", fields.len(), width)
+                } else {
+                    format!("{} fields instead of {}; the extra ones were dropped", fields.len(), width)
+                },
+            });
+            fields.resize(width, String::new());
+        }
+        lines.push(line);
+        rows.push(
+            fields
+                .into_iter()
+                .map(|field| Some(field.trim().to_string()).filter(|field| !field.is_empty()))
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    let mut columns = Vec::with_capacity(width);
+    for (index, name) in names.into_iter().enumerate() {
+        // Non-empty cells with their lines; inference skips empty ones.
+        let filled = rows
+            .iter()
+            .zip(&lines)
+            .filter_map(|(row, line)| row[index].as_deref().map(|value| (value, *line)))
+            .collect::<Vec<_>>();
+        let sampled = rows.iter().take(sample_rows).filter(|row| row[index].is_some()).count();
+        let (mut family, broken) = infer(filled[..sampled].iter().map(|(value, _)| *value));
+        let conflict = match broken {
+            Some((position, typed)) => Some((filled[position], typed)),
+            None => filled[sampled..]
+                .iter()
+                .find(|(value, _)| !fits(value, &family))
+                .map(|cell| (*cell, family.clone())),
+        };
+        if let Some(((value, line), typed)) = conflict {
+            issues.push(PasteIssue {
+                line,
+                column: Some(name.clone()),
+                message: format!(
+                    "{:?} isn't {} like the values above it; the column is stored as text",
+                    value,
+                    format!("{:?}", typed).to_lowercase()
+                ),
+            });
+            family = ColumnTypeFamily::Text;
+        }
+        columns.push(PastedColumn { name, family });
+    }
+    issues.sort_by_key(|issue| issue.line);
+
+    Ok(PastedData {
+        delimiter,
+        columns,
+        rows,
+        issues,
+    })
+}
+
+/// A cell as a bind value for a column of `family`.
+pub fn bind_value(cell: Option<&str>, family: &ColumnTypeFamily) -> BindValue {
+    let Some(value) = cell else {
+        return BindValue::Null;
+    };
+    match family {
+        ColumnTypeFamily::Integer => value.parse().map_or(BindValue::Null, BindValue::Int),
+        ColumnTypeFamily::Float => value.parse().map_or(BindValue::Null, BindValue::Float),
+        ColumnTypeFamily::Boolean => parse_bool(value).map_or(BindValue::Null, BindValue::Bool),
+        _ => BindValue::Text(value.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spreadsheet_text_is_split_named_and_typed() {
+        let text = "Order #\tPlaced\tTotal\tPaid?\tNote\tnote\n\
+                    1\t2026-03-01\t12\tyes\t\"says \"\"hi\"\"\"\tx\n\
+                    2\t2026-03-02\t7.5\tno\t\"two\nlines\"\n\
+                    \n\
+                    x3\t2026-03-03\t1\tyes\t\t\t\n";
+        assert_eq!(sniff_delimiter(text), '\t');
+        let data = read(text, None, true, DEFAULT_SAMPLE_ROWS).unwrap();
+        let names = data.columns.iter().map(|column| column.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["order", "placed", "total", "paid", "note", "note_2"]);
+        let families = data.columns.iter().map(|column| column.family.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            families,
+            vec![
+                ColumnTypeFamily::Text,
+                ColumnTypeFamily::Date,
+                ColumnTypeFamily::Float,
+                ColumnTypeFamily::Boolean,
+                ColumnTypeFamily::Text,
+                ColumnTypeFamily::Text,
+            ]
+        );
+        assert_eq!(data.rows.len(), 3);
+        assert_eq!(data.rows[0][4].as_deref(), Some("says \"hi\""));
+        assert_eq!(data.rows[1][4].as_deref(), Some("two\nlines"));
+
+        let lines = data.issues.iter().map(|issue| (issue.line, issue.column.clone())).collect::<Vec<_>>();
+        assert_eq!(lines, vec![(3, None), (6, None), (6, Some("order".to_string()))]);
+        assert!(data.issues[2].message.contains("isn't integer"));
+
+        assert_eq!(identifier("2024 Revenue (€)", "c"), "_2024_revenue");
+        assert_eq!(sniff_delimiter("a;b,c\n1;2,3\n4;5"), ';');
+    }
+}
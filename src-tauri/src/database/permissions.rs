@@ -0,0 +1,219 @@
+use crate::models::{DatabasePermissions, TablePermissions};
+use std::collections::HashMap;
+
+/// Permission probes for one connection; cleared whenever it (re)connects.
+#[derive(Debug, Clone, Default)]
+pub struct CachedPermissions {
+    pub tables: HashMap<String, TablePermissions>,
+    pub database: Option<DatabasePermissions>,
+}
+
+/// Privileges granted by a set of MySQL `GRANT` lines for one object.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GrantSet {
+    pub select: bool,
+    pub insert: bool,
+    pub update: bool,
+    pub delete: bool,
+    pub alter: bool,
+    pub drop: bool,
+    pub create: bool,
+}
+
+impl GrantSet {
+    fn all() -> Self {
+        Self {
+            select: true,
+            insert: true,
+            update: true,
+            delete: true,
+            alter: true,
+            drop: true,
+            create: true,
+        }
+    }
+
+    fn add(&mut self, privilege: &str) {
+        match privilege {
+            "ALL" | "ALL PRIVILEGES" => *self = Self::all(),
+            "SELECT" => self.select = true,
+            "INSERT" => self.insert = true,
+            "UPDATE" => self.update = true,
+            "DELETE" => self.delete = true,
+            "ALTER" => self.alter = true,
+            "DROP" => self.drop = true,
+            "CREATE" => self.create = true,
+            _ => {}
+        }
+    }
+}
+
+/// Table permissions with every write turned off.
+pub fn without_writes(permissions: TablePermissions) -> TablePermissions {
+    TablePermissions {
+        can_insert: false,
+        can_update: false,
+        can_delete: false,
+        can_alter: false,
+        can_drop: false,
+        ..permissions
+    }
+}
+
+fn unquote(identifier: &str) -> String {
+    let identifier = identifier.trim();
+    match identifier
+        .strip_prefix('`')
+        .and_then(|rest| rest.strip_suffix('`'))
+    {
+        Some(inner) => inner.replace("``", "`"),
+        None => identifier.to_string(),
+    }
+}
+
+/// Splits `db.table` at the first dot outside backticks.
+fn split_scope(scope: &str) -> (String, String) {
+    let mut quoted = false;
+    for (index, c) in scope.char_indices() {
+        match c {
+            '`' => quoted = !quoted,
+            '.' if !quoted => return (unquote(&scope[..index]), unquote(&scope[index + 1..])),
+            _ => {}
+        }
+    }
+    (unquote(scope), "*".to_string())
+}
+
+/// MySQL database-name pattern match: `%` and `_` are wildcards, `\` escapes.
+fn like_match(pattern: &str, value: &str) -> bool {
+    fn matches(pattern: &[char], value: &[char]) -> bool {
+        match pattern.split_first() {
+            None => value.is_empty(),
+            Some(('%', rest)) => (0..=value.len()).any(|skip| matches(rest, &value[skip..])),
+            Some(('_', rest)) => !value.is_empty() && matches(rest, &value[1..]),
+            Some(('\\', rest)) if !rest.is_empty() => {
+                value.first() == Some(&rest[0]) && matches(&rest[1..], &value[1..])
+            }
+            Some((c, rest)) => {
+                value.first().is_some_and(|v| v.eq_ignore_ascii_case(c))
+                    && matches(rest, &value[1..])
+            }
+        }
+    }
+    let pattern: Vec<char> = pattern.chars().collect();
+    let value: Vec<char> = value.chars().collect();
+    matches(&pattern, &value)
+}
+
+/// Splits a privilege list at commas outside column lists.
+fn privileges(list: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut depth = 0;
+    let mut current = String::new();
+    for c in list.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                out.push(current.trim().to_uppercase());
+                current.clear();
+            }
+            // Column-level grants still allow the action on the table.
+            _ if depth > 0 => {}
+            _ => current.push(c),
+        }
+    }
+    out.push(current.trim().to_uppercase());
+    out
+}
+
+/// Privileges that `SHOW GRANTS` lines give on `database`.`table`, or on the
+/// database as a whole when `table` is `None`. Role grants aren't followed.
+pub fn mysql_grants(grants: &[String], database: &str, table: Option<&str>) -> GrantSet {
+    let mut set = GrantSet::default();
+    for grant in grants {
+        let Some(rest) = grant.trim().strip_prefix("GRANT ") else {
+            continue;
+        };
+        let Some((list, target)) = rest.split_once(" ON ") else {
+            continue;
+        };
+        let scope = target.split(" TO ").next().unwrap_or_default().trim();
+        let scope = scope.strip_prefix("TABLE ").unwrap_or(scope);
+        if scope.starts_with("FUNCTION ") || scope.starts_with("PROCEDURE ") {
+            continue;
+        }
+        let (scope_db, scope_table) = split_scope(scope);
+        let db_matches = scope_db == "*" || like_match(&scope_db, database);
+        let table_matches = match table {
+            Some(table) => scope_table == "*" || scope_table.eq_ignore_ascii_case(table),
+            None => scope_table == "*",
+        };
+        if db_matches && table_matches {
+            for privilege in privileges(list) {
+                set.add(&privilege);
+            }
+        }
+    }
+    set
+}
+
+/// Whether a SQLite database file can be written: `mode=ro` / `immutable=1`
+/// URIs can't, and otherwise the file and its directory must be writable.
+pub fn sqlite_file_writable(file_path: &str) -> bool {
+    let file_path = file_path.trim_start_matches("sqlite://");
+    let (path, query) = file_path.split_once('?').unwrap_or((file_path, ""));
+    if query
+        .split('&')
+        .any(|param| param == "mode=ro" || param == "immutable=1")
+    {
+        return false;
+    }
+    let path = path.trim_start_matches("file:");
+    if path.is_empty() || path == ":memory:" {
+        return true;
+    }
+    let path = std::path::Path::new(path);
+    let directory_writable = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .is_none_or(|parent| {
+            std::fs::metadata(parent).is_ok_and(|meta| !meta.permissions().readonly())
+        });
+    directory_writable && std::fs::OpenOptions::new().write(true).open(path).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_mysql_grants_for_tables_and_databases() {
+        let grants = vec![
+            "GRANT USAGE ON *.* TO `app`@`%`".to_string(),
+            "GRANT SELECT, UPDATE (`name`, `email`) ON `shop`.* TO `app`@`%`".to_string(),
+            "GRANT INSERT, DELETE ON `shop`.`orders` TO `app`@`%`".to_string(),
+            "GRANT ALL PRIVILEGES ON `scratch\\_%`.* TO `app`@`%`".to_string(),
+            "GRANT `reporting`@`%` TO `app`@`%`".to_string(),
+        ];
+
+        let orders = mysql_grants(&grants, "shop", Some("orders"));
+        assert!(orders.select && orders.update && orders.insert && orders.delete);
+        assert!(!orders.alter && !orders.drop);
+
+        let customers = mysql_grants(&grants, "shop", Some("customers"));
+        assert!(customers.select && customers.update);
+        assert!(!customers.insert && !customers.delete);
+        assert!(!mysql_grants(&grants, "shop", None).create);
+
+        assert_eq!(mysql_grants(&grants, "scratch_1", None), GrantSet::all());
+        assert_eq!(mysql_grants(&grants, "scratchy", None), GrantSet::default());
+    }
+
+    #[test]
+    fn read_only_sqlite_uris_are_not_writable() {
+        assert!(sqlite_file_writable(":memory:"));
+        assert!(!sqlite_file_writable("file:app.db?mode=ro"));
+        assert!(!sqlite_file_writable("/no/such/dir/app.db"));
+    }
+}
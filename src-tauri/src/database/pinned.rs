@@ -0,0 +1,698 @@
+use super::aggregates::ColumnAccumulator;
+use super::ConnectionManager;
+use crate::models::{
+    AggregateFunction, ColumnAggregates, PinnedExportFormat, PinnedResultDiff, PinnedResultPage,
+    PinnedRowChange, QueryResult, TableSort,
+};
+use anyhow::{anyhow, Result};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::Row;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::OnceCell;
+
+/// How long a pinned result stays available when no TTL is given.
+pub const DEFAULT_PIN_TTL_MINUTES: u64 = 60;
+/// Upper bound on the stored size of all pinned results together.
+pub const PINNED_RESULTS_SIZE_CAP: u64 = 512 * 1024 * 1024;
+pub const DEFAULT_PINNED_PAGE_SIZE: u32 = 500;
+/// Rows listed per category in a diff; the counts are never capped.
+pub const MAX_DIFF_ROWS: usize = 1000;
+
+const READ_BATCH: i64 = 1000;
+
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS pinned_results (
+    result_id TEXT PRIMARY KEY,
+    connection_id TEXT NOT NULL,
+    query TEXT NOT NULL,
+    columns TEXT NOT NULL,
+    row_count INTEGER NOT NULL,
+    size_bytes INTEGER NOT NULL,
+    created_at INTEGER NOT NULL,
+    expires_at INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS pinned_rows (
+    result_id TEXT NOT NULL,
+    row_index INTEGER NOT NULL,
+    data TEXT NOT NULL,
+    PRIMARY KEY (result_id, row_index)
+) WITHOUT ROWID;
+"#;
+
+struct PinnedMeta {
+    columns: Vec<String>,
+    row_count: u64,
+    expires_at: i64,
+}
+
+fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+fn not_found(result_id: &str) -> anyhow::Error {
+    anyhow!("Pinned result {} not found or expired", result_id)
+}
+
+/// Row as a JSON array in `columns` order, which keeps storage compact and lets
+/// pages sort by column position.
+fn row_array(row: &serde_json::Value, columns: &[String]) -> serde_json::Value {
+    serde_json::Value::Array(
+        columns
+            .iter()
+            .map(|column| row.get(column).cloned().unwrap_or(serde_json::Value::Null))
+            .collect(),
+    )
+}
+
+fn row_object(data: &str, columns: &[String]) -> Result<serde_json::Value> {
+    let values: Vec<serde_json::Value> = serde_json::from_str(data)?;
+    Ok(serde_json::Value::Object(
+        columns.iter().cloned().zip(values).collect(),
+    ))
+}
+
+/// Query results snapshotted into a temporary SQLite file so they can be paged,
+/// exported and diffed without re-running the query. The file is removed on
+/// `close` or when the store is dropped.
+pub struct PinnedResults {
+    path: PathBuf,
+    pool: OnceCell<SqlitePool>,
+    size_cap: u64,
+}
+
+impl PinnedResults {
+    pub fn new() -> Self {
+        Self::with_size_cap(PINNED_RESULTS_SIZE_CAP)
+    }
+
+    pub fn with_size_cap(size_cap: u64) -> Self {
+        Self {
+            path: std::env::temp_dir().join(format!(
+                "nodadb-pinned-{}-{}.sqlite",
+                std::process::id(),
+                uuid::Uuid::new_v4()
+            )),
+            pool: OnceCell::new(),
+            size_cap,
+        }
+    }
+
+    async fn pool(&self) -> Result<&SqlitePool> {
+        self.pool
+            .get_or_try_init(|| async {
+                let options = SqliteConnectOptions::new()
+                    .filename(&self.path)
+                    .create_if_missing(true);
+                let pool = SqlitePoolOptions::new()
+                    .max_connections(1)
+                    .connect_with(options)
+                    .await?;
+                sqlx::raw_sql(SCHEMA).execute(&pool).await?;
+                Ok::<_, anyhow::Error>(pool)
+            })
+            .await
+    }
+
+    async fn purge_expired(&self, pool: &SqlitePool) -> Result<()> {
+        let now = now_ms();
+        sqlx::query(
+            "DELETE FROM pinned_rows WHERE result_id IN (SELECT result_id FROM pinned_results WHERE expires_at <= ?)",
+        )
+        .bind(now)
+        .execute(pool)
+        .await?;
+        sqlx::query("DELETE FROM pinned_results WHERE expires_at <= ?")
+            .bind(now)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn meta(&self, pool: &SqlitePool, result_id: &str) -> Result<PinnedMeta> {
+        self.purge_expired(pool).await?;
+        let row = sqlx::query(
+            "SELECT columns, row_count, expires_at FROM pinned_results WHERE result_id = ?",
+        )
+        .bind(result_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| not_found(result_id))?;
+        Ok(PinnedMeta {
+            columns: serde_json::from_str(&row.try_get::<String, _>("columns")?)?,
+            row_count: row.try_get::<i64, _>("row_count")? as u64,
+            expires_at: row.try_get("expires_at")?,
+        })
+    }
+
+    /// Stores `result` and returns its id. Older pins are evicted when the store
+    /// would exceed its size cap.
+    pub async fn pin(
+        &self,
+        connection_id: &str,
+        query: &str,
+        result: &QueryResult,
+        ttl: Duration,
+    ) -> Result<String> {
+        let pool = self.pool().await?;
+        self.purge_expired(pool).await?;
+
+        let rows: Vec<String> = result
+            .rows
+            .iter()
+            .map(|row| row_array(row, &result.columns).to_string())
+            .collect();
+        let size: u64 = rows.iter().map(|row| row.len() as u64).sum();
+        if size > self.size_cap {
+            return Err(anyhow!(
+                "Result is too large to pin ({} bytes, limit {} bytes)",
+                size,
+                self.size_cap
+            ));
+        }
+
+        let mut tx = pool.begin().await?;
+        let mut used: i64 =
+            sqlx::query_scalar("SELECT COALESCE(SUM(size_bytes), 0) FROM pinned_results")
+                .fetch_one(&mut *tx)
+                .await?;
+        while used as u64 + size > self.size_cap {
+            let Some(oldest) = sqlx::query(
+                "SELECT result_id, size_bytes FROM pinned_results ORDER BY created_at, rowid LIMIT 1",
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+            else {
+                break;
+            };
+            let oldest_id: String = oldest.try_get("result_id")?;
+            used -= oldest.try_get::<i64, _>("size_bytes")?;
+            sqlx::query("DELETE FROM pinned_rows WHERE result_id = ?")
+                .bind(&oldest_id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM pinned_results WHERE result_id = ?")
+                .bind(&oldest_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        let result_id = uuid::Uuid::new_v4().to_string();
+        let created_at = now_ms();
+        sqlx::query(
+            "INSERT INTO pinned_results (result_id, connection_id, query, columns, row_count, size_bytes, created_at, expires_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&result_id)
+        .bind(connection_id)
+        .bind(query)
+        .bind(serde_json::to_string(&result.columns)?)
+        .bind(rows.len() as i64)
+        .bind(size as i64)
+        .bind(created_at)
+        .bind(created_at.saturating_add(ttl.as_millis() as i64))
+        .execute(&mut *tx)
+        .await?;
+        for (index, data) in rows.iter().enumerate() {
+            sqlx::query("INSERT INTO pinned_rows (result_id, row_index, data) VALUES (?, ?, ?)")
+                .bind(&result_id)
+                .bind(index as i64)
+                .bind(data)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(result_id)
+    }
+
+    /// One page of a pinned result, optionally sorted by a column.
+    pub async fn page(
+        &self,
+        result_id: &str,
+        page: u32,
+        page_size: u32,
+        sort: Option<&TableSort>,
+    ) -> Result<PinnedResultPage> {
+        let pool = self.pool().await?;
+        let meta = self.meta(pool, result_id).await?;
+        let page_size = page_size.max(1);
+
+        let order = match sort {
+            Some(sort) => {
+                let position = meta
+                    .columns
+                    .iter()
+                    .position(|column| column == &sort.column)
+                    .ok_or_else(|| anyhow!("Column {} is not in the pinned result", sort.column))?;
+                let direction = if sort.direction.eq_ignore_ascii_case("desc") {
+                    "DESC"
+                } else {
+                    "ASC"
+                };
+                format!(
+                    "json_extract(data, '$[{}]') {}, row_index",
+                    position, direction
+                )
+            }
+            None => "row_index".to_string(),
+        };
+        let rows = sqlx::query(&format!(
+            "SELECT data FROM pinned_rows WHERE result_id = ? ORDER BY {} LIMIT ? OFFSET ?",
+            order
+        ))
+        .bind(result_id)
+        .bind(page_size as i64)
+        .bind(page as i64 * page_size as i64)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(PinnedResultPage {
+            result_id: result_id.to_string(),
+            rows: rows
+                .iter()
+                .map(|row| row_object(&row.try_get::<String, _>("data")?, &meta.columns))
+                .collect::<Result<_>>()?,
+            columns: meta.columns,
+            page,
+            page_size,
+            total_rows: meta.row_count,
+            expires_at: chrono::DateTime::from_timestamp_millis(meta.expires_at)
+                .unwrap_or_default()
+                .to_rfc3339(),
+        })
+    }
+
+    /// Aggregates of one column over every pinned row, not just a page.
+    pub async fn aggregate(
+        &self,
+        result_id: &str,
+        column: &str,
+        functions: &[AggregateFunction],
+    ) -> Result<ColumnAggregates> {
+        let mut accumulator = ColumnAccumulator::new();
+        let columns = self
+            .for_each_row(result_id, |_, row| {
+                accumulator.add(&row[column]);
+                Ok(())
+            })
+            .await?;
+        if !columns.iter().any(|name| name == column) {
+            return Err(anyhow!("Column {} is not in the pinned result", column));
+        }
+        Ok(accumulator.finish(column, functions))
+    }
+
+    /// Hands every row, in pinned order, to `visit` in batches.
+    async fn for_each_row(
+        &self,
+        result_id: &str,
+        mut visit: impl FnMut(&[String], serde_json::Value) -> Result<()>,
+    ) -> Result<Vec<String>> {
+        let pool = self.pool().await?;
+        let meta = self.meta(pool, result_id).await?;
+        let mut next_index = 0_i64;
+        loop {
+            let rows = sqlx::query(
+                "SELECT row_index, data FROM pinned_rows WHERE result_id = ? AND row_index >= ? ORDER BY row_index LIMIT ?",
+            )
+            .bind(result_id)
+            .bind(next_index)
+            .bind(READ_BATCH)
+            .fetch_all(pool)
+            .await?;
+            for row in &rows {
+                next_index = row.try_get::<i64, _>("row_index")? + 1;
+                visit(
+                    &meta.columns,
+                    row_object(&row.try_get::<String, _>("data")?, &meta.columns)?,
+                )?;
+            }
+            if (rows.len() as i64) < READ_BATCH {
+                return Ok(meta.columns);
+            }
+        }
+    }
+
+    /// Writes a pinned result to `path`; returns the number of rows written.
+    pub async fn export(
+        &self,
+        result_id: &str,
+        format: PinnedExportFormat,
+        path: &str,
+    ) -> Result<u64> {
+        use std::io::Write;
+
+        let file =
+            std::fs::File::create(path).map_err(|e| anyhow!("Failed to create {}: {}", path, e))?;
+        let mut writer = std::io::BufWriter::new(file);
+        let mut written = 0_u64;
+        let outcome = self
+            .for_each_row(result_id, |columns, row| {
+                match format {
+                    PinnedExportFormat::Csv => {
+                        if written == 0 {
+                            let header = columns
+                                .iter()
+                                .map(|column| {
+                                    ConnectionManager::csv_escape(&serde_json::Value::String(
+                                        column.clone(),
+                                    ))
+                                })
+                                .collect::<Vec<_>>()
+                                .join(",");
+                            writeln!(writer, "{}", header)?;
+                        }
+                        let line = columns
+                            .iter()
+                            .map(|column| ConnectionManager::csv_escape(&row[column]))
+                            .collect::<Vec<_>>()
+                            .join(",");
+                        writeln!(writer, "{}", line)?;
+                    }
+                    PinnedExportFormat::Json => {
+                        write!(writer, "{}", if written == 0 { "[\n  " } else { ",\n  " })?;
+                        serde_json::to_writer(&mut writer, &row)?;
+                    }
+                }
+                written += 1;
+                Ok(())
+            })
+            .await;
+
+        let finished = outcome.and_then(|columns| {
+            match format {
+                PinnedExportFormat::Csv if written == 0 => {
+                    let header = columns
+                        .iter()
+                        .map(|column| {
+                            ConnectionManager::csv_escape(&serde_json::Value::String(
+                                column.clone(),
+                            ))
+                        })
+                        .collect::<Vec<_>>()
+                        .join(",");
+                    writeln!(writer, "{}", header)?;
+                }
+                PinnedExportFormat::Json if written == 0 => writeln!(writer, "[]")?,
+                PinnedExportFormat::Json => writeln!(writer, "\n]")?,
+                PinnedExportFormat::Csv => {}
+            }
+            writer.flush()?;
+            Ok(written)
+        });
+        if finished.is_err() {
+            let _ = std::fs::remove_file(path);
+        }
+        finished
+    }
+
+    async fn keyed_rows(
+        &self,
+        result_id: &str,
+        key_columns: &[String],
+    ) -> Result<Vec<(String, serde_json::Value)>> {
+        let mut rows = Vec::new();
+        let columns = self
+            .for_each_row(result_id, |_, row| {
+                let key: Vec<&serde_json::Value> =
+                    key_columns.iter().map(|key| &row[key]).collect();
+                rows.push((serde_json::to_string(&key)?, row));
+                Ok(())
+            })
+            .await?;
+        if let Some(missing) = key_columns.iter().find(|key| !columns.contains(key)) {
+            return Err(anyhow!(
+                "Key column {} is not in pinned result {}",
+                missing,
+                result_id
+            ));
+        }
+        Ok(rows)
+    }
+
+    /// Compares two pinned results row by row, matching rows on `key_columns`.
+    pub async fn diff(
+        &self,
+        before_id: &str,
+        after_id: &str,
+        key_columns: &[String],
+    ) -> Result<PinnedResultDiff> {
+        if key_columns.is_empty() {
+            return Err(anyhow!("At least one key column is required"));
+        }
+        let before_rows = self.keyed_rows(before_id, key_columns).await?;
+        let after_rows = self.keyed_rows(after_id, key_columns).await?;
+
+        let mut before: HashMap<String, serde_json::Value> =
+            HashMap::with_capacity(before_rows.len());
+        for (key, row) in before_rows {
+            if before.insert(key.clone(), row).is_some() {
+                return Err(anyhow!(
+                    "Key columns don't identify rows uniquely in {} (duplicate key {})",
+                    before_id,
+                    key
+                ));
+            }
+        }
+
+        let mut diff = PinnedResultDiff {
+            key_columns: key_columns.to_vec(),
+            added: Vec::new(),
+            removed: Vec::new(),
+            changed: Vec::new(),
+            added_count: 0,
+            removed_count: 0,
+            changed_count: 0,
+            unchanged_count: 0,
+            truncated: false,
+        };
+        let mut seen = std::collections::HashSet::new();
+        for (key, after) in after_rows {
+            if !seen.insert(key.clone()) {
+                return Err(anyhow!(
+                    "Key columns don't identify rows uniquely in {} (duplicate key {})",
+                    after_id,
+                    key
+                ));
+            }
+            let Some(before) = before.remove(&key) else {
+                diff.added_count += 1;
+                if diff.added.len() < MAX_DIFF_ROWS {
+                    diff.added.push(after);
+                }
+                continue;
+            };
+            let mut changed_columns: Vec<String> = Vec::new();
+            if let (Some(before_map), Some(after_map)) = (before.as_object(), after.as_object()) {
+                for column in before_map.keys().chain(after_map.keys()) {
+                    if before_map.get(column) != after_map.get(column)
+                        && !changed_columns.contains(column)
+                    {
+                        changed_columns.push(column.clone());
+                    }
+                }
+            }
+            if changed_columns.is_empty() {
+                diff.unchanged_count += 1;
+                continue;
+            }
+            diff.changed_count += 1;
+            if diff.changed.len() < MAX_DIFF_ROWS {
+                diff.changed.push(PinnedRowChange {
+                    key: serde_json::Value::Object(
+                        key_columns
+                            .iter()
+                            .map(|column| (column.clone(), after[column].clone()))
+                            .collect(),
+                    ),
+                    before,
+                    after,
+                    changed_columns,
+                });
+            }
+        }
+        diff.removed_count = before.len() as u64;
+        let mut removed: Vec<_> = before.into_values().collect();
+        removed.truncate(MAX_DIFF_ROWS);
+        diff.removed = removed;
+        diff.truncated = diff.added_count > diff.added.len() as u64
+            || diff.removed_count > diff.removed.len() as u64
+            || diff.changed_count > diff.changed.len() as u64;
+        Ok(diff)
+    }
+
+    /// Drops a pinned result; returns false if it didn't exist.
+    pub async fn drop_result(&self, result_id: &str) -> Result<bool> {
+        let pool = self.pool().await?;
+        sqlx::query("DELETE FROM pinned_rows WHERE result_id = ?")
+            .bind(result_id)
+            .execute(pool)
+            .await?;
+        let deleted = sqlx::query("DELETE FROM pinned_results WHERE result_id = ?")
+            .bind(result_id)
+            .execute(pool)
+            .await?;
+        Ok(deleted.rows_affected() > 0)
+    }
+
+    /// Closes the store and deletes its file.
+    pub async fn close(&self) {
+        if let Some(pool) = self.pool.get() {
+            pool.close().await;
+        }
+        self.remove_file();
+    }
+
+    fn remove_file(&self) {
+        for suffix in ["", "-journal", "-wal", "-shm"] {
+            let mut path = self.path.clone().into_os_string();
+            path.push(suffix);
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+impl Drop for PinnedResults {
+    fn drop(&mut self) {
+        self.remove_file();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(rows: Vec<serde_json::Value>) -> QueryResult {
+        QueryResult {
+            columns: vec!["id".to_string(), "name".to_string()],
+            rows,
+            rows_affected: 0,
+            attempts_made: 1,
+            truncated_cells: vec![],
+            result_id: None,
+            lineage: None,
+            status_message: None,
+            no_result_set: false,
+            decoded_columns: vec![],
+            binary_columns: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn diffs_pinned_snapshots_on_key_columns() {
+        let store = PinnedResults::new();
+        let ttl = Duration::from_secs(60);
+        let before = store
+            .pin(
+                "c",
+                "SELECT id, name FROM t",
+                &result(vec![
+                    serde_json::json!({"id": 1, "name": "a"}),
+                    serde_json::json!({"id": 2, "name": "b"}),
+                    serde_json::json!({"id": 3, "name": "c"}),
+                ]),
+                ttl,
+            )
+            .await
+            .unwrap();
+        let after = store
+            .pin(
+                "c",
+                "SELECT id, name FROM t",
+                &result(vec![
+                    serde_json::json!({"id": 1, "name": "a"}),
+                    serde_json::json!({"id": 2, "name": "B"}),
+                    serde_json::json!({"id": 4, "name": "d"}),
+                ]),
+                ttl,
+            )
+            .await
+            .unwrap();
+
+        let diff = store
+            .diff(&before, &after, &["id".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(
+            (
+                diff.added_count,
+                diff.removed_count,
+                diff.changed_count,
+                diff.unchanged_count
+            ),
+            (1, 1, 1, 1)
+        );
+        assert_eq!(diff.added[0]["id"], 4);
+        assert_eq!(diff.removed[0]["id"], 3);
+        assert_eq!(diff.changed[0].key, serde_json::json!({"id": 2}));
+        assert_eq!(diff.changed[0].changed_columns, vec!["name"]);
+        assert!(store
+            .diff(&before, &after, &["missing".to_string()])
+            .await
+            .is_err());
+
+        let page = store
+            .page(
+                &after,
+                0,
+                2,
+                Some(&TableSort {
+                    column: "name".to_string(),
+                    direction: "desc".to_string(),
+                }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(page.total_rows, 3);
+        assert_eq!(
+            page.rows,
+            vec![
+                serde_json::json!({"id": 4, "name": "d"}),
+                serde_json::json!({"id": 1, "name": "a"})
+            ]
+        );
+
+        assert!(store.drop_result(&before).await.unwrap());
+        assert!(store.page(&before, 0, 10, None).await.is_err());
+        store.close().await;
+    }
+
+    #[tokio::test]
+    async fn evicts_oldest_pins_and_expires_by_ttl() {
+        let rows = vec![serde_json::json!({"id": 1, "name": "x".repeat(100)})];
+        let one = row_array(&rows[0], &["id".to_string(), "name".to_string()])
+            .to_string()
+            .len() as u64;
+        let store = PinnedResults::with_size_cap(one * 2);
+
+        let first = store
+            .pin("c", "q", &result(rows.clone()), Duration::from_secs(60))
+            .await
+            .unwrap();
+        let second = store
+            .pin("c", "q", &result(rows.clone()), Duration::from_secs(60))
+            .await
+            .unwrap();
+        let third = store
+            .pin("c", "q", &result(rows.clone()), Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert!(store.page(&first, 0, 10, None).await.is_err());
+        assert!(store.page(&second, 0, 10, None).await.is_ok());
+        assert!(store.page(&third, 0, 10, None).await.is_ok());
+
+        let expired = store
+            .pin("c", "q", &result(rows.clone()), Duration::ZERO)
+            .await
+            .unwrap();
+        assert!(store.page(&expired, 0, 10, None).await.is_err());
+
+        let mut too_big = rows;
+        too_big.extend(too_big.clone().into_iter().cycle().take(2));
+        assert!(store
+            .pin("c", "q", &result(too_big), Duration::from_secs(60))
+            .await
+            .is_err());
+        store.close().await;
+    }
+}
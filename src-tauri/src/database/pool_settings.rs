@@ -0,0 +1,105 @@
+use crate::models::{ConnectionConfig, DatabaseType, PoolSettings, SqliteOpenMode};
+use anyhow::{anyhow, Result};
+use sqlx::pool::PoolOptions;
+use std::time::Duration;
+
+/// sqlx's own defaults, used for whatever a connection leaves unset.
+pub const DEFAULT_MAX_CONNECTIONS: u32 = 10;
+pub const DEFAULT_ACQUIRE_TIMEOUT_SECONDS: u64 = 30;
+pub const DEFAULT_IDLE_TIMEOUT_SECONDS: u64 = 600;
+
+/// The pool settings `config` opens with, every field filled in.
+///
+/// SQLite keeps several connections by default too: console sessions and
+/// `execute_with_plan` pin one for as long as they run, so a one-connection
+/// pool would stall every other query meanwhile. Writers wait out each
+/// other's locks through the busy timeout; `max_connections: 1` is there
+/// for files where even that isn't wanted.
+pub fn effective(config: &ConnectionConfig) -> Result<PoolSettings> {
+    if config.db_type == DatabaseType::SQLite && config.sqlite_open_mode == SqliteOpenMode::InMemory {
+        // Every connection to `sqlite::memory:` is its own database, so the
+        // pool keeps exactly one open for good.
+        return Ok(PoolSettings {
+            max_connections: Some(1),
+            min_connections: Some(1),
+            acquire_timeout_seconds: Some(
+                config
+                    .pool_settings
+                    .as_ref()
+                    .and_then(|settings| settings.acquire_timeout_seconds)
+                    .unwrap_or(DEFAULT_ACQUIRE_TIMEOUT_SECONDS),
+            ),
+            idle_timeout_seconds: Some(0),
+        });
+    }
+    let requested = config.pool_settings.clone().unwrap_or_default();
+    let max_connections = requested.max_connections.unwrap_or(DEFAULT_MAX_CONNECTIONS);
+    let min_connections = requested.min_connections.unwrap_or(0);
+    if max_connections == 0 {
+        return Err(anyhow!("max_connections must be at least 1"));
+    }
+    if min_connections > max_connections {
+        return Err(anyhow!(
+            "min_connections ({}) can't be more than max_connections ({})",
+            min_connections,
+            max_connections
+        ));
+    }
+    Ok(PoolSettings {
+        max_connections: Some(max_connections),
+        min_connections: Some(min_connections),
+        acquire_timeout_seconds: Some(
+            requested.acquire_timeout_seconds.unwrap_or(DEFAULT_ACQUIRE_TIMEOUT_SECONDS),
+        ),
+        // 0 keeps idle connections open for good.
+        idle_timeout_seconds: Some(requested.idle_timeout_seconds.unwrap_or(DEFAULT_IDLE_TIMEOUT_SECONDS)),
+    })
+}
+
+/// `options` sized and timed per `settings`, as returned by `effective`.
+pub fn apply<DB: sqlx::Database>(options: PoolOptions<DB>, settings: &PoolSettings) -> PoolOptions<DB> {
+    let idle_timeout = settings
+        .idle_timeout_seconds
+        .filter(|seconds| *seconds > 0)
+        .map(Duration::from_secs);
+    options
+        .max_connections(settings.max_connections.unwrap_or(DEFAULT_MAX_CONNECTIONS))
+        .min_connections(settings.min_connections.unwrap_or(0))
+        .acquire_timeout(Duration::from_secs(
+            settings.acquire_timeout_seconds.unwrap_or(DEFAULT_ACQUIRE_TIMEOUT_SECONDS),
+        ))
+        .idle_timeout(idle_timeout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unset_settings_take_the_defaults() {
+        let mut config: ConnectionConfig = serde_json::from_value(serde_json::json!({
+            "id": "c", "name": "c", "db_type": "sqlite"
+        }))
+        .unwrap();
+        let sqlite = effective(&config).unwrap();
+        assert_eq!(sqlite.max_connections, Some(DEFAULT_MAX_CONNECTIONS));
+        assert_eq!(sqlite.acquire_timeout_seconds, Some(DEFAULT_ACQUIRE_TIMEOUT_SECONDS));
+
+        config.db_type = crate::models::DatabaseType::PostgreSQL;
+        config.pool_settings = Some(PoolSettings {
+            max_connections: Some(4),
+            idle_timeout_seconds: Some(0),
+            ..Default::default()
+        });
+        let postgres = effective(&config).unwrap();
+        assert_eq!((postgres.max_connections, postgres.min_connections), (Some(4), Some(0)));
+        assert_eq!(postgres.idle_timeout_seconds, Some(0));
+
+        config.pool_settings = Some(PoolSettings {
+            max_connections: Some(2),
+            min_connections: Some(3),
+            ..Default::default()
+        });
+        assert!(effective(&config).is_err());
+    }
+}
@@ -0,0 +1,71 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Figures the manager keeps per connection next to its pool; sqlx itself
+/// only knows how many connections are open and idle.
+#[derive(Default)]
+pub struct PoolCounters {
+    queries: AtomicU64,
+    errors: AtomicU64,
+    in_flight: AtomicUsize,
+}
+
+impl PoolCounters {
+    /// Counts one `execute_query` call, however many attempts it took.
+    pub fn record_query(&self, failed: bool) {
+        self.queries.fetch_add(1, Ordering::Relaxed);
+        if failed {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Marks an operation as running on the connection until the guard drops.
+    pub fn enter(self: &Arc<Self>) -> InFlight {
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        InFlight { counters: self.clone() }
+    }
+
+    pub fn queries(&self) -> u64 {
+        self.queries.load(Ordering::Relaxed)
+    }
+
+    pub fn errors(&self) -> u64 {
+        self.errors.load(Ordering::Relaxed)
+    }
+
+    /// Running operations beyond the connections checked out of the pool;
+    /// those are waiting to acquire one. Connections held outside operations,
+    /// like console sessions, make this an undercount rather than an overcount.
+    pub fn pending_acquires(&self, pool_size: u32, idle: usize) -> usize {
+        let in_use = (pool_size as usize).saturating_sub(idle);
+        self.in_flight.load(Ordering::Relaxed).saturating_sub(in_use)
+    }
+}
+
+pub struct InFlight {
+    counters: Arc<PoolCounters>,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.counters.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operations_without_a_connection_are_pending() {
+        let counters = Arc::new(PoolCounters::default());
+        let first = counters.enter();
+        let _second = counters.enter();
+        let _third = counters.enter();
+        // Two connections open, one of them idle: one operation has it, two wait.
+        assert_eq!(counters.pending_acquires(2, 1), 2);
+        drop(first);
+        assert_eq!(counters.pending_acquires(2, 1), 1);
+        assert_eq!(counters.pending_acquires(2, 0), 0);
+    }
+}
@@ -0,0 +1,211 @@
+use crate::operations::CancellationToken;
+use anyhow::{anyhow, Result};
+use sqlx::mysql::MySqlConnectOptions;
+use sqlx::postgres::PgConnectOptions;
+use sqlx::{Connection, MySqlConnection, PgConnection};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+tokio::task_local! {
+    /// Query the current task runs for the frontend under a query id.
+    static RUNNING: Arc<RunningQuery>;
+}
+
+/// A statement stopped by `cancel_query`.
+#[derive(Debug)]
+pub struct QueryCancelled;
+
+impl std::fmt::Display for QueryCancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Query cancelled by user")
+    }
+}
+
+impl std::error::Error for QueryCancelled {}
+
+/// How to stop the statement of a running query, read from its connection
+/// before the statement is sent.
+pub enum CancelTarget {
+    /// SQLite's progress handler watches the query's token and interrupts it.
+    Interrupt,
+    /// `pg_cancel_backend` of the backend, sent over a connection of its own.
+    Postgres { pid: i32, options: Arc<PgConnectOptions> },
+    /// `KILL QUERY` of the connection id, sent over a connection of its own.
+    MySql { id: u64, options: Arc<MySqlConnectOptions> },
+}
+
+impl CancelTarget {
+    async fn cancel(&self) -> Result<()> {
+        match self {
+            CancelTarget::Interrupt => {}
+            CancelTarget::Postgres { pid, options } => {
+                let mut conn = PgConnection::connect_with(options).await?;
+                sqlx::query("SELECT pg_cancel_backend($1)").bind(pid).execute(&mut conn).await?;
+                conn.close().await?;
+            }
+            CancelTarget::MySql { id, options } => {
+                let mut conn = MySqlConnection::connect_with(options).await?;
+                sqlx::query(&format!("KILL QUERY {}", id)).execute(&mut conn).await?;
+                conn.close().await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A query registered under its query id.
+#[derive(Default)]
+pub struct RunningQuery {
+    token: CancellationToken,
+    /// Set while the statement runs. Cancelling holds the lock until the server
+    /// has been told, so the connection can't go back to the pool and have
+    /// another statement cancelled in its place.
+    target: tokio::sync::Mutex<Option<CancelTarget>>,
+}
+
+impl RunningQuery {
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Records how to stop the statement about to be sent; fails if the query
+    /// was cancelled before it got that far.
+    pub async fn started(&self, target: CancelTarget) -> Result<()> {
+        let mut current = self.target.lock().await;
+        if self.is_cancelled() {
+            return Err(QueryCancelled.into());
+        }
+        *current = Some(target);
+        Ok(())
+    }
+
+    /// Forgets the statement's connection once it has finished.
+    pub async fn finished(&self) {
+        self.target.lock().await.take();
+    }
+
+    async fn cancel(&self) -> Result<()> {
+        let target = self.target.lock().await;
+        self.token.cancel();
+        match target.as_ref() {
+            Some(target) => target.cancel().await,
+            None => Ok(()),
+        }
+    }
+}
+
+type QueryMap = HashMap<(String, String), Arc<RunningQuery>>;
+
+/// Queries running under a frontend-chosen id, by connection and query id.
+#[derive(Default)]
+pub struct RunningQueries {
+    queries: Arc<Mutex<QueryMap>>,
+}
+
+impl RunningQueries {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs `work` as `query_id` on `connection_id`; statements it sends can be
+    /// stopped with `cancel` until it returns.
+    pub async fn scope<T>(
+        &self,
+        connection_id: &str,
+        query_id: &str,
+        work: impl std::future::Future<Output = Result<T>>,
+    ) -> Result<T> {
+        let key = (connection_id.to_string(), query_id.to_string());
+        let running = Arc::new(RunningQuery::default());
+        {
+            let mut queries = self.queries.lock().map_err(|_| unavailable())?;
+            if queries.contains_key(&key) {
+                return Err(anyhow!("Query {} is already running", query_id));
+            }
+            queries.insert(key.clone(), running.clone());
+        }
+        let _registered = Registered {
+            queries: self.queries.clone(),
+            key,
+        };
+        let result = RUNNING.scope(running.clone(), work).await;
+        match result {
+            Err(_) if running.is_cancelled() => Err(QueryCancelled.into()),
+            result => result,
+        }
+    }
+
+    /// Cancels `query_id` on `connection_id`. Returns false when no such query
+    /// is running, e.g. because it already finished.
+    pub async fn cancel(&self, connection_id: &str, query_id: &str) -> Result<bool> {
+        let key = (connection_id.to_string(), query_id.to_string());
+        let running = self.queries.lock().map_err(|_| unavailable())?.get(&key).cloned();
+        let Some(running) = running else {
+            return Ok(false);
+        };
+        running.cancel().await?;
+        Ok(true)
+    }
+}
+
+fn unavailable() -> anyhow::Error {
+    anyhow!("Query registry is unavailable")
+}
+
+/// Unregisters a query however its work ends, including by being dropped.
+struct Registered {
+    queries: Arc<Mutex<QueryMap>>,
+    key: (String, String),
+}
+
+impl Drop for Registered {
+    fn drop(&mut self) {
+        if let Ok(mut queries) = self.queries.lock() {
+            queries.remove(&self.key);
+        }
+    }
+}
+
+/// The query the current task runs under a query id, if any.
+pub fn current() -> Option<Arc<RunningQuery>> {
+    RUNNING.try_with(|running| running.clone()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn queries_are_cancelled_by_id_until_they_finish() {
+        let queries = RunningQueries::new();
+        let work = queries.scope("c1", "q1", async {
+            let running = current().unwrap();
+            running.started(CancelTarget::Interrupt).await?;
+            while !running.is_cancelled() {
+                tokio::task::yield_now().await;
+            }
+            Err::<(), _>(anyhow!("SQLSTATE 9: interrupted"))
+        });
+        let cancel = async {
+            tokio::task::yield_now().await;
+            assert!(!queries.cancel("c2", "q1").await.unwrap());
+            assert!(queries.cancel("c1", "q1").await.unwrap());
+        };
+        let (result, ()) = tokio::join!(work, cancel);
+        assert!(result.unwrap_err().downcast_ref::<QueryCancelled>().is_some());
+        assert!(!queries.cancel("c1", "q1").await.unwrap());
+        assert!(current().is_none());
+    }
+
+    #[tokio::test]
+    async fn a_query_cancelled_before_its_statement_is_not_sent() {
+        let running = RunningQuery::default();
+        running.cancel().await.unwrap();
+        let error = running.started(CancelTarget::Interrupt).await.unwrap_err();
+        assert_eq!(error.to_string(), "Query cancelled by user");
+    }
+}
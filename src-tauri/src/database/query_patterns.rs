@@ -0,0 +1,948 @@
+use crate::models::{
+    ColumnTypeFamily, DatabaseType, QueryPatternFinding, SqlSpan, TableColumn, TableIndex,
+};
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use sqlparser::ast::{
+    BinaryOperator, Expr, FunctionArg, FunctionArgExpr, FunctionArguments, JoinConstraint,
+    JoinOperator, ObjectName, Query, Select, SelectItem, SelectItemQualifiedWildcardKind, SetExpr,
+    Spanned, Statement, TableFactor, Value,
+};
+use sqlparser::dialect::{Dialect, MsSqlDialect, MySqlDialect, PostgreSqlDialect, SQLiteDialect};
+use sqlparser::parser::Parser;
+use sqlparser::tokenizer::{Span, Token};
+use std::collections::{HashMap, HashSet};
+
+pub const RULE_SELECT_STAR_JOIN_SORT: &str = "select_star_join_sort";
+pub const RULE_LEADING_WILDCARD_LIKE: &str = "leading_wildcard_like";
+pub const RULE_FUNCTION_ON_INDEXED_COLUMN: &str = "function_on_indexed_column";
+pub const RULE_NOT_IN_NULLABLE_SUBQUERY: &str = "not_in_nullable_subquery";
+pub const RULE_JOIN_TYPE_MISMATCH: &str = "join_type_mismatch";
+
+/// Columns and leading index columns of one table referenced by a query.
+#[derive(Debug, Clone, Default)]
+pub struct TableMetadata {
+    pub columns: Vec<TableColumn>,
+    /// Lowercased names of columns that lead at least one index.
+    pub indexed_columns: HashSet<String>,
+}
+
+impl TableMetadata {
+    pub fn new(columns: Vec<TableColumn>, indexes: &[TableIndex]) -> Self {
+        let indexed_columns = indexes
+            .iter()
+            .filter_map(|index| index.columns.first())
+            .map(|column| column.to_lowercase())
+            .collect();
+        Self {
+            columns,
+            indexed_columns,
+        }
+    }
+
+    fn column(&self, name: &str) -> Option<&TableColumn> {
+        self.columns
+            .iter()
+            .find(|column| column.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Schema for the referenced tables, keyed by the table name as written in the query.
+pub type SchemaMap = HashMap<String, TableMetadata>;
+
+pub(super) fn dialect(db_type: &DatabaseType) -> Box<dyn Dialect> {
+    match db_type {
+        DatabaseType::PostgreSQL => Box::new(PostgreSqlDialect {}),
+        DatabaseType::MySQL => Box::new(MySqlDialect {}),
+        DatabaseType::SQLite => Box::new(SQLiteDialect {}),
+        DatabaseType::MsSql => Box::new(MsSqlDialect {}),
+    }
+}
+
+pub fn parse(sql: &str, db_type: &DatabaseType) -> Result<Vec<Statement>> {
+    Parser::parse_sql(dialect(db_type).as_ref(), sql)
+        .map_err(|e| anyhow!("Could not parse query: {}", e))
+}
+
+/// Parses a single SQL expression, rejecting anything after it (such as a
+/// second statement).
+pub fn parse_expression(sql: &str, db_type: &DatabaseType) -> Result<Expr> {
+    let dialect = dialect(db_type);
+    let mut parser = Parser::new(dialect.as_ref())
+        .try_with_sql(sql)
+        .map_err(|e| anyhow!("Could not parse expression: {}", e))?;
+    let expr = parser
+        .parse_expr()
+        .map_err(|e| anyhow!("Could not parse expression: {}", e))?;
+    parser
+        .expect_token(&Token::EOF)
+        .map_err(|_| anyhow!("Expression must not contain anything after {}", expr))?;
+    Ok(expr)
+}
+
+/// Tables named in the FROM and JOIN clauses of `statements`, in order of appearance.
+pub fn referenced_tables(statements: &[Statement], db_type: &DatabaseType) -> Vec<String> {
+    let schema = SchemaMap::new();
+    let mut analyzer = Analyzer::new(db_type, &schema);
+    analyzer.visit_statements(statements);
+    analyzer.tables
+}
+
+/// Where each output column of a single plain SELECT is read from, as (table
+/// as written in the query, column), with `*` expanded from `schema`. `None`
+/// when the SELECT list can't be lined up with the result columns; entries
+/// are `None` for computed or ambiguous columns.
+pub fn projection_origins(
+    statements: &[Statement],
+    schema: &SchemaMap,
+) -> Option<Vec<Option<(String, String)>>> {
+    let [Statement::Query(query)] = statements else {
+        return None;
+    };
+    let SetExpr::Select(select) = query.body.as_ref() else {
+        return None;
+    };
+    let scope = select_scope(select);
+    let mut origins = Vec::new();
+    let expand = |entry: &ScopeTable, origins: &mut Vec<_>| {
+        let table = schema.get(&entry.table).filter(|table| !table.columns.is_empty())?;
+        origins.extend(
+            table
+                .columns
+                .iter()
+                .map(|column| Some((entry.table.clone(), column.name.clone()))),
+        );
+        Some(())
+    };
+    for item in &select.projection {
+        match item {
+            SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => {
+                origins.push(column_origin(expr, &scope, schema));
+            }
+            SelectItem::Wildcard(_) => {
+                for entry in &scope {
+                    expand(entry, &mut origins)?;
+                }
+            }
+            SelectItem::QualifiedWildcard(SelectItemQualifiedWildcardKind::ObjectName(name), _) => {
+                let qualifier = object_name(name);
+                let entry = scope
+                    .iter()
+                    .find(|entry| entry.reference.eq_ignore_ascii_case(&qualifier))?;
+                expand(entry, &mut origins)?;
+            }
+            _ => return None,
+        }
+    }
+    Some(origins)
+}
+
+fn column_origin(expr: &Expr, scope: &[ScopeTable], schema: &SchemaMap) -> Option<(String, String)> {
+    match expr {
+        Expr::Identifier(ident) => {
+            let mut matches = scope.iter().filter_map(|entry| {
+                let column = schema.get(&entry.table)?.column(&ident.value)?;
+                Some((entry.table.clone(), column.name.clone()))
+            });
+            let found = matches.next()?;
+            matches.next().is_none().then_some(found)
+        }
+        Expr::CompoundIdentifier(parts) if parts.len() >= 2 => {
+            let qualifier = &parts[parts.len() - 2].value;
+            let entry = scope
+                .iter()
+                .find(|entry| entry.reference.eq_ignore_ascii_case(qualifier))?;
+            let column = schema.get(&entry.table)?.column(&parts[parts.len() - 1].value)?;
+            Some((entry.table.clone(), column.name.clone()))
+        }
+        Expr::Nested(inner) => column_origin(inner, scope, schema),
+        _ => None,
+    }
+}
+
+/// Anti-patterns in `statements`, using `schema` for the checks that need
+/// column types, nullability or indexes. Only queries are analyzed.
+pub fn analyze(
+    statements: &[Statement],
+    db_type: &DatabaseType,
+    schema: &SchemaMap,
+) -> Vec<QueryPatternFinding> {
+    let mut analyzer = Analyzer::new(db_type, schema);
+    analyzer.visit_statements(statements);
+    analyzer.findings
+}
+
+/// One-line form of `finding` for the explain recommendations list.
+pub fn recommendation(finding: &QueryPatternFinding) -> String {
+    let mut text = finding.message.clone();
+    if let Some(span) = &finding.span {
+        text.push_str(&format!(" (line {}, column {})", span.line, span.column));
+    }
+    if let Some(suggestion) = &finding.suggestion {
+        text.push_str(&format!(". Suggested rewrite: {}", suggestion));
+    }
+    text
+}
+
+fn sql_span(span: Span) -> Option<SqlSpan> {
+    (span.start.line > 0).then_some(SqlSpan {
+        line: span.start.line,
+        column: span.start.column,
+        end_line: span.end.line,
+        end_column: span.end.column,
+    })
+}
+
+fn object_name(name: &ObjectName) -> String {
+    name.0
+        .iter()
+        .filter_map(|part| part.as_ident())
+        .map(|ident| ident.value.clone())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// A table in a SELECT's FROM clause and the name it's referred to by.
+struct ScopeTable {
+    reference: String,
+    table: String,
+}
+
+struct Analyzer<'a> {
+    db_type: &'a DatabaseType,
+    schema: &'a SchemaMap,
+    tables: Vec<String>,
+    findings: Vec<QueryPatternFinding>,
+}
+
+impl<'a> Analyzer<'a> {
+    fn new(db_type: &'a DatabaseType, schema: &'a SchemaMap) -> Self {
+        Self {
+            db_type,
+            schema,
+            tables: Vec::new(),
+            findings: Vec::new(),
+        }
+    }
+
+    fn report(&mut self, rule: &str, message: String, span: Span, suggestion: Option<String>) {
+        self.findings.push(QueryPatternFinding {
+            rule: rule.to_string(),
+            message,
+            span: sql_span(span),
+            suggestion,
+        });
+    }
+
+    fn visit_statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            if let Statement::Query(query) = statement {
+                self.visit_query(query);
+            }
+        }
+    }
+
+    fn visit_query(&mut self, query: &Query) {
+        if let Some(with) = &query.with {
+            for cte in &with.cte_tables {
+                self.visit_query(&cte.query);
+            }
+        }
+        self.visit_set_expr(&query.body, query.order_by.is_some());
+    }
+
+    fn visit_set_expr(&mut self, body: &SetExpr, sorted: bool) {
+        match body {
+            SetExpr::Select(select) => self.visit_select(select, sorted),
+            SetExpr::Query(query) => self.visit_query(query),
+            SetExpr::SetOperation { left, right, .. } => {
+                self.visit_set_expr(left, sorted);
+                self.visit_set_expr(right, sorted);
+            }
+            _ => {}
+        }
+    }
+
+    fn visit_select(&mut self, select: &Select, sorted: bool) {
+        let scope = select_scope(select);
+        for entry in &scope {
+            if !self.tables.contains(&entry.table) {
+                self.tables.push(entry.table.clone());
+            }
+        }
+        for from in &select.from {
+            let factors =
+                std::iter::once(&from.relation).chain(from.joins.iter().map(|join| &join.relation));
+            for factor in factors {
+                if let TableFactor::Derived { subquery, .. } = factor {
+                    self.visit_query(subquery);
+                }
+            }
+        }
+
+        let joined = select.from.len() > 1 || select.from.iter().any(|from| !from.joins.is_empty());
+        if joined && sorted {
+            self.check_select_star(select, &scope);
+        }
+
+        for from in &select.from {
+            for join in &from.joins {
+                if let Some(JoinConstraint::On(condition)) = join_constraint(&join.join_operator) {
+                    self.check_join_condition(condition, &scope);
+                }
+            }
+        }
+
+        for item in &select.projection {
+            if let SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } = item {
+                self.visit_subqueries(expr);
+            }
+        }
+        if let Some(selection) = &select.selection {
+            self.check_filter(selection, &scope);
+        }
+        if let Some(having) = &select.having {
+            self.visit_subqueries(having);
+        }
+    }
+
+    fn lookup(&self, table: &str) -> Option<&'a TableMetadata> {
+        self.schema.get(table)
+    }
+
+    /// Resolves a column reference against the tables of the enclosing SELECT.
+    fn resolve(
+        &self,
+        expr: &Expr,
+        scope: &[ScopeTable],
+    ) -> Option<(&'a TableMetadata, &'a TableColumn)> {
+        match expr {
+            Expr::Identifier(ident) => {
+                let mut matches = scope.iter().filter_map(|entry| {
+                    let schema = self.lookup(&entry.table)?;
+                    Some((schema, schema.column(&ident.value)?))
+                });
+                let found = matches.next()?;
+                // Ambiguous unqualified names are left alone.
+                matches.next().is_none().then_some(found)
+            }
+            Expr::CompoundIdentifier(parts) if parts.len() >= 2 => {
+                let column = &parts[parts.len() - 1].value;
+                let qualifier = &parts[parts.len() - 2].value;
+                let entry = scope
+                    .iter()
+                    .find(|entry| entry.reference.eq_ignore_ascii_case(qualifier))?;
+                let schema = self.lookup(&entry.table)?;
+                Some((schema, schema.column(column)?))
+            }
+            Expr::Nested(inner) => self.resolve(inner, scope),
+            _ => None,
+        }
+    }
+
+    fn check_select_star(&mut self, select: &Select, scope: &[ScopeTable]) {
+        for item in &select.projection {
+            let expanded = match item {
+                SelectItem::Wildcard(_) => self.expand_columns(scope.iter()),
+                SelectItem::QualifiedWildcard(
+                    SelectItemQualifiedWildcardKind::ObjectName(name),
+                    _,
+                ) => {
+                    let qualifier = object_name(name);
+                    self.expand_columns(
+                        scope
+                            .iter()
+                            .filter(|entry| entry.reference.eq_ignore_ascii_case(&qualifier)),
+                    )
+                }
+                _ => continue,
+            };
+            self.report(
+                RULE_SELECT_STAR_JOIN_SORT,
+                "SELECT * across a join carries every column of every joined row through the sort; list only the columns you need".to_string(),
+                item.span(),
+                expanded,
+            );
+        }
+    }
+
+    /// Explicit `reference.column` list for `entries`, when all their columns are known.
+    fn expand_columns<'s>(&self, entries: impl Iterator<Item = &'s ScopeTable>) -> Option<String> {
+        let mut columns = Vec::new();
+        for entry in entries {
+            let schema = self
+                .lookup(&entry.table)
+                .filter(|schema| !schema.columns.is_empty())?;
+            columns.extend(
+                schema
+                    .columns
+                    .iter()
+                    .map(|column| format!("{}.{}", entry.reference, column.name)),
+            );
+        }
+        (!columns.is_empty()).then(|| columns.join(", "))
+    }
+
+    fn check_join_condition(&mut self, condition: &Expr, scope: &[ScopeTable]) {
+        match condition {
+            Expr::BinaryOp {
+                left,
+                op: BinaryOperator::And,
+                right,
+            } => {
+                self.check_join_condition(left, scope);
+                self.check_join_condition(right, scope);
+            }
+            Expr::Nested(inner) => self.check_join_condition(inner, scope),
+            Expr::BinaryOp {
+                left,
+                op: BinaryOperator::Eq,
+                right,
+            } => {
+                let (Some((_, left_column)), Some((_, right_column))) =
+                    (self.resolve(left, scope), self.resolve(right, scope))
+                else {
+                    return;
+                };
+                if !comparable_families(&left_column.type_family, &right_column.type_family) {
+                    self.report(
+                        RULE_JOIN_TYPE_MISMATCH,
+                        format!(
+                            "Join compares {} ({}) with {} ({}); the implicit cast can keep the index on the converted side from being used, so align the column types",
+                            left, left_column.data_type, right, right_column.data_type
+                        ),
+                        condition.span(),
+                        None,
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Walks a WHERE clause: boolean structure, comparisons and nested subqueries.
+    fn check_filter(&mut self, expr: &Expr, scope: &[ScopeTable]) {
+        match expr {
+            Expr::BinaryOp { left, op, right } => match op {
+                BinaryOperator::And | BinaryOperator::Or => {
+                    self.check_filter(left, scope);
+                    self.check_filter(right, scope);
+                }
+                BinaryOperator::Eq
+                | BinaryOperator::NotEq
+                | BinaryOperator::Lt
+                | BinaryOperator::LtEq
+                | BinaryOperator::Gt
+                | BinaryOperator::GtEq => {
+                    self.check_wrapped_column(left, op, right, scope);
+                    self.check_wrapped_column(right, op, left, scope);
+                    self.visit_subqueries(left);
+                    self.visit_subqueries(right);
+                }
+                _ => {}
+            },
+            Expr::Nested(inner) => self.check_filter(inner, scope),
+            Expr::UnaryOp { expr, .. } => self.check_filter(expr, scope),
+            Expr::Like { expr, pattern, .. } | Expr::ILike { expr, pattern, .. } => {
+                self.check_like(expr, pattern, scope)
+            }
+            Expr::InSubquery {
+                expr,
+                subquery,
+                negated,
+            } => {
+                if *negated {
+                    self.check_not_in(expr, subquery);
+                }
+                self.visit_query(subquery);
+            }
+            _ => self.visit_subqueries(expr),
+        }
+    }
+
+    fn visit_subqueries(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Subquery(query)
+            | Expr::Exists {
+                subquery: query, ..
+            } => self.visit_query(query),
+            Expr::InSubquery { subquery, .. } => self.visit_query(subquery),
+            Expr::Nested(inner) | Expr::UnaryOp { expr: inner, .. } => self.visit_subqueries(inner),
+            Expr::BinaryOp { left, right, .. } => {
+                self.visit_subqueries(left);
+                self.visit_subqueries(right);
+            }
+            _ => {}
+        }
+    }
+
+    fn check_like(&mut self, expr: &Expr, pattern: &Expr, scope: &[ScopeTable]) {
+        let Some(text) = string_literal(pattern) else {
+            return;
+        };
+        if !text.starts_with('%') {
+            return;
+        }
+        let suggestion = match (self.db_type, expr) {
+            (DatabaseType::PostgreSQL, Expr::Identifier(_) | Expr::CompoundIdentifier(_)) => scope
+                .iter()
+                .find(|entry| self.resolve(expr, std::slice::from_ref(*entry)).is_some())
+                .or(match scope {
+                    [only] => Some(only),
+                    _ => None,
+                })
+                .map(|entry| {
+                    format!(
+                        "CREATE EXTENSION IF NOT EXISTS pg_trgm; CREATE INDEX ON {} USING gin ({} gin_trgm_ops);",
+                        entry.table,
+                        column_name(expr)
+                    )
+                }),
+            _ => None,
+        };
+        self.report(
+            RULE_LEADING_WILDCARD_LIKE,
+            format!(
+                "LIKE '{}' starts with a wildcard, so no ordinary index on {} can be used",
+                text, expr
+            ),
+            pattern.span(),
+            suggestion,
+        );
+    }
+
+    fn check_wrapped_column(
+        &mut self,
+        side: &Expr,
+        op: &BinaryOperator,
+        other: &Expr,
+        scope: &[ScopeTable],
+    ) {
+        let Expr::Function(function) = side else {
+            return;
+        };
+        let FunctionArguments::List(list) = &function.args else {
+            return;
+        };
+        let [FunctionArg::Unnamed(FunctionArgExpr::Expr(argument))] = list.args.as_slice() else {
+            return;
+        };
+        let Some((schema, column)) = self.resolve(argument, scope) else {
+            return;
+        };
+        if !schema.indexed_columns.contains(&column.name.to_lowercase()) {
+            return;
+        }
+
+        let function_name = object_name(&function.name);
+        let suggestion = (function_name.eq_ignore_ascii_case("date") && *op == BinaryOperator::Eq)
+            .then(|| string_literal(other))
+            .flatten()
+            .and_then(|text| NaiveDate::parse_from_str(&text, "%Y-%m-%d").ok())
+            .and_then(|day| Some((day, day.succ_opt()?)))
+            .map(|(day, next)| {
+                format!(
+                    "{column} >= '{}' AND {column} < '{}'",
+                    day.format("%Y-%m-%d"),
+                    next.format("%Y-%m-%d"),
+                    column = argument
+                )
+            });
+        self.report(
+            RULE_FUNCTION_ON_INDEXED_COLUMN,
+            format!(
+                "{}() wraps the indexed column {}, so its index can't be used; compare the bare column against a range instead",
+                function_name, argument
+            ),
+            side.span(),
+            suggestion,
+        );
+    }
+
+    fn check_not_in(&mut self, outer: &Expr, subquery: &Query) {
+        let SetExpr::Select(select) = subquery.body.as_ref() else {
+            return;
+        };
+        let [SelectItem::UnnamedExpr(projected)] = select.projection.as_slice() else {
+            return;
+        };
+        let scope = select_scope(select);
+        let Some((_, column)) = self.resolve(projected, &scope) else {
+            return;
+        };
+        if !column.is_nullable || filters_out_nulls(select.selection.as_ref(), projected) {
+            return;
+        }
+
+        let suggestion = self.not_exists_rewrite(outer, projected, select, &scope);
+        self.report(
+            RULE_NOT_IN_NULLABLE_SUBQUERY,
+            format!(
+                "NOT IN over the nullable column {} matches no rows once the subquery returns a NULL; use NOT EXISTS",
+                projected
+            ),
+            outer.span().union(&subquery.span()),
+            suggestion,
+        );
+    }
+
+    /// `NOT EXISTS` form of `outer NOT IN (SELECT projected FROM ...)` for a
+    /// plain single-table subquery whose correlation can't be captured.
+    fn not_exists_rewrite(
+        &self,
+        outer: &Expr,
+        projected: &Expr,
+        select: &Select,
+        scope: &[ScopeTable],
+    ) -> Option<String> {
+        let [entry] = scope else {
+            return None;
+        };
+        let [from] = select.from.as_slice() else {
+            return None;
+        };
+        if !from.joins.is_empty() {
+            return None;
+        }
+        let Expr::CompoundIdentifier(parts) = outer else {
+            return None;
+        };
+        let qualifier = &parts[parts.len().checked_sub(2)?].value;
+        if qualifier.eq_ignore_ascii_case(&entry.reference) {
+            return None;
+        }
+        let inner = format!("{}.{}", entry.reference, column_name(projected));
+        let condition = match &select.selection {
+            Some(selection) => format!("({}) AND {} = {}", selection, inner, outer),
+            None => format!("{} = {}", inner, outer),
+        };
+        Some(format!(
+            "NOT EXISTS (SELECT 1 FROM {} WHERE {})",
+            from.relation, condition
+        ))
+    }
+}
+
+fn scope_of(factor: &TableFactor, scope: &mut Vec<ScopeTable>) {
+    match factor {
+        TableFactor::Table { name, alias, .. } => {
+            let table = object_name(name);
+            let reference = match alias {
+                Some(alias) => alias.name.value.clone(),
+                None => table.rsplit('.').next().unwrap_or_default().to_string(),
+            };
+            scope.push(ScopeTable { reference, table });
+        }
+        TableFactor::NestedJoin {
+            table_with_joins, ..
+        } => {
+            scope_of(&table_with_joins.relation, scope);
+            for join in &table_with_joins.joins {
+                scope_of(&join.relation, scope);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Tables in a SELECT's FROM clause, joins included.
+fn select_scope(select: &Select) -> Vec<ScopeTable> {
+    let mut scope = Vec::new();
+    for from in &select.from {
+        scope_of(&from.relation, &mut scope);
+        for join in &from.joins {
+            scope_of(&join.relation, &mut scope);
+        }
+    }
+    scope
+}
+
+fn join_constraint(operator: &JoinOperator) -> Option<&JoinConstraint> {
+    match operator {
+        JoinOperator::Join(constraint)
+        | JoinOperator::Inner(constraint)
+        | JoinOperator::Left(constraint)
+        | JoinOperator::LeftOuter(constraint)
+        | JoinOperator::Right(constraint)
+        | JoinOperator::RightOuter(constraint)
+        | JoinOperator::FullOuter(constraint) => Some(constraint),
+        _ => None,
+    }
+}
+
+/// Families whose mix doesn't force a conversion worth flagging.
+fn comparable_families(left: &ColumnTypeFamily, right: &ColumnTypeFamily) -> bool {
+    use ColumnTypeFamily::*;
+    let unknown =
+        |family: &ColumnTypeFamily| matches!(family, Unknown | Custom | Domain | Extension);
+    left == right || unknown(left) || unknown(right)
+}
+
+fn string_literal(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Value(value) => match &value.value {
+            Value::SingleQuotedString(text) | Value::DoubleQuotedString(text) => Some(text.clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn column_name(expr: &Expr) -> String {
+    match expr {
+        Expr::Identifier(ident) => ident.to_string(),
+        Expr::CompoundIdentifier(parts) => parts
+            .last()
+            .map(|ident| ident.to_string())
+            .unwrap_or_default(),
+        other => other.to_string(),
+    }
+}
+
+/// True when `selection` has a top-level `column IS NOT NULL` conjunct.
+fn filters_out_nulls(selection: Option<&Expr>, column: &Expr) -> bool {
+    match selection {
+        Some(Expr::IsNotNull(expr)) => expr.as_ref() == column,
+        Some(Expr::Nested(inner)) => filters_out_nulls(Some(inner), column),
+        Some(Expr::BinaryOp {
+            left,
+            op: BinaryOperator::And,
+            right,
+        }) => filters_out_nulls(Some(left), column) || filters_out_nulls(Some(right), column),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIALECTS: [DatabaseType; 3] = [
+        DatabaseType::PostgreSQL,
+        DatabaseType::MySQL,
+        DatabaseType::SQLite,
+    ];
+
+    fn column(
+        name: &str,
+        data_type: &str,
+        family: ColumnTypeFamily,
+        nullable: bool,
+    ) -> TableColumn {
+        TableColumn {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            raw_type: None,
+            normalized_type: data_type.to_uppercase(),
+            type_family: family,
+            db_type: DatabaseType::PostgreSQL,
+            is_nullable: nullable,
+            default_value: None,
+            is_primary_key: false,
+            is_boolean_like: false,
+            is_array: false,
+            enum_values: None,
+            identity_kind: None,
+            generated_kind: None,
+            generation_expression: None,
+            column_comment: None,
+            collation_name: None,
+            domain_name: None,
+            domain_schema: None,
+            domain_base_type: None,
+            array_dimensions: None,
+            element_raw_type: None,
+            character_maximum_length: None,
+            numeric_precision: None,
+            numeric_scale: None,
+        }
+    }
+
+    fn table(columns: Vec<TableColumn>, indexed: &[&str]) -> TableMetadata {
+        TableMetadata {
+            columns,
+            indexed_columns: indexed.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    fn schema() -> SchemaMap {
+        use ColumnTypeFamily::*;
+        let mut schema = SchemaMap::new();
+        schema.insert(
+            "users".to_string(),
+            table(
+                vec![
+                    column("id", "integer", Integer, false),
+                    column("email", "text", Text, true),
+                    column("created_at", "timestamp", DateTime, false),
+                ],
+                &["id", "created_at"],
+            ),
+        );
+        schema.insert(
+            "orders".to_string(),
+            table(
+                vec![
+                    column("id", "integer", Integer, false),
+                    column("user_id", "integer", Integer, true),
+                ],
+                &["id"],
+            ),
+        );
+        schema.insert(
+            "events".to_string(),
+            table(vec![column("user_ref", "varchar(36)", Text, true)], &[]),
+        );
+        schema
+    }
+
+    fn findings(sql: &str, db_type: &DatabaseType) -> Vec<QueryPatternFinding> {
+        analyze(&parse(sql, db_type).unwrap(), db_type, &schema())
+    }
+
+    fn only(sql: &str, db_type: &DatabaseType, rule: &str) -> QueryPatternFinding {
+        let found = findings(sql, db_type);
+        assert_eq!(found.len(), 1, "{:?} on {:?}: {:?}", sql, db_type, found);
+        assert_eq!(found[0].rule, rule);
+        found[0].clone()
+    }
+
+    #[test]
+    fn flags_select_star_in_sorted_joins() {
+        for db_type in &DIALECTS {
+            let finding = only(
+                "SELECT *\nFROM users u JOIN orders o ON o.user_id = u.id\nORDER BY o.id",
+                db_type,
+                RULE_SELECT_STAR_JOIN_SORT,
+            );
+            assert_eq!(
+                finding.span.as_ref().map(|span| (span.line, span.column)),
+                Some((1, 8))
+            );
+            assert_eq!(
+                finding.suggestion.as_deref(),
+                Some("u.id, u.email, u.created_at, o.id, o.user_id")
+            );
+            assert!(findings(
+                "SELECT * FROM users u JOIN orders o ON o.user_id = u.id",
+                db_type
+            )
+            .is_empty());
+        }
+    }
+
+    #[test]
+    fn flags_leading_wildcard_like() {
+        for db_type in &DIALECTS {
+            let finding = only(
+                "SELECT id FROM users WHERE email LIKE '%@example.com'",
+                db_type,
+                RULE_LEADING_WILDCARD_LIKE,
+            );
+            assert_eq!(finding.span.as_ref().map(|span| span.column), Some(39));
+            match db_type {
+                DatabaseType::PostgreSQL => assert!(finding
+                    .suggestion
+                    .unwrap()
+                    .contains("CREATE INDEX ON users USING gin (email gin_trgm_ops)")),
+                _ => assert!(finding.suggestion.is_none()),
+            }
+            assert!(findings("SELECT id FROM users WHERE email LIKE 'admin%'", db_type).is_empty());
+        }
+    }
+
+    #[test]
+    fn flags_functions_wrapping_indexed_columns() {
+        for db_type in &DIALECTS {
+            let finding = only(
+                "SELECT id FROM users WHERE date(created_at) = '2024-02-29'",
+                db_type,
+                RULE_FUNCTION_ON_INDEXED_COLUMN,
+            );
+            assert_eq!(
+                finding.suggestion.as_deref(),
+                Some("created_at >= '2024-02-29' AND created_at < '2024-03-01'")
+            );
+            // email has no index, so wrapping it costs nothing extra.
+            assert!(
+                findings("SELECT id FROM users WHERE lower(email) = 'a@b.c'", db_type).is_empty()
+            );
+        }
+    }
+
+    #[test]
+    fn flags_not_in_over_nullable_subquery() {
+        for db_type in &DIALECTS {
+            let finding = only(
+                "SELECT id FROM users u WHERE u.id NOT IN (SELECT user_id FROM orders)",
+                db_type,
+                RULE_NOT_IN_NULLABLE_SUBQUERY,
+            );
+            assert_eq!(
+                finding.suggestion.as_deref(),
+                Some("NOT EXISTS (SELECT 1 FROM orders WHERE orders.user_id = u.id)")
+            );
+            assert!(findings(
+                "SELECT id FROM users u WHERE u.id NOT IN (SELECT user_id FROM orders WHERE user_id IS NOT NULL)",
+                db_type
+            )
+            .is_empty());
+            assert!(findings(
+                "SELECT id FROM users WHERE id NOT IN (SELECT id FROM orders)",
+                db_type
+            )
+            .is_empty());
+        }
+    }
+
+    #[test]
+    fn flags_join_columns_of_different_types() {
+        for db_type in &DIALECTS {
+            let finding = only(
+                "SELECT u.id FROM users u JOIN events e ON e.user_ref = u.id",
+                db_type,
+                RULE_JOIN_TYPE_MISMATCH,
+            );
+            assert!(finding.message.contains("e.user_ref (varchar(36))"));
+            assert!(findings(
+                "SELECT u.id FROM users u JOIN orders o ON o.user_id = u.id",
+                db_type
+            )
+            .is_empty());
+        }
+        assert_eq!(
+            referenced_tables(
+                &parse(
+                    "SELECT 1 FROM users u JOIN events e ON e.user_ref = u.id",
+                    &DatabaseType::SQLite
+                )
+                .unwrap(),
+                &DatabaseType::SQLite
+            ),
+            vec!["users", "events"]
+        );
+    }
+
+    #[test]
+    fn traces_select_list_columns_to_their_tables() {
+        let origins = |sql: &str| projection_origins(&parse(sql, &DatabaseType::MySQL).unwrap(), &schema());
+        let origin = |table: &str, column: &str| Some((table.to_string(), column.to_string()));
+        assert_eq!(
+            origins("SELECT u.email AS mail, user_id, id + 1, o.* FROM users u JOIN orders o ON o.user_id = u.id"),
+            Some(vec![
+                origin("users", "email"),
+                origin("orders", "user_id"),
+                None,
+                origin("orders", "id"),
+                origin("orders", "user_id"),
+            ])
+        );
+        // `id` is in both tables.
+        assert_eq!(
+            origins("SELECT id FROM users JOIN orders ON orders.user_id = users.id"),
+            Some(vec![None])
+        );
+        assert_eq!(origins("SELECT * FROM users JOIN missing ON 1 = 1"), None);
+        assert_eq!(origins("SELECT id FROM users UNION SELECT id FROM orders"), None);
+    }
+}
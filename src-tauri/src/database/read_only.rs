@@ -0,0 +1,133 @@
+use crate::models::DatabaseType;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Why a write was refused; the error every write path returns on a
+/// read-only connection, so the UI can tell it from a failed statement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadOnlyConnection {
+    /// The read-only lock is on.
+    Locked,
+    /// The database itself was opened read-only.
+    OpenedReadOnly,
+    /// The connection is saved as read-only.
+    Configured,
+}
+
+impl std::fmt::Display for ReadOnlyConnection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ReadOnlyConnection::Locked => "Connection is read-only; turn off the read-only lock to make changes",
+            ReadOnlyConnection::OpenedReadOnly => {
+                "The database was opened read-only; reconnect it in read-write mode to make changes"
+            }
+            ReadOnlyConnection::Configured => {
+                "Connection is set up as read-only; edit the connection and reconnect to make changes"
+            }
+        })
+    }
+}
+
+impl std::error::Error for ReadOnlyConnection {}
+
+/// Per-connection write lock. The manager checks it before every write, and the
+/// pool hooks read it to put server sessions into read-only mode as well.
+#[derive(Debug, Default)]
+pub struct ReadOnlyGuard {
+    enabled: AtomicBool,
+    on_server: AtomicBool,
+    /// Set once server-side enforcement was requested, so the hooks keep
+    /// resetting sessions after it is turned off again.
+    server_touched: AtomicBool,
+    /// The database itself was opened read-only, whatever the lock says.
+    opened_read_only: AtomicBool,
+    /// `ConnectionConfig::read_only`: writes are refused and sessions are
+    /// read-only on the server for as long as the connection is open.
+    configured: AtomicBool,
+}
+
+impl ReadOnlyGuard {
+    pub fn is_enabled(&self) -> bool {
+        self.violation().is_some()
+    }
+
+    /// Why writes are refused right now, if they are.
+    pub fn violation(&self) -> Option<ReadOnlyConnection> {
+        if self.configured.load(Ordering::SeqCst) {
+            Some(ReadOnlyConnection::Configured)
+        } else if self.is_opened_read_only() {
+            Some(ReadOnlyConnection::OpenedReadOnly)
+        } else if self.enabled.load(Ordering::SeqCst) {
+            Some(ReadOnlyConnection::Locked)
+        } else {
+            None
+        }
+    }
+
+    /// Applies `ConnectionConfig::read_only`; set before the pool opens so
+    /// its first sessions are read-only too.
+    pub fn set_configured(&self, read_only: bool) {
+        if read_only {
+            self.server_touched.store(true, Ordering::SeqCst);
+        }
+        self.configured.store(read_only, Ordering::SeqCst);
+    }
+
+    pub fn is_opened_read_only(&self) -> bool {
+        self.opened_read_only.load(Ordering::SeqCst)
+    }
+
+    /// Records whether the pool was opened read-only; turning the lock off
+    /// doesn't lift that.
+    pub fn set_opened_read_only(&self, read_only: bool) {
+        self.opened_read_only.store(read_only, Ordering::SeqCst);
+    }
+
+    /// True when sessions are also made read-only on the server.
+    pub fn is_on_server(&self) -> bool {
+        self.configured.load(Ordering::SeqCst) || (self.is_enabled() && self.on_server.load(Ordering::SeqCst))
+    }
+
+    pub fn set(&self, enabled: bool, on_server: bool) {
+        if on_server {
+            self.server_touched.store(true, Ordering::SeqCst);
+        }
+        self.on_server.store(on_server, Ordering::SeqCst);
+        self.enabled.store(enabled, Ordering::SeqCst);
+    }
+
+    /// Statement that brings a session in line with the guard, if sessions need touching.
+    pub(super) fn session_statement(&self, db_type: &DatabaseType) -> Option<&'static str> {
+        if !self.server_touched.load(Ordering::SeqCst) {
+            return None;
+        }
+        let read_only = self.is_on_server();
+        Some(match db_type {
+            DatabaseType::PostgreSQL if read_only => "SET default_transaction_read_only = on",
+            DatabaseType::PostgreSQL => "SET default_transaction_read_only = off",
+            DatabaseType::MySQL if read_only => "SET SESSION TRANSACTION READ ONLY",
+            DatabaseType::MySQL => "SET SESSION TRANSACTION READ WRITE",
+            DatabaseType::SQLite if read_only => "PRAGMA query_only = ON",
+            DatabaseType::SQLite => "PRAGMA query_only = OFF",
+            // SQL Server has no session-wide read-only switch, so only the
+            // statement checks apply.
+            DatabaseType::MsSql => return None,
+        })
+    }
+}
+
+/// True for BEGIN / COMMIT / ROLLBACK and friends, which can't write on their own.
+pub fn is_transaction_control(sql: &str) -> bool {
+    let first = sql
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches(';')
+        .to_ascii_uppercase();
+    let single_statement = !sql.trim().trim_end_matches(';').contains(';');
+    single_statement
+        && matches!(
+            first.as_str(),
+            "BEGIN" | "START" | "COMMIT" | "END" | "ROLLBACK" | "SAVEPOINT" | "RELEASE"
+        )
+}
+
@@ -0,0 +1,154 @@
+use super::{unix_socket, DatabasePool};
+use crate::models::{ConnectionConfig, DatabaseType};
+use crate::ssh_tunnel::SshTunnel;
+use std::time::{Duration, Instant};
+
+/// Emitted when reads fall back to the primary because the replica is down.
+pub const REPLICA_FALLBACK_EVENT: &str = "replica-fallback";
+/// How long a replica that failed stays out of rotation before it is tried again.
+pub const REPLICA_RETRY_AFTER: Duration = Duration::from_secs(30);
+
+/// The read replica of a connection, kept under the primary's connection id.
+pub struct ReplicaState {
+    /// `None` when the replica couldn't be reached on connect.
+    pub pool: Option<DatabasePool>,
+    pub endpoint: String,
+    pub tunnel: Option<SshTunnel>,
+    pub down_since: Option<Instant>,
+    pub last_error: Option<String>,
+}
+
+impl ReplicaState {
+    /// The replica pool, unless it or its SSH tunnel failed recently.
+    pub fn usable_pool(&self) -> Option<DatabasePool> {
+        if self.tunnel.as_ref().is_some_and(|tunnel| tunnel.session().is_failed()) {
+            return None;
+        }
+        match self.down_since {
+            Some(since) if since.elapsed() < REPLICA_RETRY_AFTER => None,
+            _ => self.pool.clone(),
+        }
+    }
+}
+
+/// The primary's config with the replica's settings laid over it, so a
+/// replica only needs to name what differs (usually just the host).
+pub fn replica_config(primary: &ConnectionConfig) -> Option<ConnectionConfig> {
+    let replica = primary.read_replica.as_ref()?;
+    let mut config = primary.clone();
+    config.read_replica = None;
+    config.failover_hosts = Vec::new();
+    config.require_read_write = false;
+    // The primary's URL was resolved into the fields below when it connected.
+    config.connection_url = None;
+    if replica.host.is_some() {
+        config.host = replica.host.clone();
+        config.socket_path = None;
+    }
+    if replica.port.is_some() {
+        config.port = replica.port;
+    }
+    if replica.username.is_some() {
+        config.username = replica.username.clone();
+    }
+    if replica.password.is_some() {
+        config.password = replica.password.clone();
+    }
+    if replica.database.is_some() {
+        config.database = replica.database.clone();
+    }
+    Some(config)
+}
+
+pub fn endpoint(config: &ConnectionConfig) -> String {
+    match (&config.db_type, unix_socket::path(config)) {
+        (DatabaseType::SQLite, _) => config.file_path.clone().unwrap_or_default(),
+        (_, Some(path)) => path.to_string(),
+        _ => format!(
+            "{}:{}",
+            config.host.as_deref().unwrap_or_default(),
+            config.port.unwrap_or_default()
+        ),
+    }
+}
+
+/// Whether `error` means the server couldn't be reached, as opposed to the
+/// statement failing there. SQL errors are reported as they are: the primary
+/// would reject the statement the same way.
+pub fn is_unavailable(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<sqlx::Error>(),
+        Some(
+            sqlx::Error::Io(_)
+                | sqlx::Error::Tls(_)
+                | sqlx::Error::Protocol(_)
+                | sqlx::Error::PoolTimedOut
+                | sqlx::Error::PoolClosed
+                | sqlx::Error::WorkerCrashed
+        )
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ReadReplicaConfig;
+
+    #[test]
+    fn replica_settings_override_the_primary() {
+        let primary = ConnectionConfig {
+            id: "main".to_string(),
+            name: "main".to_string(),
+            db_type: DatabaseType::PostgreSQL,
+            host: Some("db-primary".to_string()),
+            port: Some(5432),
+            username: Some("app".to_string()),
+            password: Some("secret".to_string()),
+            database: Some("shop".to_string()),
+            file_path: None,
+            ssh_config: None,
+            retry_policy: None,
+            idle_disconnect_minutes: None,
+            idle_disconnect_rollback: false,
+            encryption_key: None,
+            cipher_settings: None,
+            schema_watch_seconds: None,
+            max_concurrent_operations: None,
+            read_replica: Some(ReadReplicaConfig {
+                host: Some("db-replica".to_string()),
+                port: Some(6432),
+                ..ReadReplicaConfig::default()
+            }),
+            failover_hosts: Vec::new(),
+            require_read_write: false,
+            connect_timeout_seconds: None,
+            scratch_schema: None,
+            sql_mode: None,
+            decode_overrides: Default::default(),
+            mysql_ssl: None,
+            pool_settings: None,
+            socket_path: None,
+            connection_url: None,
+            sqlite_open_mode: Default::default(),
+            read_only: false,
+            statement_timeout_seconds: None,
+            reconnect_attempts: None,
+            keepalive_seconds: None,
+            application_name: None,
+            session_params: Default::default(),
+            default_schema: None,
+            charset: None,
+            collation: None,
+            use_stored_credential: false,
+            trust_server_certificate: false,
+        };
+        let replica = replica_config(&primary).unwrap();
+        assert_eq!(endpoint(&replica), "db-replica:6432");
+        assert_eq!(replica.username.as_deref(), Some("app"));
+        assert_eq!(replica.database.as_deref(), Some("shop"));
+        assert!(replica.read_replica.is_none());
+
+        assert!(is_unavailable(&anyhow::Error::from(sqlx::Error::PoolTimedOut)));
+        assert!(!is_unavailable(&anyhow::anyhow!("SQLSTATE 42P01: relation \"t\" does not exist")));
+    }
+}
@@ -0,0 +1,251 @@
+use crate::models::RetryPolicy;
+use std::time::Duration;
+
+pub const CLASS_CONNECTION_RESET: &str = "connection_reset";
+pub const CLASS_POOL_TIMEOUT: &str = "pool_timeout";
+pub const CLASS_SERVER_CLOSED: &str = "server_closed";
+
+/// Cap on the query log kept in memory by the connection manager.
+pub const MAX_QUERY_LOG_ENTRIES: usize = 500;
+
+/// Messages drivers use when the server side of the socket went away.
+const SERVER_CLOSED_MESSAGES: &[&str] = &[
+    "server closed the connection",
+    "connection closed",
+    "server has gone away",
+    "lost connection to",
+    "broken pipe",
+    "connection reset",
+    "unexpected end of file",
+    "connection is closed",
+];
+
+/// Transient error class of `error`, or `None` when retrying can't help.
+pub fn classify(error: &anyhow::Error) -> Option<&'static str> {
+    if let Some(error) = error.downcast_ref::<sqlx::Error>() {
+        match error {
+            sqlx::Error::PoolTimedOut => return Some(CLASS_POOL_TIMEOUT),
+            sqlx::Error::Io(io) => {
+                return match io.kind() {
+                    std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+                    | std::io::ErrorKind::BrokenPipe
+                    | std::io::ErrorKind::NotConnected => Some(CLASS_CONNECTION_RESET),
+                    std::io::ErrorKind::UnexpectedEof => Some(CLASS_SERVER_CLOSED),
+                    _ => None,
+                };
+            }
+            // A pool closed by disconnect is intentional, never retry it.
+            sqlx::Error::PoolClosed => return None,
+            _ => {}
+        }
+    }
+
+    let message = error.to_string().to_lowercase();
+    SERVER_CLOSED_MESSAGES
+        .iter()
+        .any(|needle| message.contains(needle))
+        .then_some(CLASS_SERVER_CLOSED)
+}
+
+/// Whether `error` means the connection to the server is gone, so the pool
+/// has to be rebuilt rather than just asked again.
+pub fn is_connection_lost(error: &anyhow::Error) -> bool {
+    if let Some(error) = error.downcast_ref::<sqlx::Error>() {
+        match error {
+            sqlx::Error::Io(_) | sqlx::Error::Tls(_) => return true,
+            sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed => return false,
+            _ => {}
+        }
+    }
+    classify(error).is_some_and(|class| class == CLASS_CONNECTION_RESET || class == CLASS_SERVER_CLOSED)
+}
+
+/// Delay before retry number `retry` (1-based): exponential, capped at `max_backoff_ms`.
+pub fn backoff_delay(policy: &RetryPolicy, retry: u32) -> Duration {
+    let factor = 2_u64.saturating_pow(retry.saturating_sub(1));
+    Duration::from_millis(
+        policy
+            .initial_backoff_ms
+            .saturating_mul(factor)
+            .min(policy.max_backoff_ms),
+    )
+}
+
+/// Strips leading whitespace and `--` / `/* */` comments.
+fn skip_comments(mut sql: &str) -> &str {
+    loop {
+        sql = sql.trim_start();
+        if let Some(rest) = sql.strip_prefix("--") {
+            sql = rest.split_once('\n').map(|(_, rest)| rest).unwrap_or("");
+        } else if let Some(rest) = sql.strip_prefix("/*") {
+            sql = rest.split_once("*/").map(|(_, rest)| rest).unwrap_or("");
+        } else {
+            return sql;
+        }
+    }
+}
+
+/// Upper-cased words of `sql` outside string literals, quoted identifiers and comments.
+pub(super) fn keywords(sql: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' | '`' => {
+                for next in chars.by_ref() {
+                    if next == c {
+                        break;
+                    }
+                }
+            }
+            '-' if chars.peek() == Some(&'-') => {
+                for next in chars.by_ref() {
+                    if next == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for next in chars.by_ref() {
+                    if previous == '*' && next == '/' {
+                        break;
+                    }
+                    previous = next;
+                }
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                current.push(c.to_ascii_uppercase());
+                continue;
+            }
+            ';' => {
+                if !current.is_empty() {
+                    words.push(std::mem::take(&mut current));
+                }
+                words.push(";".to_string());
+                continue;
+            }
+            _ => {}
+        }
+        if !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+/// True only when `sql` is a single statement that can't change data or schema,
+/// so running it twice is harmless. Anything the check doesn't understand is
+/// treated as a write.
+pub fn is_read_only_statement(sql: &str) -> bool {
+    const WRITE_KEYWORDS: &[&str] = &[
+        "INSERT", "UPDATE", "DELETE", "MERGE", "CREATE", "ALTER", "DROP", "TRUNCATE", "GRANT",
+        "REVOKE", "COPY", "CALL", "DO", "LOCK", "VACUUM", "REINDEX", "ATTACH", "DETACH", "INTO",
+        "SET", "NEXTVAL", "SETVAL",
+    ];
+
+    let mut words = keywords(skip_comments(sql));
+    while words.last().map(String::as_str) == Some(";") {
+        words.pop();
+    }
+    if words.is_empty() || words.iter().any(|word| word == ";") {
+        return false;
+    }
+
+    let first = words[0].as_str();
+    let rest = &words[1..];
+    let no_writes = || {
+        !rest
+            .iter()
+            .any(|word| WRITE_KEYWORDS.contains(&word.as_str()))
+    };
+    match first {
+        "SELECT" | "WITH" | "VALUES" | "TABLE" | "SHOW" | "DESCRIBE" | "DESC" => no_writes(),
+        // EXPLAIN ANALYZE runs the statement, so it's only safe for reads.
+        "EXPLAIN" => no_writes(),
+        // `PRAGMA x = y` and `PRAGMA x(y)` change settings; bare `PRAGMA x` reads.
+        "PRAGMA" => rest.len() <= 2 && !sql.contains('=') && !sql.contains('('),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_transient_errors() {
+        let reset = anyhow::Error::from(sqlx::Error::Io(std::io::Error::from(
+            std::io::ErrorKind::ConnectionReset,
+        )));
+        assert_eq!(classify(&reset), Some(CLASS_CONNECTION_RESET));
+        assert_eq!(
+            classify(&anyhow::Error::from(sqlx::Error::PoolTimedOut)),
+            Some(CLASS_POOL_TIMEOUT)
+        );
+        assert_eq!(
+            classify(&anyhow::anyhow!(
+                "SQLSTATE 57P01: server closed the connection unexpectedly"
+            )),
+            Some(CLASS_SERVER_CLOSED)
+        );
+        assert_eq!(
+            classify(&anyhow::Error::from(sqlx::Error::PoolClosed)),
+            None
+        );
+        assert_eq!(
+            classify(&anyhow::anyhow!(
+                "SQLSTATE 42P01: relation \"x\" does not exist"
+            )),
+            None
+        );
+        assert!(is_connection_lost(&anyhow::Error::from(sqlx::Error::Io(std::io::Error::from(
+            std::io::ErrorKind::ConnectionRefused,
+        )))));
+        assert!(is_connection_lost(&anyhow::anyhow!("server closed the connection unexpectedly")));
+        assert!(is_connection_lost(&anyhow::anyhow!("The SQL Server connection is closed")));
+        assert!(!is_connection_lost(&anyhow::Error::from(sqlx::Error::PoolTimedOut)));
+        assert!(!is_connection_lost(&anyhow::Error::from(sqlx::Error::PoolClosed)));
+    }
+
+    #[test]
+    fn only_single_read_statements_are_read_only() {
+        assert!(is_read_only_statement(
+            "  -- recent\nSELECT * FROM users WHERE name = 'DROP';"
+        ));
+        assert!(is_read_only_statement(
+            "WITH t AS (SELECT 1) SELECT * FROM t"
+        ));
+        assert!(is_read_only_statement("EXPLAIN SELECT 1"));
+        assert!(is_read_only_statement("PRAGMA table_info"));
+        assert!(!is_read_only_statement("EXPLAIN ANALYZE DELETE FROM users"));
+        assert!(!is_read_only_statement(
+            "WITH gone AS (DELETE FROM users RETURNING *) SELECT * FROM gone"
+        ));
+        assert!(!is_read_only_statement("SELECT * INTO backup FROM users"));
+        assert!(!is_read_only_statement("SELECT nextval('users_id_seq')"));
+        assert!(!is_read_only_statement("SELECT 1; DROP TABLE users"));
+        assert!(!is_read_only_statement("PRAGMA journal_mode = WAL"));
+        assert!(!is_read_only_statement("UPDATE users SET name = 'x'"));
+        assert!(!is_read_only_statement(""));
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            initial_backoff_ms: 100,
+            max_backoff_ms: 300,
+            retry_on: vec![],
+        };
+        assert_eq!(backoff_delay(&policy, 1), Duration::from_millis(100));
+        assert_eq!(backoff_delay(&policy, 2), Duration::from_millis(200));
+        assert_eq!(backoff_delay(&policy, 3), Duration::from_millis(300));
+    }
+}
@@ -0,0 +1,59 @@
+/// The count came from `COUNT(*)` (or an engine that tracks it exactly).
+pub const ACCURACY_EXACT: &str = "exact";
+/// The count came from planner / storage engine statistics.
+pub const ACCURACY_ESTIMATE: &str = "estimate";
+/// The count came from statistics that predate a significant share of writes.
+pub const ACCURACY_STALE: &str = "stale-statistics";
+
+/// Tables estimated at or below this many rows are counted exactly by default.
+pub const DEFAULT_EXACT_COUNT_THRESHOLD: u64 = 100_000;
+/// Share of rows modified since the last ANALYZE after which Postgres statistics
+/// count as stale (the autovacuum analyze default).
+const STALE_MODIFIED_FRACTION: f64 = 0.2;
+
+/// Accuracy of `pg_stat_user_tables.n_live_tup`.
+pub fn postgres_accuracy(
+    live_rows: i64,
+    modified_since_analyze: i64,
+    analyzed: bool,
+) -> &'static str {
+    if !analyzed
+        || modified_since_analyze as f64 > live_rows.max(1) as f64 * STALE_MODIFIED_FRACTION
+    {
+        ACCURACY_STALE
+    } else {
+        ACCURACY_ESTIMATE
+    }
+}
+
+/// Accuracy of `information_schema.tables.table_rows`. MyISAM, Aria and MEMORY
+/// keep an exact row count; InnoDB only samples pages and can be far off.
+pub fn mysql_accuracy(engine: Option<&str>, modified_after_statistics: bool) -> &'static str {
+    match engine.map(str::to_ascii_uppercase).as_deref() {
+        Some("MYISAM" | "ARIA" | "MEMORY") => ACCURACY_EXACT,
+        _ if modified_after_statistics => ACCURACY_STALE,
+        _ => ACCURACY_ESTIMATE,
+    }
+}
+
+/// Row count at the start of a `sqlite_stat1.stat` value ("rows avg-per-key ...").
+pub fn sqlite_stat_rows(stat: &str) -> Option<i64> {
+    stat.split_whitespace().next()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_statistics_accuracy() {
+        assert_eq!(postgres_accuracy(1_000, 50, true), ACCURACY_ESTIMATE);
+        assert_eq!(postgres_accuracy(1_000, 900, true), ACCURACY_STALE);
+        assert_eq!(postgres_accuracy(1_000, 0, false), ACCURACY_STALE);
+        assert_eq!(mysql_accuracy(Some("MyISAM"), true), ACCURACY_EXACT);
+        assert_eq!(mysql_accuracy(Some("InnoDB"), false), ACCURACY_ESTIMATE);
+        assert_eq!(mysql_accuracy(Some("InnoDB"), true), ACCURACY_STALE);
+        assert_eq!(sqlite_stat_rows("1000 1"), Some(1000));
+        assert_eq!(sqlite_stat_rows(""), None);
+    }
+}
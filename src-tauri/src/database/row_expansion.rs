@@ -0,0 +1,176 @@
+use crate::models::{ColumnOrigin, ColumnTypeFamily, DatabaseType, TableColumn};
+use anyhow::{anyhow, Result};
+use serde_json::{Map, Value};
+
+/// Column names that usually hold what people call a row by.
+const LABEL_COLUMN_NAMES: &[&str] = &["name", "title", "label", "display_name", "full_name", "username", "email", "code"];
+
+/// A result row whose primary key wasn't selected, so the full row can't be found.
+#[derive(Debug)]
+pub struct RowNotIdentifiable {
+    pub table_name: String,
+    pub missing_columns: Vec<String>,
+}
+
+impl std::fmt::Display for RowNotIdentifiable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Cannot identify the row in {}: the result doesn't include its key column(s) {}",
+            self.table_name,
+            self.missing_columns.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for RowNotIdentifiable {}
+
+/// `table` with the schema Postgres assumes when none is given.
+pub fn qualified(table: &str, db_type: &DatabaseType) -> String {
+    match db_type {
+        DatabaseType::PostgreSQL if !table.contains('.') => format!("public.{}", table),
+        _ => table.to_string(),
+    }
+}
+
+fn origin_table(origin: &ColumnOrigin) -> String {
+    match &origin.schema {
+        Some(schema) => format!("{}.{}", schema, origin.table),
+        None => origin.table.clone(),
+    }
+}
+
+fn same_table(origin: &ColumnOrigin, table: &str, db_type: &DatabaseType) -> bool {
+    let (schema, name) = match table.rsplit_once('.') {
+        Some((schema, name)) => (Some(schema), name),
+        None => (None, table),
+    };
+    let schema_matches = match (schema, origin.schema.as_deref()) {
+        (Some(wanted), Some(actual)) => wanted == actual,
+        _ => true,
+    };
+    schema_matches
+        && match db_type {
+            DatabaseType::PostgreSQL => origin.table == name,
+            DatabaseType::SQLite | DatabaseType::MySQL | DatabaseType::MsSql => {
+                origin.table.eq_ignore_ascii_case(name)
+            }
+        }
+}
+
+/// The table a result row came from: `hint` when given, otherwise the one
+/// table its columns were read from.
+pub fn source_table(hint: Option<&str>, origins: &[Option<ColumnOrigin>]) -> Result<String> {
+    if let Some(hint) = hint.filter(|hint| !hint.trim().is_empty()) {
+        return Ok(hint.to_string());
+    }
+    let mut tables = origins.iter().flatten().map(origin_table).collect::<Vec<_>>();
+    tables.sort();
+    tables.dedup();
+    match tables.as_slice() {
+        [table] => Ok(table.clone()),
+        [] => Err(anyhow!(
+            "The result has no column origins; name the table the row comes from"
+        )),
+        _ => Err(anyhow!(
+            "The result reads from {}; name the table to expand",
+            tables.join(", ")
+        )),
+    }
+}
+
+/// Key of the `table` row behind `row`, read from the output columns that
+/// hold `key_columns`. Without origins an output column counts when its name
+/// is the key column's.
+pub fn key_values(
+    table: &str,
+    key_columns: &[String],
+    output_columns: &[String],
+    row: &Value,
+    origins: &[Option<ColumnOrigin>],
+    db_type: &DatabaseType,
+) -> Result<Value> {
+    let mut key = Map::new();
+    let mut missing = Vec::new();
+    for key_column in key_columns {
+        let position = if origins.is_empty() {
+            output_columns.iter().position(|output| output == key_column)
+        } else {
+            origins.iter().position(|origin| {
+                origin
+                    .as_ref()
+                    .is_some_and(|origin| origin.column == *key_column && same_table(origin, table, db_type))
+            })
+        };
+        let value = position
+            .and_then(|position| output_columns.get(position))
+            .and_then(|output| row.get(output))
+            .filter(|value| !value.is_null());
+        match value {
+            Some(value) => {
+                key.insert(key_column.clone(), value.clone());
+            }
+            None => missing.push(key_column.clone()),
+        }
+    }
+    if !missing.is_empty() {
+        return Err(RowNotIdentifiable {
+            table_name: table.to_string(),
+            missing_columns: missing,
+        }
+        .into());
+    }
+    Ok(Value::Object(key))
+}
+
+/// Column to show for a row of a referenced table: a text column with a
+/// telling name, else the first text column that isn't part of `key_columns`.
+pub fn label_column<'a>(columns: &'a [TableColumn], key_columns: &[String]) -> Option<&'a TableColumn> {
+    let text = columns
+        .iter()
+        .filter(|column| column.type_family == ColumnTypeFamily::Text && !key_columns.contains(&column.name))
+        .collect::<Vec<_>>();
+    LABEL_COLUMN_NAMES
+        .iter()
+        .find_map(|name| text.iter().find(|column| column.name.eq_ignore_ascii_case(name)))
+        .or_else(|| text.first())
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::OriginConfidence;
+
+    fn origin(table: &str, column: &str) -> Option<ColumnOrigin> {
+        Some(ColumnOrigin {
+            schema: Some("public".to_string()),
+            table: table.to_string(),
+            column: column.to_string(),
+            confidence: OriginConfidence::Exact,
+        })
+    }
+
+    #[test]
+    fn key_values_come_from_the_output_columns_holding_the_key() {
+        let columns = vec!["order_no".to_string(), "total".to_string(), "total_2".to_string()];
+        let origins = vec![origin("orders", "id"), origin("orders", "total"), None];
+        let row = serde_json::json!({ "order_no": 7, "total": 12.5, "total_2": 25.0 });
+        let pg = DatabaseType::PostgreSQL;
+
+        assert_eq!(source_table(None, &origins).unwrap(), "public.orders");
+        assert_eq!(
+            key_values("public.orders", &["id".to_string()], &columns, &row, &origins, &pg).unwrap(),
+            serde_json::json!({ "id": 7 })
+        );
+
+        let error = key_values("orders", &["id".to_string(), "region".to_string()], &columns, &row, &origins, &pg)
+            .unwrap_err();
+        let error = error.downcast_ref::<RowNotIdentifiable>().unwrap();
+        assert_eq!(error.missing_columns, vec!["region".to_string()]);
+
+        let mixed = vec![origin("orders", "id"), origin("customers", "name")];
+        assert!(source_table(None, &mixed).unwrap_err().to_string().contains("public.customers, public.orders"));
+        assert_eq!(source_table(Some("orders"), &mixed).unwrap(), "orders");
+    }
+}
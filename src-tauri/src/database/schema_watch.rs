@@ -0,0 +1,123 @@
+use crate::models::{ConnectionConfig, DatabaseType};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Emitted when a watched connection's schema changed outside NodaDB.
+pub const SCHEMA_CHANGED_EVENT: &str = "schema-changed";
+/// How often the watcher looks for connections due a check.
+pub const SCHEMA_WATCH_TICK: Duration = Duration::from_secs(1);
+/// Poll interval for local SQLite files, which are watched unless turned off.
+pub const DEFAULT_SQLITE_WATCH_SECONDS: u64 = 5;
+/// Shortest poll interval a connection may ask for.
+pub const MIN_WATCH_SECONDS: u64 = 2;
+
+/// Per-table definition signatures: a table whose signature changes, appears
+/// or disappears has been altered, created or dropped.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SchemaFingerprint {
+    /// Cheap change counter checked before the tables are read (SQLite's
+    /// `schema_version`).
+    pub version: Option<i64>,
+    pub tables: BTreeMap<String, String>,
+}
+
+pub struct WatchState {
+    pub checked_at: Instant,
+    pub file_modified: Option<SystemTime>,
+    pub fingerprint: SchemaFingerprint,
+}
+
+/// How often `config`'s schema is polled. Servers are only watched when the
+/// connection sets an interval, since every poll is a round trip that may be
+/// metered; SQLite files are watched by default. An interval of 0 turns it off.
+pub fn watch_interval(config: &ConnectionConfig) -> Option<Duration> {
+    let seconds = match config.schema_watch_seconds {
+        Some(0) => return None,
+        Some(seconds) => seconds,
+        None if config.db_type == DatabaseType::SQLite && sqlite_file(config).is_some() => {
+            DEFAULT_SQLITE_WATCH_SECONDS
+        }
+        None => return None,
+    };
+    Some(Duration::from_secs(seconds.max(MIN_WATCH_SECONDS)))
+}
+
+/// The database file of a file-backed SQLite connection.
+pub fn sqlite_file(config: &ConnectionConfig) -> Option<&str> {
+    config
+        .file_path
+        .as_deref()
+        .filter(|path| !path.is_empty() && *path != ":memory:" && !path.starts_with("file::memory:"))
+}
+
+/// Last modification time of the SQLite file; unchanged means nothing was written.
+pub fn file_modified(config: &ConnectionConfig) -> Option<SystemTime> {
+    std::fs::metadata(sqlite_file(config)?).ok()?.modified().ok()
+}
+
+/// Tables created, dropped or altered between two fingerprints, sorted.
+pub fn changed_tables(before: &SchemaFingerprint, after: &SchemaFingerprint) -> Vec<String> {
+    let mut changed: Vec<String> = after
+        .tables
+        .iter()
+        .filter(|(table, signature)| before.tables.get(*table) != Some(signature))
+        .map(|(table, _)| table.clone())
+        .chain(
+            before
+                .tables
+                .keys()
+                .filter(|table| !after.tables.contains_key(*table))
+                .cloned(),
+        )
+        .collect();
+    changed.sort();
+    changed
+}
+
+/// Per-table signatures from the catalog: column names and types, plus
+/// the relation oid and file node so drops, recreations and rewrites show.
+pub const POSTGRES_FINGERPRINT_SQL: &str = "SELECT n.nspname || '.' || c.relname AS table_name, \
+     c.relkind::text || ':' || c.oid::text || ':' || c.relfilenode::text || ':' || \
+     md5(COALESCE((SELECT string_agg(a.attname || ' ' || a.atttypid::text || ' ' || a.attnotnull::text, ',' ORDER BY a.attnum) \
+     FROM pg_attribute a WHERE a.attrelid = c.oid AND a.attnum > 0 AND NOT a.attisdropped), '')) AS signature \
+     FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace \
+     WHERE c.relkind IN ('r', 'p', 'v', 'm', 'f') \
+     AND n.nspname NOT IN ('pg_catalog', 'information_schema') AND n.nspname NOT LIKE 'pg_toast%'";
+
+/// Column checksums per table of the current database. `UPDATE_TIME` moves
+/// with every data change, so only `CREATE_TIME` (reset by rebuilding ALTERs)
+/// and the column list are used.
+pub const MYSQL_FINGERPRINT_SQL: &str = "SELECT t.TABLE_NAME AS table_name, \
+     CONCAT_WS(':', t.TABLE_TYPE, t.CREATE_TIME, \
+     (SELECT SUM(CRC32(CONCAT_WS(' ', c.ORDINAL_POSITION, c.COLUMN_NAME, c.COLUMN_TYPE, c.IS_NULLABLE))) \
+     FROM information_schema.COLUMNS c WHERE c.TABLE_SCHEMA = t.TABLE_SCHEMA AND c.TABLE_NAME = t.TABLE_NAME)) AS signature \
+     FROM information_schema.TABLES t WHERE t.TABLE_SCHEMA = DATABASE()";
+
+/// `modify_date` moves on every ALTER of the table or view.
+pub const MSSQL_FINGERPRINT_SQL: &str = "SELECT o.name AS table_name, \
+     RTRIM(o.type) + ':' + CONVERT(varchar(33), o.modify_date, 126) AS signature \
+     FROM sys.objects o WHERE o.type IN ('U', 'V') AND o.is_ms_shipped = 0";
+
+pub const SQLITE_FINGERPRINT_SQL: &str =
+    "SELECT name AS table_name, type || ':' || COALESCE(sql, '') AS signature \
+     FROM sqlite_master WHERE type IN ('table', 'view') AND name NOT LIKE 'sqlite_%'";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_created_dropped_and_altered_tables() {
+        let fingerprint = |tables: &[(&str, &str)]| SchemaFingerprint {
+            version: None,
+            tables: tables
+                .iter()
+                .map(|(table, signature)| (table.to_string(), signature.to_string()))
+                .collect(),
+        };
+        let before = fingerprint(&[("orders", "a"), ("users", "b"), ("old", "c")]);
+        let after = fingerprint(&[("orders", "a"), ("users", "b2"), ("new", "d")]);
+        assert_eq!(changed_tables(&before, &after), vec!["new", "old", "users"]);
+        assert!(changed_tables(&after, &after).is_empty());
+    }
+}
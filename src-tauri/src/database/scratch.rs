@@ -0,0 +1,179 @@
+use super::read_only::ReadOnlyGuard;
+use crate::models::{DatabaseType, ScratchObject};
+use chrono::{DateTime, Utc};
+use sqlx::pool::PoolOptions;
+use sqlx::sqlite::{Sqlite, SqliteConnection};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Scratch schema (a database on MySQL, an attached file on SQLite) used when
+/// the connection doesn't name one.
+pub const DEFAULT_SCRATCH_SCHEMA: &str = "noda_scratch";
+/// Table in the scratch schema recording when each object was first seen there.
+pub const LEDGER_TABLE: &str = "noda_scratch_objects";
+
+/// Creating the scratch schema was refused for lack of CREATE rights.
+#[derive(Debug)]
+pub struct ScratchPermissionDenied {
+    pub schema: String,
+    pub reason: String,
+}
+
+impl std::fmt::Display for ScratchPermissionDenied {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Permission denied: this login can't create the scratch schema {} ({})",
+            self.schema, self.reason
+        )
+    }
+}
+
+impl std::error::Error for ScratchPermissionDenied {}
+
+/// Whether `error` means the login lacks the rights to create the schema.
+pub fn is_permission_error(error: &sqlx::Error, db_type: &DatabaseType) -> bool {
+    let sqlx::Error::Database(db_err) = error else {
+        return false;
+    };
+    let code = db_err.code().unwrap_or_default();
+    match db_type {
+        DatabaseType::PostgreSQL => code == "42501",
+        // ER_DBACCESS_DENIED_ERROR, ER_ACCESS_DENIED_ERROR, ER_TABLEACCESS_DENIED_ERROR,
+        // ER_SPECIFIC_ACCESS_DENIED_ERROR.
+        DatabaseType::MySQL => db_err
+            .try_downcast_ref::<sqlx::mysql::MySqlDatabaseError>()
+            .is_some_and(|mysql| matches!(mysql.number(), 1044 | 1045 | 1142 | 1227)),
+        // SQLITE_READONLY and SQLITE_CANTOPEN, for a temp directory it can't write to.
+        DatabaseType::SQLite => code == "8" || code == "14",
+        // SQL Server errors don't come through sqlx.
+        DatabaseType::MsSql => false,
+    }
+}
+
+/// File attached as the scratch schema of a SQLite connection.
+pub fn sqlite_scratch_path(connection_id: &str) -> PathBuf {
+    let safe_id = connection_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect::<String>();
+    std::env::temp_dir().join(format!("noda_scratch_{}.sqlite", safe_id))
+}
+
+/// Scratch file a SQLite pool attaches to each session, once the scratch
+/// schema was first used. Kept per connection so a reconnect attaches it again.
+#[derive(Debug, Default)]
+pub struct ScratchAttachment {
+    attached: Mutex<Option<(String, String)>>,
+}
+
+impl ScratchAttachment {
+    pub fn set(&self, alias: Option<(String, String)>) {
+        *self.attached.lock().unwrap_or_else(|e| e.into_inner()) = alias;
+    }
+
+    /// The schema alias and file path, once the scratch schema is in use.
+    pub fn get(&self) -> Option<(String, String)> {
+        self.attached.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+/// Attaches the scratch file to `conn` unless it already is.
+pub async fn attach(conn: &mut SqliteConnection, attachment: &ScratchAttachment) -> Result<(), sqlx::Error> {
+    let Some((alias, path)) = attachment.get() else {
+        return Ok(());
+    };
+    let attached: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM pragma_database_list WHERE name = ?")
+        .bind(&alias)
+        .fetch_one(&mut *conn)
+        .await?;
+    if attached == 0 {
+        let statement = format!("ATTACH DATABASE ? AS \"{}\"", alias.replace('"', "\"\""));
+        sqlx::query(&statement).bind(&path).execute(&mut *conn).await?;
+    }
+    Ok(())
+}
+
+/// SQLite pool options whose connect and acquire hooks apply `guard` to each
+/// session and attach the scratch file to it. A session the file can't be attached to is still handed out;
+/// statements against the scratch schema then fail on their own.
+pub fn sqlite_pool_options(guard: Arc<ReadOnlyGuard>, attachment: Arc<ScratchAttachment>) -> PoolOptions<Sqlite> {
+    let connect_guard = guard.clone();
+    let connect_attachment = attachment.clone();
+    PoolOptions::<Sqlite>::new()
+        .after_connect(move |conn, _| {
+            let statement = connect_guard.session_statement(&DatabaseType::SQLite);
+            let attachment = connect_attachment.clone();
+            Box::pin(async move {
+                if let Some(statement) = statement {
+                    sqlx::Executor::execute(&mut *conn, statement).await?;
+                }
+                let _ = attach(conn, &attachment).await;
+                Ok(())
+            })
+        })
+        .before_acquire(move |conn, _| {
+            let statement = guard.session_statement(&DatabaseType::SQLite);
+            let attachment = attachment.clone();
+            Box::pin(async move {
+                if let Some(statement) = statement {
+                    sqlx::Executor::execute(&mut *conn, statement).await?;
+                }
+                let _ = attach(conn, &attachment).await;
+                Ok(true)
+            })
+        })
+}
+
+/// Objects created more than `older_than_hours` ago, or all of them.
+pub fn stale_objects(objects: Vec<ScratchObject>, older_than_hours: Option<u64>, now: DateTime<Utc>) -> Vec<ScratchObject> {
+    let Some(hours) = older_than_hours else {
+        return objects;
+    };
+    let cutoff = now - chrono::Duration::hours(hours.min(i64::MAX as u64 / 3600) as i64);
+    objects
+        .into_iter()
+        .filter(|object| {
+            DateTime::parse_from_rfc3339(&object.created_at).is_ok_and(|created| created.with_timezone(&Utc) <= cutoff)
+        })
+        .collect()
+}
+
+/// What a cleanup confirmation is issued for: the exact objects listed.
+pub fn cleanup_fingerprint(connection_id: &str, schema: &str, objects: &[ScratchObject]) -> String {
+    let mut names = objects.iter().map(|object| object.name.as_str()).collect::<Vec<_>>();
+    names.sort_unstable();
+    format!("cleanup_scratch\u{1f}{}\u{1f}{}\u{1f}{}", connection_id, schema, names.join("\u{1f}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(name: &str, created_at: &str) -> ScratchObject {
+        ScratchObject {
+            name: name.to_string(),
+            object_type: "TABLE".to_string(),
+            created_at: created_at.to_string(),
+        }
+    }
+
+    #[test]
+    fn only_objects_past_the_cutoff_are_stale() {
+        let now = DateTime::parse_from_rfc3339("2024-05-02T12:00:00Z").unwrap().with_timezone(&Utc);
+        let objects = vec![
+            object("old", "2024-05-01T11:00:00Z"),
+            object("new", "2024-05-02T11:00:00Z"),
+            object("unknown", "yesterday"),
+        ];
+        let stale = stale_objects(objects.clone(), Some(24), now);
+        assert_eq!(stale.iter().map(|object| object.name.as_str()).collect::<Vec<_>>(), vec!["old"]);
+        assert_eq!(stale_objects(objects.clone(), None, now).len(), 3);
+
+        let reordered = vec![objects[1].clone(), objects[0].clone()];
+        assert_eq!(
+            cleanup_fingerprint("c", DEFAULT_SCRATCH_SCHEMA, &objects[..2]),
+            cleanup_fingerprint("c", DEFAULT_SCRATCH_SCHEMA, &reordered)
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use super::escaping::quote_identifier;
+use super::read_only::ReadOnlyGuard;
+use crate::models::{ConnectionConfig, DatabaseType};
+use sqlx::pool::PoolOptions;
+use sqlx::postgres::{PgConnectOptions, Postgres};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// What NodaDB's Postgres sessions show in pg_stat_activity by default.
+pub const DEFAULT_APPLICATION_NAME: &str = "NodaDB";
+
+/// `options` with the connection's application_name. One given in a
+/// connection URL is kept unless the connection names its own.
+pub fn apply(options: PgConnectOptions, config: &ConnectionConfig) -> PgConnectOptions {
+    match config.application_name.as_deref().map(str::trim).filter(|name| !name.is_empty()) {
+        Some(name) => options.application_name(name),
+        None if options.get_application_name().is_none() => options.application_name(DEFAULT_APPLICATION_NAME),
+        None => options,
+    }
+}
+
+/// The parameters set on each of `config`'s Postgres sessions: its own, and
+/// a `search_path` leading with `default_schema` when it sets none itself.
+pub fn postgres_params(config: &ConnectionConfig) -> BTreeMap<String, String> {
+    let mut params = config.session_params.clone();
+    let default_schema = config.default_schema.as_deref().map(str::trim).filter(|schema| !schema.is_empty());
+    if let Some(schema) = default_schema {
+        if !params.keys().any(|name| name.eq_ignore_ascii_case("search_path")) {
+            let quoted = quote_identifier(schema, &DatabaseType::PostgreSQL);
+            let search_path = if schema == "public" { quoted } else { format!("{}, public", quoted) };
+            params.insert("search_path".to_string(), search_path);
+        }
+    }
+    params
+}
+
+/// Postgres pool options whose connect and acquire hooks apply `guard` to each
+/// session, after setting `params` on new ones. They go through `set_config` so a value like `public, audit` is
+/// taken as SET would take it; an unknown parameter fails the connect with
+/// the server's error.
+pub fn postgres_pool_options(guard: Arc<ReadOnlyGuard>, params: BTreeMap<String, String>) -> PoolOptions<Postgres> {
+    let connect_guard = guard.clone();
+    let params = Arc::new(params);
+    PoolOptions::<Postgres>::new()
+        .after_connect(move |conn, _| {
+            let statement = connect_guard.session_statement(&DatabaseType::PostgreSQL);
+            let params = params.clone();
+            Box::pin(async move {
+                for (name, value) in params.iter() {
+                    sqlx::query("SELECT set_config($1, $2, false)")
+                        .bind(name)
+                        .bind(value)
+                        .execute(&mut *conn)
+                        .await?;
+                }
+                // After the parameters, so they can't undo the read-only lock.
+                if let Some(statement) = statement {
+                    sqlx::Executor::execute(&mut *conn, statement).await?;
+                }
+                Ok(())
+            })
+        })
+        .before_acquire(move |conn, _| {
+            let statement = guard.session_statement(&DatabaseType::PostgreSQL);
+            Box::pin(async move {
+                if let Some(statement) = statement {
+                    sqlx::Executor::execute(conn, statement).await?;
+                }
+                Ok(true)
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sessions_are_named_nodadb_unless_told_otherwise() {
+        let mut config: ConnectionConfig = serde_json::from_value(serde_json::json!({
+            "id": "c", "name": "c", "db_type": "postgresql"
+        }))
+        .unwrap();
+        let options = || "postgres://app@db/shop".parse::<PgConnectOptions>().unwrap();
+        assert_eq!(apply(options(), &config).get_application_name(), Some(DEFAULT_APPLICATION_NAME));
+
+        let from_url = "postgres://app@db/shop?application_name=reports".parse::<PgConnectOptions>().unwrap();
+        assert_eq!(apply(from_url, &config).get_application_name(), Some("reports"));
+
+        config.application_name = Some("NodaDB (ops)".to_string());
+        assert_eq!(apply(options(), &config).get_application_name(), Some("NodaDB (ops)"));
+
+        config.default_schema = Some("Billing".to_string());
+        assert_eq!(postgres_params(&config)["search_path"], "\"Billing\", public");
+        config.session_params.insert("search_path".to_string(), "audit".to_string());
+        assert_eq!(postgres_params(&config)["search_path"], "audit");
+    }
+}
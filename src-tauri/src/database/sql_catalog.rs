@@ -0,0 +1,246 @@
+use super::checksum::fnv1a64;
+use crate::models::{SqlFunction, SqlFunctionKind, SqlKeyword};
+use std::collections::HashSet;
+
+/// SQLite's keywords (https://sqlite.org/lang_keywords.html); SQLite can't list them itself.
+pub const SQLITE_KEYWORDS: &[&str] = &[
+    "ABORT", "ACTION", "ADD", "AFTER", "ALL", "ALTER", "ALWAYS", "ANALYZE", "AND", "AS", "ASC",
+    "ATTACH", "AUTOINCREMENT", "BEFORE", "BEGIN", "BETWEEN", "BY", "CASCADE", "CASE", "CAST",
+    "CHECK", "COLLATE", "COLUMN", "COMMIT", "CONFLICT", "CONSTRAINT", "CREATE", "CROSS",
+    "CURRENT", "CURRENT_DATE", "CURRENT_TIME", "CURRENT_TIMESTAMP", "DATABASE", "DEFAULT",
+    "DEFERRABLE", "DEFERRED", "DELETE", "DESC", "DETACH", "DISTINCT", "DO", "DROP", "EACH",
+    "ELSE", "END", "ESCAPE", "EXCEPT", "EXCLUDE", "EXCLUSIVE", "EXISTS", "EXPLAIN", "FAIL",
+    "FILTER", "FIRST", "FOLLOWING", "FOR", "FOREIGN", "FROM", "FULL", "GENERATED", "GLOB",
+    "GROUP", "GROUPS", "HAVING", "IF", "IGNORE", "IMMEDIATE", "IN", "INDEX", "INDEXED",
+    "INITIALLY", "INNER", "INSERT", "INSTEAD", "INTERSECT", "INTO", "IS", "ISNULL", "JOIN", "KEY",
+    "LAST", "LEFT", "LIKE", "LIMIT", "MATCH", "MATERIALIZED", "NATURAL", "NO", "NOT", "NOTHING",
+    "NOTNULL", "NULL", "NULLS", "OF", "OFFSET", "ON", "OR", "ORDER", "OTHERS", "OUTER", "OVER",
+    "PARTITION", "PLAN", "PRAGMA", "PRECEDING", "PRIMARY", "QUERY", "RAISE", "RANGE",
+    "RECURSIVE", "REFERENCES", "REGEXP", "REINDEX", "RELEASE", "RENAME", "REPLACE", "RESTRICT",
+    "RETURNING", "RIGHT", "ROLLBACK", "ROW", "ROWS", "SAVEPOINT", "SELECT", "SET", "TABLE",
+    "TEMP", "TEMPORARY", "THEN", "TIES", "TO", "TRANSACTION", "TRIGGER", "UNBOUNDED", "UNION",
+    "UNIQUE", "UPDATE", "USING", "VACUUM", "VALUES", "VIEW", "VIRTUAL", "WHEN", "WHERE",
+    "WINDOW", "WITH", "WITHOUT",
+];
+
+/// Core SQLite functions, used when `pragma_function_list` isn't compiled in.
+pub const SQLITE_BUILTIN_FUNCTIONS: &[(&str, SqlFunctionKind)] = &[
+    ("abs", SqlFunctionKind::Scalar), ("changes", SqlFunctionKind::Scalar),
+    ("char", SqlFunctionKind::Scalar), ("coalesce", SqlFunctionKind::Scalar),
+    ("date", SqlFunctionKind::Scalar), ("datetime", SqlFunctionKind::Scalar),
+    ("format", SqlFunctionKind::Scalar), ("glob", SqlFunctionKind::Scalar),
+    ("hex", SqlFunctionKind::Scalar), ("ifnull", SqlFunctionKind::Scalar),
+    ("iif", SqlFunctionKind::Scalar), ("instr", SqlFunctionKind::Scalar),
+    ("json", SqlFunctionKind::Scalar), ("json_array", SqlFunctionKind::Scalar),
+    ("json_extract", SqlFunctionKind::Scalar), ("json_object", SqlFunctionKind::Scalar),
+    ("julianday", SqlFunctionKind::Scalar), ("last_insert_rowid", SqlFunctionKind::Scalar),
+    ("length", SqlFunctionKind::Scalar), ("like", SqlFunctionKind::Scalar),
+    ("lower", SqlFunctionKind::Scalar), ("ltrim", SqlFunctionKind::Scalar),
+    ("max", SqlFunctionKind::Scalar), ("min", SqlFunctionKind::Scalar),
+    ("nullif", SqlFunctionKind::Scalar), ("printf", SqlFunctionKind::Scalar),
+    ("quote", SqlFunctionKind::Scalar), ("random", SqlFunctionKind::Scalar),
+    ("randomblob", SqlFunctionKind::Scalar), ("replace", SqlFunctionKind::Scalar),
+    ("round", SqlFunctionKind::Scalar), ("rtrim", SqlFunctionKind::Scalar),
+    ("sqlite_version", SqlFunctionKind::Scalar), ("strftime", SqlFunctionKind::Scalar),
+    ("substr", SqlFunctionKind::Scalar), ("time", SqlFunctionKind::Scalar),
+    ("total_changes", SqlFunctionKind::Scalar), ("trim", SqlFunctionKind::Scalar),
+    ("typeof", SqlFunctionKind::Scalar), ("unicode", SqlFunctionKind::Scalar),
+    ("upper", SqlFunctionKind::Scalar), ("zeroblob", SqlFunctionKind::Scalar),
+    ("avg", SqlFunctionKind::Aggregate), ("count", SqlFunctionKind::Aggregate),
+    ("group_concat", SqlFunctionKind::Aggregate), ("json_group_array", SqlFunctionKind::Aggregate),
+    ("json_group_object", SqlFunctionKind::Aggregate), ("sum", SqlFunctionKind::Aggregate),
+    ("total", SqlFunctionKind::Aggregate), ("cume_dist", SqlFunctionKind::Window),
+    ("dense_rank", SqlFunctionKind::Window), ("first_value", SqlFunctionKind::Window),
+    ("lag", SqlFunctionKind::Window), ("last_value", SqlFunctionKind::Window),
+    ("lead", SqlFunctionKind::Window), ("nth_value", SqlFunctionKind::Window),
+    ("ntile", SqlFunctionKind::Window), ("percent_rank", SqlFunctionKind::Window),
+    ("rank", SqlFunctionKind::Window), ("row_number", SqlFunctionKind::Window),
+];
+
+/// MySQL built-ins, which `information_schema.routines` doesn't list. Merged
+/// with the server's help tables when those are readable.
+pub const MYSQL_BUILTIN_FUNCTIONS: &[(&str, SqlFunctionKind)] = &[
+    ("ABS", SqlFunctionKind::Scalar), ("ADDDATE", SqlFunctionKind::Scalar),
+    ("ADDTIME", SqlFunctionKind::Scalar), ("BIN", SqlFunctionKind::Scalar),
+    ("CAST", SqlFunctionKind::Scalar), ("CEIL", SqlFunctionKind::Scalar),
+    ("CHAR_LENGTH", SqlFunctionKind::Scalar), ("COALESCE", SqlFunctionKind::Scalar),
+    ("CONCAT", SqlFunctionKind::Scalar), ("CONCAT_WS", SqlFunctionKind::Scalar),
+    ("CONVERT", SqlFunctionKind::Scalar), ("CONVERT_TZ", SqlFunctionKind::Scalar),
+    ("CURDATE", SqlFunctionKind::Scalar), ("CURRENT_USER", SqlFunctionKind::Scalar),
+    ("CURTIME", SqlFunctionKind::Scalar), ("DATABASE", SqlFunctionKind::Scalar),
+    ("DATE", SqlFunctionKind::Scalar), ("DATE_ADD", SqlFunctionKind::Scalar),
+    ("DATE_FORMAT", SqlFunctionKind::Scalar), ("DATE_SUB", SqlFunctionKind::Scalar),
+    ("DATEDIFF", SqlFunctionKind::Scalar), ("DAYOFWEEK", SqlFunctionKind::Scalar),
+    ("ELT", SqlFunctionKind::Scalar), ("EXTRACT", SqlFunctionKind::Scalar),
+    ("FIELD", SqlFunctionKind::Scalar), ("FIND_IN_SET", SqlFunctionKind::Scalar),
+    ("FLOOR", SqlFunctionKind::Scalar), ("FORMAT", SqlFunctionKind::Scalar),
+    ("FROM_UNIXTIME", SqlFunctionKind::Scalar), ("GREATEST", SqlFunctionKind::Scalar),
+    ("HEX", SqlFunctionKind::Scalar), ("IF", SqlFunctionKind::Scalar),
+    ("IFNULL", SqlFunctionKind::Scalar), ("INET_ATON", SqlFunctionKind::Scalar),
+    ("INET_NTOA", SqlFunctionKind::Scalar), ("INSTR", SqlFunctionKind::Scalar),
+    ("JSON_ARRAY", SqlFunctionKind::Scalar), ("JSON_CONTAINS", SqlFunctionKind::Scalar),
+    ("JSON_EXTRACT", SqlFunctionKind::Scalar), ("JSON_OBJECT", SqlFunctionKind::Scalar),
+    ("JSON_SET", SqlFunctionKind::Scalar), ("JSON_TABLE", SqlFunctionKind::Scalar),
+    ("JSON_UNQUOTE", SqlFunctionKind::Scalar), ("LAST_INSERT_ID", SqlFunctionKind::Scalar),
+    ("LCASE", SqlFunctionKind::Scalar), ("LEAST", SqlFunctionKind::Scalar),
+    ("LEFT", SqlFunctionKind::Scalar), ("LENGTH", SqlFunctionKind::Scalar),
+    ("LOCATE", SqlFunctionKind::Scalar), ("LOWER", SqlFunctionKind::Scalar),
+    ("LPAD", SqlFunctionKind::Scalar), ("LTRIM", SqlFunctionKind::Scalar),
+    ("MD5", SqlFunctionKind::Scalar), ("MOD", SqlFunctionKind::Scalar),
+    ("NOW", SqlFunctionKind::Scalar), ("NULLIF", SqlFunctionKind::Scalar),
+    ("POWER", SqlFunctionKind::Scalar), ("RAND", SqlFunctionKind::Scalar),
+    ("REGEXP_LIKE", SqlFunctionKind::Scalar), ("REGEXP_REPLACE", SqlFunctionKind::Scalar),
+    ("REPEAT", SqlFunctionKind::Scalar), ("REPLACE", SqlFunctionKind::Scalar),
+    ("REVERSE", SqlFunctionKind::Scalar), ("RIGHT", SqlFunctionKind::Scalar),
+    ("ROUND", SqlFunctionKind::Scalar), ("RPAD", SqlFunctionKind::Scalar),
+    ("RTRIM", SqlFunctionKind::Scalar), ("SHA2", SqlFunctionKind::Scalar),
+    ("SIGN", SqlFunctionKind::Scalar), ("SQRT", SqlFunctionKind::Scalar),
+    ("STR_TO_DATE", SqlFunctionKind::Scalar), ("SUBSTRING", SqlFunctionKind::Scalar),
+    ("SUBSTRING_INDEX", SqlFunctionKind::Scalar), ("SYSDATE", SqlFunctionKind::Scalar),
+    ("TIMESTAMPDIFF", SqlFunctionKind::Scalar), ("TRIM", SqlFunctionKind::Scalar),
+    ("TRUNCATE", SqlFunctionKind::Scalar), ("UCASE", SqlFunctionKind::Scalar),
+    ("UNHEX", SqlFunctionKind::Scalar), ("UNIX_TIMESTAMP", SqlFunctionKind::Scalar),
+    ("UPPER", SqlFunctionKind::Scalar), ("UTC_TIMESTAMP", SqlFunctionKind::Scalar),
+    ("UUID", SqlFunctionKind::Scalar), ("VERSION", SqlFunctionKind::Scalar),
+    ("AVG", SqlFunctionKind::Aggregate), ("BIT_AND", SqlFunctionKind::Aggregate),
+    ("BIT_OR", SqlFunctionKind::Aggregate), ("BIT_XOR", SqlFunctionKind::Aggregate),
+    ("COUNT", SqlFunctionKind::Aggregate), ("GROUP_CONCAT", SqlFunctionKind::Aggregate),
+    ("JSON_ARRAYAGG", SqlFunctionKind::Aggregate), ("JSON_OBJECTAGG", SqlFunctionKind::Aggregate),
+    ("MAX", SqlFunctionKind::Aggregate), ("MIN", SqlFunctionKind::Aggregate),
+    ("STDDEV", SqlFunctionKind::Aggregate), ("STDDEV_SAMP", SqlFunctionKind::Aggregate),
+    ("SUM", SqlFunctionKind::Aggregate), ("VAR_POP", SqlFunctionKind::Aggregate),
+    ("VAR_SAMP", SqlFunctionKind::Aggregate), ("CUME_DIST", SqlFunctionKind::Window),
+    ("DENSE_RANK", SqlFunctionKind::Window), ("FIRST_VALUE", SqlFunctionKind::Window),
+    ("LAG", SqlFunctionKind::Window), ("LAST_VALUE", SqlFunctionKind::Window),
+    ("LEAD", SqlFunctionKind::Window), ("NTH_VALUE", SqlFunctionKind::Window),
+    ("NTILE", SqlFunctionKind::Window), ("PERCENT_RANK", SqlFunctionKind::Window),
+    ("RANK", SqlFunctionKind::Window), ("ROW_NUMBER", SqlFunctionKind::Window),
+];
+
+/// Postgres `prokind` / SQLite `pragma_function_list.type` code as a kind.
+pub fn function_kind(code: &str) -> SqlFunctionKind {
+    match code {
+        "a" => SqlFunctionKind::Aggregate,
+        "w" => SqlFunctionKind::Window,
+        "p" => SqlFunctionKind::Procedure,
+        _ => SqlFunctionKind::Scalar,
+    }
+}
+
+/// Placeholder argument list for a function known only by its arity.
+pub fn arguments_for_arity(arity: i64) -> String {
+    if arity < 0 {
+        "...".to_string()
+    } else {
+        (1..=arity)
+            .map(|index| format!("arg{}", index))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// A built-in from a curated list, with no signature.
+pub fn builtin(name: &str, kind: SqlFunctionKind) -> SqlFunction {
+    SqlFunction {
+        name: name.to_string(),
+        schema: None,
+        arguments: None,
+        return_type: None,
+        kind,
+        builtin: true,
+        detail: String::new(),
+    }
+}
+
+/// Sorts functions by name, drops repeated name/argument pairs (the first
+/// one wins, so live entries should come before curated ones) and fills in
+/// the completion detail.
+pub fn finish_functions(functions: Vec<SqlFunction>) -> Vec<SqlFunction> {
+    let with_arguments: HashSet<(Option<String>, String)> = functions
+        .iter()
+        .filter(|function| function.arguments.is_some())
+        .map(|function| (function.schema.clone(), function.name.to_lowercase()))
+        .collect();
+    let mut seen = HashSet::new();
+    let mut functions: Vec<SqlFunction> = functions
+        .into_iter()
+        .filter(|function| {
+            let name = (function.schema.clone(), function.name.to_lowercase());
+            // An entry without arguments only stands in for a name not known otherwise.
+            if function.arguments.is_none() && with_arguments.contains(&name) {
+                return false;
+            }
+            seen.insert((name, function.arguments.clone()))
+        })
+        .collect();
+    functions.sort_by(|a, b| {
+        a.name
+            .to_lowercase()
+            .cmp(&b.name.to_lowercase())
+            .then_with(|| a.arguments.cmp(&b.arguments))
+    });
+    for function in &mut functions {
+        function.detail = match (&function.arguments, &function.return_type) {
+            (Some(arguments), Some(return_type)) => {
+                format!("{}({}) → {}", function.name, arguments, return_type)
+            }
+            (Some(arguments), None) => format!("{}({})", function.name, arguments),
+            (None, _) => format!("{}(…)", function.name),
+        };
+    }
+    functions
+}
+
+/// Keywords upper-cased, deduplicated and sorted.
+pub fn finish_keywords(keywords: Vec<SqlKeyword>) -> Vec<SqlKeyword> {
+    let mut seen = HashSet::new();
+    let mut keywords: Vec<SqlKeyword> = keywords
+        .into_iter()
+        .map(|keyword| SqlKeyword {
+            word: keyword.word.to_uppercase(),
+            ..keyword
+        })
+        .filter(|keyword| seen.insert(keyword.word.clone()))
+        .collect();
+    keywords.sort_by(|a, b| a.word.cmp(&b.word));
+    keywords
+}
+
+/// Changes whenever the catalog does, so the editor can skip reloading it.
+pub fn version_hash(keywords: &[SqlKeyword], functions: &[SqlFunction]) -> String {
+    let mut text = String::new();
+    for keyword in keywords {
+        text.push_str(&keyword.word);
+        text.push(if keyword.reserved { '!' } else { '\n' });
+    }
+    for function in functions {
+        text.push_str(&function.detail);
+        text.push('\n');
+    }
+    format!("{:016x}", fnv1a64(text.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn live_functions_win_over_curated_ones() {
+        let mut live = builtin("lower", SqlFunctionKind::Scalar);
+        live.arguments = Some("text".to_string());
+        live.return_type = Some("text".to_string());
+        let functions = finish_functions(vec![
+            live,
+            builtin("upper", SqlFunctionKind::Scalar),
+            builtin("LOWER", SqlFunctionKind::Scalar),
+        ]);
+        let details: Vec<_> = functions.iter().map(|function| function.detail.as_str()).collect();
+        assert_eq!(details, vec!["lower(text) → text", "upper(…)"]);
+        assert_eq!(arguments_for_arity(2), "arg1, arg2");
+        assert_eq!(arguments_for_arity(-1), "...");
+
+        let hash = version_hash(&[], &functions);
+        assert_eq!(hash, version_hash(&[], &functions));
+        assert_ne!(hash, version_hash(&[], &functions[..1]));
+    }
+}
@@ -0,0 +1,154 @@
+use super::read_only::ReadOnlyGuard;
+use crate::models::{DatabaseType, SqlModeInfo};
+use sqlx::mysql::{MySql, MySqlConnection};
+use sqlx::pool::PoolOptions;
+use sqlx::Row;
+use std::sync::Arc;
+
+/// Warnings MySQL raises when it changes a value to make it fit instead of
+/// rejecting it: a NULL or missing value replaced by a default (1048, 1263,
+/// 1364), a number clipped to the column's range (1264), text or a date cut
+/// short (1265, 1292, 1406) and a value that didn't parse (1366).
+const VALUE_CHANGED_CODES: [u32; 8] = [1048, 1263, 1264, 1265, 1292, 1364, 1366, 1406];
+
+/// Whether `sql_mode` makes MySQL reject values that don't fit.
+pub fn is_strict(sql_mode: &str) -> bool {
+    sql_mode
+        .split(',')
+        .any(|mode| matches!(mode.trim().to_ascii_uppercase().as_str(), "STRICT_TRANS_TABLES" | "STRICT_ALL_TABLES"))
+}
+
+/// Banner text for a connection whose server lets bad values through.
+pub fn connect_warning(info: &SqlModeInfo) -> Option<String> {
+    if is_strict(&info.global) {
+        return None;
+    }
+    let server = if info.global.trim().is_empty() {
+        "The server's sql_mode is empty".to_string()
+    } else {
+        format!("The server's sql_mode ({}) is not strict", info.global)
+    };
+    Some(if info.strict {
+        format!(
+            "{}: other clients may store truncated or coerced values without an error",
+            server
+        )
+    } else {
+        format!(
+            "{}: values that don't fit a column are truncated or coerced without an error",
+            server
+        )
+    })
+}
+
+fn set_statement(sql_mode: &str) -> String {
+    format!("SET SESSION sql_mode = '{}'", sql_mode.replace('\\', "\\\\").replace('\'', "''"))
+}
+
+/// MySQL pool options whose connect and acquire hooks apply `guard` to each
+/// session, also setting new sessions' sql_mode when the connection forces one.
+pub fn mysql_pool_options(guard: Arc<ReadOnlyGuard>, sql_mode: Option<String>) -> PoolOptions<MySql> {
+    let connect_guard = guard.clone();
+    let sql_mode = sql_mode.map(|sql_mode| set_statement(&sql_mode));
+    PoolOptions::<MySql>::new()
+        .after_connect(move |conn, _| {
+            let statement = connect_guard.session_statement(&DatabaseType::MySQL);
+            let sql_mode = sql_mode.clone();
+            Box::pin(async move {
+                if let Some(sql_mode) = sql_mode {
+                    sqlx::Executor::execute(&mut *conn, sql_mode.as_str()).await?;
+                }
+                if let Some(statement) = statement {
+                    sqlx::Executor::execute(&mut *conn, statement).await?;
+                }
+                Ok(())
+            })
+        })
+        .before_acquire(move |conn, _| {
+            let statement = guard.session_statement(&DatabaseType::MySQL);
+            Box::pin(async move {
+                if let Some(statement) = statement {
+                    sqlx::Executor::execute(conn, statement).await?;
+                }
+                Ok(true)
+            })
+        })
+}
+
+/// Value-changing warnings among SHOW WARNINGS rows of `(level, code, message)`.
+pub fn value_warnings(rows: &[(String, u32, String)]) -> Vec<String> {
+    rows.iter()
+        .filter(|(_, code, _)| VALUE_CHANGED_CODES.contains(code))
+        .map(|(level, code, message)| format!("{} {}: {}", level, code, message))
+        .collect()
+}
+
+/// Value-changing warnings left by the last statement on `conn`.
+pub async fn read_warnings(conn: &mut MySqlConnection) -> Result<Vec<String>, sqlx::Error> {
+    let rows = sqlx::query("SHOW WARNINGS").fetch_all(&mut *conn).await?;
+    let rows = rows
+        .iter()
+        .map(|row| {
+            (
+                row.try_get::<String, _>("Level").unwrap_or_default(),
+                row.try_get::<u32, _>("Code").unwrap_or_default(),
+                row.try_get::<String, _>("Message").unwrap_or_default(),
+            )
+        })
+        .collect::<Vec<_>>();
+    Ok(value_warnings(&rows))
+}
+
+/// `message` with the warnings a write raised, if any.
+pub fn with_warnings(message: String, warnings: &[String]) -> String {
+    if warnings.is_empty() {
+        return message;
+    }
+    format!(
+        "{}, but MySQL changed values to make them fit ({} warning(s)): {}",
+        message,
+        warnings.len(),
+        warnings.join("; ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncation_under_a_lenient_sql_mode_is_reported() {
+        assert!(is_strict("ONLY_FULL_GROUP_BY,STRICT_TRANS_TABLES,NO_ZERO_IN_DATE"));
+        assert!(!is_strict("NO_ENGINE_SUBSTITUTION"));
+        assert!(!is_strict(""));
+
+        let rows = vec![
+            (
+                "Warning".to_string(),
+                1265,
+                "Data truncated for column 'code' at row 1".to_string(),
+            ),
+            ("Note".to_string(), 1051, "Unknown table 'tmp'".to_string()),
+        ];
+        let warnings = value_warnings(&rows);
+        assert_eq!(warnings, vec!["Warning 1265: Data truncated for column 'code' at row 1"]);
+        assert_eq!(
+            with_warnings("Successfully inserted 1 row into t".to_string(), &warnings),
+            "Successfully inserted 1 row into t, but MySQL changed values to make them fit (1 warning(s)): \
+             Warning 1265: Data truncated for column 'code' at row 1"
+        );
+
+        let lenient = SqlModeInfo {
+            session: String::new(),
+            global: String::new(),
+            forced: false,
+            strict: false,
+        };
+        assert!(connect_warning(&lenient).unwrap().starts_with("The server's sql_mode is empty"));
+        let strict = SqlModeInfo {
+            global: "STRICT_TRANS_TABLES".to_string(),
+            ..lenient
+        };
+        assert_eq!(connect_warning(&strict), None);
+    }
+}
@@ -0,0 +1,279 @@
+use super::cascade;
+use crate::models::ForeignKeyDefinition;
+use anyhow::Result;
+use sqlx::sqlite::{SqlitePool, SqliteRow};
+use sqlx::Row;
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+/// Whether the linked SQLite has pragma table-valued functions. The library
+/// is the same for every connection in the process, so it's probed once.
+static PRAGMA_FUNCTIONS: OnceLock<bool> = OnceLock::new();
+
+const FOREIGN_KEYS_SQL: &str = "SELECT m.name AS table_name, fk.id AS id, fk.seq AS seq, fk.\"table\" AS referenced_table, \
+     fk.\"from\" AS from_column, fk.\"to\" AS to_column, fk.on_update AS on_update, fk.on_delete AS on_delete \
+     FROM sqlite_master m JOIN pragma_foreign_key_list(m.name) fk \
+     WHERE m.type = 'table' AND m.name NOT LIKE 'sqlite_%' \
+     ORDER BY m.name, fk.id, fk.seq";
+
+const PRIMARY_KEYS_SQL: &str = "SELECT m.name AS table_name, p.name AS column_name \
+     FROM sqlite_master m JOIN pragma_table_info(m.name) p \
+     WHERE m.type = 'table' AND m.name NOT LIKE 'sqlite_%' AND p.pk > 0 \
+     ORDER BY m.name, p.pk";
+
+const COLUMNS_SQL: &str = "SELECT m.name AS table_name, p.name AS column_name, p.type AS column_type, p.pk AS pk \
+     FROM sqlite_master m JOIN pragma_table_info(m.name) p \
+     WHERE m.type = 'table' AND m.name NOT LIKE 'sqlite_%' \
+     ORDER BY m.name, p.cid";
+
+const INDEX_COLUMNS_SQL: &str = "SELECT il.name AS index_name, il.\"unique\" AS is_unique, ii.name AS column_name \
+     FROM pragma_index_list(?1) il JOIN pragma_index_info(il.name) ii \
+     WHERE il.name NOT LIKE 'sqlite_autoindex%' \
+     ORDER BY il.seq, ii.seqno";
+
+const TABLES_SQL: &str = "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name";
+
+#[cfg(test)]
+thread_local! {
+    /// Statements run by this module on the current thread, for tests.
+    pub static ROUND_TRIPS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+async fn fetch(pool: &SqlitePool, sql: &str, bind: Option<&str>) -> Result<Vec<SqliteRow>> {
+    #[cfg(test)]
+    ROUND_TRIPS.with(|count| count.set(count.get() + 1));
+    let query = sqlx::query(sql);
+    let query = match bind {
+        Some(value) => query.bind(value.to_string()),
+        None => query,
+    };
+    Ok(query.fetch_all(pool).await?)
+}
+
+fn quoted(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Pragma functions arrived in SQLite 3.16.0.
+pub fn supports_pragma_functions(version: &str) -> bool {
+    let parts = version
+        .split('.')
+        .take(2)
+        .map(|part| part.parse::<u32>().unwrap_or(0))
+        .collect::<Vec<_>>();
+    matches!(parts.as_slice(), [major, minor] if (*major, *minor) >= (3, 16))
+}
+
+/// Whether introspection can use set-based pragma queries.
+pub async fn pragma_functions(pool: &SqlitePool) -> bool {
+    if let Some(supported) = PRAGMA_FUNCTIONS.get() {
+        return *supported;
+    }
+    let version = sqlx::query_scalar::<_, String>("SELECT sqlite_version()")
+        .fetch_one(pool)
+        .await;
+    match version {
+        Ok(version) => *PRAGMA_FUNCTIONS.get_or_init(|| supports_pragma_functions(&version)),
+        // Not cached: the next call tries again.
+        Err(_) => false,
+    }
+}
+
+/// A column as `find_relations` needs it.
+pub struct SqliteColumn {
+    pub name: String,
+    pub data_type: String,
+    pub primary_key: bool,
+}
+
+/// Columns of every table, by table name.
+pub async fn columns(pool: &SqlitePool, pragma_functions: bool) -> Result<BTreeMap<String, Vec<SqliteColumn>>> {
+    let mut by_table: BTreeMap<String, Vec<SqliteColumn>> = BTreeMap::new();
+    if pragma_functions {
+        for row in fetch(pool, COLUMNS_SQL, None).await? {
+            by_table.entry(row.try_get(0).unwrap_or_default()).or_default().push(SqliteColumn {
+                name: row.try_get(1).unwrap_or_default(),
+                data_type: row.try_get(2).unwrap_or_default(),
+                primary_key: row.try_get::<i64, _>(3).unwrap_or(0) > 0,
+            });
+        }
+        return Ok(by_table);
+    }
+    for table in fetch(pool, TABLES_SQL, None).await? {
+        let table: String = table.try_get(0).unwrap_or_default();
+        let rows = fetch(pool, &format!("PRAGMA table_info({})", quoted(&table)), None).await?;
+        let columns = rows
+            .iter()
+            .map(|row| SqliteColumn {
+                name: row.try_get(1).unwrap_or_default(),
+                data_type: row.try_get(2).unwrap_or_default(),
+                primary_key: row.try_get::<i64, _>(5).unwrap_or(0) > 0,
+            })
+            .collect();
+        by_table.insert(table, columns);
+    }
+    Ok(by_table)
+}
+
+/// One foreign key, gathered from its `foreign_key_list` rows.
+struct ForeignKeyRows {
+    table: String,
+    id: i64,
+    referenced_table: String,
+    columns: Vec<String>,
+    referenced_columns: Vec<Option<String>>,
+    on_update: String,
+    on_delete: String,
+}
+
+/// Groups rows laid out like `PRAGMA foreign_key_list` from column `offset`
+/// on (id, seq, table, from, to, on_update, on_delete), sorted by id and seq.
+fn group_foreign_keys(rows: impl Iterator<Item = (String, SqliteRow)>, offset: usize) -> Vec<ForeignKeyRows> {
+    let mut keys: Vec<ForeignKeyRows> = Vec::new();
+    for (table, row) in rows {
+        let id: i64 = row.try_get(offset).unwrap_or_default();
+        let from: String = row.try_get(offset + 3).unwrap_or_default();
+        let to: Option<String> = row.try_get(offset + 4).ok().flatten();
+        match keys.last_mut().filter(|key| key.table == table && key.id == id) {
+            Some(key) => {
+                key.columns.push(from);
+                key.referenced_columns.push(to);
+            }
+            None => keys.push(ForeignKeyRows {
+                table,
+                id,
+                referenced_table: row.try_get(offset + 2).unwrap_or_default(),
+                columns: vec![from],
+                referenced_columns: vec![to],
+                on_update: row.try_get(offset + 5).unwrap_or_default(),
+                on_delete: row.try_get(offset + 6).unwrap_or_default(),
+            }),
+        }
+    }
+    keys
+}
+
+/// Every foreign key in the database. `REFERENCES parent` without columns
+/// points at the parent's primary key.
+pub async fn foreign_keys(pool: &SqlitePool, pragma_functions: bool) -> Result<Vec<ForeignKeyDefinition>> {
+    let keys = if pragma_functions {
+        let rows = fetch(pool, FOREIGN_KEYS_SQL, None).await?;
+        group_foreign_keys(
+            rows.into_iter()
+                .map(|row| (row.try_get::<String, _>(0).unwrap_or_default(), row)),
+            1,
+        )
+    } else {
+        let mut keys = Vec::new();
+        for table in fetch(pool, TABLES_SQL, None).await? {
+            let table: String = table.try_get(0).unwrap_or_default();
+            let rows = fetch(pool, &format!("PRAGMA foreign_key_list({})", quoted(&table)), None).await?;
+            let mut sorted = rows
+                .into_iter()
+                .map(|row| {
+                    let order = (row.try_get::<i64, _>(0).unwrap_or_default(), row.try_get::<i64, _>(1).unwrap_or_default());
+                    (order, row)
+                })
+                .collect::<Vec<_>>();
+            sorted.sort_by_key(|(order, _)| *order);
+            keys.extend(group_foreign_keys(
+                sorted.into_iter().map(|(_, row)| (table.clone(), row)),
+                0,
+            ));
+        }
+        keys
+    };
+
+    let mut primary_keys: Option<BTreeMap<String, Vec<String>>> = None;
+    let mut graph = Vec::with_capacity(keys.len());
+    for key in keys {
+        let mut referenced_column_names = key
+            .referenced_columns
+            .iter()
+            .cloned()
+            .collect::<Option<Vec<_>>>()
+            .unwrap_or_default();
+        if referenced_column_names.is_empty() {
+            let parent = key.referenced_table.to_lowercase();
+            referenced_column_names = if pragma_functions {
+                if primary_keys.is_none() {
+                    let mut by_table: BTreeMap<String, Vec<String>> = BTreeMap::new();
+                    for row in fetch(pool, PRIMARY_KEYS_SQL, None).await? {
+                        by_table
+                            .entry(row.try_get::<String, _>(0).unwrap_or_default().to_lowercase())
+                            .or_default()
+                            .push(row.try_get(1).unwrap_or_default());
+                    }
+                    primary_keys = Some(by_table);
+                }
+                primary_keys
+                    .as_ref()
+                    .and_then(|by_table| by_table.get(&parent).cloned())
+                    .unwrap_or_default()
+            } else {
+                let mut rows = fetch(pool, &format!("PRAGMA table_info({})", quoted(&key.referenced_table)), None)
+                    .await?
+                    .into_iter()
+                    .filter_map(|row| {
+                        let pk = row.try_get::<i64, _>(5).unwrap_or(0);
+                        (pk > 0).then(|| (pk, row.try_get::<String, _>(1).unwrap_or_default()))
+                    })
+                    .collect::<Vec<_>>();
+                rows.sort();
+                rows.into_iter().map(|(_, name)| name).collect()
+            };
+        }
+        graph.push(ForeignKeyDefinition {
+            constraint_name: format!("fk_{}_{}", key.table, key.id),
+            table_name: key.table,
+            column_names: key.columns,
+            referenced_table_name: key.referenced_table,
+            referenced_column_names,
+            on_delete: Some(cascade::delete_action(&key.on_delete)),
+            on_update: Some(cascade::delete_action(&key.on_update)),
+        });
+    }
+    Ok(graph)
+}
+
+/// A table's explicitly created indexes as (name, unique, columns).
+pub async fn indexes(pool: &SqlitePool, table: &str, pragma_functions: bool) -> Result<Vec<(String, bool, Vec<String>)>> {
+    let mut indexes: Vec<(String, bool, Vec<String>)> = Vec::new();
+    if pragma_functions {
+        for row in fetch(pool, INDEX_COLUMNS_SQL, Some(table)).await? {
+            let name: String = row.try_get(0).unwrap_or_default();
+            let column: String = row.try_get::<Option<String>, _>(2).ok().flatten().unwrap_or_default();
+            match indexes.last_mut().filter(|(last, _, _)| *last == name) {
+                Some((_, _, columns)) => columns.push(column),
+                None => indexes.push((name, row.try_get::<i64, _>(1).unwrap_or(0) == 1, vec![column])),
+            }
+        }
+        return Ok(indexes);
+    }
+    for row in fetch(pool, &format!("PRAGMA index_list({})", quoted(table)), None).await? {
+        let name: String = row.try_get(1).unwrap_or_default();
+        if name.starts_with("sqlite_autoindex") {
+            continue;
+        }
+        let columns = fetch(pool, &format!("PRAGMA index_info({})", quoted(&name)), None)
+            .await?
+            .iter()
+            .map(|info| info.try_get::<Option<String>, _>(2).ok().flatten().unwrap_or_default())
+            .collect();
+        indexes.push((name, row.try_get::<i64, _>(2).unwrap_or(0) == 1, columns));
+    }
+    Ok(indexes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pragma_functions_need_sqlite_3_16() {
+        assert!(supports_pragma_functions("3.45.1"));
+        assert!(supports_pragma_functions("3.16.0"));
+        assert!(!supports_pragma_functions("3.15.2"));
+        assert!(!supports_pragma_functions("unknown"));
+    }
+}
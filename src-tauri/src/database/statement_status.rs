@@ -0,0 +1,153 @@
+use super::retry::keywords;
+use sqlparser::dialect::GenericDialect;
+use sqlparser::tokenizer::{Token, Tokenizer};
+
+/// Leading statement words that return rows, or may.
+const ROW_RETURNING: &[&str] = &[
+    "SELECT", "WITH", "VALUES", "TABLE", "SHOW", "DESCRIBE", "DESC", "EXPLAIN", "PRAGMA", "CALL", "EXEC",
+    "EXECUTE", "FETCH",
+];
+
+/// Statements whose psql tag is the leading word alone.
+const SINGLE_WORD: &[&str] = &[
+    "UPDATE", "DELETE", "MERGE", "COPY", "SET", "RESET", "VACUUM", "ANALYZE", "REINDEX", "CLUSTER", "GRANT",
+    "REVOKE", "BEGIN", "COMMIT", "ROLLBACK", "SAVEPOINT", "RELEASE", "DO", "USE", "CHECKPOINT", "LISTEN",
+    "NOTIFY", "UNLISTEN", "DISCARD", "PREPARE", "DEALLOCATE", "ATTACH", "DETACH", "COMMENT",
+];
+
+/// Words between CREATE/DROP/ALTER and the kind of object, left out of the tag.
+const MODIFIERS: &[&str] = &[
+    "OR", "REPLACE", "TEMP", "TEMPORARY", "UNLOGGED", "UNIQUE", "GLOBAL", "LOCAL", "RECURSIVE", "VIRTUAL",
+    "TRUSTED", "PROCEDURAL", "CONSTRAINT", "DEFAULT",
+];
+
+/// Object kinds spelled with two words.
+const TWO_WORD_OBJECTS: &[&str] = &["MATERIALIZED VIEW", "FOREIGN TABLE", "EVENT TRIGGER", "TEXT SEARCH", "ACCESS METHOD"];
+
+/// Tags that psql follows with the number of rows changed.
+const COUNTED: &[&str] = &["INSERT", "UPDATE", "DELETE", "MERGE", "COPY"];
+
+/// Statements a leading WITH's common table expressions can feed.
+const CTE_STATEMENTS: &[&str] = &["SELECT", "VALUES", "TABLE", "INSERT", "REPLACE", "UPDATE", "DELETE", "MERGE"];
+
+/// Writes that can return rows with RETURNING.
+const WRITES: &[&str] = &["INSERT", "REPLACE", "UPDATE", "DELETE", "MERGE"];
+
+/// The statement a leading WITH feeds, e.g. "UPDATE" for
+/// `WITH x AS (...) UPDATE t ...`: its first such word outside parentheses.
+fn cte_statement(sql: &str) -> Option<String> {
+    let tokens = Tokenizer::new(&GenericDialect {}, sql).tokenize().ok()?;
+    let mut depth = 0usize;
+    for token in tokens {
+        match token {
+            Token::LParen => depth += 1,
+            Token::RParen => depth = depth.saturating_sub(1),
+            Token::Word(word) if depth == 0 && word.quote_style.is_none() => {
+                let word = word.value.to_ascii_uppercase();
+                if CTE_STATEMENTS.contains(&word.as_str()) {
+                    return Some(word);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The leading word of `words`, or for a WITH the statement it feeds.
+fn main_word(sql: &str, words: &[String]) -> Option<String> {
+    let first = words.first()?;
+    match first.as_str() {
+        "WITH" => Some(cte_statement(sql).unwrap_or_else(|| first.clone())),
+        _ => Some(first.clone()),
+    }
+}
+
+/// The command tag psql prints for `sql`, e.g. "CREATE TABLE" or "SET", or
+/// `None` when the statement returns rows or isn't recognised.
+pub fn command_tag(sql: &str) -> Option<String> {
+    let mut words = keywords(sql);
+    while words.last().map(String::as_str) == Some(";") {
+        words.pop();
+    }
+    let main = main_word(sql, &words)?;
+    let first = main.as_str();
+    if ROW_RETURNING.contains(&first) || words.iter().any(|word| word == "RETURNING") {
+        return None;
+    }
+    match first {
+        // MySQL's REPLACE is an insert that may delete first.
+        "INSERT" | "REPLACE" => Some("INSERT".to_string()),
+        "START" => Some("START TRANSACTION".to_string()),
+        "END" => Some("COMMIT".to_string()),
+        "TRUNCATE" | "LOCK" => Some(format!("{} TABLE", first)),
+        "CREATE" | "DROP" | "ALTER" => {
+            let mut object = words[1..].iter().skip_while(|word| MODIFIERS.contains(&word.as_str()));
+            let kind = object.next()?;
+            let kind = object
+                .next()
+                .map(|second| format!("{} {}", kind, second))
+                .filter(|kind| TWO_WORD_OBJECTS.contains(&kind.as_str()))
+                .unwrap_or_else(|| kind.clone());
+            Some(format!("{} {}", first, kind))
+        }
+        _ if SINGLE_WORD.contains(&first) => Some(first.to_string()),
+        _ => None,
+    }
+}
+
+/// What psql prints once a statement with `tag` completes.
+pub fn status_message(tag: &str, rows_affected: u64) -> String {
+    match tag {
+        // The 0 is the OID psql still prints for inserts.
+        "INSERT" => format!("INSERT 0 {}", rows_affected),
+        _ if COUNTED.contains(&tag) => format!("{} {}", tag, rows_affected),
+        _ => tag.to_string(),
+    }
+}
+
+/// Whether statements with `tag` report a row count.
+pub fn is_counted(tag: &str) -> bool {
+    COUNTED.contains(&tag)
+}
+
+/// Whether `sql` is an INSERT, UPDATE, DELETE or MERGE with a RETURNING
+/// clause, whose rows come back alongside the count of rows it changed.
+pub fn is_write_returning(sql: &str) -> bool {
+    let words = keywords(sql);
+    main_word(sql, &words).is_some_and(|main| WRITES.contains(&main.as_str()))
+        && words.iter().any(|word| word == "RETURNING")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_follow_psql() {
+        let tag = |sql: &str| command_tag(sql);
+        assert_eq!(tag("create unique index if not exists i on t (a);").as_deref(), Some("CREATE INDEX"));
+        assert_eq!(tag("CREATE OR REPLACE MATERIALIZED VIEW v AS SELECT 1").as_deref(), Some("CREATE MATERIALIZED VIEW"));
+        assert_eq!(tag("-- tidy\nVACUUM").as_deref(), Some("VACUUM"));
+        assert_eq!(tag("SET search_path = app").as_deref(), Some("SET"));
+        assert_eq!(tag("truncate orders").as_deref(), Some("TRUNCATE TABLE"));
+        assert_eq!(tag("SELECT 1"), None);
+        assert_eq!(tag("DELETE FROM t RETURNING id"), None);
+        assert_eq!(tag("FROBNICATE"), None);
+
+        assert_eq!(status_message("INSERT", 3), "INSERT 0 3");
+        assert_eq!(status_message("UPDATE", 0), "UPDATE 0");
+        assert_eq!(status_message("CREATE TABLE", 7), "CREATE TABLE");
+
+        assert!(is_write_returning("update t set a = 1 returning id"));
+        assert!(!is_write_returning("SELECT 'returning' FROM t"));
+        assert!(!is_write_returning("UPDATE t SET a = 1"));
+
+        let stale = "WITH stale AS (SELECT id FROM t WHERE seen < now() - interval '1 day')";
+        assert!(is_write_returning(&format!("{} UPDATE t SET gone = true FROM stale RETURNING t.id", stale)));
+        assert!(!is_write_returning(&format!("{} SELECT id AS returning FROM stale", stale)));
+        assert_eq!(tag(&format!("{} DELETE FROM t WHERE id IN (SELECT id FROM stale)", stale)).as_deref(), Some("DELETE"));
+        assert_eq!(tag(&format!("{} SELECT * FROM stale", stale)), None);
+        assert_eq!(tag("WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n) SELECT x FROM n"), None);
+    }
+}
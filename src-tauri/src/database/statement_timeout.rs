@@ -0,0 +1,350 @@
+use super::mssql::MsSqlPool;
+use super::multi_results::{self, DrainedBatch};
+use super::params::{bind_values, BindValue};
+use super::query_cancel::{self, CancelTarget, QueryCancelled};
+use crate::models::{ConnectionConfig, QueryResult};
+use crate::operations::CancellationToken;
+use anyhow::Result;
+use futures_util::future::BoxFuture;
+use sqlx::database::HasArguments;
+use sqlx::{Encode, IntoArguments, MySql, Postgres, Sqlite, Type};
+use std::time::{Duration, Instant};
+
+/// How long past the server's own limit the app waits before giving up on a
+/// statement itself, so the server's cancel normally comes first.
+const CLIENT_GRACE: Duration = Duration::from_secs(2);
+/// SQLite virtual machine steps between checks of the deadline.
+const SQLITE_PROGRESS_STEPS: i32 = 10_000;
+
+/// A statement cancelled for running past the connection's statement timeout.
+#[derive(Debug)]
+pub struct StatementTimedOut {
+    pub seconds: u64,
+}
+
+impl std::fmt::Display for StatementTimedOut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Query cancelled: it ran longer than the connection's {}-second statement timeout",
+            self.seconds
+        )
+    }
+}
+
+impl std::error::Error for StatementTimedOut {}
+
+/// The statement timeout `config` asks for; unset or 0 means none.
+pub fn limit(config: &ConnectionConfig) -> Option<u64> {
+    config.statement_timeout_seconds.filter(|seconds| *seconds > 0)
+}
+
+/// Whether `error` is the database cancelling a statement for running too long.
+fn is_timeout_error(error: &anyhow::Error) -> bool {
+    let message = error.to_string();
+    // Postgres: 57014 also covers pg_cancel_backend, so the reason is checked too.
+    (message.starts_with("SQLSTATE 57014") && message.contains("statement timeout"))
+        || message.contains("maximum statement execution time exceeded")
+        // SQLite reports the progress handler's interrupt as SQLITE_INTERRUPT.
+        || message.starts_with("SQLSTATE 9:")
+}
+
+/// Databases whose sessions can be given a statement deadline.
+pub trait Limited: sqlx::Database {
+    /// Limits statements on `conn` to `limit` until `disarm`, returning the
+    /// session's own setting when `disarm` needs it back.
+    fn arm(conn: &mut Self::Connection, limit: Duration) -> BoxFuture<'_, Result<Option<String>>>;
+    /// Puts `conn` back as it was before `arm`.
+    fn disarm(conn: &mut Self::Connection, previous: Option<String>) -> BoxFuture<'_, Result<()>>;
+    /// Readies `conn`, from `pool`, to have its statement stopped through
+    /// `token` or from another connection. `limit` is what `arm` set, which
+    /// SQLite's progress handler keeps enforcing.
+    fn watch<'c>(
+        conn: &'c mut Self::Connection,
+        pool: &sqlx::Pool<Self>,
+        token: CancellationToken,
+        limit: Option<Duration>,
+    ) -> BoxFuture<'c, Result<CancelTarget>>;
+    /// Puts `conn` back as it was before `watch`.
+    fn unwatch(_conn: &mut Self::Connection) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// The session's statement_timeout may come from the connection's session
+/// parameters, which RESET would drop, so it is read first and put back.
+impl Limited for Postgres {
+    fn arm(conn: &mut Self::Connection, limit: Duration) -> BoxFuture<'_, Result<Option<String>>> {
+        Box::pin(async move {
+            let previous: String = sqlx::query_scalar("SELECT current_setting('statement_timeout')")
+                .fetch_one(&mut *conn)
+                .await?;
+            sqlx::query(&format!("SET statement_timeout = {}", limit.as_millis()))
+                .execute(conn)
+                .await?;
+            Ok(Some(previous))
+        })
+    }
+
+    fn disarm(conn: &mut Self::Connection, previous: Option<String>) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async move {
+            match previous {
+                Some(previous) => {
+                    sqlx::query("SELECT set_config('statement_timeout', $1, false)")
+                        .bind(previous)
+                        .execute(conn)
+                        .await?;
+                }
+                None => {
+                    sqlx::query("RESET statement_timeout").execute(conn).await?;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn watch<'c>(
+        conn: &'c mut Self::Connection,
+        pool: &sqlx::Pool<Self>,
+        _token: CancellationToken,
+        _limit: Option<Duration>,
+    ) -> BoxFuture<'c, Result<CancelTarget>> {
+        let options = pool.connect_options();
+        Box::pin(async move {
+            let pid: i32 = sqlx::query_scalar("SELECT pg_backend_pid()").fetch_one(conn).await?;
+            Ok(CancelTarget::Postgres { pid, options })
+        })
+    }
+}
+
+/// MySQL only applies `max_execution_time` to read-only SELECTs; other
+/// statements are stopped by the client-side deadline alone. The session's
+/// own value is read first and put back, as for Postgres.
+impl Limited for MySql {
+    fn arm(conn: &mut Self::Connection, limit: Duration) -> BoxFuture<'_, Result<Option<String>>> {
+        Box::pin(async move {
+            let previous: u64 = sqlx::query_scalar("SELECT CAST(@@SESSION.max_execution_time AS UNSIGNED)")
+                .fetch_one(&mut *conn)
+                .await?;
+            sqlx::query(&format!("SET SESSION max_execution_time = {}", limit.as_millis()))
+                .execute(conn)
+                .await?;
+            Ok(Some(previous.to_string()))
+        })
+    }
+
+    fn disarm(conn: &mut Self::Connection, previous: Option<String>) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async move {
+            let value = previous.unwrap_or_else(|| "DEFAULT".to_string());
+            sqlx::query(&format!("SET SESSION max_execution_time = {}", value))
+                .execute(conn)
+                .await?;
+            Ok(())
+        })
+    }
+
+    fn watch<'c>(
+        conn: &'c mut Self::Connection,
+        pool: &sqlx::Pool<Self>,
+        _token: CancellationToken,
+        _limit: Option<Duration>,
+    ) -> BoxFuture<'c, Result<CancelTarget>> {
+        let options = pool.connect_options();
+        Box::pin(async move {
+            let id: u64 = sqlx::query_scalar("SELECT CONNECTION_ID()").fetch_one(conn).await?;
+            Ok(CancelTarget::MySql { id, options })
+        })
+    }
+}
+
+/// SQLite has no server to cancel anything, so a progress handler interrupts
+/// the statement once the deadline passes.
+impl Limited for Sqlite {
+    fn arm(conn: &mut Self::Connection, limit: Duration) -> BoxFuture<'_, Result<Option<String>>> {
+        Box::pin(async move {
+            let deadline = Instant::now() + limit;
+            conn.lock_handle()
+                .await?
+                .set_progress_handler(SQLITE_PROGRESS_STEPS, move || Instant::now() < deadline);
+            Ok(None)
+        })
+    }
+
+    fn disarm(conn: &mut Self::Connection, _previous: Option<String>) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async move {
+            conn.lock_handle().await?.remove_progress_handler();
+            Ok(())
+        })
+    }
+
+    /// Replaces `arm`'s progress handler with one that also stops once `token`
+    /// is cancelled.
+    fn watch<'c>(
+        conn: &'c mut Self::Connection,
+        _pool: &sqlx::Pool<Self>,
+        token: CancellationToken,
+        limit: Option<Duration>,
+    ) -> BoxFuture<'c, Result<CancelTarget>> {
+        Box::pin(async move {
+            let deadline = limit.map(|limit| Instant::now() + limit);
+            conn.lock_handle().await?.set_progress_handler(SQLITE_PROGRESS_STEPS, move || {
+                !token.is_cancelled() && deadline.is_none_or(|deadline| Instant::now() < deadline)
+            });
+            Ok(CancelTarget::Interrupt)
+        })
+    }
+
+    fn unwatch(conn: &mut Self::Connection) -> BoxFuture<'_, Result<()>> {
+        Self::disarm(conn, None)
+    }
+}
+
+/// `multi_results::drain` of `sql` on `pool`, cancelled after `seconds` when
+/// a timeout is set. A cancelled statement fails with `StatementTimedOut`.
+pub async fn drain<DB>(pool: &sqlx::Pool<DB>, sql: &str, seconds: Option<u64>) -> Result<DrainedBatch<DB>>
+where
+    DB: Limited,
+    for<'c> &'c mut DB::Connection: sqlx::Executor<'c, Database = DB>,
+    for<'q> <DB as HasArguments<'q>>::Arguments: IntoArguments<'q, DB>,
+    for<'q> Option<bool>: Encode<'q, DB> + Type<DB>,
+    for<'q> Option<i64>: Encode<'q, DB> + Type<DB>,
+    for<'q> Option<f64>: Encode<'q, DB> + Type<DB>,
+    for<'q> Option<String>: Encode<'q, DB> + Type<DB>,
+    for<'q> Option<Vec<u8>>: Encode<'q, DB> + Type<DB>,
+{
+    drain_bound(pool, sql, &[], seconds).await
+}
+
+/// `drain` of `sql` with `binds` for its placeholders. Inside a
+/// `RunningQueries::scope` the statement can be cancelled by its query id.
+pub async fn drain_bound<DB>(
+    pool: &sqlx::Pool<DB>,
+    sql: &str,
+    binds: &[BindValue],
+    seconds: Option<u64>,
+) -> Result<DrainedBatch<DB>>
+where
+    DB: Limited,
+    for<'c> &'c mut DB::Connection: sqlx::Executor<'c, Database = DB>,
+    for<'q> <DB as HasArguments<'q>>::Arguments: IntoArguments<'q, DB>,
+    for<'q> Option<bool>: Encode<'q, DB> + Type<DB>,
+    for<'q> Option<i64>: Encode<'q, DB> + Type<DB>,
+    for<'q> Option<f64>: Encode<'q, DB> + Type<DB>,
+    for<'q> Option<String>: Encode<'q, DB> + Type<DB>,
+    for<'q> Option<Vec<u8>>: Encode<'q, DB> + Type<DB>,
+{
+    let running = query_cancel::current();
+    if seconds.is_none() && running.is_none() {
+        return multi_results::drain(pool, bind_values(sqlx::query(sql), binds)).await;
+    }
+    let limit = seconds.map(Duration::from_secs);
+    let mut conn = pool.acquire().await?;
+    let previous = match limit {
+        Some(limit) => Some(DB::arm(&mut conn, limit).await?),
+        None => None,
+    };
+    if let Some(running) = &running {
+        let watched = match DB::watch(&mut conn, pool, running.token(), limit).await {
+            Ok(target) => running.started(target).await,
+            Err(error) => Err(error),
+        };
+        if let Err(error) = watched {
+            // Armed but not reset; it isn't handed out again.
+            let _ = conn.close().await;
+            return Err(error);
+        }
+    }
+
+    let work = multi_results::drain(&mut *conn, bind_values(sqlx::query(sql), binds));
+    let drained = match seconds {
+        Some(seconds) => tokio::time::timeout(Duration::from_secs(seconds) + CLIENT_GRACE, work)
+            .await
+            .map_err(|_| StatementTimedOut { seconds }),
+        None => Ok(work.await),
+    };
+    if let Some(running) = &running {
+        running.finished().await;
+    }
+    match drained {
+        Ok(drained) => {
+            let mut reset = match previous {
+                Some(previous) => DB::disarm(&mut conn, previous).await,
+                None => Ok(()),
+            };
+            if running.is_some() {
+                reset = reset.and(DB::unwatch(&mut conn).await);
+            }
+            // A connection that can't be reset isn't handed out again.
+            if reset.is_err() {
+                let _ = conn.close().await;
+            }
+            drained.map_err(|error| {
+                if running.as_ref().is_some_and(|running| running.is_cancelled()) {
+                    return QueryCancelled.into();
+                }
+                match seconds {
+                    Some(seconds) if is_timeout_error(&error) => StatementTimedOut { seconds }.into(),
+                    _ => error,
+                }
+            })
+        }
+        Err(timed_out) => {
+            // The statement may still be running; the connection goes with it.
+            let _ = conn.close().await;
+            Err(timed_out.into())
+        }
+    }
+}
+
+/// `MsSqlPool::execute_with` of `sql`, given up on after `seconds` when a
+/// timeout is set. tiberius can't cancel a statement, so the session is
+/// closed with it; the connection's next use reconnects.
+pub async fn execute_mssql(
+    pool: &MsSqlPool,
+    sql: &str,
+    binds: &[BindValue],
+    seconds: Option<u64>,
+) -> Result<(QueryResult, u64)> {
+    let Some(seconds) = seconds else {
+        return pool.execute_with(sql, binds).await;
+    };
+    match tokio::time::timeout(Duration::from_secs(seconds), pool.execute_with(sql, binds)).await {
+        Ok(executed) => executed,
+        Err(_) => {
+            pool.close().await;
+            Err(StatementTimedOut { seconds }.into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn server_timeouts_are_told_apart_from_other_cancels() {
+        assert!(is_timeout_error(&anyhow::anyhow!(
+            "SQLSTATE 57014: canceling statement due to statement timeout"
+        )));
+        assert!(!is_timeout_error(&anyhow::anyhow!(
+            "SQLSTATE 57014: canceling statement due to user request"
+        )));
+        assert!(is_timeout_error(&anyhow::anyhow!(
+            "SQLSTATE HY000: Query execution was interrupted, maximum statement execution time exceeded"
+        )));
+        assert!(!is_timeout_error(&anyhow::anyhow!("SQLSTATE 42P01: relation \"x\" does not exist")));
+    }
+
+    #[test]
+    fn the_limit_is_also_read_under_its_short_name() {
+        let config: ConnectionConfig = serde_json::from_value(serde_json::json!({
+            "id": "c1",
+            "name": "Local",
+            "db_type": "sqlite",
+            "file_path": "/tmp/app.db",
+            "statement_timeout_secs": 30
+        }))
+        .unwrap();
+        assert_eq!(limit(&config), Some(30));
+    }
+}
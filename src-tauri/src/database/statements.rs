@@ -0,0 +1,181 @@
+use crate::models::StatementScope;
+
+/// Byte range of one statement in a script, trimmed of surrounding whitespace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatementRange {
+    pub start: usize,
+    pub end: usize,
+    /// False for segments holding only comments.
+    pub has_code: bool,
+}
+
+/// Splits a script at semicolons outside quotes and comments. Comments stay
+/// with the statement that follows them.
+pub fn statement_ranges(sql: &str) -> Vec<StatementRange> {
+    let mut ranges = Vec::new();
+    let mut chars = sql.char_indices().peekable();
+    let mut segment_start = 0;
+    let mut has_code = false;
+    let mut in_single = false;
+    let mut in_double = false;
+    let mut in_line_comment = false;
+    let mut in_block_comment = false;
+
+    let push = |ranges: &mut Vec<StatementRange>, start: usize, end: usize, has_code: bool| {
+        let segment = &sql[start..end];
+        let trimmed_start = start + (segment.len() - segment.trim_start().len());
+        let trimmed_end = start + segment.trim_end().len();
+        if trimmed_start < trimmed_end {
+            ranges.push(StatementRange {
+                start: trimmed_start,
+                end: trimmed_end,
+                has_code,
+            });
+        }
+    };
+
+    while let Some((index, ch)) = chars.next() {
+        if in_line_comment {
+            if ch == '\n' {
+                in_line_comment = false;
+            }
+            continue;
+        }
+
+        if in_block_comment {
+            if ch == '*' && matches!(chars.peek(), Some((_, '/'))) {
+                chars.next();
+                in_block_comment = false;
+            }
+            continue;
+        }
+
+        if !in_single && !in_double {
+            if ch == '-' && matches!(chars.peek(), Some((_, '-'))) {
+                chars.next();
+                in_line_comment = true;
+                continue;
+            }
+
+            if ch == '/' && matches!(chars.peek(), Some((_, '*'))) {
+                chars.next();
+                in_block_comment = true;
+                continue;
+            }
+        }
+
+        if ch == '\'' && !in_double {
+            in_single = !in_single;
+        } else if ch == '"' && !in_single {
+            in_double = !in_double;
+        } else if ch == ';' && !in_single && !in_double {
+            push(&mut ranges, segment_start, index, has_code);
+            segment_start = index + 1;
+            has_code = false;
+            continue;
+        }
+
+        if !ch.is_whitespace() {
+            has_code = true;
+        }
+    }
+
+    push(&mut ranges, segment_start, sql.len(), has_code);
+    ranges
+}
+
+/// Index of the statement to run for a cursor at byte `cursor`: the one
+/// containing it, else the nearest one before it, else the first. Comment-only
+/// segments are never picked.
+pub fn statement_at(ranges: &[StatementRange], cursor: usize) -> Option<usize> {
+    let executable = || ranges.iter().enumerate().filter(|(_, range)| range.has_code);
+    executable()
+        .find(|(_, range)| range.start <= cursor && cursor <= range.end)
+        .or_else(|| executable().rfind(|(_, range)| range.end < cursor))
+        .or_else(|| executable().next())
+        .map(|(index, _)| index)
+}
+
+/// Statements to run for `scope`, in script order.
+pub fn statements_for_scope(
+    ranges: &[StatementRange],
+    cursor: usize,
+    scope: StatementScope,
+) -> Vec<StatementRange> {
+    let Some(current) = statement_at(ranges, cursor) else {
+        return Vec::new();
+    };
+    let selected = match scope {
+        StatementScope::Current => &ranges[current..=current],
+        StatementScope::FromCursor => &ranges[current..],
+        StatementScope::ToCursor => &ranges[..=current],
+    };
+    selected.iter().filter(|range| range.has_code).copied().collect()
+}
+
+/// Byte offset of a UTF-16 offset (how editors count), clamped to the text.
+pub fn byte_offset(sql: &str, utf16_offset: usize) -> usize {
+    let mut units = 0;
+    for (index, ch) in sql.char_indices() {
+        if units >= utf16_offset {
+            return index;
+        }
+        units += ch.len_utf16();
+    }
+    sql.len()
+}
+
+/// UTF-16 offset of a byte offset on a char boundary.
+pub fn utf16_offset(sql: &str, byte_offset: usize) -> usize {
+    sql[..byte_offset].encode_utf16().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pick(sql: &str, cursor: usize, scope: StatementScope) -> Vec<&str> {
+        statements_for_scope(&statement_ranges(sql), cursor, scope)
+            .into_iter()
+            .map(|range| &sql[range.start..range.end])
+            .collect()
+    }
+
+    #[test]
+    fn picks_the_statement_around_or_before_the_cursor() {
+        let sql = "SELECT 1;\n\nSELECT 'a;b'; /* lead; */ SELECT 3; -- done; really";
+        let second = sql.find("'a;b'").unwrap();
+        assert_eq!(pick(sql, second + 2, StatementScope::Current), vec!["SELECT 'a;b'"]);
+        // Between statements: the previous one.
+        assert_eq!(pick(sql, sql.find("\n\n").unwrap() + 1, StatementScope::Current), vec!["SELECT 1"]);
+        // Inside a comment-only segment: the statement before it.
+        let note = sql.find("really").unwrap();
+        assert_eq!(pick(sql, note, StatementScope::Current), vec!["/* lead; */ SELECT 3"]);
+        // Inside a comment leading a statement: that statement.
+        let lead = sql.find("lead").unwrap();
+        assert_eq!(pick(sql, lead, StatementScope::Current), vec!["/* lead; */ SELECT 3"]);
+    }
+
+    #[test]
+    fn handles_buffer_boundaries_and_scopes() {
+        let sql = "  SELECT 1; SELECT 2;\nSELECT 3";
+        assert_eq!(pick(sql, 0, StatementScope::Current), vec!["SELECT 1"]);
+        assert_eq!(pick(sql, sql.len(), StatementScope::Current), vec!["SELECT 3"]);
+        assert_eq!(pick(sql, sql.len() + 10, StatementScope::Current), vec!["SELECT 3"]);
+        let second = sql.find("SELECT 2").unwrap();
+        assert_eq!(pick(sql, second, StatementScope::FromCursor), vec!["SELECT 2", "SELECT 3"]);
+        assert_eq!(pick(sql, second, StatementScope::ToCursor), vec!["SELECT 1", "SELECT 2"]);
+        assert!(pick("  -- nothing here\n", 3, StatementScope::Current).is_empty());
+        assert!(pick("", 0, StatementScope::Current).is_empty());
+    }
+
+    #[test]
+    fn converts_utf16_offsets() {
+        let sql = "SELECT 'é😀'; SELECT 2";
+        let byte = sql.find("; ").unwrap();
+        let units = utf16_offset(sql, byte);
+        assert_eq!(units, "SELECT 'é😀'".encode_utf16().count());
+        assert_eq!(byte_offset(sql, units), byte);
+        assert_eq!(byte_offset(sql, 1000), sql.len());
+    }
+}
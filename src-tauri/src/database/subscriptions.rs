@@ -0,0 +1,51 @@
+use super::checksum::fnv1a64;
+use crate::models::{DatabaseType, QueryResult, TableViewParams, TableViewPreferences};
+use std::time::Duration;
+
+/// Emitted when an auto-refreshed table view's content changed.
+pub const TABLE_REFRESHED_EVENT: &str = "table-refreshed";
+/// Shortest refresh interval a subscription may ask for.
+pub const MIN_REFRESH_SECONDS: u64 = 1;
+/// How often a subscription checks for unsubscribe and disconnect between refreshes.
+pub const SUBSCRIPTION_POLL: Duration = Duration::from_millis(250);
+
+/// A table view kept fresh in the background.
+pub struct TableSubscription {
+    pub connection_id: String,
+    pub db_type: DatabaseType,
+    pub params: TableViewParams,
+    /// Saved view applied to every refresh.
+    pub view: Option<TableViewPreferences>,
+    pub interval: Duration,
+}
+
+/// Hash of what a refresh shows: the columns and rows, not how they were fetched.
+pub fn content_hash(result: &QueryResult) -> String {
+    let content = serde_json::to_vec(&(&result.columns, &result.rows)).unwrap_or_default();
+    format!("{:016x}", fnv1a64(&content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash_covers_content_only() {
+        let result = |rows: Vec<serde_json::Value>, attempts_made: u32| QueryResult {
+            columns: vec!["id".to_string()],
+            rows,
+            rows_affected: 0,
+            attempts_made,
+            truncated_cells: vec![],
+            result_id: None,
+            lineage: None,
+            status_message: None,
+            no_result_set: false,
+            decoded_columns: vec![],
+            binary_columns: vec![],
+        };
+        let one = vec![serde_json::json!({ "id": 1 })];
+        assert_eq!(content_hash(&result(one.clone(), 1)), content_hash(&result(one.clone(), 3)));
+        assert_ne!(content_hash(&result(one, 1)), content_hash(&result(vec![], 1)));
+    }
+}
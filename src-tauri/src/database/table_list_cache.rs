@@ -0,0 +1,49 @@
+use super::retry;
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+
+/// Emitted with the fresh list when a background refresh finishes.
+pub const TABLE_LIST_REFRESHED_EVENT: &str = "table-list-refreshed";
+
+/// Keywords of statements that may create, drop or rename a table.
+const SCHEMA_KEYWORDS: &[&str] = &["CREATE", "DROP", "ALTER", "RENAME", "ATTACH", "DETACH"];
+
+/// Which connections' persisted table lists can't be trusted and which are
+/// being refreshed in the background.
+#[derive(Default)]
+pub struct TableListState {
+    pub stale: HashSet<String>,
+    pub refreshing: HashSet<String>,
+}
+
+/// Whether `sql` may change the list of tables. Errs on the side of yes; a
+/// false positive only costs one uncached listing.
+pub fn changes_table_list(sql: &str) -> bool {
+    retry::keywords(sql)
+        .iter()
+        .any(|word| SCHEMA_KEYWORDS.contains(&word.as_str()))
+}
+
+/// Whole seconds since `cached_at`, or None if it isn't a timestamp.
+pub fn age_seconds(cached_at: &str, now: DateTime<Utc>) -> Option<u64> {
+    let at = DateTime::parse_from_rfc3339(cached_at).ok()?;
+    Some((now - at.with_timezone(&Utc)).num_seconds().max(0) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schema_statements_are_recognised_outside_literals() {
+        assert!(changes_table_list("create table t (id int)"));
+        assert!(changes_table_list("-- tidy up\nDROP VIEW v"));
+        assert!(changes_table_list("ALTER TABLE t RENAME TO u"));
+        assert!(!changes_table_list("SELECT created_at FROM t WHERE note = 'drop table'"));
+        assert!(!changes_table_list("UPDATE t SET \"create\" = 1"));
+
+        let now = DateTime::parse_from_rfc3339("2026-01-01T00:01:30Z").unwrap().with_timezone(&Utc);
+        assert_eq!(age_seconds("2026-01-01T00:00:00Z", now), Some(90));
+        assert_eq!(age_seconds("yesterday", now), None);
+    }
+}
@@ -0,0 +1,354 @@
+use super::types::{
+    classify_mysql_type, classify_postgres_type, classify_sqlite_type, declared_size, is_mysql_boolean_like, mysql_enum_values,
+    normalize_type_name,
+};
+use super::escaping::quote_literal;
+use crate::models::{ColumnTypeFamily, DatabaseType, TableColumn, TableRef};
+use anyhow::Result;
+use sqlx::mysql::{MySqlPool, MySqlRow};
+use sqlx::postgres::{PgPool, PgRow};
+use sqlx::sqlite::{SqlitePool, SqliteRow};
+use sqlx::Row;
+
+/// Columns of each table named in `$1` (anything `to_regclass` reads),
+/// tagged with the name's index in the array.
+const POSTGRES_COLUMNS_SQL: &str = r#"
+    SELECT
+      att.attname AS column_name,
+      pg_catalog.format_type(att.atttypid, att.atttypmod) AS formatted_type,
+      typ.typname AS raw_type_name,
+      typ_ns.nspname AS type_schema,
+      typ.typtype AS type_kind,
+      typ.typcategory AS type_category,
+      att.attnotnull AS not_null,
+      pg_get_expr(def.adbin, def.adrelid) AS default_value,
+      pk.indexrelid IS NOT NULL AS is_primary_key,
+      CASE WHEN att.attndims > 0 OR typ.typcategory = 'A' THEN true ELSE false END AS is_array,
+      att.attndims AS array_dimensions,
+      CASE WHEN typ.typcategory = 'A' THEN elem.typname ELSE NULL END AS element_raw_type,
+      (
+        SELECT array_agg(enumlabel ORDER BY enumsortorder)
+        FROM pg_enum
+        WHERE enumtypid = typ.oid
+      ) AS enum_values,
+      NULLIF(att.attidentity::text, '') AS identity_kind,
+      NULLIF(att.attgenerated::text, '') AS generated_kind,
+      CASE WHEN att.attgenerated <> '' THEN pg_get_expr(def.adbin, def.adrelid) ELSE NULL END AS generation_expression,
+      pg_catalog.col_description(att.attrelid, att.attnum) AS column_comment,
+      col.collname AS collation_name,
+      CASE WHEN typ.typtype = 'd' THEN typ.typname ELSE NULL END AS domain_name,
+      CASE WHEN typ.typtype = 'd' THEN typ_ns.nspname ELSE NULL END AS domain_schema,
+      CASE WHEN typ.typtype = 'd' THEN base_typ.typname ELSE NULL END AS domain_base_type,
+      information_schema._pg_char_max_length(
+        information_schema._pg_truetypid(att.*, typ.*),
+        information_schema._pg_truetypmod(att.*, typ.*)
+      )::int8 AS character_maximum_length,
+      CASE WHEN information_schema._pg_truetypid(att.*, typ.*) = 'numeric'::regtype THEN
+        information_schema._pg_numeric_precision(
+          information_schema._pg_truetypid(att.*, typ.*),
+          information_schema._pg_truetypmod(att.*, typ.*)
+        )::int8
+      END AS numeric_precision,
+      CASE WHEN information_schema._pg_truetypid(att.*, typ.*) = 'numeric'::regtype THEN
+        information_schema._pg_numeric_scale(
+          information_schema._pg_truetypid(att.*, typ.*),
+          information_schema._pg_truetypmod(att.*, typ.*)
+        )::int8
+      END AS numeric_scale,
+      req.ord - 1 AS request_index
+    FROM unnest($1::text[]) WITH ORDINALITY AS req(name, ord)
+    JOIN pg_attribute att ON att.attrelid = to_regclass(req.name)
+    JOIN pg_class cls ON cls.oid = att.attrelid
+    JOIN pg_namespace ns ON ns.oid = cls.relnamespace
+    JOIN pg_type typ ON typ.oid = att.atttypid
+    JOIN pg_namespace typ_ns ON typ_ns.oid = typ.typnamespace
+    LEFT JOIN pg_type elem ON elem.oid = typ.typelem
+    LEFT JOIN pg_type base_typ ON base_typ.oid = typ.typbasetype
+    LEFT JOIN pg_attrdef def
+      ON def.adrelid = att.attrelid
+     AND def.adnum = att.attnum
+    LEFT JOIN pg_collation col ON col.oid = att.attcollation
+    LEFT JOIN pg_index pk
+      ON pk.indrelid = att.attrelid
+     AND pk.indisprimary
+     AND att.attnum = ANY(pk.indkey)
+    WHERE att.attnum > 0
+      AND NOT att.attisdropped
+    ORDER BY req.ord, att.attnum
+"#;
+
+/// The column list both MySQL queries read, in the order `mysql_column` expects.
+pub const MYSQL_COLUMNS: &str = "c.COLUMN_NAME, c.DATA_TYPE, c.IS_NULLABLE, c.COLUMN_DEFAULT, \
+     IF(c.COLUMN_KEY = 'PRI', 1, 0) as is_primary_key, c.EXTRA, \
+     CAST(c.COLUMN_TYPE AS CHAR) AS column_type, \
+     CAST(c.CHARACTER_MAXIMUM_LENGTH AS SIGNED) AS character_maximum_length, \
+     CAST(c.NUMERIC_PRECISION AS SIGNED) AS numeric_precision, \
+     CAST(c.NUMERIC_SCALE AS SIGNED) AS numeric_scale";
+
+/// A row laid out like `PRAGMA table_info`.
+pub fn sqlite_column(row: &SqliteRow) -> TableColumn {
+    let name: String = row.try_get(1).unwrap_or_default();
+    let data_type: String = row.try_get(2).unwrap_or_default();
+    let not_null: i64 = row.try_get(3).unwrap_or(0);
+    // A missing default reads as "" unless asked for as an Option.
+    let default_value: Option<String> = row.try_get(4).ok().flatten();
+    let is_pk: i64 = row.try_get(5).unwrap_or(0);
+    let family = classify_sqlite_type(&data_type);
+    let (size, scale) = declared_size(&data_type);
+
+    TableColumn {
+        name,
+        data_type: data_type.clone(),
+        raw_type: Some(data_type.clone()),
+        normalized_type: normalize_type_name(&data_type),
+        type_family: family.clone(),
+        db_type: DatabaseType::SQLite,
+        is_nullable: not_null == 0,
+        default_value,
+        is_primary_key: is_pk > 0,
+        is_boolean_like: matches!(family, ColumnTypeFamily::Boolean),
+        is_array: false,
+        enum_values: None,
+        identity_kind: None,
+        generated_kind: None,
+        generation_expression: None,
+        column_comment: None,
+        collation_name: None,
+        domain_name: None,
+        domain_schema: None,
+        domain_base_type: None,
+        array_dimensions: None,
+        element_raw_type: None,
+        character_maximum_length: (family == ColumnTypeFamily::Text).then_some(size).flatten(),
+        numeric_precision: (family == ColumnTypeFamily::Decimal).then_some(size).flatten(),
+        numeric_scale: (family == ColumnTypeFamily::Decimal).then_some(scale).flatten(),
+    }
+}
+
+/// A row of `POSTGRES_COLUMNS_SQL`.
+fn postgres_column(row: &PgRow) -> TableColumn {
+    let name: String = row.try_get(0).unwrap_or_default();
+    let data_type: String = row.try_get(1).unwrap_or_default();
+    let raw_type: String = row.try_get(2).unwrap_or_default();
+    let _type_schema: String = row.try_get(3).unwrap_or_default();
+    let type_kind: String = row.try_get(4).unwrap_or_default();
+    let _type_category: String = row.try_get(5).unwrap_or_default();
+    let not_null: bool = row.try_get(6).unwrap_or(false);
+    let default_value: Option<String> = row.try_get(7).ok();
+    let is_primary_key: bool = row.try_get(8).unwrap_or(false);
+    let is_array: bool = row.try_get(9).unwrap_or(false);
+    let array_dimensions: Option<i32> = row.try_get(10).ok();
+    let element_raw_type: Option<String> = row.try_get(11).ok();
+    let enum_values: Option<Vec<String>> = row.try_get(12).ok().flatten();
+    let identity_kind: Option<String> = row.try_get(13).ok();
+    let generated_kind: Option<String> = row.try_get(14).ok();
+    let generation_expression: Option<String> = row.try_get(15).ok();
+    let column_comment: Option<String> = row.try_get(16).ok();
+    let collation_name: Option<String> = row.try_get(17).ok();
+    let domain_name: Option<String> = row.try_get(18).ok();
+    let domain_schema: Option<String> = row.try_get(19).ok();
+    let domain_base_type: Option<String> = row.try_get(20).ok();
+    let character_maximum_length: Option<i64> = row.try_get(21).ok().flatten();
+    let numeric_precision: Option<i64> = row.try_get(22).ok().flatten();
+    let numeric_scale: Option<i64> = row.try_get(23).ok().flatten();
+    let family = classify_postgres_type(&data_type, &raw_type, &type_kind, is_array);
+
+    TableColumn {
+        name,
+        data_type: data_type.clone(),
+        raw_type: Some(raw_type),
+        normalized_type: normalize_type_name(&data_type),
+        type_family: family.clone(),
+        db_type: DatabaseType::PostgreSQL,
+        is_nullable: !not_null,
+        default_value,
+        is_primary_key,
+        is_boolean_like: matches!(family, ColumnTypeFamily::Boolean),
+        is_array,
+        enum_values,
+        identity_kind,
+        generated_kind,
+        generation_expression,
+        column_comment,
+        collation_name,
+        domain_name,
+        domain_schema,
+        domain_base_type,
+        array_dimensions,
+        element_raw_type,
+        character_maximum_length,
+        numeric_precision,
+        numeric_scale,
+    }
+}
+
+/// A row starting with `MYSQL_COLUMNS`.
+pub fn mysql_column(row: &MySqlRow) -> TableColumn {
+    let name: String = row.try_get(0).unwrap_or_default();
+    let data_type: String = row.try_get(1).unwrap_or_default();
+    let is_nullable: String = row.try_get(2).unwrap_or_default();
+    let default_value: Option<String> = row.try_get(3).ok();
+    let is_primary_key: i32 = row.try_get(4).unwrap_or(0);
+    let extra: String = row.try_get::<String, _>(5).unwrap_or_default().to_lowercase();
+    // The full declared type, e.g. `int unsigned` or `enum('a','b')`.
+    let column_type: Option<String> = row.try_get(6).ok();
+    let character_maximum_length: Option<i64> = row.try_get(7).ok().flatten();
+    let numeric_precision: Option<i64> = row.try_get(8).ok().flatten();
+    let numeric_scale: Option<i64> = row.try_get(9).ok().flatten();
+    let family = classify_mysql_type(&data_type);
+    let generated_kind = if extra.contains("stored generated") {
+        Some("stored".to_string())
+    } else if extra.contains("virtual generated") {
+        Some("virtual".to_string())
+    } else {
+        None
+    };
+
+    TableColumn {
+        name,
+        data_type: data_type.clone(),
+        raw_type: column_type.clone().or_else(|| Some(data_type.clone())),
+        normalized_type: normalize_type_name(&data_type),
+        type_family: family.clone(),
+        db_type: DatabaseType::MySQL,
+        is_nullable: is_nullable.to_uppercase() == "YES",
+        default_value,
+        is_primary_key: is_primary_key > 0,
+        is_boolean_like: matches!(family, ColumnTypeFamily::Boolean)
+            || column_type.as_deref().is_some_and(is_mysql_boolean_like),
+        is_array: false,
+        enum_values: column_type.as_deref().and_then(mysql_enum_values),
+        identity_kind: extra
+            .contains("auto_increment")
+            .then(|| "auto_increment".to_string()),
+        generated_kind,
+        generation_expression: None,
+        column_comment: None,
+        collation_name: None,
+        domain_name: None,
+        domain_schema: None,
+        domain_base_type: None,
+        array_dimensions: None,
+        element_raw_type: None,
+        // TEXT limits are in bytes, so only char/varchar lengths are kept.
+        character_maximum_length: matches!(data_type.to_lowercase().as_str(), "char" | "varchar")
+            .then_some(character_maximum_length)
+            .flatten(),
+        numeric_precision: (family == ColumnTypeFamily::Decimal).then_some(numeric_precision).flatten(),
+        numeric_scale: (family == ColumnTypeFamily::Decimal).then_some(numeric_scale).flatten(),
+    }
+}
+
+/// Sorts columns tagged with a request index into one list per request.
+fn by_request(columns: impl Iterator<Item = (usize, TableColumn)>, requests: usize) -> Vec<Vec<TableColumn>> {
+    let mut grouped = vec![Vec::new(); requests];
+    for (index, column) in columns {
+        if let Some(columns) = grouped.get_mut(index) {
+            columns.push(column);
+        }
+    }
+    grouped
+}
+
+fn sqlite_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Columns of each of `tables`, in one query when pragma functions are
+/// available and one `PRAGMA table_info` per table otherwise.
+pub async fn sqlite(pool: &SqlitePool, tables: &[TableRef], pragma_functions: bool) -> Result<Vec<Vec<TableColumn>>> {
+    if tables.is_empty() {
+        return Ok(Vec::new());
+    }
+    if !pragma_functions {
+        let mut structures = Vec::with_capacity(tables.len());
+        for table in tables {
+            let pragma = match &table.schema {
+                Some(schema) => format!("PRAGMA {}.table_info({})", sqlite_identifier(schema), sqlite_identifier(&table.table)),
+                None => format!("PRAGMA table_info({})", sqlite_identifier(&table.table)),
+            };
+            let rows = sqlx::query(&pragma).fetch_all(pool).await?;
+            structures.push(rows.iter().map(sqlite_column).collect());
+        }
+        return Ok(structures);
+    }
+    let requested = tables
+        .iter()
+        .enumerate()
+        .map(|(index, table)| {
+            let schema = table
+                .schema
+                .as_deref()
+                .map_or_else(|| "NULL".to_string(), |schema| quote_literal(schema, &DatabaseType::SQLite));
+            format!("({}, {}, {})", index, schema, quote_literal(&table.table, &DatabaseType::SQLite))
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let sql = format!(
+        "WITH req(request_index, schema_name, table_name) AS (VALUES {}) \
+         SELECT p.cid, p.name, p.type, p.\"notnull\", p.dflt_value, p.pk, req.request_index \
+         FROM req JOIN pragma_table_info(req.table_name, req.schema_name) p \
+         ORDER BY req.request_index, p.cid",
+        requested
+    );
+    let rows = sqlx::query(&sql).fetch_all(pool).await?;
+    Ok(by_request(
+        rows.iter()
+            .map(|row| (row.try_get::<i64, _>(6).unwrap_or_default() as usize, sqlite_column(row))),
+        tables.len(),
+    ))
+}
+
+/// Columns of each of `names`, resolved like `to_regclass` does, in one query.
+pub async fn postgres(pool: &PgPool, names: &[String]) -> Result<Vec<Vec<TableColumn>>> {
+    let rows = sqlx::query(POSTGRES_COLUMNS_SQL).bind(names).fetch_all(pool).await?;
+    Ok(by_request(
+        rows.iter()
+            .map(|row| (row.try_get::<i64, _>(24).unwrap_or_default() as usize, postgres_column(row))),
+        names.len(),
+    ))
+}
+
+/// Columns of each of `tables` in one query. Tables without a schema are
+/// looked up in the current database.
+pub async fn mysql(pool: &MySqlPool, tables: &[TableRef]) -> Result<Vec<Vec<TableColumn>>> {
+    if tables.is_empty() {
+        return Ok(Vec::new());
+    }
+    let conditions = tables
+        .iter()
+        .map(|table| {
+            let schema = table
+                .schema
+                .as_deref()
+                .map_or_else(|| "DATABASE()".to_string(), |schema| quote_literal(schema, &DatabaseType::MySQL));
+            format!(
+                "(c.table_schema = {} AND c.table_name = {})",
+                schema,
+                quote_literal(&table.table, &DatabaseType::MySQL)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" OR ");
+    let sql = format!(
+        "SELECT {}, CAST(c.TABLE_SCHEMA AS CHAR) AS table_schema, CAST(c.TABLE_NAME AS CHAR) AS table_name, \
+         DATABASE() AS current_schema \
+         FROM information_schema.columns c WHERE {} \
+         ORDER BY c.TABLE_SCHEMA, c.TABLE_NAME, c.ORDINAL_POSITION",
+        MYSQL_COLUMNS, conditions
+    );
+    let rows = sqlx::query(&sql).fetch_all(pool).await?;
+    let mut structures = vec![Vec::new(); tables.len()];
+    for row in &rows {
+        let schema: String = row.try_get(10).unwrap_or_default();
+        let name: String = row.try_get(11).unwrap_or_default();
+        let current: Option<String> = row.try_get(12).ok().flatten();
+        let column = mysql_column(row);
+        for (index, table) in tables.iter().enumerate() {
+            if table.table == name && table.schema.as_deref().or(current.as_deref()) == Some(schema.as_str()) {
+                structures[index].push(column.clone());
+            }
+        }
+    }
+    Ok(structures)
+}
@@ -0,0 +1,371 @@
+use super::checksum::fnv1a64;
+use super::{redaction, retry, top_queries};
+use crate::models::{TelemetrySettings, TelemetryStatus};
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Spans sent per OTLP request.
+const EXPORT_BATCH_SIZE: usize = 512;
+/// Finished spans held while the collector is slow or down; beyond this the
+/// oldest are dropped.
+const MAX_PENDING_SPANS: usize = 4096;
+/// How often pending spans are sent.
+const EXPORT_INTERVAL: Duration = Duration::from_secs(5);
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_SERVICE_NAME: &str = "nodadb";
+/// OTLP span kind for a call out to another service.
+const SPAN_KIND_CLIENT: u8 = 3;
+
+tokio::task_local! {
+    /// `traceparent` of the operation running on this task, set only when
+    /// executed SQL should carry it.
+    static TRACEPARENT: String;
+}
+
+/// A span that has started but not ended.
+pub struct ActiveSpan {
+    trace_id: String,
+    span_id: String,
+    started_at: SystemTime,
+    inject_traceparent: bool,
+}
+
+impl ActiveSpan {
+    /// The W3C `traceparent` value for this span, sampled.
+    pub fn traceparent(&self) -> String {
+        format!("00-{}-{}-01", self.trace_id, self.span_id)
+    }
+
+    /// Runs `work` with this span's `traceparent` available to `annotate`,
+    /// when the settings ask for it.
+    pub async fn scope<F: std::future::Future>(&self, work: F) -> F::Output {
+        if self.inject_traceparent {
+            TRACEPARENT.scope(self.traceparent(), work).await
+        } else {
+            work.await
+        }
+    }
+}
+
+/// `sql` behind a `/* traceparent='...' */` comment when it runs inside a
+/// traced operation that injects one, so server logs can be joined with
+/// client traces.
+pub fn annotate(sql: &str) -> Cow<'_, str> {
+    TRACEPARENT
+        .try_with(|traceparent| Cow::Owned(format!("/* traceparent='{}' */ {}", traceparent, sql)))
+        .unwrap_or(Cow::Borrowed(sql))
+}
+
+fn unix_nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos().to_string()
+}
+
+/// An OTLP string attribute. Every exported string goes through here, so
+/// redaction can't be skipped.
+fn string_attribute(key: &str, value: &str) -> Value {
+    json!({ "key": key, "value": { "stringValue": redaction::redact(value) } })
+}
+
+fn int_attribute(key: &str, value: u64) -> Value {
+    // OTLP/JSON writes 64-bit integers as strings.
+    json!({ "key": key, "value": { "intValue": value.to_string() } })
+}
+
+/// Hash of the statement's fingerprint, the same for statements that differ
+/// only in literals.
+pub fn statement_hash(sql: &str) -> String {
+    format!("{:016x}", fnv1a64(top_queries::fingerprint(sql).as_bytes()))
+}
+
+/// A finished span in OTLP/JSON form.
+pub fn span_json(
+    span: &ActiveSpan,
+    ended_at: SystemTime,
+    operation: &str,
+    connection_id: &str,
+    sql: Option<&str>,
+    include_sql: bool,
+    error: Option<&anyhow::Error>,
+) -> Value {
+    let duration = ended_at.duration_since(span.started_at).unwrap_or_default();
+    let mut attributes = vec![
+        string_attribute("db.operation.name", operation),
+        string_attribute("nodadb.connection_id", connection_id),
+        int_attribute("nodadb.duration_ms", duration.as_millis() as u64),
+    ];
+    if let Some(sql) = sql {
+        attributes.push(string_attribute("db.query.hash", &statement_hash(sql)));
+        if include_sql {
+            attributes.push(string_attribute("db.query.text", sql));
+        }
+    }
+    let status = match error {
+        Some(error) => {
+            attributes.push(string_attribute("error.type", retry::classify(error).unwrap_or("other")));
+            json!({ "code": 2, "message": redaction::redact(&error.to_string()) })
+        }
+        None => json!({ "code": 1 }),
+    };
+    json!({
+        "traceId": span.trace_id,
+        "spanId": span.span_id,
+        "name": operation,
+        "kind": SPAN_KIND_CLIENT,
+        "startTimeUnixNano": unix_nanos(span.started_at),
+        "endTimeUnixNano": unix_nanos(ended_at),
+        "attributes": attributes,
+        "status": status,
+    })
+}
+
+/// An OTLP/JSON `ExportTraceServiceRequest` for `spans`.
+pub fn export_request(service_name: &str, spans: Vec<Value>) -> Value {
+    json!({
+        "resourceSpans": [{
+            "resource": { "attributes": [string_attribute("service.name", service_name)] },
+            "scopeSpans": [{
+                "scope": { "name": "nodadb.database", "version": env!("CARGO_PKG_VERSION") },
+                "spans": spans,
+            }],
+        }],
+    })
+}
+
+/// Where spans are posted: the OTLP/HTTP traces path under `endpoint`.
+pub fn traces_url(endpoint: &str) -> String {
+    let endpoint = endpoint.trim_end_matches('/');
+    if endpoint.ends_with("/v1/traces") {
+        endpoint.to_string()
+    } else {
+        format!("{}/v1/traces", endpoint)
+    }
+}
+
+#[derive(Default)]
+struct ExportStats {
+    exported: u64,
+    dropped: u64,
+    last_error: Option<String>,
+    last_export_at: Option<String>,
+}
+
+/// Tracing settings, spans waiting to be exported and how exports went.
+#[derive(Default)]
+pub struct Telemetry {
+    settings: RwLock<Option<TelemetrySettings>>,
+    pending: Mutex<VecDeque<Value>>,
+    stats: Mutex<ExportStats>,
+    /// Bumped on every change of settings, so an older export loop stops.
+    generation: AtomicU64,
+    client: OnceLock<reqwest::Client>,
+}
+
+impl Telemetry {
+    fn enabled_settings(&self) -> Option<TelemetrySettings> {
+        self.settings
+            .read()
+            .ok()
+            .and_then(|settings| settings.clone())
+            .filter(|settings| settings.enabled)
+    }
+
+    /// Starts a span, or returns `None` while tracing is off.
+    pub fn start(&self) -> Option<ActiveSpan> {
+        let settings = self.enabled_settings()?;
+        Some(ActiveSpan {
+            trace_id: uuid::Uuid::new_v4().simple().to_string(),
+            span_id: uuid::Uuid::new_v4().simple().to_string()[..16].to_string(),
+            started_at: SystemTime::now(),
+            inject_traceparent: settings.inject_traceparent,
+        })
+    }
+
+    /// Ends `span` and queues it for export.
+    pub fn finish(
+        &self,
+        span: ActiveSpan,
+        operation: &str,
+        connection_id: &str,
+        sql: Option<&str>,
+        error: Option<&anyhow::Error>,
+    ) {
+        let include_sql = self.enabled_settings().is_some_and(|settings| settings.include_sql);
+        let span = span_json(&span, SystemTime::now(), operation, connection_id, sql, include_sql, error);
+        let Ok(mut pending) = self.pending.lock() else {
+            return;
+        };
+        if pending.len() >= MAX_PENDING_SPANS {
+            pending.pop_front();
+            if let Ok(mut stats) = self.stats.lock() {
+                stats.dropped += 1;
+            }
+        }
+        pending.push_back(span);
+    }
+
+    /// Replaces the settings; while enabled, spans are sent every few seconds.
+    pub fn configure(self: &Arc<Self>, settings: TelemetrySettings) -> Result<TelemetryStatus> {
+        let url = url::Url::parse(&settings.endpoint).map_err(|e| anyhow!("Invalid OTLP endpoint: {}", e))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(anyhow!("OTLP endpoint must be an http or https URL"));
+        }
+        let enabled = settings.enabled;
+        if let Ok(mut current) = self.settings.write() {
+            *current = Some(settings);
+        }
+        self.restart(enabled);
+        Ok(self.status())
+    }
+
+    /// Turns tracing on or off, keeping the configured endpoint.
+    pub fn set_enabled(self: &Arc<Self>, enabled: bool) -> Result<TelemetryStatus> {
+        {
+            let mut settings = self.settings.write().map_err(|_| anyhow!("Telemetry settings are unavailable"))?;
+            match settings.as_mut() {
+                Some(settings) => settings.enabled = enabled,
+                None if enabled => return Err(anyhow!("Configure an OTLP endpoint before enabling telemetry")),
+                None => {}
+            }
+        }
+        self.restart(enabled);
+        Ok(self.status())
+    }
+
+    /// Stops the running export loop after one last send, and starts a new
+    /// one when tracing is on.
+    fn restart(self: &Arc<Self>, enabled: bool) {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        if !enabled {
+            if let Ok(handle) = tokio::runtime::Handle::try_current() {
+                let telemetry = Arc::clone(self);
+                handle.spawn(async move { telemetry.flush().await });
+            }
+            return;
+        }
+        let telemetry = Arc::clone(self);
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            handle.spawn(async move {
+                while telemetry.generation.load(Ordering::SeqCst) == generation {
+                    tokio::time::sleep(EXPORT_INTERVAL).await;
+                    telemetry.flush().await;
+                }
+            });
+        }
+    }
+
+    /// Sends pending spans to the collector. Spans from a failed send are
+    /// dropped rather than retried, so a dead collector can't grow memory.
+    pub async fn flush(&self) {
+        let Some(settings) = self.settings.read().ok().and_then(|settings| settings.clone()) else {
+            return;
+        };
+        loop {
+            let batch = match self.pending.lock() {
+                Ok(mut pending) => {
+                    let count = pending.len().min(EXPORT_BATCH_SIZE);
+                    pending.drain(..count).collect::<Vec<_>>()
+                }
+                Err(_) => return,
+            };
+            if batch.is_empty() {
+                return;
+            }
+            let count = batch.len() as u64;
+            let result = self.send(&settings, batch).await;
+            let Ok(mut stats) = self.stats.lock() else {
+                return;
+            };
+            match result {
+                Ok(()) => {
+                    stats.exported += count;
+                    stats.last_error = None;
+                    stats.last_export_at = Some(chrono::Utc::now().to_rfc3339());
+                }
+                Err(error) => {
+                    stats.dropped += count;
+                    stats.last_error = Some(redaction::redact(&error.to_string()));
+                    return;
+                }
+            }
+        }
+    }
+
+    async fn send(&self, settings: &TelemetrySettings, spans: Vec<Value>) -> Result<()> {
+        let client = self.client.get_or_init(|| {
+            reqwest::Client::builder()
+                .timeout(EXPORT_TIMEOUT)
+                .build()
+                .unwrap_or_default()
+        });
+        let service_name = settings.service_name.as_deref().unwrap_or(DEFAULT_SERVICE_NAME);
+        let mut request = client
+            .post(traces_url(&settings.endpoint))
+            .header("content-type", "application/json")
+            .body(export_request(service_name, spans).to_string());
+        for (name, value) in &settings.headers {
+            request = request.header(name, value);
+        }
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("Collector answered {}", response.status()));
+        }
+        Ok(())
+    }
+
+    pub fn status(&self) -> TelemetryStatus {
+        let settings = self.settings.read().ok().and_then(|settings| settings.clone());
+        let pending_spans = self.pending.lock().map(|pending| pending.len()).unwrap_or(0);
+        let stats = self.stats.lock().ok();
+        let stats = stats.as_deref();
+        TelemetryStatus {
+            enabled: settings.as_ref().is_some_and(|settings| settings.enabled),
+            endpoint: settings.as_ref().map(|settings| redaction::redact(&settings.endpoint)),
+            include_sql: settings.as_ref().is_some_and(|settings| settings.include_sql),
+            inject_traceparent: settings.as_ref().is_some_and(|settings| settings.inject_traceparent),
+            pending_spans,
+            exported_spans: stats.map_or(0, |stats| stats.exported),
+            dropped_spans: stats.map_or(0, |stats| stats.dropped),
+            last_error: stats.and_then(|stats| stats.last_error.clone()),
+            last_export_at: stats.and_then(|stats| stats.last_export_at.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn spans_carry_a_hash_and_redacted_attributes() {
+        let span = ActiveSpan {
+            trace_id: "0af7651916cd43dd8448eb211c80319c".to_string(),
+            span_id: "b7ad6b7169203331".to_string(),
+            started_at: UNIX_EPOCH + Duration::from_millis(1_000),
+            inject_traceparent: true,
+        };
+        assert_eq!(span.traceparent(), "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01");
+        assert_eq!(annotate("SELECT 1"), "SELECT 1");
+        let annotated = span.scope(async { annotate("SELECT 1").into_owned() }).await;
+        assert_eq!(annotated, "/* traceparent='00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01' */ SELECT 1");
+
+        let sql = "ALTER ROLE app PASSWORD 'hunter2'";
+        let error = anyhow!("could not reach postgres://app:hunter2@db/prod");
+        let ended = UNIX_EPOCH + Duration::from_millis(1_250);
+        let hidden = span_json(&span, ended, "execute_query", "staging", Some(sql), false, Some(&error));
+        let shown = span_json(&span, ended, "execute_query", "staging", Some(sql), true, None);
+        let text = |value: &Value| value.to_string();
+        assert!(!text(&hidden).contains("ALTER ROLE"));
+        assert!(!text(&hidden).contains("hunter2"));
+        assert!(text(&hidden).contains(&statement_hash("ALTER ROLE app PASSWORD 'other'")));
+        assert_eq!(hidden["status"]["code"], 2);
+        assert_eq!(hidden["endTimeUnixNano"], "1250000000");
+        assert!(text(&shown).contains("ALTER ROLE app PASSWORD '***'"));
+        assert!(!text(&shown).contains("hunter2"));
+        assert_eq!(traces_url("http://localhost:4318/"), "http://localhost:4318/v1/traces");
+    }
+}
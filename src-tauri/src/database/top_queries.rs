@@ -0,0 +1,213 @@
+use crate::models::{StatementStatsUnavailable, TopQuery, TopQueryOrder};
+use std::collections::HashMap;
+
+pub const SOURCE_PG_STAT_STATEMENTS: &str = "pg_stat_statements";
+pub const SOURCE_PERFORMANCE_SCHEMA: &str = "performance_schema";
+pub const SOURCE_QUERY_LOG: &str = "query_log";
+
+/// Default number of statements in the top-queries report.
+pub const DEFAULT_TOP_QUERY_LIMIT: u32 = 50;
+/// Distinct statement fingerprints tracked per connection in the app-local stats.
+pub const MAX_TRACKED_STATEMENTS: usize = 1000;
+
+const PG_STAT_STATEMENTS_SETUP: &str = "Add pg_stat_statements to shared_preload_libraries in postgresql.conf, restart the server, then run CREATE EXTENSION pg_stat_statements.";
+const PG_STATS_PERMISSION: &str = "Ask an administrator to GRANT pg_read_all_stats to your role.";
+const PERFORMANCE_SCHEMA_SETUP: &str =
+    "Set performance_schema = ON in the [mysqld] section of my.cnf and restart the server.";
+const DIGEST_CONSUMER_SETUP: &str = "Run UPDATE performance_schema.setup_consumers SET ENABLED = 'YES' WHERE NAME = 'statements_digest'.";
+const MYSQL_STATS_PERMISSION: &str =
+    "Ask an administrator to GRANT SELECT ON performance_schema.* to your user.";
+
+/// Calls, time and rows aggregated for one statement fingerprint.
+#[derive(Debug, Clone, Default)]
+pub struct StatementStats {
+    pub sample: String,
+    pub calls: u64,
+    pub total_ms: f64,
+    pub rows: u64,
+}
+
+fn unavailable(kind: &str, message: &str, instructions: &str) -> StatementStatsUnavailable {
+    StatementStatsUnavailable {
+        kind: kind.to_string(),
+        message: message.to_string(),
+        instructions: instructions.to_string(),
+    }
+}
+
+pub fn pg_stat_statements_missing() -> StatementStatsUnavailable {
+    unavailable(
+        "extension_missing",
+        "The pg_stat_statements extension is not installed.",
+        PG_STAT_STATEMENTS_SETUP,
+    )
+}
+
+pub fn performance_schema_disabled() -> StatementStatsUnavailable {
+    unavailable(
+        "instrumentation_disabled",
+        "performance_schema is disabled on this server.",
+        PERFORMANCE_SCHEMA_SETUP,
+    )
+}
+
+pub fn digest_consumer_disabled() -> StatementStatsUnavailable {
+    unavailable(
+        "instrumentation_disabled",
+        "The statements_digest consumer is disabled.",
+        DIGEST_CONSUMER_SETUP,
+    )
+}
+
+/// Explains a database error raised while reading statement statistics, or
+/// `None` when it isn't a setup or permission problem.
+pub fn unavailable_for_error(sqlstate: &str, message: &str) -> Option<StatementStatsUnavailable> {
+    let lowered = message.to_lowercase();
+    if lowered.contains("shared_preload_libraries") {
+        return Some(unavailable("not_loaded", message, PG_STAT_STATEMENTS_SETUP));
+    }
+    if sqlstate == "42501" {
+        return Some(unavailable(
+            "permission_denied",
+            message,
+            PG_STATS_PERMISSION,
+        ));
+    }
+    if lowered.contains("command denied") || lowered.contains("access denied") {
+        return Some(unavailable(
+            "permission_denied",
+            message,
+            MYSQL_STATS_PERMISSION,
+        ));
+    }
+    None
+}
+
+/// Normalizes `sql` so statements differing only in literals group together:
+/// string and numeric literals become `?`, whitespace collapses and case folds.
+pub fn fingerprint(sql: &str) -> String {
+    let mut out = String::with_capacity(sql.len());
+    let mut chars = sql.trim().trim_end_matches(';').chars().peekable();
+    let mut previous_is_word = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                while let Some(next) = chars.next() {
+                    if next == '\'' {
+                        if chars.peek() == Some(&'\'') {
+                            chars.next();
+                            continue;
+                        }
+                        break;
+                    }
+                }
+                out.push('?');
+                previous_is_word = false;
+            }
+            c if c.is_ascii_digit() && !previous_is_word => {
+                while chars
+                    .peek()
+                    .is_some_and(|next| next.is_ascii_alphanumeric() || *next == '.')
+                {
+                    chars.next();
+                }
+                out.push('?');
+                previous_is_word = false;
+            }
+            c if c.is_whitespace() => {
+                while chars.peek().is_some_and(|next| next.is_whitespace()) {
+                    chars.next();
+                }
+                out.push(' ');
+                previous_is_word = false;
+            }
+            c => {
+                previous_is_word = c.is_alphanumeric() || c == '_';
+                out.extend(c.to_lowercase());
+            }
+        }
+    }
+    out
+}
+
+/// Adds one execution of `sql` to `stats`. New fingerprints are dropped once
+/// `MAX_TRACKED_STATEMENTS` are tracked.
+pub fn record(stats: &mut HashMap<String, StatementStats>, sql: &str, duration_ms: f64, rows: u64) {
+    let key = fingerprint(sql);
+    if !stats.contains_key(&key) && stats.len() >= MAX_TRACKED_STATEMENTS {
+        return;
+    }
+    let entry = stats.entry(key).or_insert_with(|| StatementStats {
+        sample: sql.trim().to_string(),
+        ..Default::default()
+    });
+    entry.calls += 1;
+    entry.total_ms += duration_ms;
+    entry.rows += rows;
+}
+
+/// The `limit` most expensive fingerprints in `stats` by `order`.
+pub fn top(
+    stats: &HashMap<String, StatementStats>,
+    order: TopQueryOrder,
+    limit: usize,
+) -> Vec<TopQuery> {
+    let mut queries: Vec<TopQuery> = stats
+        .iter()
+        .map(|(fingerprint, stats)| TopQuery {
+            fingerprint: Some(fingerprint.clone()),
+            query: stats.sample.clone(),
+            calls: stats.calls as i64,
+            total_time_ms: stats.total_ms,
+            mean_time_ms: stats.total_ms / stats.calls.max(1) as f64,
+            rows: stats.rows as i64,
+            cache_hit_ratio: None,
+        })
+        .collect();
+    queries.sort_by(|a, b| {
+        let (a, b) = match order {
+            TopQueryOrder::TotalTime => (a.total_time_ms, b.total_time_ms),
+            TopQueryOrder::MeanTime => (a.mean_time_ms, b.mean_time_ms),
+            TopQueryOrder::Calls => (a.calls as f64, b.calls as f64),
+        };
+        b.total_cmp(&a)
+    });
+    queries.truncate(limit);
+    queries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fingerprints_ignore_literals_and_layout() {
+        assert_eq!(
+            fingerprint("SELECT * FROM users WHERE id = 42 AND name = 'O''Brien';"),
+            "select * from users where id = ? and name = ?"
+        );
+        assert_eq!(
+            fingerprint("select *\n  from users where id = 7 and name = 'x'"),
+            "select * from users where id = ? and name = ?"
+        );
+        assert_eq!(fingerprint("SELECT col1 FROM t2"), "select col1 from t2");
+    }
+
+    #[test]
+    fn aggregates_and_orders_by_requested_metric() {
+        let mut stats = HashMap::new();
+        record(&mut stats, "SELECT * FROM a WHERE id = 1", 10.0, 1);
+        record(&mut stats, "SELECT * FROM a WHERE id = 2", 10.0, 1);
+        record(&mut stats, "SELECT * FROM a WHERE id = 3", 10.0, 1);
+        record(&mut stats, "SELECT count(*) FROM b", 25.0, 1);
+
+        let by_total = top(&stats, TopQueryOrder::TotalTime, 10);
+        assert_eq!(by_total[0].calls, 3);
+        assert_eq!(by_total[0].total_time_ms, 30.0);
+        assert_eq!(by_total[0].query, "SELECT * FROM a WHERE id = 1");
+
+        let by_mean = top(&stats, TopQueryOrder::MeanTime, 1);
+        assert_eq!(by_mean.len(), 1);
+        assert_eq!(by_mean[0].mean_time_ms, 25.0);
+    }
+}
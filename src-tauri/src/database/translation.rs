@@ -0,0 +1,548 @@
+use super::query_patterns;
+use crate::models::{DatabaseType, SqlSpan, TranslatedSql, UntranslatedConstruct};
+use anyhow::Result;
+use sqlparser::ast::{
+    BinaryLength, CharacterLength, ColumnDef, ColumnOption, ColumnOptionDef, DataType, Expr, Function, FunctionArg,
+    FunctionArgExpr, FunctionArguments, Ident, LimitClause, ObjectName, Offset, OffsetRows, Query,
+    Spanned, Statement, TimezoneInfo, Value, ValueWithSpan, VisitMut, VisitorMut,
+};
+use sqlparser::keywords::Keyword;
+use sqlparser::tokenizer::{Span, Token};
+use std::collections::BTreeSet;
+use std::ops::ControlFlow;
+
+pub const RULE_IDENTIFIER_QUOTES: &str = "identifier_quotes";
+pub const RULE_LIMIT_OFFSET: &str = "limit_offset";
+pub const RULE_BOOLEAN_LITERALS: &str = "boolean_literals";
+pub const RULE_FUNCTIONS: &str = "functions";
+pub const RULE_DDL_TYPES: &str = "ddl_types";
+pub const RULE_AUTO_INCREMENT: &str = "auto_increment";
+
+/// A function call renamed outright, from `from` (any dialect when `None`)
+/// to `to`.
+struct FunctionRule {
+    name: &'static str,
+    from: Option<DatabaseType>,
+    to: DatabaseType,
+    replacement: &'static str,
+    /// The replacement is a keyword written without parentheses, such as
+    /// `CURRENT_TIMESTAMP`; only calls without arguments, or with just
+    /// `'now'`, are rewritten.
+    bare: bool,
+}
+
+const FUNCTION_RULES: &[FunctionRule] = &[
+    FunctionRule { name: "ifnull", from: None, to: DatabaseType::PostgreSQL, replacement: "COALESCE", bare: false },
+    FunctionRule { name: "nvl", from: None, to: DatabaseType::PostgreSQL, replacement: "COALESCE", bare: false },
+    FunctionRule { name: "char_length", from: None, to: DatabaseType::SQLite, replacement: "LENGTH", bare: false },
+    FunctionRule { name: "rand", from: None, to: DatabaseType::PostgreSQL, replacement: "RANDOM", bare: false },
+    FunctionRule {
+        name: "random",
+        from: Some(DatabaseType::PostgreSQL),
+        to: DatabaseType::MySQL,
+        replacement: "RAND",
+        bare: false,
+    },
+    FunctionRule { name: "now", from: None, to: DatabaseType::SQLite, replacement: "CURRENT_TIMESTAMP", bare: true },
+    FunctionRule {
+        name: "datetime",
+        from: Some(DatabaseType::SQLite),
+        to: DatabaseType::PostgreSQL,
+        replacement: "CURRENT_TIMESTAMP",
+        bare: true,
+    },
+    FunctionRule {
+        name: "datetime",
+        from: Some(DatabaseType::SQLite),
+        to: DatabaseType::MySQL,
+        replacement: "CURRENT_TIMESTAMP",
+        bare: true,
+    },
+];
+
+/// Functions only some dialects have, with no mechanical equivalent
+/// elsewhere; calls are kept and flagged.
+const NATIVE_FUNCTIONS: &[(&str, &[DatabaseType])] = &[
+    ("strftime", &[DatabaseType::SQLite]),
+    ("julianday", &[DatabaseType::SQLite]),
+    ("datetime", &[DatabaseType::SQLite]),
+    ("date_trunc", &[DatabaseType::PostgreSQL]),
+    ("to_char", &[DatabaseType::PostgreSQL]),
+    ("string_agg", &[DatabaseType::PostgreSQL]),
+    ("date_format", &[DatabaseType::MySQL]),
+    ("group_concat", &[DatabaseType::SQLite, DatabaseType::MySQL]),
+];
+
+fn dialect_name(db_type: &DatabaseType) -> &'static str {
+    match db_type {
+        DatabaseType::SQLite => "SQLite",
+        DatabaseType::PostgreSQL => "PostgreSQL",
+        DatabaseType::MySQL => "MySQL",
+        DatabaseType::MsSql => "SQL Server",
+    }
+}
+
+fn sql_span(span: Span) -> Option<SqlSpan> {
+    (span.start.line > 0).then_some(SqlSpan {
+        line: span.start.line,
+        column: span.start.column,
+        end_line: span.end.line,
+        end_column: span.end.column,
+    })
+}
+
+fn is_auto_increment_token(option: &ColumnOption) -> bool {
+    matches!(
+        option,
+        ColumnOption::DialectSpecific(tokens)
+            if tokens.iter().any(|token| matches!(
+                token,
+                Token::Word(word) if matches!(word.keyword, Keyword::AUTOINCREMENT | Keyword::AUTO_INCREMENT)
+            ))
+    )
+}
+
+fn serial_width(data_type: &DataType) -> Option<&'static str> {
+    let DataType::Custom(name, modifiers) = data_type else {
+        return None;
+    };
+    if !modifiers.is_empty() {
+        return None;
+    }
+    match name.to_string().to_lowercase().as_str() {
+        "serial" | "smallserial" | "serial4" | "serial2" => Some("SERIAL"),
+        "bigserial" | "serial8" => Some("BIGSERIAL"),
+        _ => None,
+    }
+}
+
+fn is_big_integer(data_type: &DataType) -> bool {
+    matches!(data_type, DataType::BigInt(_) | DataType::Int8(_) | DataType::BigIntUnsigned(_))
+}
+
+fn is_unsigned(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::TinyIntUnsigned(_)
+            | DataType::SmallIntUnsigned(_)
+            | DataType::MediumIntUnsigned(_)
+            | DataType::IntUnsigned(_)
+            | DataType::IntegerUnsigned(_)
+            | DataType::Int4Unsigned(_)
+            | DataType::BigIntUnsigned(_)
+            | DataType::DecimalUnsigned(_)
+            | DataType::FloatUnsigned(_)
+            | DataType::DoubleUnsigned(_)
+    )
+}
+
+/// The column type `to` should get: `Some(Ok)` for a mechanical rewrite,
+/// `Some(Err)` with the reason when it can't be mapped without a guess,
+/// `None` when it stays as written.
+fn ddl_type(data_type: &DataType, to: &DatabaseType) -> Option<Result<DataType, &'static str>> {
+    if matches!(data_type, DataType::Enum(..) | DataType::Set(_)) && *to != DatabaseType::MySQL {
+        return Some(Err("ENUM and SET columns need a lookup table or CHECK constraint written by hand"));
+    }
+    match to {
+        DatabaseType::PostgreSQL => Some(Ok(match data_type {
+            DataType::TinyInt(Some(1)) => return Some(Err("TINYINT(1) may hold a boolean or a small number")),
+            _ if is_unsigned(data_type) => {
+                return Some(Err("PostgreSQL has no unsigned types; pick a wider signed type"))
+            }
+            DataType::Datetime(precision) => DataType::Timestamp(*precision, TimezoneInfo::None),
+            DataType::TinyInt(_) => DataType::SmallInt(None),
+            DataType::MediumInt(_) => DataType::Integer(None),
+            DataType::Int(Some(_)) | DataType::Integer(Some(_)) => DataType::Integer(None),
+            DataType::SmallInt(Some(_)) => DataType::SmallInt(None),
+            DataType::BigInt(Some(_)) => DataType::BigInt(None),
+            DataType::Double(_) => DataType::DoublePrecision,
+            DataType::Blob(_) | DataType::TinyBlob | DataType::MediumBlob | DataType::LongBlob => DataType::Bytea,
+            DataType::TinyText | DataType::MediumText | DataType::LongText => DataType::Text,
+            _ => return None,
+        })),
+        DatabaseType::MySQL => Some(Ok(match data_type {
+            DataType::Timestamp(_, TimezoneInfo::WithTimeZone | TimezoneInfo::Tz) => {
+                return Some(Err("MySQL has no time zone-aware timestamp type"))
+            }
+            DataType::Bytea => DataType::LongBlob,
+            DataType::JSONB => DataType::JSON,
+            DataType::Uuid => DataType::Char(Some(CharacterLength::IntegerLength { length: 36, unit: None })),
+            _ => return None,
+        })),
+        // SQLite takes any type name, so only the auto-increment rule
+        // touches its columns.
+        DatabaseType::SQLite => None,
+        DatabaseType::MsSql => Some(Ok(match data_type {
+            DataType::Boolean => DataType::Bit(None),
+            DataType::Datetime(_) | DataType::Timestamp(_, TimezoneInfo::None) => {
+                DataType::Custom(ObjectName::from(vec![Ident::new("DATETIME2")]), vec![])
+            }
+            DataType::Bytea => DataType::Varbinary(Some(BinaryLength::Max)),
+            DataType::Text | DataType::TinyText | DataType::MediumText | DataType::LongText => {
+                DataType::Nvarchar(Some(CharacterLength::Max))
+            }
+            DataType::JSON | DataType::JSONB => DataType::Nvarchar(Some(CharacterLength::Max)),
+            DataType::Uuid => DataType::Custom(ObjectName::from(vec![Ident::new("UNIQUEIDENTIFIER")]), vec![]),
+            _ => return None,
+        })),
+    }
+}
+
+struct Translator {
+    from: DatabaseType,
+    to: DatabaseType,
+    applied: BTreeSet<&'static str>,
+    untranslated: Vec<UntranslatedConstruct>,
+}
+
+impl Translator {
+    fn flag(&mut self, construct: String, message: impl Into<String>, span: Span) {
+        self.untranslated.push(UntranslatedConstruct {
+            construct,
+            message: message.into(),
+            span: sql_span(span),
+        });
+    }
+
+    fn quote(&self) -> char {
+        if self.to == DatabaseType::MySQL {
+            '`'
+        } else {
+            '"'
+        }
+    }
+
+    fn function(&mut self, function: &mut Function) {
+        let name = function.name.to_string().to_lowercase();
+        let rule = FUNCTION_RULES
+            .iter()
+            .find(|rule| rule.name == name && rule.to == self.to && rule.from.as_ref().is_none_or(|from| *from == self.from));
+        if let Some(rule) = rule {
+            if !rule.bare || only_now_arguments(&function.args) {
+                function.name = ObjectName::from(vec![Ident::new(rule.replacement)]);
+                if rule.bare {
+                    function.args = FunctionArguments::None;
+                }
+                self.applied.insert(RULE_FUNCTIONS);
+                return;
+            }
+        }
+        let native = NATIVE_FUNCTIONS.iter().find(|(native, _)| *native == name);
+        if let Some((_, dialects)) = native.filter(|(_, dialects)| !dialects.contains(&self.to)) {
+            let owners = dialects.iter().map(dialect_name).collect::<Vec<_>>().join(" and ");
+            self.flag(
+                function.to_string(),
+                format!(
+                    "{} is specific to {} and has no direct {} equivalent; rewrite it by hand",
+                    name,
+                    owners,
+                    dialect_name(&self.to)
+                ),
+                function.span(),
+            );
+        }
+    }
+
+    fn column(&mut self, column: &mut ColumnDef) {
+        let serial = serial_width(&column.data_type);
+        let auto = serial.is_some() || column.options.iter().any(|option| is_auto_increment_token(&option.option));
+        if auto {
+            self.auto_increment(column, serial);
+        } else if let Some(mapped) = ddl_type(&column.data_type, &self.to) {
+            match mapped {
+                Ok(data_type) => {
+                    column.data_type = data_type;
+                    self.applied.insert(RULE_DDL_TYPES);
+                }
+                Err(message) => self.flag(column.to_string(), message, column.span()),
+            }
+        }
+    }
+
+    fn auto_increment(&mut self, column: &mut ColumnDef, serial: Option<&'static str>) {
+        let big = serial == Some("BIGSERIAL") || is_big_integer(&column.data_type);
+        match self.to {
+            DatabaseType::PostgreSQL => {
+                if serial.is_some() {
+                    return;
+                }
+                column.options.retain(|option| !is_auto_increment_token(&option.option));
+                column.data_type =
+                    DataType::Custom(ObjectName::from(vec![Ident::new(if big { "BIGSERIAL" } else { "SERIAL" })]), vec![]);
+            }
+            DatabaseType::MySQL => {
+                column.options.retain(|option| !is_auto_increment_token(&option.option));
+                if serial.is_some() {
+                    column.data_type = if big { DataType::BigInt(None) } else { DataType::Int(None) };
+                }
+                column.options.push(auto_increment_option("AUTO_INCREMENT"));
+            }
+            DatabaseType::SQLite => {
+                let Some(primary_key) = column
+                    .options
+                    .iter()
+                    .position(|option| matches!(option.option, ColumnOption::PrimaryKey(_)))
+                else {
+                    self.flag(
+                        column.to_string(),
+                        "SQLite only auto-increments an INTEGER PRIMARY KEY column",
+                        column.span(),
+                    );
+                    return;
+                };
+                column.options.retain(|option| !is_auto_increment_token(&option.option));
+                column.data_type = DataType::Integer(None);
+                // AUTOINCREMENT has to follow PRIMARY KEY directly.
+                column.options.insert(primary_key + 1, auto_increment_option("AUTOINCREMENT"));
+            }
+            DatabaseType::MsSql => {
+                column.options.retain(|option| !is_auto_increment_token(&option.option));
+                if serial.is_some() {
+                    column.data_type = if big { DataType::BigInt(None) } else { DataType::Int(None) };
+                }
+                column.options.push(auto_increment_option("IDENTITY"));
+            }
+        }
+        self.applied.insert(RULE_AUTO_INCREMENT);
+    }
+}
+
+fn auto_increment_option(keyword: &str) -> ColumnOptionDef {
+    ColumnOptionDef {
+        name: None,
+        option: ColumnOption::DialectSpecific(vec![Token::make_keyword(keyword)]),
+    }
+}
+
+fn only_now_arguments(args: &FunctionArguments) -> bool {
+    match args {
+        FunctionArguments::None => true,
+        FunctionArguments::List(list) => list.args.iter().all(|arg| {
+            matches!(
+                arg,
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(Expr::Value(ValueWithSpan {
+                    value: Value::SingleQuotedString(text),
+                    ..
+                }))) if text.eq_ignore_ascii_case("now")
+            )
+        }),
+        FunctionArguments::Subquery(_) => false,
+    }
+}
+
+impl VisitorMut for Translator {
+    type Break = ();
+
+    fn post_visit_ident(&mut self, ident: &mut Ident) -> ControlFlow<()> {
+        let quote = self.quote();
+        if matches!(ident.quote_style, Some('"' | '`' | '[')) && ident.quote_style != Some(quote) {
+            ident.quote_style = Some(quote);
+            self.applied.insert(RULE_IDENTIFIER_QUOTES);
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_value(&mut self, value: &mut ValueWithSpan) -> ControlFlow<()> {
+        match &value.value {
+            // MySQL reads "text" as a string; everywhere else it's a name.
+            Value::DoubleQuotedString(text) if self.to != DatabaseType::MySQL => {
+                value.value = Value::SingleQuotedString(text.clone());
+                self.applied.insert(RULE_IDENTIFIER_QUOTES);
+            }
+            Value::Boolean(flag) if self.to == DatabaseType::SQLite => {
+                value.value = Value::Number(if *flag { "1" } else { "0" }.to_string(), false);
+                self.applied.insert(RULE_BOOLEAN_LITERALS);
+            }
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_expr(&mut self, expr: &mut Expr) -> ControlFlow<()> {
+        match expr {
+            Expr::Function(function) => self.function(function),
+            // SQLite has only the comma form.
+            Expr::Substring { special, .. } if self.to == DatabaseType::SQLite && !*special => {
+                *special = true;
+                self.applied.insert(RULE_FUNCTIONS);
+            }
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_query(&mut self, query: &mut Query) -> ControlFlow<()> {
+        if let Some(LimitClause::OffsetCommaLimit { offset, limit }) = &query.limit_clause {
+            query.limit_clause = Some(LimitClause::LimitOffset {
+                limit: Some(limit.clone()),
+                offset: Some(Offset { value: offset.clone(), rows: OffsetRows::None }),
+                limit_by: vec![],
+            });
+            self.applied.insert(RULE_LIMIT_OFFSET);
+        }
+        if self.to == DatabaseType::PostgreSQL {
+            return ControlFlow::Continue(());
+        }
+        if let Some(LimitClause::LimitOffset { offset: Some(offset), .. }) = &mut query.limit_clause {
+            if offset.rows != OffsetRows::None {
+                offset.rows = OffsetRows::None;
+                self.applied.insert(RULE_LIMIT_OFFSET);
+            }
+        }
+        let Some(fetch) = query.fetch.take() else {
+            return ControlFlow::Continue(());
+        };
+        if fetch.with_ties || fetch.percent {
+            let message = format!("{} has no FETCH ... PERCENT or WITH TIES", dialect_name(&self.to));
+            self.flag(fetch.to_string(), message, query.span());
+            query.fetch = Some(fetch);
+            return ControlFlow::Continue(());
+        }
+        let offset = match query.limit_clause.take() {
+            Some(LimitClause::LimitOffset { offset, .. }) => offset,
+            _ => None,
+        };
+        query.limit_clause = Some(LimitClause::LimitOffset {
+            limit: fetch.quantity.or_else(|| Some(Expr::value(Value::Number("1".to_string(), false)))),
+            offset,
+            limit_by: vec![],
+        });
+        self.applied.insert(RULE_LIMIT_OFFSET);
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_statement(&mut self, statement: &mut Statement) -> ControlFlow<()> {
+        if let Statement::CreateTable(create) = statement {
+            for column in &mut create.columns {
+                self.column(column);
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Rewrites `sql` from one dialect to another with the mechanical rules
+/// above. Anything that would need a guess is left as written and listed in
+/// `untranslated`. The output is re-rendered from the parse tree, so
+/// whitespace and comments aren't kept.
+pub fn translate(sql: &str, from: &DatabaseType, to: &DatabaseType) -> Result<TranslatedSql> {
+    let mut statements = query_patterns::parse(sql, from)?;
+    let mut translator = Translator {
+        from: from.clone(),
+        to: to.clone(),
+        applied: BTreeSet::new(),
+        untranslated: Vec::new(),
+    };
+    if from != to {
+        let _ = statements.visit(&mut translator);
+    }
+    Ok(TranslatedSql {
+        sql: statements.iter().map(ToString::to_string).collect::<Vec<_>>().join(";\n"),
+        applied_rules: translator.applied.into_iter().map(str::to_string).collect(),
+        untranslated: translator.untranslated,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use DatabaseType::{MySQL, PostgreSQL, SQLite};
+
+    /// (rule, from, to, input, expected output)
+    const SNAPSHOTS: &[(&str, DatabaseType, DatabaseType, &str, &str)] = &[
+        (
+            RULE_IDENTIFIER_QUOTES,
+            MySQL,
+            PostgreSQL,
+            "SELECT `id`, \"x\" FROM `users`",
+            "SELECT \"id\", 'x' FROM \"users\"",
+        ),
+        (RULE_IDENTIFIER_QUOTES, SQLite, MySQL, "SELECT \"id\" FROM \"users\"", "SELECT `id` FROM `users`"),
+        (RULE_LIMIT_OFFSET, MySQL, PostgreSQL, "SELECT id FROM t LIMIT 20, 10", "SELECT id FROM t LIMIT 10 OFFSET 20"),
+        (
+            RULE_LIMIT_OFFSET,
+            PostgreSQL,
+            SQLite,
+            "SELECT id FROM t ORDER BY id OFFSET 5 ROWS FETCH FIRST 10 ROWS ONLY",
+            "SELECT id FROM t ORDER BY id LIMIT 10 OFFSET 5",
+        ),
+        (RULE_BOOLEAN_LITERALS, PostgreSQL, SQLite, "SELECT id FROM t WHERE active = true", "SELECT id FROM t WHERE active = 1"),
+        (RULE_FUNCTIONS, SQLite, PostgreSQL, "SELECT ifnull(name, '-') FROM t", "SELECT COALESCE(name, '-') FROM t"),
+        (RULE_FUNCTIONS, PostgreSQL, SQLite, "SELECT now()", "SELECT CURRENT_TIMESTAMP"),
+        (RULE_FUNCTIONS, SQLite, PostgreSQL, "SELECT datetime('now')", "SELECT CURRENT_TIMESTAMP"),
+        (
+            RULE_FUNCTIONS,
+            PostgreSQL,
+            SQLite,
+            "SELECT SUBSTRING(name FROM 2 FOR 3) FROM t",
+            "SELECT SUBSTRING(name, 2, 3) FROM t",
+        ),
+        (
+            RULE_DDL_TYPES,
+            MySQL,
+            PostgreSQL,
+            "CREATE TABLE t (a DATETIME, b DOUBLE, c LONGTEXT, d BLOB, e INT(11))",
+            "CREATE TABLE t (a TIMESTAMP, b DOUBLE PRECISION, c TEXT, d BYTEA, e INTEGER)",
+        ),
+        (
+            RULE_DDL_TYPES,
+            PostgreSQL,
+            MySQL,
+            "CREATE TABLE t (a BYTEA, b JSONB, c UUID)",
+            "CREATE TABLE t (a LONGBLOB, b JSON, c CHAR(36))",
+        ),
+        (
+            RULE_AUTO_INCREMENT,
+            SQLite,
+            PostgreSQL,
+            "CREATE TABLE t (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT)",
+            "CREATE TABLE t (id SERIAL PRIMARY KEY, name TEXT)",
+        ),
+        (
+            RULE_AUTO_INCREMENT,
+            PostgreSQL,
+            SQLite,
+            "CREATE TABLE t (id BIGSERIAL PRIMARY KEY)",
+            "CREATE TABLE t (id INTEGER PRIMARY KEY AUTOINCREMENT)",
+        ),
+        (
+            RULE_AUTO_INCREMENT,
+            PostgreSQL,
+            MySQL,
+            "CREATE TABLE t (id BIGSERIAL PRIMARY KEY)",
+            "CREATE TABLE t (id BIGINT PRIMARY KEY AUTO_INCREMENT)",
+        ),
+    ];
+
+    #[test]
+    fn each_rule_matches_its_snapshot() {
+        for (rule, from, to, input, expected) in SNAPSHOTS {
+            let translated = translate(input, from, to).unwrap();
+            assert_eq!(translated.sql, *expected, "{} {:?} -> {:?}", rule, from, to);
+            assert_eq!(translated.applied_rules, vec![rule.to_string()], "{}", input);
+            assert!(translated.untranslated.is_empty(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn ambiguous_constructs_are_flagged_and_left_alone() {
+        let translated = translate(
+            "SELECT strftime('%Y', created_at) FROM t WHERE flag = 1",
+            &SQLite,
+            &PostgreSQL,
+        )
+        .unwrap();
+        assert_eq!(translated.sql, "SELECT strftime('%Y', created_at) FROM t WHERE flag = 1");
+        assert_eq!(translated.untranslated.len(), 1);
+        assert_eq!(translated.untranslated[0].construct, "strftime('%Y', created_at)");
+        assert_eq!(translated.untranslated[0].span.as_ref().map(|span| span.column), Some(8));
+
+        let ddl = translate("CREATE TABLE t (a TINYINT(1), b INT UNSIGNED)", &MySQL, &PostgreSQL).unwrap();
+        assert_eq!(ddl.sql, "CREATE TABLE t (a TINYINT(1), b INT UNSIGNED)");
+        assert_eq!(ddl.untranslated.len(), 2);
+
+        let same = translate("SELECT `a` FROM t LIMIT 1, 2", &MySQL, &MySQL).unwrap();
+        assert!(same.applied_rules.is_empty());
+        assert!(translate("SELEC 1", &SQLite, &PostgreSQL).is_err());
+    }
+}
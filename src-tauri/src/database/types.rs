@@ -108,6 +108,74 @@ pub fn classify_mysql_type(data_type: &str) -> ColumnTypeFamily {
     }
 }
 
+pub fn classify_mssql_type(data_type: &str) -> ColumnTypeFamily {
+    let t = data_type.trim().to_lowercase();
+
+    match t.as_str() {
+        "bit" => ColumnTypeFamily::Boolean,
+        "tinyint" | "smallint" | "int" | "bigint" => ColumnTypeFamily::Integer,
+        "float" | "real" => ColumnTypeFamily::Float,
+        "decimal" | "numeric" | "money" | "smallmoney" => ColumnTypeFamily::Decimal,
+        "char" | "varchar" | "text" | "nchar" | "nvarchar" | "ntext" | "xml" => ColumnTypeFamily::Text,
+        "date" => ColumnTypeFamily::Date,
+        "time" => ColumnTypeFamily::Time,
+        "datetime" | "datetime2" | "smalldatetime" | "datetimeoffset" => ColumnTypeFamily::DateTime,
+        "uniqueidentifier" => ColumnTypeFamily::Uuid,
+        "binary" | "varbinary" | "image" | "timestamp" | "rowversion" => ColumnTypeFamily::Binary,
+        "geometry" | "geography" | "hierarchyid" => ColumnTypeFamily::Extension,
+        _ => ColumnTypeFamily::Unknown,
+    }
+}
+
+/// MySQL's BOOL is TINYINT(1), so that's how a boolean column shows up.
+pub fn is_mysql_boolean_like(column_type: &str) -> bool {
+    column_type.trim().to_lowercase().starts_with("tinyint(1)")
+}
+
+/// Numbers in a declared type's parentheses: `VARCHAR(20)` gives (20, None)
+/// and `DECIMAL(10,2)` gives (10, 2).
+pub fn declared_size(declared_type: &str) -> (Option<i64>, Option<i64>) {
+    let Some(inner) = declared_type
+        .split_once('(')
+        .and_then(|(_, rest)| rest.split_once(')'))
+        .map(|(inner, _)| inner)
+    else {
+        return (None, None);
+    };
+    let mut numbers = inner.split(',').map(|part| part.trim().parse::<i64>().ok());
+    (numbers.next().flatten(), numbers.next().flatten())
+}
+
+/// Labels of a MySQL `enum('a','b')` column type.
+pub fn mysql_enum_values(column_type: &str) -> Option<Vec<String>> {
+    let trimmed = column_type.trim();
+    let inner = trimmed
+        .get(..5)
+        .filter(|prefix| prefix.eq_ignore_ascii_case("enum("))
+        .and_then(|_| trimmed[5..].strip_suffix(')'))?;
+    let mut values = Vec::new();
+    let mut chars = inner.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\'' {
+            continue;
+        }
+        let mut value = String::new();
+        while let Some(c) = chars.next() {
+            if c == '\'' {
+                if chars.peek() == Some(&'\'') {
+                    chars.next();
+                    value.push('\'');
+                    continue;
+                }
+                break;
+            }
+            value.push(c);
+        }
+        values.push(value);
+    }
+    Some(values)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -145,4 +213,16 @@ mod tests {
             ColumnTypeFamily::Array
         );
     }
+
+    #[test]
+    fn reads_sizes_and_enum_labels_from_declared_types() {
+        assert_eq!(declared_size("VARCHAR(20)"), (Some(20), None));
+        assert_eq!(declared_size("decimal(10, 2)"), (Some(10), Some(2)));
+        assert_eq!(declared_size("TEXT"), (None, None));
+        assert_eq!(
+            mysql_enum_values("enum('new','it''s done')"),
+            Some(vec!["new".to_string(), "it's done".to_string()])
+        );
+        assert_eq!(mysql_enum_values("int unsigned"), None);
+    }
 }
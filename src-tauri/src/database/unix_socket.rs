@@ -0,0 +1,78 @@
+use super::mysql_tls;
+use crate::models::ConnectionConfig;
+use anyhow::{anyhow, Result};
+use sqlx::mysql::MySqlConnectOptions;
+use sqlx::postgres::PgConnectOptions;
+use std::path::Path;
+
+/// File name prefix of a PostgreSQL socket; the port follows it.
+const POSTGRES_SOCKET_PREFIX: &str = ".s.PGSQL.";
+
+/// The Unix socket `config` connects through, if it names one.
+pub fn path(config: &ConnectionConfig) -> Option<&str> {
+    config.socket_path.as_deref().map(str::trim).filter(|path| !path.is_empty())
+}
+
+/// The user and database to log in as; a socket login may go without a password.
+fn login(config: &ConnectionConfig) -> Result<(&str, &str)> {
+    let username = config.username.as_deref().ok_or_else(|| anyhow!("Username is required"))?;
+    let database = config.database.as_deref().ok_or_else(|| anyhow!("Database is required"))?;
+    Ok((username, database))
+}
+
+/// Options for `config`'s PostgreSQL server at the socket `path`, either the
+/// directory holding `.s.PGSQL.<port>` or that file itself.
+pub fn postgres_options(config: &ConnectionConfig, path: &str) -> Result<PgConnectOptions> {
+    let (username, database) = login(config)?;
+    let mut options = PgConnectOptions::new().username(username).database(database);
+    let socket = Path::new(path);
+    let port = socket
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.strip_prefix(POSTGRES_SOCKET_PREFIX));
+    options = match port {
+        Some(port) => {
+            let port = port
+                .parse()
+                .map_err(|_| anyhow!("{} isn't a PostgreSQL socket file", path))?;
+            options.socket(socket.parent().unwrap_or(Path::new("/"))).port(port)
+        }
+        None => options.socket(socket),
+    };
+    if let Some(password) = config.password.as_deref().filter(|password| !password.is_empty()) {
+        options = options.password(password);
+    }
+    Ok(options)
+}
+
+/// Options for `config`'s MySQL server at the socket file `path`.
+pub fn mysql_options(config: &ConnectionConfig, path: &str) -> Result<MySqlConnectOptions> {
+    let (username, database) = login(config)?;
+    let mut options = MySqlConnectOptions::new().socket(path).username(username).database(database);
+    if let Some(password) = config.password.as_deref().filter(|password| !password.is_empty()) {
+        options = options.password(password);
+    }
+    mysql_tls::apply(options, config.mysql_ssl.as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn postgres_socket_file_names_its_directory_and_port() {
+        let config: ConnectionConfig = serde_json::from_value(serde_json::json!({
+            "id": "c", "name": "c", "db_type": "postgresql",
+            "username": "app", "database": "shop", "socket_path": " /var/run/postgresql/.s.PGSQL.5433 "
+        }))
+        .unwrap();
+        let path = path(&config).unwrap();
+        let options = postgres_options(&config, path).unwrap();
+        assert_eq!(options.get_socket(), Some(&std::path::PathBuf::from("/var/run/postgresql")));
+        assert_eq!(options.get_port(), 5433);
+
+        let options = postgres_options(&config, "/tmp").unwrap();
+        assert_eq!(options.get_socket(), Some(&std::path::PathBuf::from("/tmp")));
+        assert!(postgres_options(&config, "/tmp/.s.PGSQL.x").is_err());
+    }
+}
@@ -0,0 +1,406 @@
+use super::params;
+use super::ConnectionManager;
+use crate::models::{ColumnTypeFamily, ColumnViolation, DatabaseType, TableColumn, ViolationKind};
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+/// Longest piece of a value quoted back in a message.
+const PREVIEW_CHARS: usize = 40;
+
+/// Checks the values in `data` against the declared types of `columns`. With
+/// `insert`, NOT NULL columns that are left out and have no default are
+/// reported too. Keys that aren't columns are left for the database to reject.
+pub fn validate_row(columns: &[TableColumn], data: &Value, insert: bool) -> Result<Vec<ColumnViolation>> {
+    let object = data.as_object().ok_or_else(|| anyhow!("Data must be a JSON object"))?;
+    let mut violations = Vec::new();
+    for (key, value) in object {
+        let name = key.trim_matches(|c| c == '"' || c == '`');
+        let Some(column) = columns.iter().find(|column| column.name.eq_ignore_ascii_case(name)) else {
+            continue;
+        };
+        if let Some((kind, message)) = check_value(column, value) {
+            violations.push(ColumnViolation {
+                column: column.name.clone(),
+                kind,
+                message,
+            });
+        }
+    }
+    if insert {
+        for column in columns {
+            let given = object
+                .keys()
+                .any(|key| key.trim_matches(|c| c == '"' || c == '`').eq_ignore_ascii_case(&column.name));
+            if !given && is_required(column) {
+                violations.push(ColumnViolation {
+                    column: column.name.clone(),
+                    kind: ViolationKind::NotNull,
+                    message: format!("{} is required", column.name),
+                });
+            }
+        }
+    }
+    Ok(violations)
+}
+
+/// One line listing every violation, for errors returned instead of running SQL.
+pub fn describe(violations: &[ColumnViolation]) -> String {
+    violations
+        .iter()
+        .map(|violation| format!("{}: {}", violation.column, violation.message))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+fn is_required(column: &TableColumn) -> bool {
+    let non_empty = |value: &Option<String>| value.as_deref().is_some_and(|v| !v.is_empty());
+    !column.is_nullable
+        && column.default_value.is_none()
+        && !non_empty(&column.identity_kind)
+        && !non_empty(&column.generated_kind)
+}
+
+fn check_value(column: &TableColumn, value: &Value) -> Option<(ViolationKind, String)> {
+    // Sentinels the grid sends for DEFAULT and for an empty string.
+    if ConnectionManager::is_default_sentinel(value) || value.as_str() == Some("__NODADB_USE_DEFAULT__") {
+        return None;
+    }
+    let empty = Value::String(String::new());
+    let value = match value.as_str() {
+        Some("__NODADB_EMPTY_STRING__") => &empty,
+        _ => value,
+    };
+    if value.is_null() {
+        return (!column.is_nullable).then(|| (ViolationKind::NotNull, format!("{} can't be NULL", column.name)));
+    }
+
+    // Written as whatever the backend stores for true and false.
+    if params::is_boolean_column(column) && params::json_boolean(value).is_some() {
+        return None;
+    }
+
+    if let Some(labels) = column.enum_values.as_ref().filter(|labels| !labels.is_empty()) {
+        let text = value.as_str()?;
+        if !labels.iter().any(|label| label == text) {
+            return Some((
+                ViolationKind::NotInEnum,
+                format!("'{}' is not one of {}", preview(text), labels.join(", ")),
+            ));
+        }
+        return None;
+    }
+
+    match column.type_family {
+        ColumnTypeFamily::Integer => check_integer(column, value),
+        ColumnTypeFamily::Float => {
+            let text = number_text(value)?;
+            text.trim()
+                .parse::<f64>()
+                .is_err()
+                .then(|| (ViolationKind::InvalidNumber, format!("'{}' is not a number", preview(&text))))
+        }
+        ColumnTypeFamily::Decimal => check_decimal(column, value),
+        ColumnTypeFamily::Text => {
+            let limit = column.character_maximum_length?;
+            let text = match value {
+                Value::String(text) => text.clone(),
+                Value::Number(number) => number.to_string(),
+                _ => return None,
+            };
+            let length = text.chars().count() as i64;
+            (length > limit).then(|| {
+                (
+                    ViolationKind::TooLong,
+                    format!(
+                        "'{}' is {} characters long; {} allows at most {}",
+                        preview(&text),
+                        length,
+                        column.name,
+                        limit
+                    ),
+                )
+            })
+        }
+        _ => None,
+    }
+}
+
+fn check_integer(column: &TableColumn, value: &Value) -> Option<(ViolationKind, String)> {
+    let parsed = match value {
+        Value::Bool(_) => return None,
+        Value::Number(number) => match (number.as_i64(), number.as_u64(), number.as_f64()) {
+            (Some(n), _, _) => Some(n as i128),
+            (_, Some(n), _) => Some(n as i128),
+            (_, _, Some(n)) if n.fract() == 0.0 && n.abs() < 1e38 => Some(n as i128),
+            _ => None,
+        },
+        Value::String(text) => text.trim().parse::<i128>().ok(),
+        _ => return None,
+    };
+    let Some(parsed) = parsed else {
+        let text = number_text(value)?;
+        return Some((
+            ViolationKind::InvalidNumber,
+            format!("'{}' is not a whole number", preview(&text)),
+        ));
+    };
+    let (min, max) = integer_range(column);
+    (parsed < min || parsed > max).then(|| {
+        (
+            ViolationKind::OutOfRange,
+            format!("{} is out of range for {} ({} to {})", parsed, column.data_type, min, max),
+        )
+    })
+}
+
+/// Smallest and largest value the column's integer type holds. SQLite stores
+/// every integer in 64 bits whatever the declared type says.
+fn integer_range(column: &TableColumn) -> (i128, i128) {
+    let declared = column.raw_type.as_deref().unwrap_or(&column.data_type).to_lowercase();
+    let unsigned = declared.contains("unsigned");
+    let base = column.data_type.trim().to_lowercase();
+    let bits = match column.db_type {
+        DatabaseType::SQLite => 64,
+        DatabaseType::PostgreSQL | DatabaseType::MySQL | DatabaseType::MsSql => match base.split(['(', ' ']).next().unwrap_or_default() {
+            "tinyint" => 8,
+            "smallint" | "int2" => 16,
+            "mediumint" => 24,
+            "int" | "integer" | "int4" => 32,
+            _ => 64,
+        },
+    };
+    if unsigned {
+        (0, (1_i128 << bits) - 1)
+    } else {
+        (-(1_i128 << (bits - 1)), (1_i128 << (bits - 1)) - 1)
+    }
+}
+
+fn check_decimal(column: &TableColumn, value: &Value) -> Option<(ViolationKind, String)> {
+    let text = number_text(value)?;
+    let trimmed = text.trim();
+    if trimmed.parse::<f64>().is_err() {
+        return Some((ViolationKind::InvalidNumber, format!("'{}' is not a number", preview(&text))));
+    }
+    let precision = column.numeric_precision?;
+    let scale = column.numeric_scale.unwrap_or(0);
+    let unsigned = trimmed.trim_start_matches(['-', '+']);
+    if unsigned.contains(['e', 'E']) || unsigned.eq_ignore_ascii_case("nan") {
+        return None;
+    }
+    // Extra fractional digits are rounded by the server; only the whole part can overflow.
+    let whole = unsigned.split('.').next().unwrap_or_default().trim_start_matches('0');
+    let allowed = (precision - scale).max(0);
+    (whole.len() as i64 > allowed).then(|| {
+        (
+            ViolationKind::Precision,
+            format!(
+                "'{}' has {} digits before the decimal point; {}({}, {}) allows {}",
+                preview(&text),
+                whole.len(),
+                column.data_type.split('(').next().unwrap_or_default().trim(),
+                precision,
+                scale,
+                allowed
+            ),
+        )
+    })
+}
+
+/// The value as it would be typed, for numbers sent as JSON numbers or strings.
+fn number_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    }
+}
+
+fn preview(text: &str) -> String {
+    match text.char_indices().nth(PREVIEW_CHARS) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::types::{
+        classify_mssql_type, classify_mysql_type, classify_postgres_type, classify_sqlite_type,
+    };
+    use serde_json::json;
+
+    fn column(name: &str, db_type: DatabaseType, data_type: &str, raw_type: &str) -> TableColumn {
+        let type_family = match db_type {
+            DatabaseType::SQLite => classify_sqlite_type(data_type),
+            DatabaseType::PostgreSQL => classify_postgres_type(data_type, raw_type, "b", false),
+            DatabaseType::MySQL => classify_mysql_type(data_type),
+            DatabaseType::MsSql => classify_mssql_type(data_type),
+        };
+        TableColumn {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            raw_type: Some(raw_type.to_string()),
+            normalized_type: data_type.to_uppercase(),
+            type_family,
+            db_type,
+            is_nullable: true,
+            default_value: None,
+            is_primary_key: false,
+            is_boolean_like: false,
+            is_array: false,
+            enum_values: None,
+            identity_kind: None,
+            generated_kind: None,
+            generation_expression: None,
+            column_comment: None,
+            collation_name: None,
+            domain_name: None,
+            domain_schema: None,
+            domain_base_type: None,
+            array_dimensions: None,
+            element_raw_type: None,
+            character_maximum_length: None,
+            numeric_precision: None,
+            numeric_scale: None,
+        }
+    }
+
+    fn kinds(columns: &[TableColumn], data: Value, insert: bool) -> Vec<(String, ViolationKind)> {
+        validate_row(columns, &data, insert)
+            .unwrap()
+            .into_iter()
+            .map(|violation| (violation.column, violation.kind))
+            .collect()
+    }
+
+    #[test]
+    fn postgres_values_are_checked_against_their_columns() {
+        let mut status = column("status", DatabaseType::PostgreSQL, "mood", "mood");
+        status.enum_values = Some(vec!["happy".to_string(), "sad".to_string()]);
+        let mut name = column("name", DatabaseType::PostgreSQL, "character varying(5)", "varchar");
+        name.character_maximum_length = Some(5);
+        name.is_nullable = false;
+        let mut price = column("price", DatabaseType::PostgreSQL, "numeric(5,2)", "numeric");
+        price.numeric_precision = Some(5);
+        price.numeric_scale = Some(2);
+        let columns = vec![
+            column("qty", DatabaseType::PostgreSQL, "smallint", "int2"),
+            column("id", DatabaseType::PostgreSQL, "integer", "int4"),
+            status,
+            name,
+            price,
+        ];
+
+        assert!(kinds(&columns, json!({ "qty": "12", "name": "abcde", "price": "999.999" }), false).is_empty());
+        assert_eq!(
+            kinds(
+                &columns,
+                json!({ "qty": 40000, "id": "12a", "status": "meh", "name": "abcdef", "price": 1000 }),
+                false
+            ),
+            vec![
+                ("id".to_string(), ViolationKind::InvalidNumber),
+                ("name".to_string(), ViolationKind::TooLong),
+                ("price".to_string(), ViolationKind::Precision),
+                ("qty".to_string(), ViolationKind::OutOfRange),
+                ("status".to_string(), ViolationKind::NotInEnum),
+            ]
+        );
+        assert_eq!(
+            kinds(&columns, json!({ "name": null }), false),
+            vec![("name".to_string(), ViolationKind::NotNull)]
+        );
+        assert_eq!(
+            kinds(&columns, json!({ "qty": 1 }), true),
+            vec![("name".to_string(), ViolationKind::NotNull)]
+        );
+    }
+
+    #[test]
+    fn mysql_values_are_checked_against_their_columns() {
+        let mut state = column("state", DatabaseType::MySQL, "enum", "enum('on','off')");
+        state.enum_values = Some(vec!["on".to_string(), "off".to_string()]);
+        let mut code = column("code", DatabaseType::MySQL, "varchar", "varchar(3)");
+        code.character_maximum_length = Some(3);
+        let mut total = column("total", DatabaseType::MySQL, "decimal", "decimal(4,1)");
+        total.numeric_precision = Some(4);
+        total.numeric_scale = Some(1);
+        let mut id = column("id", DatabaseType::MySQL, "int", "int unsigned");
+        id.is_nullable = false;
+        id.identity_kind = Some("auto_increment".to_string());
+        let columns = vec![
+            column("flag", DatabaseType::MySQL, "tinyint", "tinyint(1)"),
+            id,
+            state,
+            code,
+            total,
+        ];
+
+        assert!(kinds(&columns, json!({ "flag": true, "id": 4000000000_u64, "total": "-999.95" }), true).is_empty());
+        assert_eq!(
+            kinds(
+                &columns,
+                json!({ "flag": 128, "id": -1, "state": "maybe", "code": "ABCD", "total": "12345", "x": "y" }),
+                false
+            ),
+            vec![
+                ("code".to_string(), ViolationKind::TooLong),
+                ("flag".to_string(), ViolationKind::OutOfRange),
+                ("id".to_string(), ViolationKind::OutOfRange),
+                ("state".to_string(), ViolationKind::NotInEnum),
+                ("total".to_string(), ViolationKind::Precision),
+            ]
+        );
+        assert_eq!(
+            kinds(&columns, json!({ "id": null, "total": "abc" }), false),
+            vec![
+                ("id".to_string(), ViolationKind::NotNull),
+                ("total".to_string(), ViolationKind::InvalidNumber),
+            ]
+        );
+    }
+
+    #[test]
+    fn sqlite_values_are_checked_against_declared_types() {
+        let mut name = column("name", DatabaseType::SQLite, "VARCHAR(4)", "VARCHAR(4)");
+        name.character_maximum_length = Some(4);
+        name.is_nullable = false;
+        name.default_value = Some("'none'".to_string());
+        let mut amount = column("amount", DatabaseType::SQLite, "DECIMAL(3,1)", "DECIMAL(3,1)");
+        amount.numeric_precision = Some(3);
+        amount.numeric_scale = Some(1);
+        let columns = vec![
+            column("small", DatabaseType::SQLite, "SMALLINT", "SMALLINT"),
+            column("ratio", DatabaseType::SQLite, "REAL", "REAL"),
+            name,
+            amount,
+        ];
+
+        // Declared integer widths mean nothing to SQLite, and a missing column with a default is fine.
+        assert!(kinds(&columns, json!({ "small": 100000, "name": "__NODADB_EMPTY_STRING__" }), true).is_empty());
+        assert_eq!(
+            kinds(
+                &columns,
+                json!({ "small": "1.5", "ratio": "fast", "name": "named", "amount": 100 }),
+                false
+            ),
+            vec![
+                ("amount".to_string(), ViolationKind::Precision),
+                ("name".to_string(), ViolationKind::TooLong),
+                ("ratio".to_string(), ViolationKind::InvalidNumber),
+                ("small".to_string(), ViolationKind::InvalidNumber),
+            ]
+        );
+        assert_eq!(
+            kinds(&columns, json!({ "name": null, "small": "9223372036854775808" }), false),
+            vec![
+                ("name".to_string(), ViolationKind::NotNull),
+                ("small".to_string(), ViolationKind::OutOfRange),
+            ]
+        );
+        let violations = validate_row(&columns, &json!({ "small": "x" }), false).unwrap();
+        assert_eq!(describe(&violations), "small: 'x' is not a whole number");
+    }
+}
@@ -0,0 +1,477 @@
+use crate::database::connection_url;
+use crate::models::{
+    ConnectionConfig, DatabaseType, ExternalConnectionImport, ExternalConnectionSource, SSHAuthMethod, SSHConfig,
+};
+use anyhow::{anyhow, Result};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+const DEFAULT_SSH_PORT: u16 = 22;
+
+/// Reads the connections another client saved in `contents`. Entries NodaDB
+/// can't open are left out, with a warning each.
+pub fn parse(source: &ExternalConnectionSource, contents: &[u8]) -> Result<ExternalConnectionImport> {
+    let entries = match source {
+        ExternalConnectionSource::DBeaver => dbeaver(contents)?,
+        ExternalConnectionSource::TablePlus => tableplus(contents)?,
+        ExternalConnectionSource::DataGrip => datagrip(contents)?,
+    };
+    let mut import = ExternalConnectionImport::default();
+    for (name, entry) in entries {
+        match entry {
+            Ok(config) => import.connections.push(config),
+            Err(e) => import.warnings.push(format!("Skipped {}: {}", name, e)),
+        }
+    }
+    Ok(import)
+}
+
+/// A port saved either as a number or as text.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Port {
+    Number(u16),
+    Text(String),
+}
+
+impl Port {
+    fn value(&self) -> Result<Option<u16>> {
+        match self {
+            Port::Number(port) => Ok(Some(*port)),
+            Port::Text(text) if text.trim().is_empty() => Ok(None),
+            Port::Text(text) => text
+                .trim()
+                .parse()
+                .map(Some)
+                .map_err(|_| anyhow!("invalid port {}", text)),
+        }
+    }
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.map(|value| value.trim().to_string()).filter(|value| !value.is_empty())
+}
+
+fn new_config(name: &str, db_type: DatabaseType) -> ConnectionConfig {
+    ConnectionConfig {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: name.to_string(),
+        db_type,
+        host: None,
+        port: None,
+        username: None,
+        password: None,
+        database: None,
+        file_path: None,
+        ssh_config: None,
+        retry_policy: None,
+        idle_disconnect_minutes: None,
+        idle_disconnect_rollback: false,
+        encryption_key: None,
+        cipher_settings: None,
+        schema_watch_seconds: None,
+        max_concurrent_operations: None,
+        read_replica: None,
+        failover_hosts: Vec::new(),
+        require_read_write: false,
+        connect_timeout_seconds: None,
+        scratch_schema: None,
+        sql_mode: None,
+        decode_overrides: Default::default(),
+        mysql_ssl: None,
+        pool_settings: None,
+        socket_path: None,
+        connection_url: None,
+        sqlite_open_mode: Default::default(),
+        read_only: false,
+        statement_timeout_seconds: None,
+        reconnect_attempts: None,
+        keepalive_seconds: None,
+        application_name: None,
+        session_params: Default::default(),
+        default_schema: None,
+        charset: None,
+        collation: None,
+        use_stored_credential: false,
+        trust_server_certificate: false,
+    }
+}
+
+/// Fills in `config` from a JDBC URL, keeping a user name it already has.
+fn apply_jdbc_url(config: &mut ConnectionConfig, url: &str) -> Result<()> {
+    let url = url.trim();
+    let parsed = connection_url::parse(url.strip_prefix("jdbc:").unwrap_or(url), Some(&config.db_type))?;
+    config.host = parsed.host;
+    config.port = parsed.port;
+    config.database = parsed.database;
+    config.file_path = parsed.file_path;
+    config.username = config.username.take().or(parsed.username);
+    Ok(())
+}
+
+fn ssh_tunnel(
+    host: Option<String>,
+    port: Option<u16>,
+    username: Option<String>,
+    private_key_path: Option<String>,
+    uses_key: bool,
+) -> Result<SSHConfig> {
+    Ok(SSHConfig {
+        enabled: true,
+        host: host.ok_or_else(|| anyhow!("its SSH tunnel has no host"))?,
+        port: port.unwrap_or(DEFAULT_SSH_PORT),
+        username: username.unwrap_or_default(),
+        auth_method: if uses_key || private_key_path.is_some() {
+            SSHAuthMethod::PrivateKey
+        } else {
+            SSHAuthMethod::Password
+        },
+        private_key_path,
+        password: None,
+        local_port: None,
+        jump_hosts: Vec::new(),
+    })
+}
+
+#[derive(Deserialize)]
+struct DBeaverConnection {
+    #[serde(default)]
+    provider: String,
+    #[serde(default)]
+    driver: String,
+    #[serde(default)]
+    configuration: DBeaverConfiguration,
+}
+
+#[derive(Default, Deserialize)]
+struct DBeaverConfiguration {
+    host: Option<String>,
+    port: Option<Port>,
+    database: Option<String>,
+    url: Option<String>,
+    user: Option<String>,
+    #[serde(default)]
+    handlers: HashMap<String, DBeaverHandler>,
+}
+
+#[derive(Deserialize)]
+struct DBeaverHandler {
+    #[serde(default)]
+    enabled: bool,
+    user: Option<String>,
+    #[serde(default)]
+    properties: HashMap<String, serde_json::Value>,
+}
+
+/// DBeaver's data-sources.json, keyed by connection id.
+fn dbeaver(contents: &[u8]) -> Result<Vec<(String, Result<ConnectionConfig>)>> {
+    let file: serde_json::Value =
+        serde_json::from_slice(contents).map_err(|e| anyhow!("Not a DBeaver data-sources.json: {}", e))?;
+    let connections = file
+        .get("connections")
+        .and_then(|connections| connections.as_object())
+        .ok_or_else(|| anyhow!("Not a DBeaver data-sources.json: it has no connections"))?;
+    Ok(connections
+        .iter()
+        .map(|(id, entry)| {
+            let name = entry.get("name").and_then(|name| name.as_str()).unwrap_or(id);
+            (name.to_string(), dbeaver_connection(name, entry))
+        })
+        .collect())
+}
+
+fn dbeaver_connection(name: &str, entry: &serde_json::Value) -> Result<ConnectionConfig> {
+    let connection = DBeaverConnection::deserialize(entry)?;
+    let (provider, driver) = (connection.provider.to_lowercase(), connection.driver.to_lowercase());
+    let db_type = if provider == "postgresql" || driver.starts_with("postgres") {
+        DatabaseType::PostgreSQL
+    } else if provider == "mysql" || driver.starts_with("mysql") || driver.starts_with("mariadb") {
+        DatabaseType::MySQL
+    } else if provider == "sqlite" || driver.starts_with("sqlite") {
+        DatabaseType::SQLite
+    } else {
+        return Err(anyhow!("unsupported driver {}", connection.driver));
+    };
+
+    let settings = connection.configuration;
+    let mut config = new_config(name, db_type);
+    config.username = non_empty(settings.user);
+    let host = non_empty(settings.host);
+    if config.db_type == DatabaseType::SQLite {
+        config.file_path = non_empty(settings.database);
+    } else if host.is_some() {
+        config.host = host;
+        config.port = settings.port.map(|port| port.value()).transpose()?.flatten();
+        config.database = non_empty(settings.database);
+    }
+    if config.host.is_none() && config.file_path.is_none() {
+        let url = non_empty(settings.url).ok_or_else(|| anyhow!("it has neither a host nor a URL"))?;
+        apply_jdbc_url(&mut config, &url)?;
+    }
+
+    if let Some(tunnel) = settings.handlers.get("ssh_tunnel").filter(|tunnel| tunnel.enabled) {
+        let property = |key: &str| {
+            tunnel.properties.get(key).and_then(|value| match value {
+                serde_json::Value::String(text) => non_empty(Some(text.clone())),
+                serde_json::Value::Number(number) => Some(number.to_string()),
+                _ => None,
+            })
+        };
+        let port = property("port")
+            .map(|port| port.parse().map_err(|_| anyhow!("invalid SSH port {}", port)))
+            .transpose()?;
+        config.ssh_config = Some(ssh_tunnel(
+            property("host"),
+            port,
+            non_empty(tunnel.user.clone()).or_else(|| property("user")),
+            property("keyPath"),
+            property("authType").is_some_and(|auth| auth.eq_ignore_ascii_case("PUBLIC_KEY")),
+        )?);
+    }
+    Ok(config)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct TablePlusConnection {
+    #[serde(default)]
+    driver: String,
+    database_host: Option<String>,
+    database_port: Option<Port>,
+    database_user: Option<String>,
+    database_name: Option<String>,
+    database_path: Option<String>,
+    #[serde(rename = "isOverSSH", default)]
+    is_over_ssh: bool,
+    server_address: Option<String>,
+    server_port: Option<Port>,
+    server_user: Option<String>,
+    #[serde(rename = "isUsePrivateKey", default)]
+    is_use_private_key: bool,
+    server_private_key_name: Option<String>,
+}
+
+/// TablePlus's Connections.plist, or the same list exported as JSON.
+fn tableplus(contents: &[u8]) -> Result<Vec<(String, Result<ConnectionConfig>)>> {
+    let is_json = contents
+        .iter()
+        .find(|byte| !byte.is_ascii_whitespace())
+        .is_some_and(|byte| *byte == b'[');
+    if is_json {
+        let entries: Vec<serde_json::Value> =
+            serde_json::from_slice(contents).map_err(|e| anyhow!("Not a TablePlus connection list: {}", e))?;
+        return Ok(entries
+            .iter()
+            .map(|entry| {
+                let name = entry.get("ConnectionName").and_then(|name| name.as_str()).unwrap_or_default();
+                let connection = TablePlusConnection::deserialize(entry).map_err(anyhow::Error::from);
+                (name.to_string(), connection.and_then(|connection| tableplus_connection(name, connection)))
+            })
+            .collect());
+    }
+    let entries: Vec<plist::Value> =
+        plist::from_bytes(contents).map_err(|e| anyhow!("Not a TablePlus connection list: {}", e))?;
+    Ok(entries
+        .iter()
+        .map(|entry| {
+            let name = entry
+                .as_dictionary()
+                .and_then(|entry| entry.get("ConnectionName"))
+                .and_then(|name| name.as_string())
+                .unwrap_or_default();
+            let connection = plist::from_value::<TablePlusConnection>(entry).map_err(anyhow::Error::from);
+            (name.to_string(), connection.and_then(|connection| tableplus_connection(name, connection)))
+        })
+        .collect())
+}
+
+fn tableplus_connection(name: &str, connection: TablePlusConnection) -> Result<ConnectionConfig> {
+    let db_type = match connection.driver.to_lowercase().as_str() {
+        "postgresql" | "postgres" => DatabaseType::PostgreSQL,
+        "mysql" | "mariadb" => DatabaseType::MySQL,
+        "sqlite" => DatabaseType::SQLite,
+        _ => return Err(anyhow!("unsupported driver {}", connection.driver)),
+    };
+    let mut config = new_config(name, db_type);
+    if config.db_type == DatabaseType::SQLite {
+        config.file_path =
+            Some(non_empty(connection.database_path).ok_or_else(|| anyhow!("it has no database file"))?);
+        return Ok(config);
+    }
+    config.host = non_empty(connection.database_host);
+    config.port = connection.database_port.map(|port| port.value()).transpose()?.flatten();
+    config.username = non_empty(connection.database_user);
+    config.database = non_empty(connection.database_name);
+    if connection.is_over_ssh {
+        config.ssh_config = Some(ssh_tunnel(
+            non_empty(connection.server_address),
+            connection.server_port.map(|port| port.value()).transpose()?.flatten(),
+            non_empty(connection.server_user),
+            non_empty(connection.server_private_key_name).filter(|_| connection.is_use_private_key),
+            connection.is_use_private_key,
+        )?);
+    }
+    Ok(config)
+}
+
+#[derive(Default)]
+struct DataGripSource {
+    name: String,
+    driver: String,
+    url: Option<String>,
+    user: Option<String>,
+}
+
+fn attribute(element: &BytesStart, name: &str) -> Result<Option<String>> {
+    match element.try_get_attribute(name)? {
+        Some(attribute) => {
+            let raw = String::from_utf8_lossy(&attribute.value);
+            Ok(Some(quick_xml::escape::unescape(&raw)?.into_owned()))
+        }
+        None => Ok(None),
+    }
+}
+
+/// DataGrip's dataSources.xml, or dataSources.local.xml for the user names.
+fn datagrip(contents: &[u8]) -> Result<Vec<(String, Result<ConnectionConfig>)>> {
+    let invalid = |e: &dyn std::fmt::Display| anyhow!("Not a DataGrip dataSources.xml: {}", e);
+    let mut reader = Reader::from_reader(contents);
+    let mut buf = Vec::new();
+    let mut sources = Vec::new();
+    let mut current: Option<DataGripSource> = None;
+    // The innermost element open inside a data source, and its raw text.
+    let mut field: Option<String> = None;
+    let mut text = String::new();
+    loop {
+        match reader.read_event_into(&mut buf).map_err(|e| invalid(&e))? {
+            Event::Start(element) => {
+                let tag = String::from_utf8_lossy(element.local_name().as_ref()).into_owned();
+                if tag == "data-source" {
+                    current = Some(DataGripSource {
+                        name: attribute(&element, "name").map_err(|e| invalid(&e))?.unwrap_or_default(),
+                        ..Default::default()
+                    });
+                } else if current.is_some() {
+                    field = Some(tag);
+                    text.clear();
+                }
+            }
+            Event::Text(content) if field.is_some() => text.push_str(&content.decode().map_err(|e| invalid(&e))?),
+            Event::GeneralRef(reference) if field.is_some() => {
+                text.push('&');
+                text.push_str(&reference.decode().map_err(|e| invalid(&e))?);
+                text.push(';');
+            }
+            Event::End(element) => {
+                let tag = String::from_utf8_lossy(element.local_name().as_ref()).into_owned();
+                if tag == "data-source" {
+                    sources.extend(current.take());
+                } else if let (Some(source), Some(open)) = (current.as_mut(), field.take()) {
+                    let value = quick_xml::escape::unescape(&text).map_err(|e| invalid(&e))?.trim().to_string();
+                    match open.as_str() {
+                        "driver-ref" => source.driver = value,
+                        "jdbc-url" => source.url = Some(value),
+                        "user-name" => source.user = Some(value),
+                        _ => {}
+                    }
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(sources
+        .into_iter()
+        .map(|source| (source.name.clone(), datagrip_connection(source)))
+        .collect())
+}
+
+fn datagrip_connection(source: DataGripSource) -> Result<ConnectionConfig> {
+    let url = non_empty(source.url).ok_or_else(|| anyhow!("it has no JDBC URL"))?;
+    let db_type = connection_url::scheme_type(url.strip_prefix("jdbc:").unwrap_or(&url))
+        .map_err(|_| anyhow!("unsupported driver {}", source.driver))?;
+    let mut config = new_config(&source.name, db_type);
+    config.username = non_empty(source.user);
+    apply_jdbc_url(&mut config, &url)?;
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn import(source: ExternalConnectionSource, contents: &str) -> ExternalConnectionImport {
+        parse(&source, contents.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn dbeaver_connections_map_with_their_ssh_tunnels() {
+        let import = import(
+            ExternalConnectionSource::DBeaver,
+            include_str!("../tests/fixtures/dbeaver-data-sources.json"),
+        );
+        let names: Vec<_> = import.connections.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["Local", "Shop", "Orders", "Analytics"]);
+        assert_eq!(import.connections[0].file_path.as_deref(), Some("/data/local.db"));
+        assert_eq!(import.connections[1].db_type, DatabaseType::MySQL);
+        assert!(import.connections[1].ssh_config.is_none());
+
+        let orders = &import.connections[2];
+        assert_eq!(orders.db_type, DatabaseType::PostgreSQL);
+        assert_eq!((orders.host.as_deref(), orders.port), (Some("db.internal"), Some(5432)));
+        assert_eq!((orders.username.as_deref(), orders.database.as_deref()), (Some("app"), Some("orders")));
+        let ssh = orders.ssh_config.as_ref().unwrap();
+        assert_eq!((ssh.host.as_str(), ssh.port, ssh.username.as_str()), ("bastion.example.com", 2222, "deploy"));
+        assert!(matches!(ssh.auth_method, SSHAuthMethod::PrivateKey));
+        assert_eq!(ssh.private_key_path.as_deref(), Some("/home/me/.ssh/id_ed25519"));
+
+        let analytics = &import.connections[3];
+        assert_eq!((analytics.host.as_deref(), analytics.port), (Some("warehouse"), Some(5433)));
+        assert_eq!((analytics.username.as_deref(), analytics.database.as_deref()), (Some("analyst"), Some("events")));
+
+        assert_eq!(import.warnings, vec!["Skipped Legacy: unsupported driver oracle_thin"]);
+        assert!(parse(&ExternalConnectionSource::DBeaver, b"{ nope").is_err());
+    }
+
+    #[test]
+    fn tableplus_plists_and_json_exports_map_alike() {
+        for contents in [
+            include_str!("../tests/fixtures/tableplus-connections.plist"),
+            include_str!("../tests/fixtures/tableplus-connections.json"),
+        ] {
+            let import = import(ExternalConnectionSource::TablePlus, contents);
+            assert_eq!(import.connections.len(), 2);
+            let orders = &import.connections[0];
+            assert_eq!((orders.name.as_str(), orders.port), ("Orders", Some(5432)));
+            assert_eq!(orders.username.as_deref(), Some("app"));
+            let ssh = orders.ssh_config.as_ref().unwrap();
+            assert_eq!((ssh.host.as_str(), ssh.port, ssh.username.as_str()), ("bastion.example.com", 22, "deploy"));
+            assert!(matches!(ssh.auth_method, SSHAuthMethod::Password));
+            assert_eq!(import.connections[1].file_path.as_deref(), Some("/data/local.db"));
+            assert_eq!(import.warnings, vec!["Skipped Cache: unsupported driver Redis"]);
+        }
+    }
+
+    #[test]
+    fn datagrip_data_sources_map_from_their_jdbc_urls() {
+        let import = import(
+            ExternalConnectionSource::DataGrip,
+            include_str!("../tests/fixtures/datagrip-data-sources.xml"),
+        );
+        assert_eq!(import.connections.len(), 4);
+        let orders = &import.connections[0];
+        assert_eq!(orders.name, "orders@db.internal");
+        assert_eq!((orders.host.as_deref(), orders.port), (Some("db.internal"), Some(5432)));
+        assert_eq!((orders.username.as_deref(), orders.database.as_deref()), (Some("app"), Some("orders")));
+        let shop = &import.connections[1];
+        assert_eq!((shop.db_type.clone(), shop.database.as_deref()), (DatabaseType::MySQL, Some("shop")));
+        let reports = &import.connections[2];
+        assert_eq!((reports.db_type.clone(), reports.port), (DatabaseType::MsSql, Some(1433)));
+        assert_eq!(reports.database.as_deref(), Some("reports"));
+        assert_eq!(import.connections[3].file_path.as_deref(), Some("/data/local.db"));
+        assert!(import.warnings.is_empty());
+    }
+}
@@ -1,39 +1,206 @@
+mod codegen;
 mod commands;
 mod database;
+mod external_connections;
 mod models;
+mod operations;
+mod profiles;
 mod ssh_tunnel;
+mod store;
+mod store_migrations;
+mod workspaces;
 
 use database::ConnectionManager;
+use operations::OperationRegistry;
+use store::{AppStore, STORE_FILE_NAME};
+use workspaces::WorkspaceTracker;
+use models::DeepLinkConnect;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_deep_link::DeepLinkExt;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     let connection_manager = ConnectionManager::new();
+    let operation_registry = OperationRegistry::new();
 
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
+        .plugin(tauri_plugin_deep_link::init())
         .manage(connection_manager)
+        .manage(operation_registry)
+        .manage(WorkspaceTracker::default())
+        .setup(|app| {
+            let store = match app.path().app_data_dir() {
+                Ok(dir) => AppStore::open(dir.join(STORE_FILE_NAME)).unwrap_or_else(|e| {
+                    eprintln!("Local store unavailable, keeping settings in memory: {}", e);
+                    AppStore::in_memory()
+                }),
+                Err(e) => {
+                    eprintln!("App data directory unavailable, keeping settings in memory: {}", e);
+                    AppStore::in_memory()
+                }
+            };
+            if let Err(e) = profiles::migrate_plaintext_secrets(&store) {
+                eprintln!("Saved connections still hold plaintext secrets: {}", e);
+            }
+            app.manage(store);
+
+            let handle = app.handle().clone();
+            app.deep_link().on_open_url(move |event| {
+                for url in event.urls() {
+                    tauri::async_runtime::spawn(open_deep_link(handle.clone(), url));
+                }
+            });
+            // Links that launched the app.
+            if let Ok(Some(urls)) = app.deep_link().get_current() {
+                for url in urls {
+                    tauri::async_runtime::spawn(open_deep_link(app.handle().clone(), url));
+                }
+            }
+
+            let handle = app.handle().clone();
+            app.state::<OperationRegistry>()
+                .set_event_sink(move |event, payload| {
+                    let _ = handle.emit(event, payload);
+                });
+
+            let handle = app.handle().clone();
+            let manager = app.state::<ConnectionManager>().inner().clone();
+            manager.set_event_sink(move |event, payload| {
+                let _ = handle.emit(event, payload);
+            });
+            let watcher = manager.clone();
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    tokio::time::sleep(database::schema_watch::SCHEMA_WATCH_TICK).await;
+                    watcher.check_schema_changes().await;
+                }
+            });
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    tokio::time::sleep(database::console::CONSOLE_REAPER_INTERVAL).await;
+                    manager.close_idle_console_sessions().await;
+                    manager.close_idle_connections().await;
+                    manager.check_ssh_tunnels().await;
+                }
+            });
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             commands::test_connection,
+            commands::parse_connection_url,
+            commands::ping_connection,
+            commands::get_pool_stats,
+            commands::debug_connection,
             commands::connect_database,
+            commands::reconnect_database,
+            commands::list_databases,
+            commands::switch_database,
+            commands::create_database,
+            commands::drop_database,
+            commands::resolve_connection,
+            commands::save_connection_profile,
+            commands::list_connection_profiles,
+            commands::delete_connection_profile,
+            commands::export_connections,
+            commands::import_connections,
+            commands::import_external_connections,
+            commands::save_credential,
+            commands::get_credential,
+            commands::delete_credential,
+            commands::set_workspace_state,
+            commands::save_workspace,
+            commands::restore_workspace,
+            commands::list_workspaces,
             commands::disconnect_database,
+            commands::set_connection_read_only,
+            commands::set_schema_watch,
+            commands::get_ssh_tunnel_status,
+            commands::get_connection_info,
             commands::list_tables,
+            commands::list_tables_cached,
+            commands::get_table_row_count,
+            commands::get_column_statistics,
+            commands::get_column_histogram,
+            commands::record_table_filters,
+            commands::get_index_suggestions,
+            commands::dismiss_index_suggestion,
+            commands::analyze_table,
+            commands::analyze_column,
             commands::get_table_structure,
+            commands::get_table_structures,
+            commands::get_table_data,
+            commands::save_table_view,
+            commands::get_table_view,
+            commands::subscribe_table,
+            commands::unsubscribe_table,
+            commands::export_app_data,
+            commands::get_app_store_status,
+            commands::repair_app_store,
+            commands::quick_connect_sqlite,
+            commands::create_sqlite_database,
+            commands::list_recent_sqlite_files,
+            commands::remove_recent_file,
             commands::execute_query,
+            commands::execute_query_with_params,
+            commands::cancel_query,
+            commands::execute_statement_at,
+            commands::execute_multi,
+            commands::get_result_lineage,
+            commands::get_pinned_result_page,
+            commands::export_pinned_result,
+            commands::diff_pinned_results,
+            commands::drop_pinned_result,
+            commands::aggregate_result_column,
+            commands::fetch_cell_text,
             commands::explain_query,
+            commands::analyze_query_patterns,
+            commands::translate_sql,
+            commands::execute_with_plan,
+            commands::validate_row_values,
             commands::insert_row,
             commands::bulk_insert_rows,
+            commands::clone_row,
+            commands::clone_rows,
+            commands::preview_delete_cascade,
+            commands::set_cell_from_file,
+            commands::save_cell_to_file,
             commands::update_row,
             commands::delete_rows,
+            commands::update_rows_by_filter,
+            commands::replace_in_table,
+            commands::batched_delete,
             commands::create_table,
+            commands::create_table_from_text,
+            commands::list_scratch_objects,
+            commands::cleanup_scratch,
             commands::drop_table,
             commands::alter_table_add_column,
             commands::alter_table_drop_column,
             commands::execute_transaction,
             commands::rename_table,
             commands::export_table_structure,
+            commands::generate_code,
+            commands::checksum_table,
+            commands::compare_table_checksums,
+            commands::export_table_csv,
+            commands::get_server_metrics,
+            commands::open_console_session,
+            commands::execute_console,
+            commands::close_console_session,
+            commands::cross_query,
+            commands::guarded_execute,
+            commands::commit_guard,
+            commands::rollback_guard,
+            commands::list_active_connections,
+            commands::get_query_log,
+            commands::configure_telemetry,
+            commands::set_telemetry_enabled,
+            commands::list_operations,
+            commands::cancel_operation,
             commands::get_table_constraints,
             commands::get_table_indexes,
             commands::create_foreign_key,
@@ -44,14 +211,77 @@ pub fn run() {
             commands::get_postgres_connection_info,
             commands::cancel_postgres_backend_query,
             commands::get_postgres_extensions,
+            commands::list_extensions,
+            commands::create_extension,
+            commands::drop_extension,
+            commands::get_sql_functions,
+            commands::escape_identifier,
+            commands::escape_literal,
+            commands::quote_qualified,
+            commands::get_connection_capabilities,
+            commands::list_unknown_types,
+            commands::set_decode_overrides,
+            commands::get_slow_queries,
+            commands::get_top_queries,
+            commands::reset_statement_stats,
             commands::get_postgres_table_privileges,
+            commands::get_table_permissions,
+            commands::get_database_permissions,
             commands::create_new_window,
             commands::create_window_from_label,
             commands::save_export_file,
             commands::create_export_archive,
             commands::trace_id_relations,
             commands::get_relation_rows,
+            commands::expand_result_row,
+            commands::match_keys,
+            commands::compare_keys,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                let manager = app.state::<ConnectionManager>().inner().clone();
+                tauri::async_runtime::block_on(async {
+                    if let Some(store) = app.try_state::<AppStore>() {
+                        let tracker = app.state::<WorkspaceTracker>();
+                        let last_session = workspaces::LAST_SESSION_WORKSPACE;
+                        // Best effort: a failed save must not hold up exiting.
+                        let _ = workspaces::save(&store, &manager, &tracker, last_session).await;
+                    }
+                    // Writes still under review are rolled back, never committed.
+                    manager.rollback_guards(None, "app_exit").await;
+                    manager.close_pinned_results().await;
+                    manager.disconnect_all().await;
+                });
+            }
+        });
+}
+
+/// Connects the profile a `noda://connect?name=...` link names and tells the
+/// frontend, which focuses the connection's tab. Ambiguous names are reported
+/// with their candidates rather than guessed.
+async fn open_deep_link(app: AppHandle, url: url::Url) {
+    let mut outcome = DeepLinkConnect {
+        url: url.to_string(),
+        resolution: None,
+        connection_id: None,
+        error: None,
+    };
+    let store = app.state::<AppStore>();
+    let manager = app.state::<ConnectionManager>();
+    let result = async {
+        let name = profiles::deep_link_target(&url)?;
+        let resolution = profiles::resolve_connection(&store, &manager, &name).await?;
+        outcome.resolution = Some(resolution.clone());
+        let connection = profiles::single_match(&resolution, &name)?;
+        profiles::connect_resolved(&store, &manager, &connection).await?;
+        outcome.connection_id = Some(connection.id);
+        anyhow::Ok(())
+    }
+    .await;
+    if let Err(e) = result {
+        outcome.error = Some(e.to_string());
+    }
+    let _ = app.emit(profiles::DEEP_LINK_CONNECT_EVENT, outcome);
 }
@@ -0,0 +1,356 @@
+use crate::models::{OperationFinished, OperationInfo, OperationProgress};
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+pub const OPERATION_PROGRESS_EVENT: &str = "operation-progress";
+pub const OPERATION_FINISHED_EVENT: &str = "operation-finished";
+
+/// Minimum delay between two progress events for the same operation.
+const PROGRESS_THROTTLE: Duration = Duration::from_millis(200);
+
+pub(crate) type EventSink = Arc<dyn Fn(&str, serde_json::Value) + Send + Sync>;
+
+/// Cooperative cancellation flag shared between a running operation and the registry.
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+struct OperationEntry {
+    info: OperationInfo,
+    token: CancellationToken,
+}
+
+/// Tracks long-running operations (exports, imports, bulk writes, ...) so they
+/// report progress through the same events and can be cancelled from the UI.
+pub struct OperationRegistry {
+    operations: Arc<RwLock<HashMap<String, OperationEntry>>>,
+    sink: Arc<RwLock<Option<EventSink>>>,
+}
+
+impl OperationRegistry {
+    pub fn new() -> Self {
+        Self {
+            operations: Arc::new(RwLock::new(HashMap::new())),
+            sink: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// Installs the callback used to forward progress and terminal events to the frontend.
+    pub fn set_event_sink<F>(&self, sink: F)
+    where
+        F: Fn(&str, serde_json::Value) + Send + Sync + 'static,
+    {
+        if let Ok(mut current) = self.sink.write() {
+            *current = Some(Arc::new(sink));
+        }
+    }
+
+    /// Registers a new operation. The caller-supplied id lets the frontend cancel
+    /// an operation before the invoking command has returned; it fails while
+    /// another operation runs under the same id.
+    pub fn start(
+        &self,
+        operation_id: Option<String>,
+        kind: &str,
+        connection_id: Option<&str>,
+        description: &str,
+    ) -> Result<OperationHandle> {
+        let id = operation_id
+            .filter(|id| !id.trim().is_empty())
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        let token = CancellationToken::default();
+        let info = OperationInfo {
+            id: id.clone(),
+            kind: kind.to_string(),
+            connection_id: connection_id.map(|id| id.to_string()),
+            description: description.to_string(),
+            units_done: 0,
+            units_total: None,
+            message: None,
+            cancel_requested: false,
+            started_at: Utc::now().to_rfc3339(),
+        };
+
+        let mut operations = self
+            .operations
+            .write()
+            .map_err(|_| anyhow!("Operation registry is unavailable"))?;
+        if operations.contains_key(&id) {
+            return Err(anyhow!("Operation {} is already running", id));
+        }
+        operations.insert(
+            id.clone(),
+            OperationEntry {
+                info,
+                token: token.clone(),
+            },
+        );
+        drop(operations);
+
+        Ok(OperationHandle {
+            id,
+            kind: kind.to_string(),
+            connection_id: connection_id.map(|id| id.to_string()),
+            token,
+            started: Instant::now(),
+            last_emit: Mutex::new(None),
+            finished: AtomicBool::new(false),
+            operations: self.operations.clone(),
+            sink: self.sink.clone(),
+        })
+    }
+
+    pub fn list(&self) -> Vec<OperationInfo> {
+        let Ok(operations) = self.operations.read() else {
+            return vec![];
+        };
+        let mut infos = operations
+            .values()
+            .map(|entry| entry.info.clone())
+            .collect::<Vec<_>>();
+        infos.sort_by(|a, b| a.started_at.cmp(&b.started_at));
+        infos
+    }
+
+    /// Requests cancellation. Returns false when the operation already finished.
+    pub fn cancel(&self, operation_id: &str) -> Result<bool> {
+        let mut operations = self
+            .operations
+            .write()
+            .map_err(|_| anyhow!("Operation registry is unavailable"))?;
+        match operations.get_mut(operation_id) {
+            Some(entry) => {
+                entry.token.cancel();
+                entry.info.cancel_requested = true;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+/// Handle owned by a running operation. Dropping it without calling `finish`
+/// still unregisters the operation so nothing is left behind in the registry.
+pub struct OperationHandle {
+    id: String,
+    kind: String,
+    connection_id: Option<String>,
+    token: CancellationToken,
+    started: Instant,
+    last_emit: Mutex<Option<Instant>>,
+    finished: AtomicBool,
+    operations: Arc<RwLock<HashMap<String, OperationEntry>>>,
+    sink: Arc<RwLock<Option<EventSink>>>,
+}
+
+impl OperationHandle {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Returns an error once cancellation was requested, for use with `?` between units of work.
+    pub fn check_cancelled(&self) -> Result<()> {
+        if self.is_cancelled() {
+            Err(anyhow!("Operation cancelled"))
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn report(&self, units_done: u64, units_total: Option<u64>, message: Option<&str>) {
+        if let Ok(mut operations) = self.operations.write() {
+            if let Some(entry) = operations.get_mut(&self.id) {
+                entry.info.units_done = units_done;
+                entry.info.units_total = units_total;
+                entry.info.message = message.map(|m| m.to_string());
+            }
+        }
+
+        let is_last = units_total.is_some_and(|total| units_done >= total);
+        let now = Instant::now();
+        let Ok(mut last_emit) = self.last_emit.lock() else {
+            return;
+        };
+        if let Some(previous) = *last_emit {
+            if !is_last && now.duration_since(previous) < PROGRESS_THROTTLE {
+                return;
+            }
+        }
+        *last_emit = Some(now);
+        drop(last_emit);
+
+        self.emit(
+            OPERATION_PROGRESS_EVENT,
+            &OperationProgress {
+                operation_id: self.id.clone(),
+                kind: self.kind.clone(),
+                connection_id: self.connection_id.clone(),
+                units_done,
+                units_total,
+                message: message.map(|m| m.to_string()),
+                elapsed_ms: self.started.elapsed().as_millis() as u64,
+            },
+        );
+    }
+
+    /// Unregisters the operation and emits the terminal event for `result`.
+    pub fn finish<T>(self, result: &Result<T>) {
+        let error = result.as_ref().err().map(|e| e.to_string());
+        self.complete(error);
+    }
+
+    fn complete(&self, error: Option<String>) {
+        if self.finished.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        if let Ok(mut operations) = self.operations.write() {
+            operations.remove(&self.id);
+        }
+
+        let outcome = match (&error, self.is_cancelled()) {
+            (None, _) => "completed",
+            (Some(_), true) => "cancelled",
+            (Some(_), false) => "failed",
+        };
+
+        self.emit(
+            OPERATION_FINISHED_EVENT,
+            &OperationFinished {
+                operation_id: self.id.clone(),
+                kind: self.kind.clone(),
+                connection_id: self.connection_id.clone(),
+                outcome: outcome.to_string(),
+                duration_ms: self.started.elapsed().as_millis() as u64,
+                error,
+            },
+        );
+    }
+
+    fn emit<P: serde::Serialize>(&self, event: &str, payload: &P) {
+        let sink = match self.sink.read() {
+            Ok(sink) => sink.clone(),
+            Err(_) => None,
+        };
+        if let (Some(sink), Ok(value)) = (sink, serde_json::to_value(payload)) {
+            sink(event, value);
+        }
+    }
+}
+
+impl Drop for OperationHandle {
+    fn drop(&mut self) {
+        self.complete(Some("Operation ended unexpectedly".to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type RecordedEvents = Arc<Mutex<Vec<(String, serde_json::Value)>>>;
+
+    fn recording_registry() -> (OperationRegistry, RecordedEvents) {
+        let registry = OperationRegistry::new();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = events.clone();
+        registry.set_event_sink(move |event, payload| {
+            recorded.lock().unwrap().push((event.to_string(), payload));
+        });
+        (registry, events)
+    }
+
+    #[test]
+    fn lists_running_operations_and_removes_finished_ones() {
+        let (registry, _) = recording_registry();
+        let operation = registry.start(Some("op-1".to_string()), "export_csv", Some("conn"), "Export").unwrap();
+
+        let listed = registry.list();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, "op-1");
+        assert_eq!(listed[0].connection_id.as_deref(), Some("conn"));
+
+        operation.finish::<()>(&Ok(()));
+        assert!(registry.list().is_empty());
+    }
+
+    #[test]
+    fn throttles_progress_but_always_emits_the_last_report() {
+        let (registry, events) = recording_registry();
+        let operation = registry.start(None, "bulk_insert", None, "Insert").unwrap();
+
+        for done in 1..=50 {
+            operation.report(done, Some(50), None);
+        }
+        operation.finish::<()>(&Ok(()));
+
+        let events = events.lock().unwrap();
+        let progress = events
+            .iter()
+            .filter(|(event, _)| event == OPERATION_PROGRESS_EVENT)
+            .collect::<Vec<_>>();
+        assert_eq!(progress.len(), 2);
+        assert_eq!(progress[1].1["units_done"], 50);
+
+        let (event, payload) = events.last().unwrap();
+        assert_eq!(event, OPERATION_FINISHED_EVENT);
+        assert_eq!(payload["outcome"], "completed");
+    }
+
+    #[test]
+    fn cancel_flags_the_token_and_reports_cancelled_outcome() {
+        let (registry, events) = recording_registry();
+        let operation = registry.start(Some("op-2".to_string()), "bulk_insert", None, "Insert").unwrap();
+
+        assert!(registry.cancel("op-2").unwrap());
+        assert!(registry.list()[0].cancel_requested);
+        assert!(operation.check_cancelled().is_err());
+
+        let result = operation.check_cancelled();
+        operation.finish(&result);
+        assert!(!registry.cancel("op-2").unwrap());
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.last().unwrap().1["outcome"], "cancelled");
+    }
+
+    #[test]
+    fn an_id_in_use_is_refused_and_keeps_its_operation() {
+        let (registry, _) = recording_registry();
+        let first = registry.start(Some("op-3".to_string()), "export_csv", None, "Export").unwrap();
+
+        let error = registry.start(Some("op-3".to_string()), "bulk_insert", None, "Insert").err().unwrap();
+        assert_eq!(error.to_string(), "Operation op-3 is already running");
+        assert_eq!(registry.list()[0].kind, "export_csv");
+        assert!(registry.cancel("op-3").unwrap());
+        assert!(first.is_cancelled());
+    }
+
+    #[test]
+    fn dropping_an_unfinished_handle_unregisters_it() {
+        let (registry, events) = recording_registry();
+        drop(registry.start(None, "backup", None, "Backup").unwrap());
+
+        assert!(registry.list().is_empty());
+        assert_eq!(events.lock().unwrap().last().unwrap().1["outcome"], "failed");
+    }
+}
@@ -0,0 +1,667 @@
+use crate::database::ConnectionManager;
+use crate::models::{
+    ConnectionConfig, ConnectionProfile, ConnectionResolution, DatabaseType, ResolvedConnection, SecretString,
+};
+use crate::store::AppStore;
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+/// Keychain service that profile secrets are stored under, one entry per profile id.
+const KEYCHAIN_SERVICE: &str = "NodaDB";
+pub const DEEP_LINK_SCHEME: &str = "noda";
+const BUNDLE_FORMAT_VERSION: u32 = 1;
+/// Event emitted after a `noda://connect` link was handled.
+pub const DEEP_LINK_CONNECT_EVENT: &str = "deep-link-connect";
+
+/// The secret fields of a connection config.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProfileSecrets {
+    #[serde(default)]
+    password: Option<String>,
+    #[serde(default)]
+    ssh_password: Option<String>,
+    /// Passwords of the SSH jump hosts, in hop order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    ssh_jump_passwords: Vec<Option<String>>,
+    #[serde(default)]
+    encryption_key: Option<SecretString>,
+}
+
+impl ProfileSecrets {
+    /// Moves the secrets out of `config`, leaving it safe to write to disk.
+    pub fn take(config: &mut ConnectionConfig) -> Self {
+        Self {
+            password: config.password.take(),
+            ssh_password: config
+                .ssh_config
+                .as_mut()
+                .and_then(|ssh| ssh.password.take()),
+            ssh_jump_passwords: config
+                .ssh_config
+                .as_mut()
+                .map(|ssh| ssh.jump_hosts.iter_mut().map(|hop| hop.password.take()).collect::<Vec<_>>())
+                .filter(|passwords| passwords.iter().any(Option::is_some))
+                .unwrap_or_default(),
+            encryption_key: config.encryption_key.take(),
+        }
+    }
+
+    pub fn restore(self, config: &mut ConnectionConfig) {
+        config.password = self.password;
+        if let Some(ssh) = config.ssh_config.as_mut() {
+            ssh.password = self.ssh_password;
+            for (hop, password) in ssh.jump_hosts.iter_mut().zip(self.ssh_jump_passwords) {
+                hop.password = password;
+            }
+        }
+        config.encryption_key = self.encryption_key;
+    }
+
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// These secrets, with any field they lack taken from `other`.
+    fn or(self, other: Self) -> Self {
+        Self {
+            password: self.password.or(other.password),
+            ssh_password: self.ssh_password.or(other.ssh_password),
+            ssh_jump_passwords: if self.ssh_jump_passwords.is_empty() {
+                other.ssh_jump_passwords
+            } else {
+                self.ssh_jump_passwords
+            },
+            encryption_key: self.encryption_key.or(other.encryption_key),
+        }
+    }
+}
+
+fn keychain_entry(profile_id: &str) -> Result<keyring::Entry> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, profile_id)
+        .map_err(|e| anyhow!("Keychain is unavailable: {}", e))
+}
+
+pub fn store_secrets(profile_id: &str, secrets: &ProfileSecrets) -> Result<()> {
+    if secrets.is_empty() {
+        return delete_secrets(profile_id);
+    }
+    keychain_entry(profile_id)?
+        .set_password(&serde_json::to_string(secrets)?)
+        .map_err(|e| anyhow!("Failed to save secrets to the keychain: {}", e))
+}
+
+/// Secrets saved for a profile; none if the keychain has no entry for it.
+pub fn load_secrets(profile_id: &str) -> Result<ProfileSecrets> {
+    match keychain_entry(profile_id)?.get_password() {
+        Ok(json) => serde_json::from_str(&json).map_err(|e| {
+            anyhow!("Secrets saved for {} are unreadable ({}); save the connection again to replace them", profile_id, e)
+        }),
+        Err(keyring::Error::NoEntry) => Ok(ProfileSecrets::default()),
+        Err(e) => Err(anyhow!("Failed to read secrets from the keychain: {}", e)),
+    }
+}
+
+pub fn delete_secrets(profile_id: &str) -> Result<()> {
+    match keychain_entry(profile_id)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(anyhow!("Failed to remove secrets from the keychain: {}", e)),
+    }
+}
+
+/// Saves `secret` as the password of `connection_id`, next to any other
+/// secrets already stored for it.
+pub fn save_credential(connection_id: &str, secret: String) -> Result<()> {
+    let mut secrets = load_secrets(connection_id)?;
+    secrets.password = Some(secret);
+    store_secrets(connection_id, &secrets)
+}
+
+pub fn get_credential(connection_id: &str) -> Result<Option<String>> {
+    Ok(load_secrets(connection_id)?.password)
+}
+
+pub fn delete_credential(connection_id: &str) -> Result<()> {
+    let mut secrets = load_secrets(connection_id)?;
+    secrets.password = None;
+    store_secrets(connection_id, &secrets)
+}
+
+/// Puts the stored password into a config that sets `use_stored_credential`
+/// and carries no password of its own.
+pub fn fill_stored_credential(config: &mut ConnectionConfig) -> Result<()> {
+    if !config.use_stored_credential || config.password.as_deref().is_some_and(|password| !password.is_empty()) {
+        return Ok(());
+    }
+    let password = get_credential(&config.id)?
+        .ok_or_else(|| anyhow!("No password is stored in the keychain for {}", config.name))?;
+    config.password = Some(password);
+    Ok(())
+}
+
+/// Moves secrets that older versions left in the store file into the
+/// keychain, returning how many profiles were cleaned. A profile keeps its
+/// plaintext secrets until the keychain has taken them.
+pub fn migrate_plaintext_secrets(store: &AppStore) -> Result<usize> {
+    migrate_secrets_with(store, |profile_id, secrets| {
+        store_secrets(profile_id, &secrets.or(load_secrets(profile_id)?))
+    })
+}
+
+fn migrate_secrets_with(
+    store: &AppStore,
+    mut save: impl FnMut(&str, ProfileSecrets) -> Result<()>,
+) -> Result<usize> {
+    let mut migrated = 0;
+    for mut profile in store.list_connection_profiles()? {
+        let secrets = ProfileSecrets::take(&mut profile.config);
+        if secrets.is_empty() {
+            continue;
+        }
+        save(&profile.config.id, secrets)?;
+        store.save_connection_profile(profile)?;
+        migrated += 1;
+    }
+    Ok(migrated)
+}
+
+/// A file of saved connections to share with a team.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConnectionBundle {
+    pub format_version: u32,
+    pub exported_at: String,
+    /// Whether the connections carry their passwords and keys.
+    pub includes_passwords: bool,
+    pub connections: Vec<ConnectionProfile>,
+}
+
+/// One connection in a bundle that couldn't be read.
+#[derive(Debug, PartialEq)]
+pub struct BundleEntryError {
+    /// Position in the file, from 1.
+    pub entry: usize,
+    pub name: Option<String>,
+    pub reason: String,
+}
+
+/// A bundle with connections that couldn't be read; nothing was imported.
+#[derive(Debug)]
+pub struct InvalidBundle {
+    pub entries: Vec<BundleEntryError>,
+}
+
+impl std::fmt::Display for InvalidBundle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let entries = self
+            .entries
+            .iter()
+            .map(|entry| match &entry.name {
+                Some(name) => format!("#{} ({}): {}", entry.entry, name, entry.reason),
+                None => format!("#{}: {}", entry.entry, entry.reason),
+            })
+            .collect::<Vec<_>>();
+        write!(f, "{} connection(s) in the file can't be read: {}", entries.len(), entries.join("; "))
+    }
+}
+
+impl std::error::Error for InvalidBundle {}
+
+/// The saved profiles as a bundle; without `include_passwords` their secrets
+/// stay in the keychain.
+pub fn export_bundle(store: &AppStore, include_passwords: bool) -> Result<ConnectionBundle> {
+    let mut connections = store.list_connection_profiles()?;
+    for profile in &mut connections {
+        if include_passwords {
+            load_secrets(&profile.config.id)?.restore(&mut profile.config);
+        } else {
+            ProfileSecrets::take(&mut profile.config);
+        }
+    }
+    Ok(ConnectionBundle {
+        format_version: BUNDLE_FORMAT_VERSION,
+        exported_at: Utc::now().to_rfc3339(),
+        includes_passwords: include_passwords,
+        connections,
+    })
+}
+
+/// Reads a bundle, checking every connection before any is used.
+pub fn parse_bundle(contents: &str) -> Result<ConnectionBundle> {
+    let value: serde_json::Value =
+        serde_json::from_str(contents).map_err(|e| anyhow!("The file isn't a connection bundle: {}", e))?;
+    let format_version = value
+        .get("format_version")
+        .and_then(|version| version.as_u64())
+        .ok_or_else(|| anyhow!("The file isn't a connection bundle: it has no format_version"))?;
+    if format_version > BUNDLE_FORMAT_VERSION as u64 {
+        return Err(anyhow!(
+            "The bundle is format {}, this version of NodaDB reads up to {}",
+            format_version,
+            BUNDLE_FORMAT_VERSION
+        ));
+    }
+    let entries = value
+        .get("connections")
+        .and_then(|connections| connections.as_array())
+        .ok_or_else(|| anyhow!("The file isn't a connection bundle: it has no connections list"))?;
+
+    let mut connections = Vec::new();
+    let mut failures = Vec::new();
+    for (index, entry) in entries.iter().enumerate() {
+        match serde_json::from_value::<ConnectionProfile>(entry.clone()) {
+            Ok(profile) => connections.push(profile),
+            Err(e) => failures.push(BundleEntryError {
+                entry: index + 1,
+                name: entry.get("name").and_then(|name| name.as_str()).map(str::to_string),
+                reason: e.to_string(),
+            }),
+        }
+    }
+    if !failures.is_empty() {
+        return Err(InvalidBundle { entries: failures }.into());
+    }
+    Ok(ConnectionBundle {
+        format_version: format_version as u32,
+        exported_at: value
+            .get("exported_at")
+            .and_then(|at| at.as_str())
+            .unwrap_or_default()
+            .to_string(),
+        includes_passwords: value
+            .get("includes_passwords")
+            .and_then(|includes| includes.as_bool())
+            .unwrap_or(false),
+        connections,
+    })
+}
+
+/// The bundle's connections under fresh ids, so they never replace existing
+/// ones, each with the secrets it carried. Server connections from a bundle
+/// without passwords are marked as needing credentials.
+pub fn prepare_import(bundle: ConnectionBundle) -> Vec<(ConnectionProfile, ProfileSecrets)> {
+    bundle
+        .connections
+        .into_iter()
+        .map(|mut profile| {
+            profile.config.id = uuid::Uuid::new_v4().to_string();
+            let secrets = ProfileSecrets::take(&mut profile.config);
+            profile.credentials_required =
+                !bundle.includes_passwords && profile.config.db_type != DatabaseType::SQLite;
+            (profile, secrets)
+        })
+        .collect()
+}
+
+/// Imports a bundle as new profiles, their secrets going to the keychain.
+pub fn import_bundle(store: &AppStore, contents: &str) -> Result<Vec<ConnectionProfile>> {
+    let imported = prepare_import(parse_bundle(contents)?);
+    let mut profiles = Vec::with_capacity(imported.len());
+    for (profile, secrets) in imported {
+        store_secrets(&profile.config.id, &secrets)?;
+        store.save_connection_profile(profile.clone())?;
+        profiles.push(profile);
+    }
+    Ok(profiles)
+}
+
+/// Finds a connection by id, by name, or by `folder/name`. Names compare
+/// case-insensitively; when several match, all of them are returned.
+pub fn resolve(
+    profiles: &[ConnectionProfile],
+    active: &[ConnectionConfig],
+    name_or_id: &str,
+) -> ConnectionResolution {
+    let name_or_id = name_or_id.trim();
+    let mut connections: Vec<ResolvedConnection> = profiles
+        .iter()
+        .map(|profile| ResolvedConnection {
+            id: profile.config.id.clone(),
+            name: profile.config.name.clone(),
+            folder: profile.folder.clone(),
+            saved: true,
+            active: active.iter().any(|config| config.id == profile.config.id),
+        })
+        .collect();
+    for config in active {
+        if !connections.iter().any(|connection| connection.id == config.id) {
+            connections.push(ResolvedConnection {
+                id: config.id.clone(),
+                name: config.name.clone(),
+                folder: None,
+                saved: false,
+                active: true,
+            });
+        }
+    }
+
+    if let Some(connection) = connections.iter().find(|connection| connection.id == name_or_id) {
+        return ConnectionResolution::Found {
+            connection: connection.clone(),
+        };
+    }
+
+    let by_name = |connection: &&ResolvedConnection| connection.name.eq_ignore_ascii_case(name_or_id);
+    let mut candidates: Vec<ResolvedConnection> = connections.iter().filter(by_name).cloned().collect();
+    if candidates.is_empty() {
+        if let Some((folder, name)) = name_or_id.rsplit_once('/') {
+            candidates = connections
+                .iter()
+                .filter(|connection| {
+                    connection.name.eq_ignore_ascii_case(name.trim())
+                        && connection
+                            .folder
+                            .as_deref()
+                            .is_some_and(|own| own.trim_matches('/').eq_ignore_ascii_case(folder.trim().trim_matches('/')))
+                })
+                .cloned()
+                .collect();
+        }
+    }
+
+    match candidates.len() {
+        0 => ConnectionResolution::NotFound,
+        1 => ConnectionResolution::Found {
+            connection: candidates.remove(0),
+        },
+        _ => ConnectionResolution::Ambiguous { candidates },
+    }
+}
+
+/// Resolves `name_or_id` against the saved profiles and open connections.
+pub async fn resolve_connection(
+    store: &AppStore,
+    manager: &ConnectionManager,
+    name_or_id: &str,
+) -> Result<ConnectionResolution> {
+    let profiles = store.list_connection_profiles()?;
+    let active = manager.active_configs().await;
+    Ok(resolve(&profiles, &active, name_or_id))
+}
+
+/// The single connection a resolution found; an error naming the candidates otherwise.
+pub fn single_match(resolution: &ConnectionResolution, name_or_id: &str) -> Result<ResolvedConnection> {
+    match resolution {
+        ConnectionResolution::Found { connection } => Ok(connection.clone()),
+        ConnectionResolution::Ambiguous { candidates } => Err(anyhow!(
+            "Several connections are named {}: {}",
+            name_or_id,
+            candidates
+                .iter()
+                .map(|candidate| match &candidate.folder {
+                    Some(folder) => format!("{}/{} ({})", folder, candidate.name, candidate.id),
+                    None => format!("{} ({})", candidate.name, candidate.id),
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+        ConnectionResolution::NotFound => Err(anyhow!("No connection named {}", name_or_id)),
+    }
+}
+
+/// Opens a resolved connection from its saved profile and keychain secrets.
+/// Connections that are already open are left as they are.
+pub async fn connect_resolved(
+    store: &AppStore,
+    manager: &ConnectionManager,
+    connection: &ResolvedConnection,
+) -> Result<()> {
+    if connection.active {
+        return Ok(());
+    }
+    manager.connect(profile_config(store, &connection.id)?).await
+}
+
+/// The saved config of profile `id`, with its secrets back from the keychain.
+pub fn profile_config(store: &AppStore, id: &str) -> Result<ConnectionConfig> {
+    let mut config = store
+        .list_connection_profiles()?
+        .into_iter()
+        .find(|profile| profile.config.id == id)
+        .map(|profile| profile.config)
+        .ok_or_else(|| anyhow!("Profile {} no longer exists", id))?;
+    load_secrets(&config.id)?.restore(&mut config);
+    Ok(config)
+}
+
+/// The connection a `noda://connect?name=staging` (or `?id=...`) link refers to.
+pub fn deep_link_target(url: &url::Url) -> Result<String> {
+    if url.scheme() != DEEP_LINK_SCHEME {
+        return Err(anyhow!("Unsupported link scheme {}", url.scheme()));
+    }
+    // `noda://connect` parses "connect" as the host; `noda:connect` as the path.
+    let action = url
+        .host_str()
+        .unwrap_or_else(|| url.path().trim_matches('/'));
+    if action != "connect" {
+        return Err(anyhow!("Unsupported link action {}", action));
+    }
+    url.query_pairs()
+        .find(|(key, _)| key == "name" || key == "id" || key == "profile")
+        .map(|(_, value)| value.into_owned())
+        .filter(|value| !value.trim().is_empty())
+        .ok_or_else(|| anyhow!("Link has no connection name"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ConnectTarget;
+
+    fn profile(id: &str, name: &str, folder: Option<&str>) -> ConnectionProfile {
+        ConnectionProfile {
+            config: ConnectionConfig {
+                id: id.to_string(),
+                name: name.to_string(),
+                db_type: DatabaseType::PostgreSQL,
+                host: Some("localhost".to_string()),
+                port: Some(5432),
+                username: Some("app".to_string()),
+                password: Some("secret".to_string()),
+                database: Some("app".to_string()),
+                file_path: None,
+                ssh_config: None,
+                retry_policy: None,
+                idle_disconnect_minutes: None,
+                idle_disconnect_rollback: false,
+                encryption_key: None,
+                cipher_settings: None,
+                schema_watch_seconds: None,
+                max_concurrent_operations: None,
+                read_replica: None,
+                failover_hosts: Vec::new(),
+                require_read_write: false,
+                connect_timeout_seconds: None,
+                scratch_schema: None,
+                sql_mode: None,
+                decode_overrides: Default::default(),
+                mysql_ssl: None,
+                pool_settings: None,
+                socket_path: None,
+                connection_url: None,
+                sqlite_open_mode: Default::default(),
+                read_only: false,
+                statement_timeout_seconds: None,
+                reconnect_attempts: None,
+                keepalive_seconds: None,
+                application_name: None,
+                session_params: Default::default(),
+                default_schema: None,
+                charset: None,
+                collation: None,
+                use_stored_credential: false,
+                trust_server_certificate: false,
+            },
+            folder: folder.map(str::to_string),
+            credentials_required: false,
+        }
+    }
+
+    #[test]
+    fn resolves_by_id_name_and_folder() {
+        let profiles = vec![
+            profile("p1", "Staging", Some("Client A")),
+            profile("p2", "staging", Some("Client B")),
+            profile("p3", "Production", None),
+        ];
+        let active = vec![profile("p3", "Production", None).config, profile("adhoc", "Scratch", None).config];
+
+        let found = |query: &str| match resolve(&profiles, &active, query) {
+            ConnectionResolution::Found { connection } => Some(connection.id),
+            _ => None,
+        };
+        assert_eq!(found("p2").as_deref(), Some("p2"));
+        assert_eq!(found("production").as_deref(), Some("p3"));
+        assert_eq!(found("Scratch").as_deref(), Some("adhoc"));
+        assert_eq!(found("client b/Staging").as_deref(), Some("p2"));
+
+        match resolve(&profiles, &active, "staging") {
+            ConnectionResolution::Ambiguous { candidates } => {
+                let folders: Vec<_> = candidates.iter().map(|c| c.folder.clone().unwrap()).collect();
+                assert_eq!(folders, vec!["Client A", "Client B"]);
+            }
+            other => panic!("expected an ambiguous match, got {:?}", other),
+        }
+        assert_eq!(resolve(&profiles, &active, "missing"), ConnectionResolution::NotFound);
+    }
+
+    #[test]
+    fn secrets_are_split_from_profiles_and_links_are_parsed() {
+        let mut config = profile("p1", "Staging", None).config;
+        let secrets = ProfileSecrets::take(&mut config);
+        assert_eq!(config.password, None);
+        secrets.restore(&mut config);
+        assert_eq!(config.password.as_deref(), Some("secret"));
+
+        config.ssh_config = serde_json::from_value(serde_json::json!({
+            "enabled": true, "host": "internal", "port": 22, "username": "deploy",
+            "auth_method": "password", "password": "inner", "local_port": null,
+            "jump_hosts": [{
+                "host": "edge", "port": 22, "username": "deploy",
+                "auth_method": "password", "password": "outer", "private_key_path": null,
+            }],
+        }))
+        .unwrap();
+        let secrets = ProfileSecrets::take(&mut config);
+        let ssh = config.ssh_config.as_ref().unwrap();
+        assert_eq!((ssh.password.as_deref(), ssh.jump_hosts[0].password.as_deref()), (None, None));
+        secrets.restore(&mut config);
+        let ssh = config.ssh_config.as_ref().unwrap();
+        assert_eq!(ssh.jump_hosts[0].password.as_deref(), Some("outer"));
+
+        let url = url::Url::parse("noda://connect?name=Client%20A/staging").unwrap();
+        assert_eq!(deep_link_target(&url).unwrap(), "Client A/staging");
+        assert!(deep_link_target(&url::Url::parse("noda://open?name=x").unwrap()).is_err());
+        assert!(deep_link_target(&url::Url::parse("noda://connect").unwrap()).is_err());
+    }
+
+    #[test]
+    fn connect_targets_name_a_profile_or_report_what_is_wrong_with_a_config() {
+        let target: ConnectTarget = serde_json::from_value(serde_json::json!({ "profile": "Staging" })).unwrap();
+        assert!(matches!(target, ConnectTarget::Profile { profile } if profile == "Staging"));
+
+        let config = serde_json::to_value(profile("p1", "Staging", None).config).unwrap();
+        let target: ConnectTarget = serde_json::from_value(config.clone()).unwrap();
+        assert!(matches!(target, ConnectTarget::Config(config) if config.id == "p1"));
+
+        let mut malformed = config;
+        malformed["port"] = serde_json::json!("5432");
+        let error = serde_json::from_value::<ConnectTarget>(malformed).unwrap_err().to_string();
+        assert!(error.contains("invalid type: string \"5432\""), "{}", error);
+    }
+
+    #[test]
+    fn stored_credentials_are_only_looked_up_when_asked_for() {
+        // Neither case touches the keychain.
+        let mut config = profile("p1", "Staging", None).config;
+        fill_stored_credential(&mut config).unwrap();
+        assert_eq!(config.password.as_deref(), Some("secret"));
+
+        config.use_stored_credential = true;
+        fill_stored_credential(&mut config).unwrap();
+        assert_eq!(config.password.as_deref(), Some("secret"));
+    }
+
+    #[test]
+    fn plaintext_secrets_in_an_old_store_move_out_and_damaged_profiles_are_dropped() {
+        let path = std::env::temp_dir().join(format!("nodadb-store-{}.json", uuid::Uuid::new_v4()));
+        let saved = serde_json::to_value(profile("p1", "Staging", None)).unwrap();
+        std::fs::write(
+            &path,
+            serde_json::json!({ "connection_profiles": [saved, { "config": "oops" }] }).to_string(),
+        )
+        .unwrap();
+
+        let store = AppStore::open(path.clone()).unwrap();
+        let mut keychain = Vec::new();
+        let migrated = migrate_secrets_with(&store, |id, secrets| {
+            keychain.push((id.to_string(), secrets.password.clone()));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(migrated, 1);
+        assert_eq!(keychain, vec![("p1".to_string(), Some("secret".to_string()))]);
+
+        let reopened = AppStore::open(path.clone()).unwrap();
+        let profiles = reopened.list_connection_profiles().unwrap();
+        assert_eq!(profiles.len(), 1);
+        assert_eq!(profiles[0].config.password, None);
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("secret"));
+        assert_eq!(migrate_secrets_with(&reopened, |_, _| panic!("nothing left to move")).unwrap(), 0);
+
+        let failing = AppStore::in_memory();
+        failing.save_connection_profile(profile("p2", "Prod", None)).unwrap();
+        assert!(migrate_secrets_with(&failing, |_, _| Err(anyhow!("locked"))).is_err());
+        assert!(failing.list_connection_profiles().unwrap()[0].config.password.is_some());
+
+        for leftover in [path.with_extension("json.corrupt"), path.with_extension("json.v0.bak"), path] {
+            let _ = std::fs::remove_file(leftover);
+        }
+    }
+
+    #[test]
+    fn bundles_strip_passwords_and_import_under_new_ids() {
+        let store = AppStore::in_memory();
+        store.save_connection_profile(profile("p1", "Staging", Some("Client A"))).unwrap();
+        let mut local = profile("p2", "Local", None);
+        local.config.db_type = DatabaseType::SQLite;
+        store.save_connection_profile(local).unwrap();
+
+        let bundle = export_bundle(&store, false).unwrap();
+        let contents = serde_json::to_string(&bundle).unwrap();
+        assert!(!contents.contains("secret"));
+
+        let imported = prepare_import(parse_bundle(&contents).unwrap());
+        assert_eq!(imported.len(), 2);
+        let (staging, secrets) = &imported[0];
+        assert_ne!(staging.config.id, "p1");
+        assert_eq!(staging.folder.as_deref(), Some("Client A"));
+        assert!(staging.credentials_required && secrets.is_empty());
+        assert!(!imported[1].0.credentials_required);
+
+        let mut shared = bundle;
+        shared.includes_passwords = true;
+        shared.connections[0].config.password = Some("secret".to_string());
+        let (profile, secrets) = prepare_import(shared).remove(0);
+        assert!(!profile.credentials_required);
+        assert_eq!(secrets.password.as_deref(), Some("secret"));
+    }
+
+    #[test]
+    fn malformed_bundles_list_the_entries_that_failed() {
+        let good = serde_json::to_value(profile("p1", "Staging", None)).unwrap();
+        let contents = serde_json::json!({
+            "format_version": 1,
+            "connections": [good, { "id": "x", "name": "Prod" }, 42],
+        })
+        .to_string();
+        let error = parse_bundle(&contents).unwrap_err();
+        let invalid = error.downcast_ref::<InvalidBundle>().unwrap();
+        let entries: Vec<_> = invalid.entries.iter().map(|e| (e.entry, e.name.as_deref())).collect();
+        assert_eq!(entries, vec![(2, Some("Prod")), (3, None)]);
+        assert!(error.to_string().starts_with("2 connection(s) in the file can't be read: #2 (Prod): missing field"));
+
+        assert!(parse_bundle("{ nope").is_err());
+        assert!(parse_bundle(r#"{ "connections": [] }"#).is_err());
+        assert!(parse_bundle(r#"{ "format_version": 9, "connections": [] }"#).is_err());
+    }
+}
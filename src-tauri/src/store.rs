@@ -0,0 +1,776 @@
+use crate::database::index_suggestions;
+use crate::models::{
+    AppStoreStatus, CachedTableList, ConnectionProfile, DismissedIndexSuggestion, FilterUsage, RecentSqliteFile, RowFilter,
+    StoreCollectionCount, StoreRepair, TableViewPreferences, Workspace,
+};
+use crate::store_migrations::{self, dedupe_by, salvage_list};
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+pub const STORE_FILE_NAME: &str = "nodadb-store.json";
+const BACKUP_FORMAT_VERSION: u32 = 1;
+const MAX_RECENT_SQLITE_FILES: usize = 20;
+
+/// Everything NodaDB persists locally on the backend side.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StoreData {
+    #[serde(default)]
+    pub table_views: Vec<TableViewPreferences>,
+    #[serde(default)]
+    pub recent_sqlite_files: Vec<RecentSqliteFile>,
+    /// Saved connections, with their secrets stripped.
+    #[serde(default)]
+    pub connection_profiles: Vec<ConnectionProfile>,
+    #[serde(default)]
+    pub workspaces: Vec<Workspace>,
+    /// Filters applied to tables, for index suggestions.
+    #[serde(default)]
+    pub filter_usage: Vec<FilterUsage>,
+    #[serde(default)]
+    pub dismissed_index_suggestions: Vec<DismissedIndexSuggestion>,
+    /// Last table list per connection, shown while a fresh one loads.
+    #[serde(default)]
+    pub table_lists: Vec<CachedTableList>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppDataBackup {
+    pub format_version: u32,
+    pub exported_at: String,
+    pub data: StoreData,
+}
+
+impl TableViewPreferences {
+    pub fn matches(&self, connection_id: &str, schema: Option<&str>, table_name: &str) -> bool {
+        self.connection_id == connection_id
+            && self.schema.as_deref() == schema
+            && self.table_name == table_name
+    }
+
+    /// Drops every reference to columns that are not in `existing_columns`
+    /// and returns the names that were removed.
+    pub fn reconcile(&mut self, existing_columns: &[String]) -> Vec<String> {
+        let exists = |column: &String| existing_columns.contains(column);
+        let mut dropped = self
+            .visible_columns
+            .iter()
+            .chain(self.column_order.iter())
+            .chain(self.column_widths.keys())
+            .chain(self.default_sort.iter().map(|sort| &sort.column))
+            .filter(|column| !exists(column))
+            .cloned()
+            .collect::<Vec<_>>();
+        dropped.sort();
+        dropped.dedup();
+
+        self.visible_columns.retain(exists);
+        self.column_order.retain(exists);
+        self.column_widths.retain(|column, _| exists(column));
+        if self
+            .default_sort
+            .as_ref()
+            .is_some_and(|sort| !exists(&sort.column))
+        {
+            self.default_sort = None;
+        }
+
+        dropped
+    }
+
+    /// Visible columns in the saved display order; empty means "all columns".
+    pub fn selected_columns(&self) -> Vec<String> {
+        let mut selected = self
+            .column_order
+            .iter()
+            .filter(|column| self.visible_columns.contains(column))
+            .cloned()
+            .collect::<Vec<_>>();
+        for column in &self.visible_columns {
+            if !selected.contains(column) {
+                selected.push(column.clone());
+            }
+        }
+        selected
+    }
+}
+
+impl StoreData {
+    /// Entries per list, in the order of `store_migrations::COLLECTIONS`.
+    fn collection_rows(&self) -> [(&'static str, usize); 7] {
+        [
+            ("table_views", self.table_views.len()),
+            ("recent_sqlite_files", self.recent_sqlite_files.len()),
+            ("connection_profiles", self.connection_profiles.len()),
+            ("workspaces", self.workspaces.len()),
+            ("filter_usage", self.filter_usage.len()),
+            ("dismissed_index_suggestions", self.dismissed_index_suggestions.len()),
+            ("table_lists", self.table_lists.len()),
+        ]
+    }
+
+    /// Drops entries that repeat an earlier one's key, returning how many went
+    /// from each list.
+    fn dedupe(&mut self) -> [usize; 7] {
+        [
+            dedupe_by(&mut self.table_views, |view| {
+                (view.connection_id.clone(), view.schema.clone(), view.table_name.clone())
+            }),
+            dedupe_by(&mut self.recent_sqlite_files, |file| file.file_path.clone()),
+            dedupe_by(&mut self.connection_profiles, |profile| profile.config.id.clone()),
+            dedupe_by(&mut self.workspaces, |workspace| workspace.name.to_lowercase()),
+            dedupe_by(&mut self.filter_usage, |usage| {
+                (usage.connection_id.clone(), usage.table_name.clone(), format!("{:?}", usage.columns))
+            }),
+            dedupe_by(&mut self.dismissed_index_suggestions, |dismissed| {
+                (dismissed.connection_id.clone(), dismissed.suggestion_id.clone())
+            }),
+            dedupe_by(&mut self.table_lists, |list| list.connection_id.clone()),
+        ]
+    }
+
+    /// The readable entries of a damaged store, de-duplicated, with what was
+    /// kept and dropped per list.
+    fn salvage(value: &serde_json::Value) -> (Self, Vec<StoreCollectionCount>) {
+        let (table_views, views_dropped) = salvage_list(value, "table_views");
+        let (recent_sqlite_files, recent_dropped) = salvage_list(value, "recent_sqlite_files");
+        let (connection_profiles, profiles_dropped) = salvage_list(value, "connection_profiles");
+        let (workspaces, workspaces_dropped) = salvage_list(value, "workspaces");
+        let (filter_usage, usage_dropped) = salvage_list(value, "filter_usage");
+        let (dismissed_index_suggestions, dismissed_dropped) = salvage_list(value, "dismissed_index_suggestions");
+        let (table_lists, lists_dropped) = salvage_list(value, "table_lists");
+        let unreadable = [
+            views_dropped,
+            recent_dropped,
+            profiles_dropped,
+            workspaces_dropped,
+            usage_dropped,
+            dismissed_dropped,
+            lists_dropped,
+        ];
+        let mut data = Self {
+            table_views,
+            recent_sqlite_files,
+            connection_profiles,
+            workspaces,
+            filter_usage,
+            dismissed_index_suggestions,
+            table_lists,
+        };
+        let duplicates = data.dedupe();
+        let counts = data
+            .collection_rows()
+            .iter()
+            .zip(unreadable.iter().zip(duplicates))
+            .map(|((name, rows), (unreadable, duplicates))| StoreCollectionCount {
+                name: name.to_string(),
+                rows: *rows,
+                dropped: unreadable + duplicates,
+            })
+            .collect();
+        (data, counts)
+    }
+}
+
+/// Migration and repair events since the store was opened, for its status.
+#[derive(Debug, Default)]
+struct StoreHistory {
+    migrated_from: Option<u32>,
+    migration_backup_path: Option<PathBuf>,
+    last_repair: Option<StoreRepair>,
+}
+
+/// JSON-file backed store in the app data directory. Every mutation is written
+/// through to disk so nothing is lost if the app is killed. Everything that
+/// persists app data goes through here, so the file has one writer and one
+/// migration path.
+#[derive(Clone)]
+pub struct AppStore {
+    path: Option<PathBuf>,
+    data: Arc<RwLock<StoreData>>,
+    history: Arc<RwLock<StoreHistory>>,
+}
+
+impl AppStore {
+    /// Store that lives only in memory, used until the app data directory is known and in tests.
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            data: Arc::new(RwLock::new(StoreData::default())),
+            history: Arc::new(RwLock::new(StoreHistory::default())),
+        }
+    }
+
+    /// Opens the store file. An older layout is migrated, after copying the
+    /// file aside, and a damaged file is rebuilt from its readable entries.
+    pub fn open(path: PathBuf) -> Result<Self> {
+        let mut history = StoreHistory::default();
+        let data = if path.exists() {
+            let contents = std::fs::read_to_string(&path)
+                .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+            match serde_json::from_str::<serde_json::Value>(&contents) {
+                Ok(value) => Self::load(&path, &value, &mut history)?,
+                Err(e) => {
+                    eprintln!("Local store {} is unreadable ({}), rebuilding it", path.display(), e);
+                    Self::rebuild(&path, &serde_json::Value::Null, &mut history)?
+                }
+            }
+        } else {
+            StoreData::default()
+        };
+
+        Ok(Self {
+            path: Some(path),
+            data: Arc::new(RwLock::new(data)),
+            history: Arc::new(RwLock::new(history)),
+        })
+    }
+
+    fn load(path: &Path, value: &serde_json::Value, history: &mut StoreHistory) -> Result<StoreData> {
+        let from = store_migrations::version(value);
+        let migrated = match store_migrations::migrate(value) {
+            Ok(migrated) => migrated,
+            // Rebuilding would throw away what the newer version added.
+            Err(e) if from > store_migrations::CURRENT_VERSION => return Err(e),
+            Err(e) => {
+                eprintln!("Local store {} can't be migrated ({}), rebuilding it", path.display(), e);
+                return Self::rebuild(path, value, history);
+            }
+        };
+        let data = match serde_json::from_value::<StoreData>(migrated) {
+            Ok(data) => data,
+            Err(e) => {
+                eprintln!("Local store {} is damaged ({}), rebuilding it", path.display(), e);
+                return Self::rebuild(path, value, history);
+            }
+        };
+        if from < store_migrations::CURRENT_VERSION {
+            // Nothing is written until every step succeeded, and the rename in
+            // write_file swaps the whole file at once.
+            let backup_path = path.with_extension(format!("json.v{}.bak", from));
+            std::fs::copy(path, &backup_path)
+                .map_err(|e| anyhow!("Failed to back up {} before migrating: {}", path.display(), e))?;
+            Self::write_file(path, &data)?;
+            history.migrated_from = Some(from);
+            history.migration_backup_path = Some(backup_path);
+        }
+        Ok(data)
+    }
+
+    /// Moves the damaged file aside and writes what could be salvaged from it.
+    fn rebuild(path: &Path, value: &serde_json::Value, history: &mut StoreHistory) -> Result<StoreData> {
+        let (data, collections) = StoreData::salvage(value);
+        let mut damaged_copy_path = path.with_extension("json.corrupt");
+        if damaged_copy_path.exists() {
+            damaged_copy_path = path.with_extension(format!("json.corrupt-{}", Utc::now().format("%Y%m%d%H%M%S")));
+        }
+        std::fs::rename(path, &damaged_copy_path)?;
+        Self::write_file(path, &data)?;
+        history.last_repair = Some(StoreRepair {
+            repaired_at: Utc::now().to_rfc3339(),
+            damaged_copy_path: damaged_copy_path.display().to_string(),
+            collections,
+        });
+        Ok(data)
+    }
+
+    /// Checks the file on disk: it must parse, be at the current version and
+    /// hold the same entries per list as the loaded store, none duplicated.
+    pub fn status(&self) -> Result<AppStoreStatus> {
+        let mut data = self.export_data()?;
+        let history = self
+            .history
+            .read()
+            .map_err(|_| anyhow!("Local store is unavailable"))?;
+        let mut problems = Vec::new();
+        let collections = data
+            .collection_rows()
+            .iter()
+            .map(|(name, rows)| StoreCollectionCount {
+                name: name.to_string(),
+                rows: *rows,
+                dropped: 0,
+            })
+            .collect::<Vec<_>>();
+        let names = data.collection_rows();
+        for ((name, _), duplicates) in names.iter().zip(data.dedupe()) {
+            if duplicates > 0 {
+                problems.push(format!("{} has {} duplicated entries", name, duplicates));
+            }
+        }
+
+        let mut file_size_bytes = None;
+        let mut schema_version = store_migrations::CURRENT_VERSION;
+        if let Some(path) = self.path.as_ref().filter(|path| path.exists()) {
+            file_size_bytes = std::fs::metadata(path).ok().map(|metadata| metadata.len());
+            match std::fs::read_to_string(path)
+                .map_err(anyhow::Error::from)
+                .and_then(|contents| Ok(serde_json::from_str::<serde_json::Value>(&contents)?))
+            {
+                Ok(value) => {
+                    schema_version = store_migrations::version(&value);
+                    if schema_version != store_migrations::CURRENT_VERSION {
+                        problems.push(format!(
+                            "The file is at version {}, expected {}",
+                            schema_version,
+                            store_migrations::CURRENT_VERSION
+                        ));
+                    }
+                    for count in &collections {
+                        let (on_disk, unreadable) = salvage_list::<serde_json::Value>(&value, &count.name);
+                        if unreadable > 0 {
+                            problems.push(format!("{} isn't a list in the file", count.name));
+                        } else if on_disk.len() != count.rows {
+                            problems.push(format!(
+                                "{} has {} entries in the file but {} loaded",
+                                count.name,
+                                on_disk.len(),
+                                count.rows
+                            ));
+                        }
+                    }
+                    if let Err(e) = serde_json::from_value::<StoreData>(value) {
+                        problems.push(format!("The file doesn't read back: {}", e));
+                    }
+                }
+                Err(e) => problems.push(format!("The file can't be read: {}", e)),
+            }
+        }
+
+        Ok(AppStoreStatus {
+            path: self.path.as_ref().map(|path| path.display().to_string()),
+            file_size_bytes,
+            schema_version,
+            current_schema_version: store_migrations::CURRENT_VERSION,
+            collections,
+            healthy: problems.is_empty(),
+            problems,
+            migrated_from: history.migrated_from,
+            migration_backup_path: history
+                .migration_backup_path
+                .as_ref()
+                .map(|path| path.display().to_string()),
+            last_repair: history.last_repair.clone(),
+        })
+    }
+
+    /// Rebuilds the store file from its readable, de-duplicated entries and
+    /// loads the result. The damaged file is kept next to it.
+    pub fn repair(&self) -> Result<StoreRepair> {
+        let path = self
+            .path
+            .as_ref()
+            .filter(|path| path.exists())
+            .ok_or_else(|| anyhow!("There is no store file to repair"))?;
+        let mut data = self
+            .data
+            .write()
+            .map_err(|_| anyhow!("Local store is unavailable"))?;
+        let mut history = self
+            .history
+            .write()
+            .map_err(|_| anyhow!("Local store is unavailable"))?;
+        let value = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| serde_json::from_str::<serde_json::Value>(&contents).ok())
+            .map(|value| store_migrations::migrate(&value).unwrap_or(value))
+            .unwrap_or_default();
+        *data = Self::rebuild(path, &value, &mut history)?;
+        history
+            .last_repair
+            .clone()
+            .ok_or_else(|| anyhow!("Local store repair left no record"))
+    }
+
+    fn read<T>(&self, f: impl FnOnce(&StoreData) -> T) -> Result<T> {
+        let data = self
+            .data
+            .read()
+            .map_err(|_| anyhow!("Local store is unavailable"))?;
+        Ok(f(&data))
+    }
+
+    fn update<T>(&self, f: impl FnOnce(&mut StoreData) -> T) -> Result<T> {
+        let mut data = self
+            .data
+            .write()
+            .map_err(|_| anyhow!("Local store is unavailable"))?;
+        let value = f(&mut data);
+        if let Some(path) = &self.path {
+            Self::write_file(path, &data)?;
+        }
+        Ok(value)
+    }
+
+    fn write_file(path: &Path, data: &StoreData) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut value = serde_json::to_value(data)?;
+        value["schema_version"] = serde_json::Value::from(store_migrations::CURRENT_VERSION);
+        let contents = serde_json::to_string_pretty(&value)?;
+        let temp_path = path.with_extension("json.tmp");
+        std::fs::write(&temp_path, contents)?;
+        std::fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+
+    pub fn get_table_view(
+        &self,
+        connection_id: &str,
+        schema: Option<&str>,
+        table_name: &str,
+    ) -> Result<Option<TableViewPreferences>> {
+        self.read(|data| {
+            data.table_views
+                .iter()
+                .find(|view| view.matches(connection_id, schema, table_name))
+                .cloned()
+        })
+    }
+
+    pub fn save_table_view(&self, mut view: TableViewPreferences) -> Result<TableViewPreferences> {
+        view.updated_at = Some(Utc::now().to_rfc3339());
+        self.update(|data| {
+            data.table_views.retain(|existing| {
+                !existing.matches(&view.connection_id, view.schema.as_deref(), &view.table_name)
+            });
+            data.table_views.push(view.clone());
+            view
+        })
+    }
+
+    /// Moves `file` to the top of the recent SQLite files, dropping the oldest entries.
+    pub fn record_recent_sqlite_file(&self, mut file: RecentSqliteFile) -> Result<()> {
+        file.last_opened_at = Utc::now().to_rfc3339();
+        file.missing = false;
+        self.update(|data| {
+            data.recent_sqlite_files
+                .retain(|existing| existing.file_path != file.file_path);
+            data.recent_sqlite_files.insert(0, file);
+            data.recent_sqlite_files.truncate(MAX_RECENT_SQLITE_FILES);
+        })
+    }
+
+    /// Recent SQLite files, most recent first, with files that no longer exist flagged.
+    pub fn list_recent_sqlite_files(&self) -> Result<Vec<RecentSqliteFile>> {
+        let mut files = self.read(|data| data.recent_sqlite_files.clone())?;
+        for file in &mut files {
+            file.missing = !Path::new(&file.file_path).is_file();
+        }
+        Ok(files)
+    }
+
+    pub fn remove_recent_sqlite_file(&self, file_path: &str) -> Result<bool> {
+        self.update(|data| {
+            let before = data.recent_sqlite_files.len();
+            data.recent_sqlite_files
+                .retain(|file| file.file_path != file_path);
+            data.recent_sqlite_files.len() != before
+        })
+    }
+
+    /// Adds or replaces the profile with the same id. Secrets must already be stripped.
+    pub fn save_connection_profile(&self, profile: ConnectionProfile) -> Result<()> {
+        self.update(|data| {
+            match data
+                .connection_profiles
+                .iter_mut()
+                .find(|existing| existing.config.id == profile.config.id)
+            {
+                Some(existing) => *existing = profile,
+                None => data.connection_profiles.push(profile),
+            }
+        })
+    }
+
+    pub fn list_connection_profiles(&self) -> Result<Vec<ConnectionProfile>> {
+        self.read(|data| data.connection_profiles.clone())
+    }
+
+    pub fn delete_connection_profile(&self, profile_id: &str) -> Result<bool> {
+        self.update(|data| {
+            let before = data.connection_profiles.len();
+            data.connection_profiles
+                .retain(|profile| profile.config.id != profile_id);
+            data.table_lists.retain(|list| list.connection_id != profile_id);
+            data.connection_profiles.len() != before
+        })
+    }
+
+    /// Adds or replaces the workspace with the same name (compared case-insensitively).
+    pub fn save_workspace(&self, mut workspace: Workspace) -> Result<Workspace> {
+        workspace.saved_at = Utc::now().to_rfc3339();
+        self.update(|data| {
+            data.workspaces
+                .retain(|existing| !existing.name.eq_ignore_ascii_case(&workspace.name));
+            data.workspaces.push(workspace.clone());
+            workspace
+        })
+    }
+
+    pub fn get_workspace(&self, name: &str) -> Result<Option<Workspace>> {
+        self.read(|data| {
+            data.workspaces
+                .iter()
+                .find(|workspace| workspace.name.eq_ignore_ascii_case(name))
+                .cloned()
+        })
+    }
+
+    /// Saved workspaces, most recently saved first.
+    pub fn list_workspaces(&self) -> Result<Vec<Workspace>> {
+        let mut workspaces = self.read(|data| data.workspaces.clone())?;
+        workspaces.sort_by(|a, b| b.saved_at.cmp(&a.saved_at));
+        Ok(workspaces)
+    }
+
+    /// Counts `filters` being applied to a table. Filters no index could help
+    /// with aren't recorded.
+    pub fn record_filter_usage(&self, connection_id: &str, table_name: &str, filters: &[RowFilter]) -> Result<()> {
+        let columns = index_suggestions::filter_shape(filters);
+        if columns.is_empty() {
+            return Ok(());
+        }
+        self.update(|data| {
+            index_suggestions::record(&mut data.filter_usage, connection_id, table_name, columns, Utc::now())
+        })
+    }
+
+    pub fn filter_usage(&self, connection_id: &str, table_name: &str) -> Result<Vec<FilterUsage>> {
+        self.read(|data| {
+            data.filter_usage
+                .iter()
+                .filter(|usage| usage.connection_id == connection_id && usage.table_name == table_name)
+                .cloned()
+                .collect()
+        })
+    }
+
+    pub fn dismiss_index_suggestion(&self, connection_id: &str, table_name: &str, suggestion_id: &str) -> Result<()> {
+        self.update(|data| {
+            data.dismissed_index_suggestions.retain(|dismissed| {
+                !(dismissed.connection_id == connection_id && dismissed.suggestion_id == suggestion_id)
+            });
+            data.dismissed_index_suggestions.push(DismissedIndexSuggestion {
+                connection_id: connection_id.to_string(),
+                table_name: table_name.to_string(),
+                suggestion_id: suggestion_id.to_string(),
+                dismissed_at: Utc::now().to_rfc3339(),
+            });
+        })
+    }
+
+    /// Ids of the suggestions dismissed for a table.
+    pub fn dismissed_index_suggestions(&self, connection_id: &str, table_name: &str) -> Result<Vec<String>> {
+        self.read(|data| {
+            data.dismissed_index_suggestions
+                .iter()
+                .filter(|dismissed| dismissed.connection_id == connection_id && dismissed.table_name == table_name)
+                .map(|dismissed| dismissed.suggestion_id.clone())
+                .collect()
+        })
+    }
+
+    pub fn cached_table_list(&self, connection_id: &str) -> Result<Option<CachedTableList>> {
+        self.read(|data| {
+            data.table_lists
+                .iter()
+                .find(|list| list.connection_id == connection_id)
+                .cloned()
+        })
+    }
+
+    pub fn save_table_list(&self, list: CachedTableList) -> Result<()> {
+        self.update(|data| {
+            data.table_lists.retain(|cached| cached.connection_id != list.connection_id);
+            data.table_lists.push(list);
+        })
+    }
+
+    /// Snapshot of everything in the store, for the app-data backup.
+    pub fn export_data(&self) -> Result<StoreData> {
+        self.read(|data| data.clone())
+    }
+
+    pub fn export_backup(&self, file_path: &str) -> Result<()> {
+        let backup = AppDataBackup {
+            format_version: BACKUP_FORMAT_VERSION,
+            exported_at: Utc::now().to_rfc3339(),
+            data: self.export_data()?,
+        };
+        std::fs::write(file_path, serde_json::to_string_pretty(&backup)?)
+            .map_err(|e| anyhow!("Failed to write backup to {}: {}", file_path, e))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TableSort;
+    use std::collections::BTreeMap;
+
+    fn view(visible: &[&str]) -> TableViewPreferences {
+        TableViewPreferences {
+            connection_id: "conn".to_string(),
+            schema: Some("public".to_string()),
+            table_name: "users".to_string(),
+            visible_columns: visible.iter().map(|c| c.to_string()).collect(),
+            column_order: visible.iter().map(|c| c.to_string()).collect(),
+            column_widths: BTreeMap::new(),
+            default_sort: None,
+            pinned_filters: vec![],
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn saved_views_survive_reopening_the_store() {
+        let path = std::env::temp_dir().join(format!("nodadb-store-{}.json", uuid::Uuid::new_v4()));
+        let store = AppStore::open(path.clone()).unwrap();
+        store.save_table_view(view(&["id", "email"])).unwrap();
+        store.save_table_view(view(&["id"])).unwrap();
+
+        let reopened = AppStore::open(path.clone()).unwrap();
+        let saved = reopened
+            .get_table_view("conn", Some("public"), "users")
+            .unwrap()
+            .unwrap();
+        assert_eq!(saved.visible_columns, vec!["id".to_string()]);
+        assert_eq!(reopened.export_data().unwrap().table_views.len(), 1);
+        assert!(reopened.get_table_view("conn", None, "users").unwrap().is_none());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn reconcile_drops_columns_that_no_longer_exist() {
+        let mut saved = view(&["id", "email", "legacy"]);
+        saved.column_widths.insert("legacy".to_string(), 120.0);
+        saved.default_sort = Some(TableSort {
+            column: "legacy".to_string(),
+            direction: "asc".to_string(),
+        });
+
+        let dropped = saved.reconcile(&["id".to_string(), "email".to_string()]);
+
+        assert_eq!(dropped, vec!["legacy".to_string()]);
+        assert_eq!(saved.selected_columns(), vec!["id".to_string(), "email".to_string()]);
+        assert!(saved.column_widths.is_empty());
+        assert!(saved.default_sort.is_none());
+    }
+
+    #[test]
+    fn unreadable_store_is_set_aside() {
+        let path = std::env::temp_dir().join(format!("nodadb-store-{}.json", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "{ not json").unwrap();
+
+        let store = AppStore::open(path.clone()).unwrap();
+        assert!(store.export_data().unwrap().table_views.is_empty());
+        let corrupt_path = path.with_extension("json.corrupt");
+        assert!(corrupt_path.exists());
+        assert!(store.status().unwrap().healthy);
+
+        std::fs::remove_file(corrupt_path).unwrap();
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn old_stores_are_backed_up_and_migrated_and_damaged_ones_salvaged() {
+        let path = std::env::temp_dir().join(format!("nodadb-store-{}.json", uuid::Uuid::new_v4()));
+        let saved = serde_json::to_value(view(&["id"])).unwrap();
+        std::fs::write(&path, serde_json::json!({ "table_views": [saved.clone()] }).to_string()).unwrap();
+
+        let store = AppStore::open(path.clone()).unwrap();
+        let status = store.status().unwrap();
+        assert!(status.healthy, "{:?}", status.problems);
+        assert_eq!(status.migrated_from, Some(0));
+        assert_eq!(status.schema_version, store_migrations::CURRENT_VERSION);
+        let backup_path = path.with_extension("json.v0.bak");
+        assert!(std::fs::read_to_string(&backup_path).unwrap().contains("\"users\""));
+        assert_eq!(
+            store.export_data().unwrap().collection_rows().map(|(name, _)| name),
+            store_migrations::COLLECTIONS
+        );
+
+        let damaged = serde_json::json!({
+            "schema_version": store_migrations::CURRENT_VERSION,
+            "table_views": [saved.clone(), { "table_name": 7 }, saved],
+            "workspaces": "oops",
+        });
+        std::fs::write(&path, damaged.to_string()).unwrap();
+        assert!(!store.status().unwrap().healthy);
+        let repair = store.repair().unwrap();
+        let views = &repair.collections[0];
+        assert_eq!((views.rows, views.dropped), (1, 2));
+        assert_eq!(repair.collections[3].dropped, 1);
+        assert!(Path::new(&repair.damaged_copy_path).exists());
+        assert!(store.status().unwrap().healthy);
+        assert!(store.get_table_view("conn", Some("public"), "users").unwrap().is_some());
+
+        std::fs::write(&path, serde_json::json!({ "schema_version": 99 }).to_string()).unwrap();
+        assert!(AppStore::open(path.clone()).is_err());
+
+        for leftover in [path.clone(), backup_path, PathBuf::from(repair.damaged_copy_path)] {
+            std::fs::remove_file(leftover).unwrap();
+        }
+    }
+
+    #[test]
+    fn recent_sqlite_files_are_deduplicated_and_flag_missing_files() {
+        let store = AppStore::in_memory();
+        let existing = std::env::temp_dir().join(format!("nodadb-recent-{}.db", uuid::Uuid::new_v4()));
+        std::fs::write(&existing, b"").unwrap();
+        let recent = |path: &str| RecentSqliteFile {
+            file_path: path.to_string(),
+            connection_id: format!("sqlite-{}", path.len()),
+            name: "db".to_string(),
+            last_opened_at: String::new(),
+            missing: false,
+        };
+
+        store.record_recent_sqlite_file(recent(existing.to_str().unwrap())).unwrap();
+        store.record_recent_sqlite_file(recent("/nonexistent/gone.db")).unwrap();
+        store.record_recent_sqlite_file(recent(existing.to_str().unwrap())).unwrap();
+
+        let files = store.list_recent_sqlite_files().unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].file_path, existing.to_str().unwrap());
+        assert!(!files[0].missing);
+        assert!(files[1].missing);
+
+        assert!(store.remove_recent_sqlite_file("/nonexistent/gone.db").unwrap());
+        assert!(!store.remove_recent_sqlite_file("/nonexistent/gone.db").unwrap());
+        std::fs::remove_file(existing).unwrap();
+    }
+
+    #[test]
+    fn filter_usage_and_dismissed_suggestions_survive_reopening_the_store() {
+        let path = std::env::temp_dir().join(format!("nodadb-store-{}.json", uuid::Uuid::new_v4()));
+        let store = AppStore::open(path.clone()).unwrap();
+        let filters = vec![RowFilter {
+            column: "status".to_string(),
+            operator: crate::models::FilterOperator::Eq,
+            value: serde_json::json!("open"),
+        }];
+        store.record_filter_usage("conn", "orders", &filters).unwrap();
+        store.record_filter_usage("conn", "orders", &filters).unwrap();
+        store.record_filter_usage("conn", "orders", &[]).unwrap();
+        store.dismiss_index_suggestion("conn", "orders", "orders(status)").unwrap();
+
+        let reopened = AppStore::open(path.clone()).unwrap();
+        let usage = reopened.filter_usage("conn", "orders").unwrap();
+        assert_eq!(usage.len(), 1);
+        assert_eq!(usage[0].hits, 2);
+        assert_eq!(
+            reopened.dismissed_index_suggestions("conn", "orders").unwrap(),
+            vec!["orders(status)".to_string()]
+        );
+        assert!(reopened.filter_usage("conn", "users").unwrap().is_empty());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
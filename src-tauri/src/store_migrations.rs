@@ -0,0 +1,116 @@
+use anyhow::{anyhow, Result};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::hash::Hash;
+
+/// Layout version of the store file this build reads and writes.
+pub const CURRENT_VERSION: u32 = 1;
+
+/// Lists kept in the store file, by key.
+pub const COLLECTIONS: &[&str] = &[
+    "table_views",
+    "recent_sqlite_files",
+    "connection_profiles",
+    "workspaces",
+    "filter_usage",
+    "dismissed_index_suggestions",
+    "table_lists",
+];
+
+type Migration = fn(&mut Map<String, Value>) -> Result<()>;
+
+/// Entry `n` takes a store at version `n` to version `n + 1`. Append only;
+/// released steps must never change.
+const MIGRATIONS: &[Migration] = &[baseline];
+
+/// Files from before versioning could hold null or leave out lists.
+fn baseline(store: &mut Map<String, Value>) -> Result<()> {
+    for name in COLLECTIONS {
+        match store.get(*name) {
+            None | Some(Value::Null) => {
+                store.insert(name.to_string(), Value::Array(Vec::new()));
+            }
+            Some(Value::Array(_)) => {}
+            Some(_) => return Err(anyhow!("{} isn't a list", name)),
+        }
+    }
+    Ok(())
+}
+
+/// Version recorded in a store file; 0 for files written before versioning.
+pub fn version(store: &Value) -> u32 {
+    store
+        .get("schema_version")
+        .and_then(Value::as_u64)
+        .map_or(0, |version| version as u32)
+}
+
+/// `store` with the migrations it hasn't had applied. All or nothing: a
+/// failing step leaves no partly migrated result.
+pub fn migrate(store: &Value) -> Result<Value> {
+    let from = version(store);
+    if from > CURRENT_VERSION {
+        return Err(anyhow!(
+            "The local store is version {}, written by a newer NodaDB (this one reads up to {})",
+            from,
+            CURRENT_VERSION
+        ));
+    }
+    let mut migrated = store
+        .as_object()
+        .cloned()
+        .ok_or_else(|| anyhow!("The local store isn't a JSON object"))?;
+    for (step, migration) in MIGRATIONS.iter().enumerate().skip(from as usize) {
+        let to = step as u32 + 1;
+        migration(&mut migrated).map_err(|e| anyhow!("Migrating the local store to version {} failed: {}", to, e))?;
+        migrated.insert("schema_version".to_string(), Value::from(to));
+    }
+    Ok(Value::Object(migrated))
+}
+
+/// The entries of list `name` that still read as `T`, and how many didn't.
+pub fn salvage_list<T: DeserializeOwned>(store: &Value, name: &str) -> (Vec<T>, usize) {
+    match store.get(name) {
+        Some(Value::Array(items)) => {
+            let kept = items
+                .iter()
+                .filter_map(|item| serde_json::from_value(item.clone()).ok())
+                .collect::<Vec<T>>();
+            let dropped = items.len() - kept.len();
+            (kept, dropped)
+        }
+        None | Some(Value::Null) => (Vec::new(), 0),
+        Some(_) => (Vec::new(), 1),
+    }
+}
+
+/// Removes all but the first entry per key, returning how many went.
+pub fn dedupe_by<T, K: Eq + Hash>(items: &mut Vec<T>, key: impl Fn(&T) -> K) -> usize {
+    let before = items.len();
+    let mut seen = HashSet::new();
+    items.retain(|item| seen.insert(key(item)));
+    before - items.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unversioned_stores_are_migrated_and_newer_ones_refused() {
+        let old = serde_json::json!({ "table_views": [], "workspaces": null });
+        let migrated = migrate(&old).unwrap();
+        assert_eq!(version(&migrated), CURRENT_VERSION);
+        assert_eq!(migrated["workspaces"], serde_json::json!([]));
+        assert_eq!(migrated["table_lists"], serde_json::json!([]));
+        assert_eq!(migrate(&migrated).unwrap(), migrated);
+
+        assert!(migrate(&serde_json::json!({ "schema_version": CURRENT_VERSION + 1 })).is_err());
+        assert!(migrate(&serde_json::json!({ "workspaces": 3 })).is_err());
+
+        let mut names = vec!["a", "b", "A", "a"];
+        assert_eq!(dedupe_by(&mut names, |name| name.to_string()), 1);
+        assert_eq!(names, vec!["a", "b", "A"]);
+    }
+}
@@ -0,0 +1,187 @@
+use crate::database::ConnectionManager;
+use crate::models::{
+    ConnectionConfig, ConnectionProfile, RestoredConnection, Workspace, WorkspaceConnection,
+    WorkspaceRestore,
+};
+use crate::profiles;
+use crate::store::AppStore;
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// Workspace saved on a graceful shutdown, for reopening the last session.
+pub const LAST_SESSION_WORKSPACE: &str = "last session";
+
+/// What the frontend has open on each connection, registered as it changes so
+/// a workspace can be saved at any moment, shutdown included.
+#[derive(Default)]
+pub struct WorkspaceTracker {
+    states: RwLock<HashMap<String, serde_json::Value>>,
+}
+
+impl WorkspaceTracker {
+    pub fn set_state(&self, connection_id: &str, state: serde_json::Value) {
+        if let Ok(mut states) = self.states.write() {
+            states.insert(connection_id.to_string(), state);
+        }
+    }
+
+    fn states(&self) -> HashMap<String, serde_json::Value> {
+        self.states.read().map(|states| states.clone()).unwrap_or_default()
+    }
+}
+
+/// The open connections that have a saved profile, in profile order, with the
+/// state registered for each. Connections opened without a profile have no
+/// keychain secrets to reconnect with and are left out.
+pub fn snapshot(
+    name: &str,
+    profiles: &[ConnectionProfile],
+    active: &[ConnectionConfig],
+    states: &HashMap<String, serde_json::Value>,
+) -> Workspace {
+    Workspace {
+        name: name.to_string(),
+        connections: profiles
+            .iter()
+            .filter(|profile| active.iter().any(|config| config.id == profile.config.id))
+            .map(|profile| WorkspaceConnection {
+                profile_id: profile.config.id.clone(),
+                name: profile.config.name.clone(),
+                state: states.get(&profile.config.id).cloned().unwrap_or_default(),
+            })
+            .collect(),
+        saved_at: String::new(),
+    }
+}
+
+pub async fn save(
+    store: &AppStore,
+    manager: &ConnectionManager,
+    tracker: &WorkspaceTracker,
+    name: &str,
+) -> Result<Workspace> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(anyhow!("Workspace name can't be empty"));
+    }
+    let profiles = store.list_connection_profiles()?;
+    let active = manager.active_configs().await;
+    store.save_workspace(snapshot(name, &profiles, &active, &tracker.states()))
+}
+
+/// Reconnects every profile of the workspace at once. A connection that fails
+/// is reported with its error and doesn't hold up or abort the others.
+pub async fn restore(
+    store: &AppStore,
+    manager: &ConnectionManager,
+    tracker: &WorkspaceTracker,
+    name: &str,
+) -> Result<WorkspaceRestore> {
+    let workspace = store
+        .get_workspace(name)?
+        .ok_or_else(|| anyhow!("No workspace named {}", name))?;
+    let reconnects = workspace.connections.iter().map(|connection| async move {
+        let result = async {
+            let resolution = profiles::resolve_connection(store, manager, &connection.profile_id).await?;
+            let resolved = profiles::single_match(&resolution, &connection.profile_id)?;
+            profiles::connect_resolved(store, manager, &resolved).await
+        }
+        .await;
+        if result.is_ok() {
+            tracker.set_state(&connection.profile_id, connection.state.clone());
+        }
+        RestoredConnection {
+            profile_id: connection.profile_id.clone(),
+            name: connection.name.clone(),
+            connected: result.is_ok(),
+            error: result.err().map(|e| e.to_string()),
+            state: connection.state.clone(),
+        }
+    });
+    Ok(WorkspaceRestore {
+        connections: futures_util::future::join_all(reconnects).await,
+        name: workspace.name,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::DatabaseType;
+
+    fn config(id: &str) -> ConnectionConfig {
+        ConnectionConfig {
+            id: id.to_string(),
+            name: format!("{} db", id),
+            db_type: DatabaseType::SQLite,
+            host: None,
+            port: None,
+            username: None,
+            password: None,
+            database: None,
+            file_path: Some(":memory:".to_string()),
+            ssh_config: None,
+            retry_policy: None,
+            idle_disconnect_minutes: None,
+            idle_disconnect_rollback: false,
+            encryption_key: None,
+            cipher_settings: None,
+            schema_watch_seconds: None,
+            max_concurrent_operations: None,
+            read_replica: None,
+            failover_hosts: Vec::new(),
+            require_read_write: false,
+            connect_timeout_seconds: None,
+            scratch_schema: None,
+            sql_mode: None,
+            decode_overrides: Default::default(),
+            mysql_ssl: None,
+            pool_settings: None,
+            socket_path: None,
+            connection_url: None,
+            sqlite_open_mode: Default::default(),
+            read_only: false,
+            statement_timeout_seconds: None,
+            reconnect_attempts: None,
+            keepalive_seconds: None,
+            application_name: None,
+            session_params: Default::default(),
+            default_schema: None,
+            charset: None,
+            collation: None,
+            use_stored_credential: false,
+            trust_server_certificate: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn workspaces_keep_open_profiles_and_report_each_reconnect() {
+        let profile = |id: &str| ConnectionProfile {
+            config: config(id),
+            folder: None,
+            credentials_required: false,
+        };
+        let profiles = vec![profile("a"), profile("b"), profile("c")];
+        let states = HashMap::from([("a".to_string(), serde_json::json!({ "tabs": ["users"] }))]);
+        let workspace = snapshot("work", &profiles, &[config("c"), config("a"), config("adhoc")], &states);
+        let ids: Vec<_> = workspace.connections.iter().map(|c| c.profile_id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "c"]);
+        assert_eq!(workspace.connections[0].state["tabs"][0], "users");
+        assert!(workspace.connections[1].state.is_null());
+
+        // A profile deleted since the workspace was saved fails on its own.
+        let store = AppStore::in_memory();
+        let manager = ConnectionManager::new();
+        let tracker = WorkspaceTracker::default();
+        store.save_connection_profile(profile("c")).unwrap();
+        store.save_workspace(workspace).unwrap();
+        let restored = restore(&store, &manager, &tracker, "WORK").await.unwrap();
+        assert_eq!(restored.connections.len(), 2);
+        assert!(!restored.connections[0].connected);
+        assert_eq!(restored.connections[0].error.as_deref(), Some("No connection named a"));
+        assert_eq!(restored.connections[0].state["tabs"][0], "users");
+        assert_eq!(store.list_workspaces().unwrap()[0].connections.len(), 2);
+        assert!(restore(&store, &manager, &tracker, "home").await.is_err());
+    }
+}